@@ -1,40 +1,185 @@
+use std::cell::RefCell;
+
 use raylib::prelude::Vector3;
+use crate::gamma::Gamma;
 use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::stats;
+use rayon::prelude::*;
 
 #[derive(Clone, Copy)]
 struct Aabb { min: Vector3, max: Vector3 }
 
 impl Aabb {
+    /// Neutro de `union`.
+    const EMPTY: Aabb = Aabb {
+        min: Vector3 { x: f32::INFINITY, y: f32::INFINITY, z: f32::INFINITY },
+        max: Vector3 { x: -f32::INFINITY, y: -f32::INFINITY, z: -f32::INFINITY },
+    };
+
     fn union(a: Aabb, b: Aabb) -> Aabb {
         Aabb {
             min: Vector3::new(a.min.x.min(b.min.x), a.min.y.min(b.min.y), a.min.z.min(b.min.z)),
             max: Vector3::new(a.max.x.max(b.max.x), a.max.y.max(b.max.y), a.max.z.max(b.max.z)),
         }
     }
+    /// Slabs robusto: un eje con dirección 0 (rayos alineados a los ejes) no acota t si el origen
+    /// está entre sus planos, bordes incluidos, y descarta el rayo si está afuera; así no aparecen
+    /// `0 × inf = NaN` con el origen justo sobre una cara. `f32::max`/`min` ignoran un NaN suelto.
     fn intersect_ray(&self, ro: Vector3, rd: Vector3) -> Option<(f32, f32)> {
-        let inv = Vector3::new(1.0/rd.x, 1.0/rd.y, 1.0/rd.z);
+        let (mut t_enter, mut t_exit) = (f32::NEG_INFINITY, f32::INFINITY);
+        let axes = [(ro.x, rd.x, self.min.x, self.max.x), (ro.y, rd.y, self.min.y, self.max.y), (ro.z, rd.z, self.min.z, self.max.z)];
+        for (o, d, lo, hi) in axes {
+            if d == 0.0 {
+                if o < lo || o > hi { return None; }
+                continue;
+            }
+            let inv = 1.0 / d;
+            let (t1, t2) = ((lo - o) * inv, (hi - o) * inv);
+            t_enter = t_enter.max(t1.min(t2));
+            t_exit = t_exit.min(t1.max(t2));
+            if t_enter > t_exit { return None; }
+        }
+        Some((t_enter, t_exit))
+    }
+}
+
+/// Por debajo de esto no conviene repartir (escenas chicas construyen en microsegundos).
+const PAR_MIN_OBJECTS: usize = 1024;
+
+/// Tramos en que se reparte la construcción con `n` objetos: uno por hilo de rayon, con al
+/// menos `PAR_MIN_OBJECTS` objetos cada uno (con uno solo rayon lo corre en el hilo actual).
+fn build_chunks(n: usize) -> usize {
+    rayon::current_num_threads().min(n.div_ceil(PAR_MIN_OBJECTS)).max(1)
+}
+
+/// Rango inclusivo de celdas que toca un AABB, recortado a la grilla.
+#[inline]
+fn cell_span(a: &Aabb, bounds: &Aabb, cell_size: Vector3, dims: [i32; 3]) -> ([i32; 3], [i32; 3]) {
+    let lo = [
+        (((a.min.x - bounds.min.x) / cell_size.x).floor() as i32).max(0),
+        (((a.min.y - bounds.min.y) / cell_size.y).floor() as i32).max(0),
+        (((a.min.z - bounds.min.z) / cell_size.z).floor() as i32).max(0),
+    ];
+    let hi = [
+        (((a.max.x - bounds.min.x) / cell_size.x).floor() as i32).min(dims[0] - 1),
+        (((a.max.y - bounds.min.y) / cell_size.y).floor() as i32).min(dims[1] - 1),
+        (((a.max.z - bounds.min.z) / cell_size.z).floor() as i32).min(dims[2] - 1),
+    ];
+    (lo, hi)
+}
+
+/// Buzón (mailboxing): `stamps[obj]` es el último rayo que probó ese objeto, así un objeto que
+/// cubre varias celdas se prueba una sola vez por recorrido. No asigna por rayo: el vector
+/// solo crece cuando crece la escena.
+#[derive(Default)]
+struct Mailbox {
+    next: u32,
+    stamps: Vec<u32>,
+}
+
+thread_local! {
+    static MAILBOX: RefCell<Mailbox> = RefCell::new(Mailbox::default());
+}
+
+impl Mailbox {
+    /// Presta el buzón del hilo con `lanes` ids de rayo nuevos (devuelve el primero).
+    /// Un recorrido anidado recibiría uno vacío: correcto, solo que sin reutilizar memoria.
+    fn borrow(objects: usize, lanes: u32) -> (MailboxGuard, u32) {
+        let mut mb = MAILBOX.take();
+        if mb.stamps.len() < objects { mb.stamps.resize(objects, 0); }
+        // 0 = nunca probado; al dar la vuelta el contador se limpian los sellos
+        if mb.next == 0 || mb.next.checked_add(lanes).is_none() {
+            mb.stamps.fill(0);
+            mb.next = 1;
+        }
+        let ray = mb.next;
+        mb.next += lanes;
+        (MailboxGuard(mb), ray)
+    }
+
+    /// true la primera vez que `ray` llega a `obj`; las siguientes cuentan como repetidas.
+    #[inline]
+    fn first_visit(&mut self, obj: usize, ray: u32) -> bool {
+        let stamp = &mut self.stamps[obj];
+        if *stamp == ray {
+            stats::count(|c| c.repeats += 1);
+            return false;
+        }
+        *stamp = ray;
+        true
+    }
+}
 
-        let mut t1 = (self.min.x - ro.x)*inv.x;
-        let mut t2 = (self.max.x - ro.x)*inv.x;
-        if t1>t2 { std::mem::swap(&mut t1, &mut t2); }
+/// Devuelve el buzón al hilo al terminar el recorrido (también en los `return` tempranos).
+struct MailboxGuard(Mailbox);
 
-        let mut ty1 = (self.min.y - ro.y)*inv.y;
-        let mut ty2 = (self.max.y - ro.y)*inv.y;
-        if ty1>ty2 { std::mem::swap(&mut ty1, &mut ty2); }
+impl Drop for MailboxGuard {
+    fn drop(&mut self) { MAILBOX.set(std::mem::take(&mut self.0)); }
+}
 
-        if t1>ty2 || ty1>t2 { return None; }
-        if ty1>t1 { t1=ty1; }
-        if ty2<t2 { t2=ty2; }
+/// Estado del DDA (Amanatides–Woo) de un rayo por la grilla.
+struct GridWalk {
+    ro: Vector3,
+    rd: Vector3,
+    dims: [i32; 3],
+    cell: [i32; 3],
+    step: [i32; 3],
+    t_max: [f32; 3],
+    t_delta: [f32; 3],
+    /// Distancia a la que el rayo entra en la celda actual.
+    t_enter: f32,
+    t_exit: f32,
+}
 
-        let mut tz1 = (self.min.z - ro.z)*inv.z;
-        let mut tz2 = (self.max.z - ro.z)*inv.z;
-        if tz1>tz2 { std::mem::swap(&mut tz1, &mut tz2); }
+impl GridWalk {
+    /// Primera celda del rayo dentro de la grilla; None si no la toca.
+    fn start(grid: &UniformGridAccel, ro: &Vector3, rd: &Vector3) -> Option<GridWalk> {
+        if grid.cells.is_empty() { return None; }
+        let (mut t_enter, t_exit) = grid.bounds.intersect_ray(*ro, *rd)?;
+        if t_exit < 0.0 { return None; }
+        if t_enter < 0.0 { t_enter = 0.0; }
 
-        if t1>tz2 || tz1>t2 { return None; }
-        if tz1>t1 { t1=tz1; }
-        if tz2<t2 { t2=tz2; }
+        let pos = *ro + *rd * t_enter;
+        let (p, o, size, d) = ([pos.x, pos.y, pos.z], [grid.bounds.min.x, grid.bounds.min.y, grid.bounds.min.z],
+            [grid.cell_size.x, grid.cell_size.y, grid.cell_size.z], [rd.x, rd.y, rd.z]);
+        let mut walk = GridWalk { ro: *ro, rd: *rd, dims: grid.dims, cell: [0; 3], step: [0; 3], t_max: [0.0; 3], t_delta: [0.0; 3], t_enter, t_exit };
+        for a in 0..3 {
+            let i = ((p[a] - o[a]) / size[a]).floor() as i32;
+            let i = i.clamp(0, grid.dims[a] - 1);
+            let step = if d[a] > 0.0 { 1 } else if d[a] < 0.0 { -1 } else { 0 };
+            let next = o[a] + (i + (step > 0) as i32) as f32 * size[a];
+            walk.cell[a] = i;
+            walk.step[a] = step;
+            walk.t_max[a] = if step != 0 { t_enter + (next - p[a]) / d[a] } else { f32::INFINITY };
+            walk.t_delta[a] = if step != 0 { size[a] / d[a].abs() } else { f32::INFINITY };
+        }
+        Some(walk)
+    }
 
-        Some((t1, t2))
+    /// Índice de la celda actual; None si el rayo ya salió de la grilla.
+    #[inline]
+    fn cell(&self, grid: &UniformGridAccel) -> Option<usize> {
+        let [ix, iy, iz] = self.cell;
+        if ix < 0 || ix >= grid.dims[0] || iy < 0 || iy >= grid.dims[1] || iz < 0 || iz >= grid.dims[2] {
+            return None;
+        }
+        let idx = grid.cell_index(ix, iy, iz);
+        (idx < grid.cells.len()).then_some(idx)
+    }
+
+    #[inline]
+    fn t_cell_exit(&self) -> f32 { self.t_max[0].min(self.t_max[1]).min(self.t_max[2]) }
+
+    /// Pasa a la celda vecina por la cara más cercana; false si sale de la grilla o del rango.
+    #[inline]
+    fn advance(&mut self) -> bool {
+        let [tx, ty, tz] = self.t_max;
+        let a = if tx < ty { if tx < tz { 0 } else { 2 } } else if ty < tz { 1 } else { 2 };
+        self.cell[a] += self.step[a];
+        if self.cell[a] < 0 || self.cell[a] >= self.dims[a] { return false; }
+        self.t_enter = self.t_max[a];
+        self.t_max[a] += self.t_delta[a];
+        self.t_enter <= self.t_exit
     }
 }
 
@@ -43,9 +188,78 @@ pub struct UniformGridAccel {
     dims: [i32; 3],
     cell_size: Vector3,
     cells: Vec<Vec<usize>>,
+    /// Esfera envolvente (centro, radio) por objeto, solo para los que prefieren ese prefiltro.
+    spheres: Vec<Option<(Vector3, f32)>>,
+    /// AABB por objeto: `remove`/`remap` solo tocan las celdas que cubre.
+    aabbs: Vec<Aabb>,
+}
+
+/// Tope de celdas de la grilla (`build` recorta; `insert` no crece más allá).
+const MAX_CELLS: usize = 1_000_000;
+
+/// Costo de un recorrido de `trace_cost`: celdas visitadas y referencias a objetos en ellas
+/// (candidatos antes del buzón y del prefiltro de esfera).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TraceCost {
+    pub cells: u32,
+    pub refs: u32,
+}
+
+/// Ocupación de la grilla (`UniformGridAccel::stats`): para ver si el tamaño de celda es razonable.
+#[derive(Clone, Copy, Debug)]
+pub struct GridStats {
+    pub dims: [i32; 3],
+    pub cell_size: Vector3,
+    pub cells: usize,
+    /// Referencias objeto→celda (un objeto grande cuenta una vez por celda que toca).
+    pub refs: usize,
+    pub avg_per_cell: f32,
+    /// Promedio solo sobre las celdas con algo: lo que prueba un rayo al entrar a una.
+    pub avg_per_occupied: f32,
+    pub max_per_cell: usize,
+    /// Fracción de celdas vacías (0..1).
+    pub empty_ratio: f32,
 }
 
+impl GridStats {
+    pub fn summary(&self) -> String {
+        format!(
+            "{}x{}x{} celdas de {:.2}, {:.2} obj/celda ({:.1} en ocupadas, máx {}), {:.0}% vacías",
+            self.dims[0], self.dims[1], self.dims[2], self.cell_size.x,
+            self.avg_per_cell, self.avg_per_occupied, self.max_per_cell, self.empty_ratio * 100.0,
+        )
+    }
+}
+
+/// Referencias por celda ocupada que apunta `suggest_cell_size` (cada celda es un paso del DDA;
+/// cada referencia, una prueba de intersección).
+const TARGET_REFS_PER_CELL: f32 = 2.0;
+
 impl UniformGridAccel {
+    /// Tamaño de celda sugerido para `objects`: el lado de un cubo que, repartiendo los objetos
+    /// parejo en el volumen de la escena, tendría `TARGET_REFS_PER_CELL`. Se acota a entre la
+    /// mitad y 8 veces el lado medio de los AABB (más chico repite cada objeto en muchas celdas;
+    /// más grande junta demasiados candidatos en escenas con grupos densos) y nunca baja del
+    /// tamaño que haría pasar `MAX_CELLS`.
+    pub fn suggest_cell_size(objects: &[Box<dyn RayIntersect>]) -> f32 {
+        let (mut bounds, mut side_sum, mut n) = (Aabb::EMPTY, 0.0f32, 0usize);
+        for o in objects.iter() {
+            let (mn, mx) = o.aabb();
+            let e = mx - mn;
+            if !(e.x.is_finite() && e.y.is_finite() && e.z.is_finite()) { continue; }
+            bounds = Aabb::union(bounds, Aabb { min: mn, max: mx });
+            side_sum += (e.x + e.y + e.z) / 3.0;
+            n += 1;
+        }
+        if n == 0 { return 1.0; }
+        let ext = bounds.max - bounds.min;
+        let volume = ext.x.max(1e-3) * ext.y.max(1e-3) * ext.z.max(1e-3);
+        let avg_side = side_sum / n as f32;
+        let sparse_side = (volume * TARGET_REFS_PER_CELL / n as f32).cbrt();
+        let min_side = (volume / MAX_CELLS as f32).cbrt();
+        sparse_side.clamp(avg_side * 0.5, avg_side * 8.0).max(min_side).max(0.01)
+    }
+
     pub fn build(objects: &[Box<dyn RayIntersect>], desired_cell_size: f32) -> Self {
         // === Caso escena vacía: crea grilla 1x1x1 segura ===
         if objects.is_empty() {
@@ -58,20 +272,24 @@ impl UniformGridAccel {
                 dims: [1, 1, 1],
                 cell_size: bounds.max - bounds.min,
                 cells: vec![Vec::new()], // una celda vacía
+                spheres: Vec::new(),
+                aabbs: Vec::new(),
             };
         }
 
-        let mut bounds = Aabb {
-            min: Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY),
-            max: Vector3::new(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY),
-        };
-        let mut aabbs: Vec<Aabb> = Vec::with_capacity(objects.len());
-        for obj in objects.iter() {
-            let (mn, mx) = obj.aabb();
-            let a = Aabb { min: mn, max: mx };
-            aabbs.push(a);
-            bounds = Aabb::union(bounds, a);
-        }
+        // === AABBs (y esfera envolvente) por objeto en paralelo, y su unión ===
+        let chunks = build_chunks(objects.len());
+        let chunk = objects.len().div_ceil(chunks);
+        let (aabbs, spheres): (Vec<Aabb>, Vec<Option<(Vector3, f32)>>) = objects.par_iter()
+            .map(|obj| {
+                let (mn, mx) = obj.aabb();
+                let sphere = obj.prefer_sphere_prefilter().then(|| ((mn + mx) * 0.5, (mx - mn).length() * 0.5));
+                (Aabb { min: mn, max: mx }, sphere)
+            })
+            .unzip();
+        let mut bounds = aabbs.par_iter()
+            .fold(|| Aabb::EMPTY, |b, a| Aabb::union(b, *a))
+            .reduce(|| Aabb::EMPTY, Aabb::union);
         if !bounds.min.x.is_finite() || !bounds.max.x.is_finite() {
             bounds = Aabb { min: Vector3::new(-0.5,-0.5,-0.5), max: Vector3::new(0.5,0.5,0.5) };
         }
@@ -96,344 +314,598 @@ impl UniformGridAccel {
         let cell_size = Vector3::new(ext.x / nx as f32, ext.y / ny as f32, ext.z / nz as f32);
 
         let total_u = (nx as i64) * (ny as i64) * (nz as i64);
-        let total = if total_u <= 0 { 1 } else { total_u.min(1_000_000) as usize };
-
-        let mut cells: Vec<Vec<usize>> = (0..total).map(|_| Vec::new()).collect();
-
-        for (i, a) in aabbs.iter().enumerate() {
-            let min_ix = ((a.min.x - bounds.min.x) / cell_size.x).floor() as i32;
-            let min_iy = ((a.min.y - bounds.min.y) / cell_size.y).floor() as i32;
-            let min_iz = ((a.min.z - bounds.min.z) / cell_size.z).floor() as i32;
-            let max_ix = ((a.max.x - bounds.min.x) / cell_size.x).floor() as i32;
-            let max_iy = ((a.max.y - bounds.min.y) / cell_size.y).floor() as i32;
-            let max_iz = ((a.max.z - bounds.min.z) / cell_size.z).floor() as i32;
-
-            for iz in min_iz.max(0)..=max_iz.min(dims[2] - 1) {
-                for iy in min_iy.max(0)..=max_iy.min(dims[1] - 1) {
-                    for ix in min_ix.max(0)..=max_ix.min(dims[0] - 1) {
-                        let idx64 = ((iz as i64 * dims[1] as i64 + iy as i64) * dims[0] as i64 + ix as i64);
-                        if idx64 < 0 { continue; }
-                        let idx = idx64 as usize;
-                        if idx < cells.len() {
-                            cells[idx].push(i);
+        let total = if total_u <= 0 { 1 } else { total_u.min(MAX_CELLS as i64) as usize };
+
+        // === Binning en paralelo ===
+        // Cada tramo de objetos reparte pares (celda, objeto) en un bucket por rango de celdas;
+        // después cada rango se llena con los buckets en orden de tramo (`collect` lo conserva),
+        // así cada celda queda con índices ascendentes (igual que la versión secuencial).
+        let per_range = total.div_ceil(chunks);
+        let buckets: Vec<Vec<Vec<(usize, usize)>>> = aabbs.par_chunks(chunk).enumerate()
+            .map(|(t, a_chunk)| {
+                let mut local: Vec<Vec<(usize, usize)>> = vec![Vec::new(); chunks];
+                for (k, a) in a_chunk.iter().enumerate() {
+                    let i = t * chunk + k;
+                    let (lo, hi) = cell_span(a, &bounds, cell_size, dims);
+                    for iz in lo[2]..=hi[2] {
+                        for iy in lo[1]..=hi[1] {
+                            for ix in lo[0]..=hi[0] {
+                                let idx64 = (iz as i64 * dims[1] as i64 + iy as i64) * dims[0] as i64 + ix as i64;
+                                if idx64 < 0 { continue; }
+                                let idx = idx64 as usize;
+                                if idx < total {
+                                    local[idx / per_range].push((idx, i));
+                                }
+                            }
                         }
                     }
                 }
+                local
+            })
+            .collect();
+
+        let mut cells: Vec<Vec<usize>> = (0..total).map(|_| Vec::new()).collect();
+        cells.par_chunks_mut(per_range).enumerate().for_each(|(r, range)| {
+            let base = r * per_range;
+            for local in buckets.iter() {
+                for &(idx, i) in local[r].iter() {
+                    range[idx - base].push(i);
+                }
             }
+        });
+
+        UniformGridAccel { bounds, dims, cell_size, cells, spheres, aabbs }
+    }
+
+    /// Agrega el objeto `idx` (tiene que ser el último de la lista: `objects.push`) solo en las
+    /// celdas que toca. Si cae fuera de la grilla, la grilla crece de a celdas enteras. false =
+    /// no se pudo (crecería más allá de `MAX_CELLS`): hay que llamar a `build`.
+    pub fn insert(&mut self, idx: usize, obj: &dyn RayIntersect) -> bool {
+        debug_assert_eq!(idx, self.aabbs.len(), "insert: solo al final de la lista");
+        let (mn, mx) = obj.aabb();
+        let a = Aabb { min: mn, max: mx };
+        // Grilla de escena vacía (celda de 1 fija) o AABB infinito: mejor reconstruir
+        if self.aabbs.is_empty() || !(mn.x.is_finite() && mx.x.is_finite() && mn.y.is_finite() && mx.y.is_finite() && mn.z.is_finite() && mx.z.is_finite()) {
+            return false;
         }
+        if !self.contains(&a) && !self.grow(&a) { return false; }
+        self.aabbs.push(a);
+        self.spheres.push(obj.prefer_sphere_prefilter().then(|| ((mn + mx) * 0.5, (mx - mn).length() * 0.5)));
+        self.for_each_cell(&a, |cell| cell.push(idx));
+        true
+    }
 
-        UniformGridAccel { bounds, dims, cell_size, cells }
+    /// Saca el objeto `idx` de sus celdas (la lista de objetos no cambia de largo).
+    pub fn remove(&mut self, idx: usize) {
+        let Some(a) = self.aabbs.get(idx).copied() else { return; };
+        self.for_each_cell(&a, |cell| cell.retain(|&i| i != idx));
     }
 
-    #[inline] fn cell_index(&self, ix: i32, iy: i32, iz: i32) -> usize {
-        ((iz * self.dims[1] + iy) * self.dims[0] + ix) as usize
+    /// Renombra el objeto `from` como `to` en sus celdas (el que movió `Vec::swap_remove`).
+    pub fn remap(&mut self, from: usize, to: usize) {
+        let Some(a) = self.aabbs.get(from).copied() else { return; };
+        self.for_each_cell(&a, |cell| for i in cell.iter_mut() { if *i == from { *i = to; } });
     }
 
-    pub fn trace(&self, ro: &Vector3, rd: &Vector3, objects: &[Box<dyn RayIntersect>]) -> Intersect {
-        if self.cells.is_empty() { return Intersect::empty(); }
+    /// Espejo de `objects.swap_remove(idx)`: quita `idx` y el último pasa a ocupar su lugar.
+    pub fn swap_remove(&mut self, idx: usize) {
+        if idx >= self.aabbs.len() { return; }
+        self.remove(idx);
+        let last = self.aabbs.len() - 1;
+        if idx != last { self.remap(last, idx); }
+        self.aabbs.swap_remove(idx);
+        self.spheres.swap_remove(idx);
+    }
+
+    #[inline]
+    fn contains(&self, a: &Aabb) -> bool {
+        a.min.x >= self.bounds.min.x && a.min.y >= self.bounds.min.y && a.min.z >= self.bounds.min.z &&
+        a.max.x <= self.bounds.max.x && a.max.y <= self.bounds.max.y && a.max.z <= self.bounds.max.z
+    }
+
+    fn for_each_cell(&mut self, a: &Aabb, mut f: impl FnMut(&mut Vec<usize>)) {
+        let (lo, hi) = cell_span(a, &self.bounds, self.cell_size, self.dims);
+        for iz in lo[2]..=hi[2] {
+            for iy in lo[1]..=hi[1] {
+                for ix in lo[0]..=hi[0] {
+                    let idx = self.cell_index(ix, iy, iz);
+                    if let Some(cell) = self.cells.get_mut(idx) { f(cell); }
+                }
+            }
+        }
+    }
+
+    /// Extiende la grilla (mismo tamaño de celda, celdas viejas en su lugar) hasta cubrir `a`.
+    fn grow(&mut self, a: &Aabb) -> bool {
+        let old_total = self.dims.iter().map(|&d| d as usize).product::<usize>();
+        if self.cells.len() != old_total { return false; } // grilla ya recortada por `build`
+        let (min, size) = ([self.bounds.min.x, self.bounds.min.y, self.bounds.min.z], [self.cell_size.x, self.cell_size.y, self.cell_size.z]);
+        let (a_min, a_max) = ([a.min.x, a.min.y, a.min.z], [a.max.x, a.max.y, a.max.z]);
+        let mut lo = [0i32; 3];
+        let mut dims = self.dims;
+        for k in 0..3 {
+            lo[k] = (((a_min[k] - min[k]) / size[k]).floor() as i32).min(0);
+            let hi = (((a_max[k] - min[k]) / size[k]).floor() as i32).max(self.dims[k] - 1);
+            dims[k] = hi - lo[k] + 1;
+        }
+        let total = dims.iter().map(|&d| d as usize).product::<usize>();
+        if total > MAX_CELLS { return false; }
 
-        let (mut t_enter, t_exit) = match self.bounds.intersect_ray(*ro, *rd) {
-            Some(t) => t, None => return Intersect::empty(),
+        let mut cells: Vec<Vec<usize>> = (0..total).map(|_| Vec::new()).collect();
+        for (old, cell) in self.cells.iter_mut().enumerate() {
+            let (ix, iy, iz) = (old as i32 % self.dims[0], old as i32 / self.dims[0] % self.dims[1], old as i32 / (self.dims[0] * self.dims[1]));
+            let new = ((iz - lo[2]) * dims[1] + (iy - lo[1])) * dims[0] + (ix - lo[0]);
+            cells[new as usize] = std::mem::take(cell);
+        }
+        let new_min = Vector3::new(min[0] + lo[0] as f32 * size[0], min[1] + lo[1] as f32 * size[1], min[2] + lo[2] as f32 * size[2]);
+        self.bounds = Aabb {
+            min: new_min,
+            max: new_min + Vector3::new(dims[0] as f32 * size[0], dims[1] as f32 * size[1], dims[2] as f32 * size[2]),
         };
-        if t_exit < 0.0 { return Intersect::empty(); }
-        if t_enter < 0.0 { t_enter = 0.0; }
+        self.dims = dims;
+        self.cells = cells;
+        true
+    }
 
-        let eps = 1e-4;
-        let pos = *ro + *rd * t_enter;
+    /// Descarte rápido por esfera envolvente (solo objetos que la piden): true si el rayo
+    /// no puede tocar el objeto antes de `max_t`.
+    #[inline]
+    fn sphere_rejects(&self, obj_idx: usize, ro: &Vector3, rd: &Vector3, max_t: f32) -> bool {
+        let Some((c, r)) = self.spheres[obj_idx] else { return false; };
+        let oc = c - *ro;
+        let c2 = oc.dot(oc);
+        if c2 <= r * r { return false; } // origen adentro
+        let inv_len2 = 1.0 / rd.dot(*rd);
+        let tca = oc.dot(*rd) * inv_len2;
+        if tca < 0.0 { return true; } // detrás del origen
+        let d2 = c2 - tca * tca / inv_len2;
+        if d2 > r * r { return true; }
+        // Entrada a la esfera más allá del mejor hit
+        let thc = ((r * r - d2) * inv_len2).sqrt();
+        tca - thc > max_t
+    }
 
-        let mut ix = ((pos.x - self.bounds.min.x) / self.cell_size.x).floor() as i32;
-        let mut iy = ((pos.y - self.bounds.min.y) / self.cell_size.y).floor() as i32;
-        let mut iz = ((pos.z - self.bounds.min.z) / self.cell_size.z).floor() as i32;
-        ix = ix.clamp(0, self.dims[0]-1);
-        iy = iy.clamp(0, self.dims[1]-1);
-        iz = iz.clamp(0, self.dims[2]-1);
+    /// Resumen de la grilla para cabeceras de volcados: celdas, tamaño de celda y referencias.
+    /// Celdas por eje y lado real de celda (sin recorrer las celdas: sirve para cada frame).
+    pub fn shape(&self) -> ([i32; 3], Vector3) { (self.dims, self.cell_size) }
+
+    pub fn describe(&self) -> String {
+        let refs: usize = self.cells.iter().map(|c| c.len()).sum();
+        format!(
+            "{}x{}x{} celdas de {:.3}x{:.3}x{:.3}, {} referencias",
+            self.dims[0], self.dims[1], self.dims[2],
+            self.cell_size.x, self.cell_size.y, self.cell_size.z, refs,
+        )
+    }
 
-        let step_x = if rd.x > 0.0 { 1 } else if rd.x < 0.0 { -1 } else { 0 };
-        let step_y = if rd.y > 0.0 { 1 } else if rd.y < 0.0 { -1 } else { 0 };
-        let step_z = if rd.z > 0.0 { 1 } else if rd.z < 0.0 { -1 } else { 0 };
+    /// Ocupación actual (recorre todas las celdas; para debug, no para cada frame del camino caliente).
+    pub fn stats(&self) -> GridStats {
+        let cells = self.cells.len();
+        let (mut refs, mut max_per_cell, mut empty) = (0usize, 0usize, 0usize);
+        for c in self.cells.iter() {
+            refs += c.len();
+            max_per_cell = max_per_cell.max(c.len());
+            if c.is_empty() { empty += 1; }
+        }
+        GridStats {
+            dims: self.dims,
+            cell_size: self.cell_size,
+            cells,
+            refs,
+            avg_per_cell: refs as f32 / cells.max(1) as f32,
+            avg_per_occupied: refs as f32 / (cells - empty).max(1) as f32,
+            max_per_cell,
+            empty_ratio: empty as f32 / cells.max(1) as f32,
+        }
+    }
 
-        let next_x = self.bounds.min.x + (ix + (step_x > 0) as i32) as f32 * self.cell_size.x;
-        let next_y = self.bounds.min.y + (iy + (step_y > 0) as i32) as f32 * self.cell_size.y;
-        let next_z = self.bounds.min.z + (iz + (step_z > 0) as i32) as f32 * self.cell_size.z;
+    #[inline] fn cell_index(&self, ix: i32, iy: i32, iz: i32) -> usize {
+        ((iz * self.dims[1] + iy) * self.dims[0] + ix) as usize
+    }
 
-        let mut t_max_x = if step_x != 0 { t_enter + (next_x - pos.x) / rd.x } else { f32::INFINITY };
-        let mut t_max_y = if step_y != 0 { t_enter + (next_y - pos.y) / rd.y } else { f32::INFINITY };
-        let mut t_max_z = if step_z != 0 { t_enter + (next_z - pos.z) / rd.z } else { f32::INFINITY };
+    pub fn trace(&self, ro: &Vector3, rd: &Vector3, objects: &[Box<dyn RayIntersect>]) -> Intersect {
+        self.trace_lod(ro, rd, objects, f32::INFINITY, false)
+    }
 
-        let t_delta_x = if step_x != 0 { self.cell_size.x / rd.x.abs() } else { f32::INFINITY };
-        let t_delta_y = if step_y != 0 { self.cell_size.y / rd.y.abs() } else { f32::INFINITY };
-        let t_delta_z = if step_z != 0 { self.cell_size.z / rd.z.abs() } else { f32::INFINITY };
+    /// Igual que `trace`, pero las celdas que empiezan más allá de `flat_from`
+    /// se intersectan con `ray_intersect_flat` (LOD: color promedio por cara).
+    /// `detailed` (rayos primarios): el resto usa `ray_intersect_detailed`.
+    pub fn trace_lod(&self, ro: &Vector3, rd: &Vector3, objects: &[Box<dyn RayIntersect>], flat_from: f32, detailed: bool) -> Intersect {
+        self.trace_cost(ro, rd, objects, flat_from, detailed).0
+    }
 
-        let mut best = Intersect::empty();
-        let mut best_t = f32::INFINITY;
+    /// `trace_lod` que además devuelve lo que costó el recorrido (vista de debug de calor).
+    pub fn trace_cost(&self, ro: &Vector3, rd: &Vector3, objects: &[Box<dyn RayIntersect>], flat_from: f32, detailed: bool) -> (Intersect, TraceCost) {
+        profile_scope!(Grid);
+        let mut cost = TraceCost::default();
+        let Some(mut walk) = GridWalk::start(self, ro, rd) else { return (Intersect::empty(), cost); };
 
+        let (mut mb, ray) = Mailbox::borrow(objects.len(), 1);
+        let mut best = (Intersect::empty(), f32::INFINITY);
         loop {
-            if ix < 0 || ix >= self.dims[0] || iy < 0 || iy >= self.dims[1] || iz < 0 || iz >= self.dims[2] {
-                break;
-            }
+            let Some(cell_idx) = walk.cell(self) else { break; };
+            cost.cells += 1;
+            cost.refs += self.cells[cell_idx].len() as u32;
+            self.test_cell(&walk, cell_idx, objects, walk.t_enter >= flat_from, detailed, (&mut mb.0, ray), &mut best);
+            if best.1 <= walk.t_cell_exit() || !walk.advance() { break; }
+        }
 
-            let cell_idx = self.cell_index(ix, iy, iz);
-            if cell_idx >= self.cells.len() { break; }
+        if best.0.is_intersecting { stats::count(|c| c.hits += 1); }
+        (best.0, cost)
+    }
 
-            for &obj_idx in &self.cells[cell_idx] {
-                let mut i = objects[obj_idx].ray_intersect(ro, rd);
-                i.object_index = Some(obj_idx);
-                if i.is_intersecting && i.distance >= t_enter - eps && i.distance < best_t {
-                    best_t = i.distance;
-                    best = i;
-                }
+    /// `trace_lod` de `N` rayos a la vez (quads de pixeles con rayos primarios coherentes).
+    /// Los rayos avanzan juntos, un paso de DDA por ronda, y cada uno prueba su celda en el
+    /// mismo orden que el trazado escalar: el resultado es idéntico al de `trace_lod` por rayo
+    /// (el empate entre hits a igual distancia depende del orden). Lo que se gana es
+    /// localidad: rayos vecinos suelen visitar las mismas celdas y objetos en la misma ronda.
+    /// Cada rayo tiene su id de buzón; como comparten los sellos, un rayo puede volver a probar
+    /// un objeto que otro selló después (mismo resultado, solo menos ahorro que el escalar).
+    pub fn trace_packet<const N: usize>(
+        &self, ro: &[Vector3; N], rd: &[Vector3; N], objects: &[Box<dyn RayIntersect>],
+        flat_from: &[f32; N], detailed: bool,
+    ) -> [Intersect; N] {
+        profile_scope!(Grid);
+        let mut walks: [Option<GridWalk>; N] = std::array::from_fn(|k| GridWalk::start(self, &ro[k], &rd[k]));
+        let (mut mb, first_ray) = Mailbox::borrow(objects.len(), N as u32);
+        let mut best = [(Intersect::empty(), f32::INFINITY); N];
+
+        while walks.iter().any(Option::is_some) {
+            for (k, slot) in walks.iter_mut().enumerate() {
+                let Some(walk) = slot.as_mut() else { continue; };
+                let done = match walk.cell(self) {
+                    Some(cell_idx) => {
+                        self.test_cell(walk, cell_idx, objects, walk.t_enter >= flat_from[k], detailed, (&mut mb.0, first_ray + k as u32), &mut best[k]);
+                        best[k].1 <= walk.t_cell_exit() || !walk.advance()
+                    }
+                    None => true,
+                };
+                if done { *slot = None; }
             }
+        }
 
-            let t_cell_exit = t_max_x.min(t_max_y).min(t_max_z);
-            if best_t <= t_cell_exit { break; }
+        best.map(|(hit, _)| {
+            if hit.is_intersecting { stats::count(|c| c.hits += 1); }
+            hit
+        })
+    }
 
-            if t_max_x < t_max_y {
-                if t_max_x < t_max_z {
-                    ix += step_x; if ix < 0 || ix >= self.dims[0] { break; }
-                    t_enter = t_max_x; t_max_x += t_delta_x;
-                } else {
-                    iz += step_z; if iz < 0 || iz >= self.dims[2] { break; }
-                    t_enter = t_max_z; t_max_z += t_delta_z;
-                }
+    /// Prueba los objetos de la celda actual de `walk` que el rayo todavía no probó y actualiza
+    /// el mejor hit (hit, distancia). Saltear un objeto ya probado no cambia el resultado: su
+    /// hit ya compitió, y en las celdas siguientes `t_enter` solo sube.
+    #[inline]
+    fn test_cell(
+        &self, walk: &GridWalk, cell_idx: usize, objects: &[Box<dyn RayIntersect>],
+        flat: bool, detailed: bool, (mb, ray): (&mut Mailbox, u32), best: &mut (Intersect, f32),
+    ) {
+        let eps = 1e-4;
+        let (ro, rd) = (&walk.ro, &walk.rd);
+        stats::count(|c| c.cells += 1);
+        for &obj_idx in &self.cells[cell_idx] {
+            if !mb.first_visit(obj_idx, ray) { continue; }
+            if self.sphere_rejects(obj_idx, ro, rd, best.1) { stats::count(|c| c.fast_rejects += 1); continue; }
+            stats::count(|c| c.tests += 1);
+            let mut i = if flat {
+                objects[obj_idx].ray_intersect_flat(ro, rd)
+            } else if detailed {
+                objects[obj_idx].ray_intersect_detailed(ro, rd)
             } else {
-                if t_max_y < t_max_z {
-                    iy += step_y; if iy < 0 || iy >= self.dims[1] { break; }
-                    t_enter = t_max_y; t_max_y += t_delta_y;
-                } else {
-                    iz += step_z; if iz < 0 || iz >= self.dims[2] { break; }
-                    t_enter = t_max_z; t_max_z += t_delta_z;
-                }
+                objects[obj_idx].ray_intersect(ro, rd)
+            };
+            if i.is_intersecting && i.distance >= walk.t_enter - eps && i.distance < best.1 {
+                // El índice lo pone la grilla: las primitivas no saben en qué posición están
+                i.object_index = Some(obj_idx);
+                *best = (i, i.distance);
             }
-            if t_enter > t_exit { break; }
         }
-
-        best
     }
 
     pub fn occluded(&self, ro: &Vector3, rd: &Vector3, max_t: f32, objects: &[Box<dyn RayIntersect>]) -> bool {
-        if self.cells.is_empty() { return false; }
-
-        let (mut t_enter, t_exit) = match self.bounds.intersect_ray(*ro, *rd) {
-            Some(t) => t, None => return false,
-        };
-        if t_exit < 0.0 { return false; }
-        if t_enter < 0.0 { t_enter = 0.0; }
+        profile_scope!(Grid);
+        let Some(mut walk) = GridWalk::start(self, ro, rd) else { return false; };
         let eps = 1e-4;
-        let pos = *ro + *rd * t_enter;
-
-        let mut ix = ((pos.x - self.bounds.min.x) / self.cell_size.x).floor() as i32;
-        let mut iy = ((pos.y - self.bounds.min.y) / self.cell_size.y).floor() as i32;
-        let mut iz = ((pos.z - self.bounds.min.z) / self.cell_size.z).floor() as i32;
-        ix = ix.clamp(0, self.dims[0]-1);
-        iy = iy.clamp(0, self.dims[1]-1);
-        iz = iz.clamp(0, self.dims[2]-1);
-
-        let step_x = if rd.x > 0.0 { 1 } else if rd.x < 0.0 { -1 } else { 0 };
-        let step_y = if rd.y > 0.0 { 1 } else if rd.y < 0.0 { -1 } else { 0 };
-        let step_z = if rd.z > 0.0 { 1 } else if rd.z < 0.0 { -1 } else { 0 };
-
-        let next_x = self.bounds.min.x + (ix + (step_x > 0) as i32) as f32 * self.cell_size.x;
-        let next_y = self.bounds.min.y + (iy + (step_y > 0) as i32) as f32 * self.cell_size.y;
-        let next_z = self.bounds.min.z + (iz + (step_z > 0) as i32) as f32 * self.cell_size.z;
-
-        let mut t_max_x = if step_x != 0 { t_enter + (next_x - pos.x) / rd.x } else { f32::INFINITY };
-        let mut t_max_y = if step_y != 0 { t_enter + (next_y - pos.y) / rd.y } else { f32::INFINITY };
-        let mut t_max_z = if step_z != 0 { t_enter + (next_z - pos.z) / rd.z } else { f32::INFINITY };
-
-        let t_delta_x = if step_x != 0 { self.cell_size.x / rd.x.abs() } else { f32::INFINITY };
-        let t_delta_y = if step_y != 0 { self.cell_size.y / rd.y.abs() } else { f32::INFINITY };
-        let t_delta_z = if step_z != 0 { self.cell_size.z / rd.z.abs() } else { f32::INFINITY };
 
+        // Cobertura acumulada: los huecos (0) no tapan; las parciales se suman hasta el corte
         let occ_cutoff = 0.5;
+        let mut pass = 1.0f32;
+        let (mut mb, ray) = Mailbox::borrow(objects.len(), 1);
 
-        loop {
-            if ix < 0 || ix >= self.dims[0] || iy < 0 || iy >= self.dims[1] || iz < 0 || iz >= self.dims[2] {
-                break;
-            }
-
-            let cell_idx = self.cell_index(ix, iy, iz);
-            if cell_idx >= self.cells.len() { break; }
-
+        while let Some(cell_idx) = walk.cell(self) {
+            stats::count(|c| c.cells += 1);
             for &obj_idx in &self.cells[cell_idx] {
+                if !mb.0.first_visit(obj_idx, ray) { continue; }
+                if self.sphere_rejects(obj_idx, ro, rd, max_t) { stats::count(|c| c.fast_rejects += 1); continue; }
+                stats::count(|c| c.tests += 1);
                 let i = objects[obj_idx].ray_intersect(ro, rd);
                 if i.is_intersecting && i.distance > eps && i.distance < max_t {
-                    if i.coverage >= occ_cutoff {
+                    pass *= 1.0 - i.coverage.clamp(0.0, 1.0);
+                    if pass <= 1.0 - occ_cutoff {
                         return true;
                     }
                 }
             }
-
-            let t_cell_exit = t_max_x.min(t_max_y).min(t_max_z);
-            if t_cell_exit >= max_t { break; }
-
-            if t_max_x < t_max_y {
-                if t_max_x < t_max_z {
-                    ix += step_x; if ix < 0 || ix >= self.dims[0] { break; }
-                    t_enter = t_max_x; t_max_x += t_delta_x;
-                } else {
-                    iz += step_z; if iz < 0 || iz >= self.dims[2] { break; }
-                    t_enter = t_max_z; t_max_z += t_delta_z;
-                }
-            } else {
-                if t_max_y < t_max_z {
-                    iy += step_y; if iy < 0 || iy >= self.dims[1] { break; }
-                    t_enter = t_max_y; t_max_y += t_delta_y;
-                } else {
-                    iz += step_z; if iz < 0 || iz >= self.dims[2] { break; }
-                    t_enter = t_max_z; t_max_z += t_delta_z;
-                }
-            }
-            if t_enter > t_exit { break; }
+            if walk.t_cell_exit() >= max_t || !walk.advance() { break; }
         }
         false
     }
 
-    pub fn trace_excluding(
-        &self,
-        ro: &Vector3,
-        rd: &Vector3,
-        objects: &[Box<dyn RayIntersect>],
-        exclude: Option<usize>,
-    ) -> Intersect {
-        if self.cells.is_empty() { return Intersect::empty(); }
-
-        let (mut t_enter, t_exit) = match self.bounds.intersect_ray(*ro, *rd) {
-            Some(t) => t, None => return Intersect::empty(),
-        };
-        if t_exit < 0.0 { return Intersect::empty(); }
-        if t_enter < 0.0 { t_enter = 0.0; }
-
+    /// Transmitancia RGB de un rayo de sombra hasta `max_t` (1 = libre, 0 = bloqueado).
+    /// Un hit opaco corta de inmediato; uno transparente (coverage < 1 o albedo[3] > 0) deja
+    /// pasar el hueco `1 - coverage` sin teñir y la parte cubierta filtrada por `albedo[3] * diffuse`
+    /// (en el espacio de trabajo de `gamma`), el mismo reparto que `surface_weights` al sombrear.
+    pub fn transmittance(&self, ro: &Vector3, rd: &Vector3, max_t: f32, objects: &[Box<dyn RayIntersect>], gamma: Gamma) -> Vector3 {
+        profile_scope!(Grid);
+        let clear = Vector3::new(1.0, 1.0, 1.0);
+        let Some(mut walk) = GridWalk::start(self, ro, rd) else { return clear; };
         let eps = 1e-4;
-        let pos = *ro + *rd * t_enter;
-
-        let mut ix = ((pos.x - self.bounds.min.x) / self.cell_size.x).floor() as i32;
-        let mut iy = ((pos.y - self.bounds.min.y) / self.cell_size.y).floor() as i32;
-        let mut iz = ((pos.z - self.bounds.min.z) / self.cell_size.z).floor() as i32;
-        ix = ix.clamp(0, self.dims[0]-1);
-        iy = iy.clamp(0, self.dims[1]-1);
-        iz = iz.clamp(0, self.dims[2]-1);
-
-        let step_x = if rd.x > 0.0 { 1 } else if rd.x < 0.0 { -1 } else { 0 };
-        let step_y = if rd.y > 0.0 { 1 } else if rd.y < 0.0 { -1 } else { 0 };
-        let step_z = if rd.z > 0.0 { 1 } else if rd.z < 0.0 { -1 } else { 0 };
-
-        let next_x = self.bounds.min.x + (ix + (step_x > 0) as i32) as f32 * self.cell_size.x;
-        let next_y = self.bounds.min.y + (iy + (step_y > 0) as i32) as f32 * self.cell_size.y;
-        let next_z = self.bounds.min.z + (iz + (step_z > 0) as i32) as f32 * self.cell_size.z;
-
-        let mut t_max_x = if step_x != 0 { t_enter + (next_x - pos.x) / rd.x } else { f32::INFINITY };
-        let mut t_max_y = if step_y != 0 { t_enter + (next_y - pos.y) / rd.y } else { f32::INFINITY };
-        let mut t_max_z = if step_z != 0 { t_enter + (next_z - pos.z) / rd.z } else { f32::INFINITY };
-
-        let t_delta_x = if step_x != 0 { self.cell_size.x / rd.x.abs() } else { f32::INFINITY };
-        let t_delta_y = if step_y != 0 { self.cell_size.y / rd.y.abs() } else { f32::INFINITY };
-        let t_delta_z = if step_z != 0 { self.cell_size.z / rd.z.abs() } else { f32::INFINITY };
 
-        let mut best = Intersect::empty();
-        let mut best_t = f32::INFINITY;
-
-        loop {
-            if ix < 0 || ix >= self.dims[0] || iy < 0 || iy >= self.dims[1] || iz < 0 || iz >= self.dims[2] {
-                break;
-            }
-            let cell_idx = self.cell_index(ix, iy, iz);
-            if cell_idx >= self.cells.len() { break; }
+        let mut filter = clear;
+        // Un objeto puede caer en varias celdas: el buzón hace que se filtre una sola vez
+        let (mut mb, ray) = Mailbox::borrow(objects.len(), 1);
 
+        while let Some(cell_idx) = walk.cell(self) {
+            stats::count(|c| c.cells += 1);
             for &obj_idx in &self.cells[cell_idx] {
-                if Some(obj_idx) == exclude { continue; }
+                if !mb.0.first_visit(obj_idx, ray) { continue; }
+                if self.sphere_rejects(obj_idx, ro, rd, max_t) { stats::count(|c| c.fast_rejects += 1); continue; }
+                stats::count(|c| c.tests += 1);
                 let mut i = objects[obj_idx].ray_intersect(ro, rd);
-                i.object_index = Some(obj_idx); // ← también aquí
-                if i.is_intersecting && i.distance >= t_enter - 1e-4 && i.distance < best_t {
-                    best_t = i.distance;
-                    best = i;
+                if i.is_intersecting && i.distance > eps && i.distance < max_t {
+                    gamma.resolve(&mut i);
+                    let coverage = i.coverage;
+                    let transmission = ((1.0 - coverage) + i.material.albedo[3] * coverage).clamp(0.0, 1.0);
+                    if transmission <= 0.0 {
+                        return Vector3::zero();
+                    }
+                    let tint = Vector3::one() * (1.0 - coverage) + i.material.diffuse * (i.material.albedo[3] * coverage);
+                    filter = filter * tint;
+                    if filter.x.max(filter.y).max(filter.z) < 1e-3 {
+                        return Vector3::zero();
+                    }
                 }
             }
+            if walk.t_cell_exit() >= max_t || !walk.advance() { break; }
+        }
+        filter
+    }
+}
 
-            let t_cell_exit = t_max_x.min(t_max_y).min(t_max_z);
-            if best_t <= t_cell_exit { break; }
-
-            if t_max_x < t_max_y {
-                if t_max_x < t_max_z { ix += step_x; if ix < 0 || ix >= self.dims[0] { break; } t_enter = t_max_x; t_max_x += t_delta_x; }
-                else { iz += step_z; if iz < 0 || iz >= self.dims[2] { break; } t_enter = t_max_z; t_max_z += t_delta_z; }
-            } else {
-                if t_max_y < t_max_z { iy += step_y; if iy < 0 || iy >= self.dims[1] { break; } t_enter = t_max_y; t_max_y += t_delta_y; }
-                else { iz += step_z; if iz < 0 || iz >= self.dims[2] { break; } t_enter = t_max_z; t_max_z += t_delta_z; }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cube::Cube;
+    use crate::material::Material;
+    use std::collections::BTreeSet;
+
+    fn v(a: [f32; 3]) -> Vector3 { Vector3::new(a[0], a[1], a[2]) }
+
+    const UNIT: Aabb = Aabb {
+        min: Vector3 { x: 0.0, y: 0.0, z: 0.0 },
+        max: Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+    };
+
+    #[test]
+    fn axis_parallel_rays_graze_every_face() {
+        for axis in 0..3 {
+            for other in (0..3).filter(|&o| o != axis) {
+                for face in [0.0, 1.0] {
+                    let (mut ro, mut rd) = ([0.5; 3], [0.0; 3]);
+                    ro[axis] = -1.0;
+                    ro[other] = face;
+                    rd[axis] = 1.0;
+                    assert_eq!(UNIT.intersect_ray(v(ro), v(rd)), Some((1.0, 2.0)), "eje {} rozando {}={}", axis, other, face);
+                    // un poco afuera de la cara ya no toca
+                    ro[other] = if face == 0.0 { -1e-3 } else { 1.0 + 1e-3 };
+                    assert_eq!(UNIT.intersect_ray(v(ro), v(rd)), None);
+                }
             }
-            if t_enter > t_exit { break; }
         }
-
-        best
     }
 
-    pub fn occluded_excluding(
-        &self,
-        ro: &Vector3,
-        rd: &Vector3,
-        max_t: f32,
-        objects: &[Box<dyn RayIntersect>],
-        exclude: Option<usize>,
-    ) -> bool {
-        if self.cells.is_empty() { return false; }
-        let (mut t_enter, t_exit) = match self.bounds.intersect_ray(*ro, *rd) {
-            Some(t) => t, None => return false,
-        };
-        if t_exit < 0.0 { return false; }
-        if t_enter < 0.0 { t_enter = 0.0; }
-        let eps = 1e-4;
-        let pos = *ro + *rd * t_enter;
-
-        let mut ix = ((pos.x - self.bounds.min.x) / self.cell_size.x).floor() as i32;
-        let mut iy = ((pos.y - self.bounds.min.y) / self.cell_size.y).floor() as i32;
-        let mut iz = ((pos.z - self.bounds.min.z) / self.cell_size.z).floor() as i32;
-        ix = ix.clamp(0, self.dims[0]-1);
-        iy = iy.clamp(0, self.dims[1]-1);
-        iz = iz.clamp(0, self.dims[2]-1);
-
-        let step_x = if rd.x > 0.0 { 1 } else if rd.x < 0.0 { -1 } else { 0 };
-        let step_y = if rd.y > 0.0 { 1 } else if rd.y < 0.0 { -1 } else { 0 };
-        let step_z = if rd.z > 0.0 { 1 } else if rd.z < 0.0 { -1 } else { 0 };
-
-        let next_x = self.bounds.min.x + (ix + (step_x > 0) as i32) as f32 * self.cell_size.x;
-        let next_y = self.bounds.min.y + (iy + (step_y > 0) as i32) as f32 * self.cell_size.y;
-        let next_z = self.bounds.min.z + (iz + (step_z > 0) as i32) as f32 * self.cell_size.z;
+    #[test]
+    fn origin_inside() {
+        let (t_enter, t_exit) = UNIT.intersect_ray(v([0.5, 0.5, 0.5]), v([0.0, 0.0, -1.0])).unwrap();
+        assert_eq!((t_enter, t_exit), (-0.5, 0.5));
+        let d = 1.0 / 3f32.sqrt();
+        let (t_enter, t_exit) = UNIT.intersect_ray(v([0.25, 0.5, 0.75]), v([d, d, d])).unwrap();
+        assert!(t_enter < 0.0 && t_exit > 0.0);
+    }
 
-        let mut t_max_x = if step_x != 0 { t_enter + (next_x - pos.x) / rd.x } else { f32::INFINITY };
-        let mut t_max_y = if step_y != 0 { t_enter + (next_y - pos.y) / rd.y } else { f32::INFINITY };
-        let mut t_max_z = if step_z != 0 { t_enter + (next_z - pos.z) / rd.z } else { f32::INFINITY };
+    #[test]
+    fn origin_exactly_on_a_face() {
+        // saliendo y entrando por la cara x = 0, y a lo largo de ella (dirección 0 en x)
+        assert_eq!(UNIT.intersect_ray(v([0.0, 0.5, 0.5]), v([1.0, 0.0, 0.0])), Some((0.0, 1.0)));
+        assert_eq!(UNIT.intersect_ray(v([0.0, 0.5, 0.5]), v([-1.0, 0.0, 0.0])), Some((-1.0, 0.0)));
+        assert_eq!(UNIT.intersect_ray(v([0.0, 0.5, 0.5]), v([0.0, 1.0, 0.0])), Some((-0.5, 0.5)));
+        // en una arista, con dos ejes de dirección 0: antes daba NaN
+        let hit = UNIT.intersect_ray(v([1.0, 1.0, -2.0]), v([0.0, 0.0, 1.0]));
+        assert_eq!(hit, Some((2.0, 3.0)));
+    }
 
-        let t_delta_x = if step_x != 0 { self.cell_size.x / rd.x.abs() } else { f32::INFINITY };
-        let t_delta_y = if step_y != 0 { self.cell_size.y / rd.y.abs() } else { f32::INFINITY };
-        let t_delta_z = if step_z != 0 { self.cell_size.z / rd.z.abs() } else { f32::INFINITY };
+    /// Dos cubos en la misma celda, el lejano primero en `objects`: el índice tiene que ser el
+    /// del hit más cercano, no el del último objeto probado de la celda.
+    #[test]
+    fn nearest_object_index_in_shared_cells() {
+        let mat = Material::new(Vector3::one(), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0);
+        let objects: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(v([2.0, 0.0, 0.0]), v([3.0, 1.0, 1.0]), mat)),
+            Box::new(Cube::new(v([0.0, 0.0, 0.0]), v([1.0, 1.0, 1.0]), mat)),
+        ];
+        let grid = UniformGridAccel::build(&objects, 8.0);
+        assert!(grid.cells.iter().any(|c| c.len() == 2), "los cubos tienen que compartir celda");
+
+        let hit = grid.trace(&v([-5.0, 0.5, 0.5]), &v([1.0, 0.0, 0.0]), &objects);
+        assert!(hit.is_intersecting);
+        assert_eq!(hit.object_index, Some(1));
+        assert!((hit.distance - 5.0).abs() < 1e-4);
+
+        let hit = grid.trace(&v([8.0, 0.5, 0.5]), &v([-1.0, 0.0, 0.0]), &objects);
+        assert_eq!(hit.object_index, Some(0));
+        assert!((hit.distance - 5.0).abs() < 1e-4);
+
+        let ro = [v([-5.0, 0.5, 0.5]), v([8.0, 0.5, 0.5])];
+        let rd = [v([1.0, 0.0, 0.0]), v([-1.0, 0.0, 0.0])];
+        let hits = grid.trace_packet(&ro, &rd, &objects, &[f32::INFINITY; 2], false);
+        assert_eq!(hits.map(|h| h.object_index), [Some(1), Some(0)]);
+    }
 
-        let occ_cutoff = 0.5;
+    /// Fila de cubos en celdas chicas: los recorridos de sombra cruzan varias celdas con el mismo
+    /// `GridWalk` que `trace`.
+    #[test]
+    fn shadow_walks_cross_cells() {
+        let opaque = Material::new(Vector3::one(), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0);
+        let glass = Material::new(Vector3::new(1.0, 0.5, 0.5), 10.0, [0.0, 0.0, 0.0, 1.0], 1.5);
+        let objects: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(v([2.0, 0.0, 0.0]), v([3.0, 1.0, 1.0]), glass)),
+            Box::new(Cube::new(v([6.0, 0.0, 0.0]), v([7.0, 1.0, 1.0]), opaque)),
+        ];
+        let grid = UniformGridAccel::build(&objects, 1.0);
+        let (ro, rd) = (v([0.0, 0.5, 0.5]), v([1.0, 0.0, 0.0]));
+
+        // hasta antes del cubo opaco solo filtra el vidrio (una vez, aunque tenga dos caras)
+        let t = grid.transmittance(&ro, &rd, 5.0, &objects, Gamma { correct: true });
+        assert_eq!((t.x, t.y, t.z), (1.0, 0.5, 0.5));
+        assert_eq!(grid.transmittance(&ro, &rd, 10.0, &objects, Gamma { correct: true }), Vector3::zero());
+        // `occluded` solo mira la cobertura: el vidrio ya tapa
+        assert!(!grid.occluded(&ro, &rd, 1.5, &objects));
+        assert!(grid.occluded(&ro, &rd, 5.0, &objects));
+
+        let hit = grid.trace(&ro, &rd, &objects);
+        assert_eq!(hit.object_index, Some(0));
+        assert!((hit.distance - 2.0).abs() < 1e-4);
+        // rayo que no toca la grilla
+        assert!(!grid.occluded(&v([0.0, 5.0, 0.5]), &rd, 10.0, &objects));
+    }
 
-        loop {
-            if ix < 0 || ix >= self.dims[0] || iy < 0 || iy >= self.dims[1] || iz < 0 || iz >= self.dims[2] {
-                break;
-            }
-            let cell_idx = self.cell_index(ix, iy, iz);
-            if cell_idx >= self.cells.len() { break; }
+    /// Más objetos que `PAR_MIN_OBJECTS`: cada celda queda con los índices en orden y cada
+    /// objeto en todas las celdas que toca su AABB.
+    #[test]
+    fn parallel_build_keeps_cells_sorted() {
+        let mat = Material::new(Vector3::one(), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0);
+        let objects: Vec<Box<dyn RayIntersect>> = (0..3000)
+            .map(|i| {
+                let p = v([(i % 20) as f32, ((i / 20) % 15) as f32, (i / 300) as f32]);
+                Box::new(Cube::new(p, p + v([1.0, 1.0, 1.0]), mat)) as Box<dyn RayIntersect>
+            })
+            .collect();
+        let grid = UniformGridAccel::build(&objects, 2.0);
+        assert!(grid.cells.iter().all(|c| c.windows(2).all(|w| w[0] < w[1])));
+        let refs: usize = grid.cells.iter().map(Vec::len).sum();
+        let expected: usize = grid.aabbs.iter()
+            .map(|a| {
+                let (lo, hi) = cell_span(a, &grid.bounds, grid.cell_size, grid.dims);
+                (0..3).map(|k| (hi[k] - lo[k] + 1) as usize).product::<usize>()
+            })
+            .sum();
+        assert_eq!(refs, expected);
+    }
 
-            for &obj_idx in &self.cells[cell_idx] {
-                if Some(obj_idx) == exclude { continue; }
-                let i = objects[obj_idx].ray_intersect(ro, rd);
-                if i.is_intersecting && i.distance > eps && i.distance < max_t {
-                    if i.coverage >= occ_cutoff { return true; }
+    /// Celdas como conjuntos (el orden dentro de una celda depende de la historia de altas) y
+    /// las que saldrían de repartir de cero las cajas guardadas en la misma grilla.
+    fn cell_sets(grid: &UniformGridAccel) -> (Vec<BTreeSet<usize>>, Vec<BTreeSet<usize>>) {
+        let actual = grid.cells.iter().map(|c| c.iter().copied().collect()).collect();
+        let mut expected = vec![BTreeSet::new(); grid.cells.len()];
+        for (i, a) in grid.aabbs.iter().enumerate() {
+            let (lo, hi) = cell_span(a, &grid.bounds, grid.cell_size, grid.dims);
+            for iz in lo[2]..=hi[2] {
+                for iy in lo[1]..=hi[1] {
+                    for ix in lo[0]..=hi[0] {
+                        expected[grid.cell_index(ix, iy, iz)].insert(i);
+                    }
                 }
             }
+        }
+        (actual, expected)
+    }
 
-            let t_cell_exit = t_max_x.min(t_max_y).min(t_max_z);
-            if t_cell_exit >= max_t { break; }
+    /// 1000 altas (`insert`, que hace crecer la grilla hacia afuera) con una baja
+    /// (`swap_remove`) cada cinco contra `build` sobre la misma lista. `build` ajusta el lado de
+    /// celda a la caja de la escena, así que las grillas no cortan igual: se comparan las cajas
+    /// por objeto y, en cada grilla, cada celda como conjunto contra un reparto de cero.
+    #[test]
+    fn incremental_edits_match_a_fresh_build() {
+        use std::time::Instant;
+
+        let mat = Material::new(Vector3::one(), 10.0, [1.0, 0.0, 0.0, 0.0], 1.0);
+        let block = |p: [i32; 3], long: bool| -> Box<dyn RayIntersect> {
+            let lo = v([p[0] as f32, p[1] as f32, p[2] as f32]);
+            Box::new(Cube::new(lo, lo + v([if long { 2.0 } else { 1.0 }, 1.0, 1.0]), mat))
+        };
+        let mut seed = 0x2545_f491_u32;
+        let mut rand = |n: i32| -> i32 {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            (seed % n as u32) as i32
+        };
 
-            if t_max_x < t_max_y {
-                if t_max_x < t_max_z { ix += step_x; if ix < 0 || ix >= self.dims[0] { break; } t_enter = t_max_x; t_max_x += t_delta_x; }
-                else { iz += step_z; if iz < 0 || iz >= self.dims[2] { break; } t_enter = t_max_z; t_max_z += t_delta_z; }
-            } else {
-                if t_max_y < t_max_z { iy += step_y; if iy < 0 || iy >= self.dims[1] { break; } t_enter = t_max_y; t_max_y += t_delta_y; }
-                else { iz += step_z; if iz < 0 || iz >= self.dims[2] { break; } t_enter = t_max_z; t_max_z += t_delta_z; }
+        let mut objects: Vec<Box<dyn RayIntersect>> = (0..4).map(|i| block([i, 0, 0], false)).collect();
+        let mut grid = UniformGridAccel::build(&objects, 1.0);
+        let first = grid.bounds;
+        let started = Instant::now();
+        for i in 0..1000 {
+            // El radio crece con los pasos: la grilla tiene que crecer por todos los lados
+            let r = 2 + i / 40;
+            let p = [rand(2 * r + 1) - r, rand(r + 1) - r / 2, rand(2 * r + 1) - r];
+            objects.push(block(p, i % 7 == 0));
+            assert!(grid.insert(objects.len() - 1, objects.last().unwrap().as_ref()));
+            if i % 5 == 4 {
+                let gone = rand(objects.len() as i32) as usize;
+                objects.swap_remove(gone);
+                grid.swap_remove(gone);
             }
-            if t_enter > t_exit { break; }
         }
-        false
+        let incremental = started.elapsed();
+        assert!(grid.bounds.min.x < first.min.x && grid.bounds.min.y < first.min.y && grid.bounds.min.z < first.min.z);
+        assert!(grid.bounds.max.x > first.max.x && grid.bounds.max.y > first.max.y && grid.bounds.max.z > first.max.z);
+
+        let started = Instant::now();
+        let fresh = UniformGridAccel::build(&objects, 1.0);
+        let rebuilt = started.elapsed();
+        eprintln!("{} objetos: incremental {:?} en total, build {:?}", objects.len(), incremental, rebuilt);
+
+        assert_eq!(grid.aabbs.len(), fresh.aabbs.len());
+        for (i, (a, b)) in grid.aabbs.iter().zip(fresh.aabbs.iter()).enumerate() {
+            assert!(a.min == b.min && a.max == b.max, "caja del objeto {}", i);
+        }
+        assert_eq!(grid.spheres, fresh.spheres);
+        for g in [&grid, &fresh] {
+            let (actual, expected) = cell_sets(g);
+            assert_eq!(actual, expected);
+        }
+        // Cada objeto de la lista sigue en alguna celda, en las dos
+        let present = |g: &UniformGridAccel| g.cells.iter().flatten().copied().collect::<BTreeSet<usize>>();
+        assert_eq!(present(&grid), (0..objects.len()).collect());
+        assert_eq!(present(&fresh), present(&grid));
+    }
+
+    /// Ventana con textura: el texel transparente (coverage 0) no tiñe la sombra aunque guarde RGB,
+    /// y uno a media cobertura solo filtra su parte cubierta.
+    #[test]
+    fn window_texels_tint_only_their_covered_part() {
+        use std::sync::Arc;
+        use raylib::prelude::Color;
+        use crate::palette::CubeTemplate;
+        use crate::texture::Texture;
+
+        let glass = Material::new(Vector3::one(), 10.0, [0.0, 0.0, 0.0, 1.0], 1.5);
+        let window = |texel: Color| -> Vec<Box<dyn RayIntersect>> {
+            let tpl = CubeTemplate::with_same_texture_image_alpha_window(glass, Arc::new(Texture::from_pixels(1, 1, vec![texel])), 0.05);
+            let mut cube = Cube::new(v([2.0, 0.0, 0.0]), v([3.0, 1.0, 1.0]), glass);
+            cube.set_face_textures_from_template(&tpl.face_textures);
+            vec![Box::new(cube)]
+        };
+        let (ro, rd) = (v([0.0, 0.5, 0.5]), v([1.0, 0.0, 0.0]));
+        let gamma = Gamma { correct: true };
+
+        let clear = window(Color::new(0, 0, 0, 0));
+        let grid = UniformGridAccel::build(&clear, 1.0);
+        assert_eq!(grid.transmittance(&ro, &rd, 5.0, &clear, gamma), Vector3::one());
+
+        let half_red = window(Color::new(255, 0, 0, 128));
+        let grid = UniformGridAccel::build(&half_red, 1.0);
+        let t = grid.transmittance(&ro, &rd, 5.0, &half_red, gamma);
+        let c = 128.0 / 255.0;
+        assert!((t.x - 1.0).abs() < 1e-5 && (t.y - (1.0 - c)).abs() < 1e-5 && t.y == t.z, "{:?}", t);
     }
 }
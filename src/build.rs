@@ -1,8 +1,16 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Instant;
 use raylib::prelude::*;
 use crate::material::Material;
 use crate::ray_intersect::RayIntersect;
 use crate::cube::Cube;
-use crate::palette::CubeTemplate;
+use crate::palette::{BlockShape, CubeTemplate};
+use crate::sphere::Sphere;
+use crate::slab::{Slab, SlabHalf};
+use crate::stairs::Stairs;
+use crate::camera::CameraBasis;
+use crate::tools::{self, BlockId, EditEvent, Pick, RegionPreview, SceneEdit, SlotContent, Tool, ToolConfig};
+use crate::feedback::CellEffect;
 
 /// Sprites del HUD (hotbar estilo Minecraft).
 pub struct HudSprites {
@@ -27,37 +35,67 @@ impl Default for HudConfig {
     }
 }
 
+/// Casillas visibles del hotbar (las del sprite); se muestra la página de 9 que tiene `sel_idx`.
+pub const HOTBAR_SLOTS: usize = 9;
+
+/// Duración del destello sobre el slot elegido con pick-block (segundos).
+const PICK_FLASH_SECS: f32 = 0.35;
+
 /// Estado simple de construcción.
 pub struct BuildState {
-    pub options: Vec<char>,
+    /// Slots del hotbar: bloques o herramientas. Se cambian con `set_slot`, que rehace la
+    /// herramienta del slot.
+    options: Vec<SlotContent>,
+    /// Una herramienta por slot, en el orden de `options`. Viven entre frames: el estado a medias
+    /// (p. ej. una esquina fijada) es de la instancia.
+    tools: Vec<Box<dyn Tool>>,
+    tool_cfg: ToolConfig,
+    /// Último bloque activo: con el que pintan las herramientas de región.
+    brush: Option<BlockId>,
     pub sel_idx: usize,
     pub ghost_center: Option<Vector3>,
     pub cube_size: Vector3,
     pub ghost_mat: Material,
-    pub current_char: char,
     
     pub hud: Option<HudSprites>,
     pub hud_cfg: HudConfig, 
+
+    /// Efectos de feedback pendientes (overlay, avanzan con el frame time).
+    pub effects: Vec<CellEffect>,
+
+    /// Slot prestado por pick-block a un bloque que no estaba en el hotbar: índice, contenido
+    /// original y su ícono. Hay uno solo: el próximo préstamo lo devuelve antes.
+    borrowed: Option<(usize, SlotContent, Option<Texture2D>)>,
+    /// Momento del último pick-block (destello sobre el slot activo).
+    picked_at: Option<Instant>,
 }
 
 impl BuildState {
-    pub fn new(options: Vec<char>, cube_size: Vector3) -> Self {
+    pub fn new(mut options: Vec<SlotContent>, cube_size: Vector3) -> Self {
         let ghost_mat = Material::new(Vector3::new(0.7, 0.85, 1.0), 10.0, [0.95, 0.05, 0.0, 0.0], 0.0);
-        let current_char = options.get(0).copied().unwrap_or('X');
+        if options.is_empty() { options.push(SlotContent::Block('X')); }
+        let tool_cfg = ToolConfig::new(cube_size, Vector3::zero());
+        let tools = options.iter().map(|&s| tools::tool_for(s, tool_cfg)).collect();
+        let brush = options.iter().find_map(|s| match *s { SlotContent::Block(b) => Some(b), _ => None });
         Self {
             options,
+            tools,
+            tool_cfg,
+            brush,
             sel_idx: 0,
             ghost_center: None,
             cube_size,
             ghost_mat,
-            current_char,
             hud: None,
             hud_cfg: HudConfig::default(),
+            effects: Vec::new(),
+            borrowed: None,
+            picked_at: None,
         }
     }
 
     pub fn new_with_sprites_and_cfg(
-        options: Vec<char>, cube_size: Vector3,
+        options: Vec<SlotContent>, cube_size: Vector3,
         hotbar: Texture2D, selection: Texture2D, icons: Vec<Texture2D>,
         hud_cfg: HudConfig,
     ) -> Self {
@@ -67,43 +105,115 @@ impl BuildState {
         s
     }
 
+    /// Rearma todas las herramientas con otra grilla (el origen de la escena cargada).
+    pub fn with_tool_config(mut self, cfg: ToolConfig) -> Self {
+        self.tool_cfg = cfg;
+        self.tools = self.options.iter().map(|&s| tools::tool_for(s, cfg)).collect();
+        self
+    }
+
     #[inline]
-    pub fn current_block_char(&self) -> char {
-        self.options.get(self.sel_idx).copied().unwrap_or('X')
+    pub fn active_slot(&self) -> SlotContent {
+        self.options.get(self.sel_idx).copied().unwrap_or(SlotContent::Block('X'))
+    }
+
+    /// Herramienta del slot activo (colocar bloque también es una).
+    #[inline]
+    pub fn active_tool(&mut self) -> &mut dyn Tool {
+        self.tools[self.sel_idx].as_mut()
+    }
+
+    /// Variante y estado a medias de la herramienta activa, para el HUD.
+    pub fn tool_status(&self) -> Option<String> {
+        self.tools[self.sel_idx].status()
+    }
+
+    #[inline]
+    pub fn brush(&self) -> Option<BlockId> { self.brush }
+
+    /// Pone `content` en el slot `idx` con una herramienta nueva; devuelve lo que había.
+    fn set_slot(&mut self, idx: usize, content: SlotContent) -> SlotContent {
+        self.tools[idx] = tools::tool_for(content, self.tool_cfg);
+        std::mem::replace(&mut self.options[idx], content)
+    }
+
+    /// Activa el slot `idx`; si es de bloque pasa a ser el pincel.
+    fn activate(&mut self, idx: usize) {
+        self.sel_idx = idx;
+        if let SlotContent::Block(b) = self.options[idx] { self.brush = Some(b); }
+    }
+
+    /// Eyedropper: activa el slot que tiene ese bloque. Devuelve false si no está en el hotbar.
+    pub fn select_block(&mut self, block: BlockId) -> bool {
+        match self.options.iter().position(|s| *s == SlotContent::Block(block)) {
+            Some(i) => { self.activate(i); true }
+            None => false,
+        }
+    }
+
+    /// Pick-block (click medio): activa el slot del bloque o, si no está en el hotbar, lo pone en
+    /// el slot activo (en el primer slot de bloque si el activo es una herramienta) prestado hasta
+    /// el próximo préstamo. `make_icon` arma el ícono del bloque prestado (solo con sprites).
+    /// Devuelve el contenido desplazado, si hubo préstamo.
+    pub fn pick_block(&mut self, block: BlockId, make_icon: impl FnOnce() -> Option<Texture2D>) -> Option<SlotContent> {
+        self.picked_at = Some(Instant::now());
+        // Una losa elige la mitad al colocarla: sirve el slot de cualquiera de las dos
+        if self.select_block(block) || slab_twin(block).is_some_and(|twin| self.select_block(twin)) { return None; }
+        if let Some((i, original, icon)) = self.borrowed.take() {
+            self.set_slot(i, original);
+            if let (Some(hud), Some(icon)) = (self.hud.as_mut(), icon) { hud.icons[i] = icon; }
+        }
+        let idx = match self.active_slot() {
+            SlotContent::Block(_) => self.sel_idx,
+            SlotContent::Tool(_) => self.options.iter().position(|s| matches!(s, SlotContent::Block(_)))?,
+        };
+        let old_icon = match (self.hud.as_mut(), make_icon()) {
+            (Some(hud), Some(icon)) if idx < hud.icons.len() => Some(std::mem::replace(&mut hud.icons[idx], icon)),
+            _ => None,
+        };
+        let displaced = self.set_slot(idx, SlotContent::Block(block));
+        self.borrowed = Some((idx, displaced, old_icon));
+        self.activate(idx);
+        Some(displaced)
+    }
+
+    /// 1 justo después de un pick-block, 0 pasados `PICK_FLASH_SECS`.
+    fn pick_flash(&self) -> f32 {
+        self.picked_at.map_or(0.0, |t| (1.0 - t.elapsed().as_secs_f32() / PICK_FLASH_SECS).max(0.0))
+    }
+
+    /// Activa el slot `idx` (si se pasa, el último).
+    pub fn select(&mut self, idx: usize) {
+        if !self.options.is_empty() {
+            self.activate(idx.min(self.options.len() - 1));
+        }
+    }
+
+    /// Activa la casilla `n` (0..`HOTBAR_SLOTS`) de la página visible.
+    pub fn select_on_page(&mut self, n: usize) {
+        let start = (self.sel_idx / HOTBAR_SLOTS) * HOTBAR_SLOTS;
+        self.select(start + n.min(HOTBAR_SLOTS - 1));
     }
 
     pub fn next(&mut self) {
         if !self.options.is_empty() {
-            self.sel_idx = (self.sel_idx + 1) % self.options.len();
-            self.current_char = self.current_block_char();
+            self.activate((self.sel_idx + 1) % self.options.len());
         }
     }
 
     pub fn prev(&mut self) {
         if !self.options.is_empty() {
-            if self.sel_idx == 0 { self.sel_idx = self.options.len() - 1; }
-            else { self.sel_idx -= 1; }
-            self.current_char = self.current_block_char();
+            self.activate(if self.sel_idx == 0 { self.options.len() - 1 } else { self.sel_idx - 1 });
         }
     }
 }
 
-pub fn mouse_ray_dir(
-    mouse: Vector2, width: f32, height: f32, fov: f32,
-    cam_right: Vector3, cam_up: Vector3, cam_forward: Vector3,
-) -> Vector3 {
+/// Rayo (origen, dirección) bajo el mouse; usa la misma proyección que `render`.
+pub fn mouse_ray(mouse: Vector2, width: f32, height: f32, cam: &CameraBasis) -> (Vector3, Vector3) {
     let aspect = width / height;
-    let scale = (fov * 0.5).tan();
     let sx_ndc = (2.0 * (mouse.x)) / width - 1.0;
     let sy_ndc = 1.0 - (2.0 * (mouse.y)) / height;
-    let sx = sx_ndc * aspect * scale;
-    let sy = sy_ndc * scale;
-    let v_cam = Vector3::new(sx, sy, -1.0).normalized();
-    Vector3::new(
-        v_cam.x * cam_right.x + v_cam.y * cam_up.x - v_cam.z * cam_forward.x,
-        v_cam.x * cam_right.y + v_cam.y * cam_up.y - v_cam.z * cam_forward.y,
-        v_cam.x * cam_right.z + v_cam.y * cam_up.z - v_cam.z * cam_forward.z,
-    ).normalized()
+    cam.ray(sx_ndc, sy_ndc, aspect)
 }
 
 #[inline]
@@ -148,9 +258,48 @@ pub fn adjacent_cell_center(hit_point: Vector3, normal: Vector3, cube_size: Vect
     snap_to_grid_center(hit_point + push, cube_size, grid_origin)
 }
 
-pub fn make_block_from_palette(center: Vector3, cube_size: Vector3, tpl: &CubeTemplate) -> Box<dyn RayIntersect> {
+/// Mitad de los caracteres de losa (`_` abajo, `-` arriba), como en las capas ASCII.
+#[inline]
+pub fn slab_half(block: char) -> Option<SlabHalf> {
+    match block {
+        '_' => Some(SlabHalf::Bottom),
+        '-' => Some(SlabHalf::Top),
+        _ => None,
+    }
+}
+
+/// El carácter de la otra mitad de una losa.
+#[inline]
+pub fn slab_twin(block: char) -> Option<char> {
+    match block { '_' => Some('-'), '-' => Some('_'), _ => None }
+}
+
+pub fn make_block_from_palette(center: Vector3, cube_size: Vector3, block: char, tpl: &CubeTemplate) -> Box<dyn RayIntersect> {
+    if let Some(half) = slab_half(block) {
+        let mut slab = Slab::from_block_center_size(center, cube_size, half, tpl.material);
+        slab.set_face_textures_from_template(&tpl.face_textures);
+        slab.block = Some(block);
+        return Box::new(slab);
+    }
+    match tpl.shape {
+        BlockShape::Sphere => {
+            let radius = cube_size.x.min(cube_size.y).min(cube_size.z) * 0.5;
+            let mut sphere = Sphere::new(center, radius, tpl.material);
+            sphere.set_face_textures_from_template(&tpl.face_textures);
+            sphere.block = Some(block);
+            return Box::new(sphere);
+        }
+        BlockShape::Stairs(facing) => {
+            let mut stairs = Stairs::from_block_center_size(center, cube_size, facing, tpl.material);
+            stairs.set_face_textures_from_template(&tpl.face_textures);
+            stairs.block = Some(block);
+            return Box::new(stairs);
+        }
+        BlockShape::Cube => {}
+    }
     let mut cube = Cube::from_center_size(center, cube_size, tpl.material);
     cube.set_face_textures_from_template(&tpl.face_textures);
+    cube.block = Some(block);
     Box::new(cube)
 }
 
@@ -191,7 +340,7 @@ pub fn draw_hud_hotbar(d: &mut RaylibDrawHandle, state: &BuildState, screen_w: i
     let hb_dst = Rectangle { x:hb_x, y:hb_y, width:hb_w, height:hb_h };
     d.draw_texture_pro(&hud.hotbar, hb_src, hb_dst, Vector2::zero(), 0.0, Color::WHITE);
 
-    let slots = 9usize;
+    let slots = HOTBAR_SLOTS;
     let pitch = hb_w / slots as f32;      // distancia entre centros
     let cx0   = hb_x + pitch * 0.5;
     let cy    = hb_y + hb_h * 0.5;
@@ -228,6 +377,21 @@ pub fn draw_hud_hotbar(d: &mut RaylibDrawHandle, state: &BuildState, screen_w: i
     let sel_src = Rectangle { x:0.0, y:0.0, width:hud.selection.width() as f32, height:hud.selection.height() as f32 };
     let sel_dst = Rectangle { x:sel_cx - sel_w * 0.5, y:sel_cy - sel_h * 0.5, width:sel_w, height:sel_h };
     d.draw_texture_pro(&hud.selection, sel_src, sel_dst, Vector2::zero(), 0.0, Color::WHITE);
+    let flash = state.pick_flash();
+    if flash > 0.0 {
+        d.draw_rectangle_rec(
+            Rectangle { x: sel_cx - pitch * 0.5, y: hb_y, width: pitch, height: hb_h },
+            Color::new(255, 255, 255, (flash * 150.0) as u8),
+        );
+    }
+
+    // Nombre del slot activo sobre el hotbar (las herramientas de región, con su bloque)
+    let label = match (state.active_slot(), state.brush) {
+        (SlotContent::Tool(kind), Some(b)) if kind.uses_brush() => format!("{} · '{}'", kind.name(), b),
+        (slot, _) => slot.label(),
+    };
+    let label_w = d.measure_text(&label, 18);
+    d.draw_text(&label, (screen_w - label_w) / 2, (hb_y - 26.0) as i32, 18, Color::WHITE);
 
     if total > slots {
         let page = start / slots + 1;
@@ -246,8 +410,8 @@ pub fn draw_hud_text(d: &mut RaylibDrawHandle, state: &BuildState) {
     d.draw_text("Bloque [Q/E]:", x, y, 14, Color::LIGHTGRAY);
     y += 18;
 
-    for (idx, ch) in state.options.iter().enumerate() {
-        let line = format!("{} {}", if idx == state.sel_idx { "➤" } else { "  " }, ch);
+    for (idx, slot) in state.options.iter().enumerate() {
+        let line = format!("{} {}", if idx == state.sel_idx { "➤" } else { "  " }, slot.label());
         let col = if idx == state.sel_idx { Color::WHITE } else { Color::GRAY };
         d.draw_text(&line, x, y, 18, col);
         y += 20;
@@ -258,3 +422,511 @@ pub fn draw_hud_text(d: &mut RaylibDrawHandle, state: &BuildState) {
     d.draw_text("Click der: quitar",  x, y, 14, Color::LIGHTGRAY); y += 16;
     d.draw_text("J/L/I/K: rotar luz",  x, y, 14, Color::LIGHTGRAY);
 }
+
+/// Colores de luz predefinidos (nombre, RGB): de vela a luz de luna.
+pub const LIGHT_COLOR_PRESETS: [(&str, Color); 6] = [
+    ("Blanca", Color { r: 255, g: 255, b: 255, a: 255 }),
+    ("Vela", Color { r: 255, g: 147, b: 41, a: 255 }),
+    ("Cálida", Color { r: 255, g: 197, b: 143, a: 255 }),
+    ("Día", Color { r: 255, g: 244, b: 229, a: 255 }),
+    ("Fría", Color { r: 201, g: 226, b: 255, a: 255 }),
+    ("Luna", Color { r: 150, g: 170, b: 255, a: 255 }),
+];
+
+/// Paso por pulsación al empujar un canal.
+const LIGHT_CHANNEL_STEP: i32 = 15;
+
+/// Edición en vivo del color de la luz: preset actual y canal RGB seleccionado.
+/// Escribe directo en `Light::color`, que es lo que leen difuso, especular y glints.
+pub struct LightingUi {
+    /// Último preset aplicado; None si el color se editó a mano.
+    pub preset: Option<usize>,
+    /// 0 = R, 1 = G, 2 = B.
+    pub channel: usize,
+}
+
+impl LightingUi {
+    pub fn new() -> Self {
+        Self { preset: Some(0), channel: 0 }
+    }
+
+    /// Avanza (o retrocede con `back`) al siguiente preset y lo aplica.
+    pub fn cycle_preset(&mut self, color: &mut Color, back: bool) {
+        let n = LIGHT_COLOR_PRESETS.len();
+        let i = match (self.preset, back) {
+            (Some(i), false) => (i + 1) % n,
+            (Some(i), true) => (i + n - 1) % n,
+            (None, _) => 0,
+        };
+        self.preset = Some(i);
+        *color = LIGHT_COLOR_PRESETS[i].1;
+    }
+
+    pub fn next_channel(&mut self) { self.channel = (self.channel + 1) % 3; }
+
+    /// Sube/baja el canal seleccionado `steps` pasos (saturando en 0..255).
+    pub fn nudge(&mut self, color: &mut Color, steps: i32) {
+        let ch = match self.channel { 0 => &mut color.r, 1 => &mut color.g, _ => &mut color.b };
+        *ch = (*ch as i32 + steps * LIGHT_CHANNEL_STEP).clamp(0, 255) as u8;
+        self.preset = None;
+    }
+
+    /// Línea del HUD con muestra del color: "Color luz [...]: Cálida (255,197,143) canal R".
+    pub fn draw(&self, d: &mut RaylibDrawHandle, color: Color, x: i32, y: i32) {
+        let name = self.preset.map_or("Manual", |i| LIGHT_COLOR_PRESETS[i].0);
+        d.draw_rectangle(x - 1, y - 1, 16, 16, Color::DARKGRAY);
+        d.draw_rectangle(x, y, 14, 14, Color { a: 255, ..color });
+        let txt = format!(
+            "Color luz [T preset, G canal, ; / ']: {} ({},{},{}) canal {}",
+            name, color.r, color.g, color.b, ["R", "G", "B"][self.channel],
+        );
+        d.draw_text(&txt, x + 20, y, 14, Color::LIGHTGRAY);
+    }
+}
+
+/// Celdas máximas de un relleno (evita congelar la app con una caja gigante por error).
+pub const FILL_MAX_CELLS: usize = 32 * 32 * 32;
+/// Tope de celdas de una línea (largo en pasos de cara + 1).
+pub const LINE_MAX_CELLS: usize = 256;
+
+/// Índice entero de la celda que contiene `p` (misma grilla que `snap_to_grid_center`).
+#[inline]
+pub fn cell_index(p: Vector3, size: Vector3, origin: Vector3) -> (i32, i32, i32) {
+    let rel = p - origin;
+    ((rel.x / size.x).floor() as i32, (rel.y / size.y).floor() as i32, (rel.z / size.z).floor() as i32)
+}
+
+/// Centro de mundo de la celda `c`.
+#[inline]
+pub fn cell_index_center(c: (i32, i32, i32), size: Vector3, origin: Vector3) -> Vector3 {
+    Vector3::new(
+        origin.x + (c.0 as f32 + 0.5) * size.x,
+        origin.y + (c.1 as f32 + 0.5) * size.y,
+        origin.z + (c.2 as f32 + 0.5) * size.z,
+    )
+}
+
+/// Celdas de la caja entre las esquinas `a` y `b` (inclusivas, en cualquier orden).
+/// Con `hollow` solo el cascarón: las celdas que tocan alguna cara de la caja.
+pub fn box_cells(a: (i32, i32, i32), b: (i32, i32, i32), hollow: bool) -> Vec<(i32, i32, i32)> {
+    let (x0, x1) = (a.0.min(b.0), a.0.max(b.0));
+    let (y0, y1) = (a.1.min(b.1), a.1.max(b.1));
+    let (z0, z1) = (a.2.min(b.2), a.2.max(b.2));
+    let mut cells = Vec::new();
+    for y in y0..=y1 {
+        for z in z0..=z1 {
+            for x in x0..=x1 {
+                let shell = x == x0 || x == x1 || y == y0 || y == y1 || z == z0 || z == z1;
+                if !hollow || shell { cells.push((x, y, z)); }
+            }
+        }
+    }
+    cells
+}
+
+/// Celdas de la línea de `a` a `b` (inclusivas) unidas por caras: una diagonal sale en escalera
+/// y una vertical es un pilar. DDA por centros de celda: en cada paso avanza el eje cuyo próximo
+/// borde queda más cerca sobre el segmento (empates: x, después y, después z).
+pub fn line_cells(a: (i32, i32, i32), b: (i32, i32, i32)) -> Vec<(i32, i32, i32)> {
+    let d = [b.0 - a.0, b.1 - a.1, b.2 - a.2];
+    let len = d.map(i32::abs);
+    let mut c = [a.0, a.1, a.2];
+    let mut taken = [0i32; 3];
+    let mut cells = vec![a];
+    for _ in 0..len.iter().sum::<i32>() {
+        let axis = (0..3).filter(|&i| taken[i] < len[i])
+            .min_by(|&i, &j| {
+                let t = |k: usize| (taken[k] as f32 + 0.5) / len[k] as f32;
+                t(i).total_cmp(&t(j))
+            })
+            .expect("quedan pasos");
+        c[axis] += d[axis].signum();
+        taken[axis] += 1;
+        cells.push((c[0], c[1], c[2]));
+    }
+    cells
+}
+
+/// Forma de la región entre las dos esquinas.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillShape {
+    Box,
+    Line,
+}
+
+impl FillShape {
+    pub fn name(self) -> &'static str {
+        match self { FillShape::Box => "caja", FillShape::Line => "línea" }
+    }
+}
+
+/// Relleno de caja (slot `ToolKind::Fill`) o línea (`ToolKind::Line`): el click izquierdo fija
+/// una esquina en la celda adyacente apuntada, el segundo la esquina opuesta y se llena la región
+/// con el pincel. Con el click derecho las esquinas son los bloques apuntados y la región se vacía.
+pub struct FillTool {
+    /// Solo el cascarón (paredes, piso y techo) en vez de la caja llena.
+    pub hollow: bool,
+    /// Caja o línea de una esquina a la otra (puentes, pilares); fija por slot.
+    shape: FillShape,
+    /// Esquina fijada y si la caja borra (se decide al fijarla).
+    first: Option<((i32, i32, i32), bool)>,
+    cfg: ToolConfig,
+}
+
+/// Lo que pasó al marcar una esquina.
+pub enum FillStep {
+    /// Primera esquina fijada; falta la opuesta.
+    Armed,
+    /// Segunda esquina: celdas a llenar.
+    Region(Vec<(i32, i32, i32)>),
+    /// Segunda esquina en modo borrado: celdas a vaciar.
+    Clear(Vec<(i32, i32, i32)>),
+    /// La región supera `FILL_MAX_CELLS` (`LINE_MAX_CELLS` en línea): se descarta y hay que
+    /// empezar de nuevo.
+    TooLarge(usize),
+}
+
+impl FillTool {
+    pub fn new(cfg: ToolConfig) -> Self { FillTool { hollow: false, shape: FillShape::Box, first: None, cfg } }
+
+    pub fn line(cfg: ToolConfig) -> Self { FillTool { shape: FillShape::Line, ..Self::new(cfg) } }
+
+    /// Modo de la caja a medias (None si no hay esquina fijada).
+    #[inline]
+    pub fn pending_removal(&self) -> Option<bool> { self.first.map(|(_, r)| r) }
+
+    /// Marca la celda `cell` como esquina; `removing` solo cuenta en la primera.
+    pub fn mark(&mut self, cell: (i32, i32, i32), removing: bool) -> FillStep {
+        let Some((first, removing)) = self.first.take() else {
+            self.first = Some((cell, removing));
+            return FillStep::Armed;
+        };
+        let (dims, max) = match self.shape {
+            FillShape::Box => (((first.0 - cell.0).abs() + 1) as usize
+                * ((first.1 - cell.1).abs() + 1) as usize
+                * ((first.2 - cell.2).abs() + 1) as usize, FILL_MAX_CELLS),
+            FillShape::Line => (((first.0 - cell.0).abs() + (first.1 - cell.1).abs() + (first.2 - cell.2).abs() + 1) as usize, LINE_MAX_CELLS),
+        };
+        if dims > max { return FillStep::TooLarge(dims); }
+        let cells = self.cells(first, cell);
+        if removing { FillStep::Clear(cells) } else { FillStep::Region(cells) }
+    }
+
+    /// Celdas de la región actual entre `a` y `b`, sin tope (la vista previa de la línea).
+    pub fn cells(&self, a: (i32, i32, i32), b: (i32, i32, i32)) -> Vec<(i32, i32, i32)> {
+        match self.shape {
+            FillShape::Box => box_cells(a, b, self.hollow),
+            FillShape::Line => line_cells(a, b),
+        }
+    }
+
+    /// Celda de esquina bajo el cursor: la adyacente a la cara al llenar, el bloque apuntado
+    /// (un poco hacia adentro de la cara) al borrar.
+    fn corner_cell(&self, pick: &Pick, removing: bool) -> Option<(i32, i32, i32)> {
+        let ToolConfig { cube_size, grid_origin, .. } = self.cfg;
+        if removing {
+            pick.object_index.map(|_| cell_index(pick.point - pick.normal * 1e-3, cube_size, grid_origin))
+        } else {
+            Some(cell_index(pick.place_center, cube_size, grid_origin))
+        }
+    }
+
+    fn click(&mut self, pick: &Pick, edit: &mut SceneEdit, removing: bool) {
+        let removing = self.pending_removal().unwrap_or(removing);
+        let Some(cell) = self.corner_cell(pick, removing) else { return; };
+        let brush = edit.brush;
+        if !removing && brush.is_none() {
+            edit.warn("El relleno usa el último bloque elegido: elegí uno en el hotbar");
+            return;
+        }
+        let name = self.shape.name();
+        match self.mark(cell, removing) {
+            FillStep::Armed if removing => edit.info(format!("Borrado en {}: esquina fijada, click en la esquina opuesta (Esc cancela)", name)),
+            FillStep::Armed => edit.info(format!("Relleno en {}: esquina fijada, click en la esquina opuesta (Esc cancela)", name)),
+            FillStep::TooLarge(n) => edit.warn(format!(
+                "Relleno cancelado: {} celdas (máximo {})", n,
+                if self.shape == FillShape::Line { LINE_MAX_CELLS } else { FILL_MAX_CELLS },
+            )),
+            FillStep::Region(cells) => {
+                let block = brush.expect("se revisó al entrar");
+                let (placed, skipped) = fill_cells(edit, &cells, block, self.cfg.grid_origin);
+                edit.info(format!("Relleno en {}: {} bloques '{}' ({} celdas ocupadas o fuera de límites)", name, placed, block, skipped));
+            }
+            FillStep::Clear(cells) => {
+                let removed = clear_cells(edit, &cells, self.cfg.grid_origin);
+                edit.info(format!("Borrado en {}: {} bloques quitados", name, removed));
+            }
+        }
+    }
+}
+
+impl Tool for FillTool {
+    fn on_primary(&mut self, pick: &Pick, edit: &mut SceneEdit) { self.click(pick, edit, false); }
+
+    fn on_secondary(&mut self, pick: &Pick, edit: &mut SceneEdit) { self.click(pick, edit, true); }
+
+    fn cancel(&mut self) -> bool { self.first.take().is_some() }
+
+    /// Caja llena / hueca (la línea no tiene variantes).
+    fn cycle_mode(&mut self) {
+        if self.shape == FillShape::Box { self.hollow = !self.hollow; }
+    }
+
+    fn status(&self) -> Option<String> {
+        let shape = match (self.shape, self.hollow) {
+            (FillShape::Line, _) => "línea",
+            (FillShape::Box, true) => "caja hueca",
+            (FillShape::Box, false) => "caja llena",
+        };
+        let pending = match self.pending_removal() {
+            Some(true) => " (borrando, esquina fijada)",
+            Some(false) => " (esquina fijada)",
+            None => "",
+        };
+        Some(format!("{}{}", shape, pending))
+    }
+
+    fn region(&self, pick: Option<&Pick>) -> Option<RegionPreview> {
+        let (first, removing) = self.first?;
+        let hover = pick.and_then(|p| self.corner_cell(p, removing)).unwrap_or(first);
+        Some(match self.shape {
+            FillShape::Box => RegionPreview::Box { a: first, b: hover, removing },
+            // Pasado el tope no se dibuja celda por celda: el toast avisa al confirmar
+            FillShape::Line => RegionPreview::Cells {
+                cells: line_cells(first, hover).into_iter().take(LINE_MAX_CELLS).collect(), removing,
+            },
+        })
+    }
+}
+
+/// Coloca `block` en todas las celdas libres de `cells` sin buscar objeto por objeto: la ocupación
+/// se arma una vez. Cada bloque entra por `SceneEdit::push` (grilla incremental si hay una).
+/// Devuelve (colocados, salteados por estar ocupados o fuera de límites).
+pub fn fill_cells(edit: &mut SceneEdit, cells: &[(i32, i32, i32)], block: BlockId, grid_origin: Vector3) -> (usize, usize) {
+    let Some(tpl) = edit.palette.get(block) else { return (0, cells.len()); };
+    let size = edit.cube_size;
+    let occupied: HashSet<(i32, i32, i32)> = edit.objects.iter()
+        .map(|o| { let (mn, mx) = o.aabb(); cell_index((mn + mx) * 0.5, size, grid_origin) })
+        .collect();
+    let (mut placed, mut skipped) = (0, 0);
+    for &c in cells.iter() {
+        let center = cell_index_center(c, size, grid_origin);
+        if occupied.contains(&c) || !SceneEdit::in_bounds(center) { skipped += 1; continue; }
+        edit.push(make_block_from_palette(center, size, block, tpl));
+        edit.events.push(EditEvent::Placed { center, sound: tpl.sound });
+        placed += 1;
+    }
+    (placed, skipped)
+}
+
+/// Quita los bloques de paleta cuyo centro cae en `cells` (las mallas y objetos sin bloque se
+/// quedan). Se borran de índice mayor a menor, así cada `swap_remove` solo mueve objetos ya
+/// revisados. Devuelve cuántos quitó.
+pub fn clear_cells(edit: &mut SceneEdit, cells: &[(i32, i32, i32)], grid_origin: Vector3) -> usize {
+    let wanted: HashSet<(i32, i32, i32)> = cells.iter().copied().collect();
+    let size = edit.cube_size;
+    let doomed: Vec<usize> = edit.objects.iter().enumerate()
+        .filter(|(_, o)| o.block_id().is_some())
+        .filter(|(_, o)| { let (mn, mx) = o.aabb(); wanted.contains(&cell_index((mn + mx) * 0.5, size, grid_origin)) })
+        .map(|(i, _)| i)
+        .collect();
+    for &i in doomed.iter().rev() { edit.remove(i); }
+    doomed.len()
+}
+
+/// Tope por defecto del relleno por inundación (`--flood-max`).
+pub const FLOOD_MAX_DEFAULT: usize = 4096;
+
+/// Índices de los objetos con el bloque `from` (reemplazar todos).
+pub fn blocks_of(objects: &[Box<dyn RayIntersect>], from: BlockId) -> Vec<usize> {
+    objects.iter().enumerate().filter(|(_, o)| o.block_id() == Some(from)).map(|(i, _)| i).collect()
+}
+
+/// Región conexa por caras (6 vecinos) del mismo bloque que `start`, en orden de distancia.
+/// Corta en `max` objetos; el bool dice si quedó región sin recorrer.
+pub fn flood_region(
+    objects: &[Box<dyn RayIntersect>], start: usize, size: Vector3, grid_origin: Vector3, max: usize,
+) -> (Vec<usize>, bool) {
+    let Some(block) = objects.get(start).and_then(|o| o.block_id()) else { return (Vec::new(), false); };
+    let cell_of = |o: &Box<dyn RayIntersect>| { let (mn, mx) = o.aabb(); cell_index((mn + mx) * 0.5, size, grid_origin) };
+    let cells: HashMap<(i32, i32, i32), usize> = objects.iter().enumerate()
+        .filter(|(_, o)| o.block_id() == Some(block))
+        .map(|(i, o)| (cell_of(o), i))
+        .collect();
+    let first = cell_of(&objects[start]);
+    let mut seen = HashSet::from([first]);
+    let mut queue = VecDeque::from([first]);
+    let mut region = Vec::new();
+    while let Some(c) = queue.pop_front() {
+        if region.len() == max { return (region, true); }
+        region.push(cells[&c]);
+        for (dx, dy, dz) in [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0), (0, 0, 1), (0, 0, -1)] {
+            let n = (c.0 + dx, c.1 + dy, c.2 + dz);
+            if cells.contains_key(&n) && seen.insert(n) { queue.push_back(n); }
+        }
+    }
+    (region, false)
+}
+
+/// Slots `ToolKind::Replace` / `ToolKind::Flood`: el click izquierdo repinta con el pincel todos
+/// los bloques del tipo apuntado, o solo su región conexa (hasta `ToolConfig::flood_max`). Cada
+/// bloque se cambia en su lugar y la grilla se reconstruye una vez. El click derecho no hace nada.
+pub struct ReplaceTool {
+    pub flood: bool,
+    pub cfg: ToolConfig,
+}
+
+impl Tool for ReplaceTool {
+    fn on_primary(&mut self, pick: &Pick, edit: &mut SceneEdit) {
+        let target = pick.object_index.and_then(|i| edit.objects[i].block_id().map(|b| (i, b)));
+        match (edit.brush, target) {
+            (None, _) => edit.warn("Reemplazar usa el último bloque elegido: elegí uno en el hotbar"),
+            (_, None) => edit.warn("Reemplazar: apuntá a un bloque de la paleta"),
+            (Some(to), Some((_, from))) if to == from => edit.info(format!("Reemplazar: ya es '{}'", to)),
+            (Some(to), Some((start, from))) => {
+                let ToolConfig { cube_size, grid_origin, flood_max } = self.cfg;
+                let (indices, truncated) = if self.flood {
+                    flood_region(edit.objects, start, cube_size, grid_origin, flood_max)
+                } else {
+                    (blocks_of(edit.objects, from), false)
+                };
+                for &i in indices.iter() { edit.replace(i, to, grid_origin); }
+                edit.info(format!(
+                    "{}: {} bloques '{}' → '{}'{}",
+                    if self.flood { "Inundación" } else { "Reemplazar todos" }, indices.len(), from, to,
+                    if truncated { format!(" (tope de {}, --flood-max)", flood_max) } else { String::new() },
+                ));
+            }
+        }
+    }
+
+    fn on_secondary(&mut self, _pick: &Pick, _edit: &mut SceneEdit) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::palette::{Palette, SoundCategory};
+
+    const CELL: Vector3 = Vector3 { x: 1.0, y: 1.0, z: 1.0 };
+
+    fn palette() -> Palette {
+        let material = Material::new(Vector3::new(0.5, 0.5, 0.5), 10.0, [1.0, 0.0, 0.0, 0.0], 0.0);
+        let mut palette = Palette::new();
+        palette.set('X', CubeTemplate { material, face_textures: Default::default(), sound: SoundCategory::Stone, shape: BlockShape::Cube });
+        palette
+    }
+
+    /// Cursor sobre la cara de arriba del bloque de la celda `c` (colocaría en la de encima).
+    fn pick_top_of(c: (i32, i32, i32), object_index: Option<usize>) -> Pick {
+        let center = cell_index_center(c, CELL, Vector3::zero());
+        Pick {
+            point: center + Vector3::new(0.0, 0.5, 0.0),
+            normal: Vector3::new(0.0, 1.0, 0.0),
+            object_index,
+            place_center: center + Vector3::new(0.0, 1.0, 0.0),
+        }
+    }
+
+    #[test]
+    fn fill_tool_keeps_corner_between_clicks() {
+        let palette = palette();
+        let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+        let mut tool = FillTool::new(ToolConfig::new(CELL, Vector3::zero()));
+
+        // primera esquina en (0,1,0), la opuesta en (2,1,1): 3×1×2 celdas
+        let mut edit = SceneEdit::new(&mut objects, &palette, CELL).with_brush(Some('X'));
+        tool.on_primary(&pick_top_of((0, 0, 0), None), &mut edit);
+        assert!(!edit.dirty);
+        assert_eq!(tool.pending_removal(), Some(false));
+        let mut edit = SceneEdit::new(&mut objects, &palette, CELL).with_brush(Some('X'));
+        tool.on_primary(&pick_top_of((2, 0, 1), None), &mut edit);
+        assert_eq!(edit.events.len(), 6);
+        assert_eq!(tool.pending_removal(), None);
+        assert_eq!(objects.len(), 6);
+
+        // click derecho: las esquinas son los bloques apuntados y la región se vacía
+        let mut edit = SceneEdit::new(&mut objects, &palette, CELL);
+        tool.on_secondary(&pick_top_of((0, 1, 0), Some(0)), &mut edit);
+        tool.on_primary(&pick_top_of((1, 1, 1), Some(0)), &mut edit);
+        assert_eq!(objects.len(), 2);
+    }
+
+    #[test]
+    fn line_tool_builds_a_staircase() {
+        let palette = palette();
+        let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+        let mut tool = FillTool::line(ToolConfig::new(CELL, Vector3::zero()));
+        let mut edit = SceneEdit::new(&mut objects, &palette, CELL).with_brush(Some('X'));
+        tool.on_primary(&pick_top_of((0, 0, 0), None), &mut edit);
+        // de (0,1,0) a (3,4,0): 3 pasos en x y 3 en y, unidos por caras
+        tool.on_primary(&pick_top_of((3, 3, 0), None), &mut edit);
+        assert_eq!(edit.events.len(), 7);
+        // la línea no tiene variante hueca
+        tool.cycle_mode();
+        assert!(!tool.hollow);
+    }
+
+    #[test]
+    fn flood_tool_repaints_only_the_connected_region() {
+        let mut palette = palette();
+        let tpl = palette.get('X').unwrap().clone();
+        palette.set('S', tpl.clone());
+        // dos tiras de 'X' separadas por una celda vacía
+        let mut objects: Vec<Box<dyn RayIntersect>> = [0, 1, 2, 4, 5].iter()
+            .map(|&x| make_block_from_palette(cell_index_center((x, 0, 0), CELL, Vector3::zero()), CELL, 'X', &tpl))
+            .collect();
+        let cfg = ToolConfig::new(CELL, Vector3::zero());
+        let mut edit = SceneEdit::new(&mut objects, &palette, CELL).with_brush(Some('S'));
+        ReplaceTool { flood: true, cfg }.on_primary(&pick_top_of((1, 0, 0), Some(1)), &mut edit);
+        assert!(edit.rebuild_grid);
+        let ids: Vec<_> = objects.iter().map(|o| o.block_id().unwrap()).collect();
+        assert_eq!(ids, ['S', 'S', 'S', 'X', 'X']);
+
+        let mut edit = SceneEdit::new(&mut objects, &palette, CELL).with_brush(Some('S'));
+        ReplaceTool { flood: false, cfg }.on_primary(&pick_top_of((4, 0, 0), Some(3)), &mut edit);
+        assert!(objects.iter().all(|o| o.block_id() == Some('S')));
+    }
+
+    #[test]
+    fn fill_tool_needs_a_brush_and_cancels() {
+        let palette = palette();
+        let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+        let mut tool = FillTool::new(ToolConfig::new(CELL, Vector3::zero()));
+        let mut edit = SceneEdit::new(&mut objects, &palette, CELL);
+        tool.on_primary(&pick_top_of((0, 0, 0), None), &mut edit);
+        assert_eq!(tool.pending_removal(), None);
+        assert_eq!(edit.notes.len(), 1);
+
+        let mut edit = edit.with_brush(Some('X'));
+        tool.on_primary(&pick_top_of((0, 0, 0), None), &mut edit);
+        assert!(tool.region(None).is_some());
+        assert!(tool.cancel());
+        assert!(!tool.cancel());
+        assert!(tool.region(None).is_none());
+    }
+
+    /// 20 slots: páginas de 9, 9 y 2. Un número sin casilla en la última página elige la última.
+    #[test]
+    fn select_clamps_and_last_page_is_partial() {
+        let options = ('a'..='t').map(SlotContent::Block).collect();
+        let mut state = BuildState::new(options, CELL);
+
+        state.select(100);
+        assert_eq!(state.sel_idx, 19);
+        assert_eq!(state.brush(), Some('t'));
+
+        state.select_on_page(0);
+        assert_eq!(state.sel_idx, 18);
+        state.select_on_page(5);
+        assert_eq!(state.sel_idx, 19);
+        state.select_on_page(50);
+        assert_eq!(state.sel_idx, 19);
+
+        // En una página completa la casilla es directa
+        state.select(3);
+        state.select_on_page(8);
+        assert_eq!(state.sel_idx, 8);
+        assert_eq!(state.brush(), Some('i'));
+    }
+}
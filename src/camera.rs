@@ -14,6 +14,9 @@ pub struct CameraConfig {
     /// Límites de distancia (zoom). min>0.
     pub min_distance: f32,
     pub max_distance: f32,
+    /// Rapidez (1/s) con que yaw/pitch/distancia se acercan a su objetivo en `update`.
+    /// 0 = sin suavizado: la órbita y el zoom se aplican al instante.
+    pub smoothing: f32,
 }
 
 impl Default for CameraConfig {
@@ -26,20 +29,140 @@ impl Default for CameraConfig {
             max_pitch:   1.45,   // ~  83°
             min_distance: 0.25,
             max_distance: 5000.0,
+            smoothing: 12.0,
         }
     }
 }
 
+/// Proyección de la cámara.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Projection {
+    /// `fov` vertical en radianes.
+    Perspective { fov: f32 },
+    /// `height`: alto visible del plano de imagen en unidades de mundo.
+    Orthographic { height: f32 },
+}
+
+/// FOV vertical por defecto y límites del ajuste en vivo (radianes).
+pub const DEFAULT_FOV: f32 = std::f32::consts::PI / 3.0;
+pub const MIN_FOV: f32 = 20.0 * std::f32::consts::PI / 180.0;
+pub const MAX_FOV: f32 = 120.0 * std::f32::consts::PI / 180.0;
+
+impl Default for Projection {
+    fn default() -> Self { Projection::Perspective { fov: DEFAULT_FOV } }
+}
+
 /// Base precomputada para “ray directions”.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq)]
 pub struct CameraBasis {
     pub eye: Vector3,
     pub forward: Vector3,
     pub right: Vector3,
     pub up: Vector3,
+    pub projection: Projection,
+}
+
+impl CameraBasis {
+    /// Rayo primario (origen, dirección) para coordenadas NDC (x a la derecha, y arriba, en [-1, 1]).
+    /// En ortográfica todos comparten `forward` y el origen se desplaza sobre el plano de imagen.
+    #[inline]
+    pub fn ray(&self, sx_ndc: f32, sy_ndc: f32, aspect: f32) -> (Vector3, Vector3) {
+        match self.projection {
+            Projection::Perspective { fov } => {
+                let scale = (fov * 0.5).tan();
+                let v_cam = Vector3::new(sx_ndc * aspect * scale, sy_ndc * scale, -1.0).normalized();
+                let dir = Vector3::new(
+                    v_cam.x * self.right.x + v_cam.y * self.up.x - v_cam.z * self.forward.x,
+                    v_cam.x * self.right.y + v_cam.y * self.up.y - v_cam.z * self.forward.y,
+                    v_cam.x * self.right.z + v_cam.y * self.up.z - v_cam.z * self.forward.z,
+                );
+                (self.eye, dir)
+            }
+            Projection::Orthographic { height } => {
+                let half_h = height * 0.5;
+                let origin = self.eye
+                    + self.right * (sx_ndc * aspect * half_h)
+                    + self.up * (sy_ndc * half_h);
+                (origin, self.forward)
+            }
+        }
+    }
+
+    /// Inversa de `ray`: punto de mundo → NDC. None si queda detrás del plano de la cámara.
+    #[inline]
+    pub fn project(&self, p: Vector3, aspect: f32) -> Option<(f32, f32)> {
+        self.project_depth(p, aspect).map(|(x, y, _)| (x, y))
+    }
+
+    /// Como `project`, más la profundidad de vista (distancia a lo largo de `forward`).
+    #[inline]
+    pub fn project_depth(&self, p: Vector3, aspect: f32) -> Option<(f32, f32, f32)> {
+        let v = p - self.eye;
+        let z = v.dot(self.forward);
+        match self.projection {
+            Projection::Perspective { fov } => {
+                if z <= 1e-3 { return None; }
+                let scale = (fov * 0.5).tan();
+                Some((v.dot(self.right) / (z * scale * aspect), v.dot(self.up) / (z * scale), z))
+            }
+            Projection::Orthographic { height } => {
+                if z <= 0.0 { return None; }
+                let half_h = height * 0.5;
+                Some((v.dot(self.right) / (half_h * aspect), v.dot(self.up) / half_h, z))
+            }
+        }
+    }
+}
+
+/// Vista guardada de la cámara orbital (slots de presets).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraPreset {
+    pub center: Vector3,
+    pub distance: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+    /// FOV vertical de la perspectiva (radianes).
+    pub fov: f32,
+}
+
+impl CameraPreset {
+    /// Mezcla para transiciones; el yaw va por el camino corto.
+    pub fn lerp(&self, other: &CameraPreset, t: f32) -> CameraPreset {
+        let tau = 2.0 * std::f32::consts::PI;
+        let dyaw = (other.yaw - self.yaw + std::f32::consts::PI).rem_euclid(tau) - std::f32::consts::PI;
+        CameraPreset {
+            center: self.center + (other.center - self.center) * t,
+            distance: self.distance + (other.distance - self.distance) * t,
+            yaw: self.yaw + dyaw * t,
+            pitch: self.pitch + (other.pitch - self.pitch) * t,
+            fov: self.fov + (other.fov - self.fov) * t,
+        }
+    }
+
+    /// Catmull-Rom uniforme entre `p1` (t = 0) y `p2` (t = 1), con `p0`/`p3` como vecinos.
+    /// Va por los parámetros esféricos, no por la posición del ojo: una órbita sigue siendo
+    /// una órbita. El yaw no se envuelve (0 → 2π es una vuelta entera, para turntables).
+    pub fn catmull_rom(p0: &CameraPreset, p1: &CameraPreset, p2: &CameraPreset, p3: &CameraPreset, t: f32) -> CameraPreset {
+        let (t2, t3) = (t * t, t * t * t);
+        let cr = |a: f32, b: f32, c: f32, d: f32| {
+            0.5 * (2.0 * b + (c - a) * t + (2.0 * a - 5.0 * b + 4.0 * c - d) * t2 + (3.0 * b - a - 3.0 * c + d) * t3)
+        };
+        CameraPreset {
+            center: Vector3::new(
+                cr(p0.center.x, p1.center.x, p2.center.x, p3.center.x),
+                cr(p0.center.y, p1.center.y, p2.center.y, p3.center.y),
+                cr(p0.center.z, p1.center.z, p2.center.z, p3.center.z),
+            ),
+            distance: cr(p0.distance, p1.distance, p2.distance, p3.distance),
+            yaw: cr(p0.yaw, p1.yaw, p2.yaw, p3.yaw),
+            pitch: cr(p0.pitch, p1.pitch, p2.pitch, p3.pitch),
+            fov: cr(p0.fov, p1.fov, p2.fov, p3.fov),
+        }
+    }
 }
 
 /// Cámara orbital: siempre mira al centro.
+#[derive(Clone, Copy)]
 pub struct Camera {
     /// Punto que orbitamos y observamos.
     pub center: Vector3,
@@ -55,6 +178,14 @@ pub struct Camera {
     pub up: Vector3,
     /// Config editable
     pub config: CameraConfig,
+    /// Perspectiva (por defecto) u ortográfica.
+    pub projection: Projection,
+    /// FOV vertical de la perspectiva; se conserva mientras se usa la ortográfica.
+    fov: f32,
+    /// Hacia dónde van yaw/pitch/distancia: `orbit`/`zoom` los mueven y `update` acerca los actuales.
+    target_yaw: f32,
+    target_pitch: f32,
+    target_distance: f32,
 }
 
 impl Camera {
@@ -74,6 +205,11 @@ impl Camera {
             right: Vector3::zero(),
             up: up_hint,
             config: CameraConfig::default(),
+            projection: Projection::default(),
+            fov: DEFAULT_FOV,
+            target_yaw: yaw,
+            target_pitch: pitch,
+            target_distance: distance,
         };
 
         cam.clamp_angles_and_distance();
@@ -93,6 +229,11 @@ impl Camera {
             right: Vector3::zero(),
             up: Vector3::new(0.0, 1.0, 0.0),
             config: CameraConfig::default(),
+            projection: Projection::default(),
+            fov: DEFAULT_FOV,
+            target_yaw: yaw,
+            target_pitch: pitch,
+            target_distance: distance,
         };
         cam.clamp_angles_and_distance();
         cam.update_eye_from_spherical();
@@ -104,6 +245,7 @@ impl Camera {
     pub fn set_config(&mut self, cfg: CameraConfig) {
         self.config = cfg;
         self.clamp_angles_and_distance();
+        self.sync_targets();
         self.update_eye_from_spherical();
         self.update_basis_vectors();
     }
@@ -115,32 +257,110 @@ impl Camera {
         self.update_basis_vectors();
     }
 
+    /// Mueve el objetivo de la órbita; `update` lleva la cámara hasta ahí.
     pub fn orbit(&mut self, delta_yaw: f32, delta_pitch: f32) {
-        self.yaw   += delta_yaw  * self.config.orbit_sensitivity_yaw;
-        self.pitch += delta_pitch * self.config.orbit_sensitivity_pitch;
-        if self.yaw > std::f32::consts::PI { self.yaw -= 2.0*std::f32::consts::PI; }
-        if self.yaw < -std::f32::consts::PI { self.yaw += 2.0*std::f32::consts::PI; }
+        self.target_yaw   += delta_yaw  * self.config.orbit_sensitivity_yaw;
+        self.target_pitch += delta_pitch * self.config.orbit_sensitivity_pitch;
+        if self.target_yaw > std::f32::consts::PI { self.target_yaw -= 2.0*std::f32::consts::PI; }
+        if self.target_yaw < -std::f32::consts::PI { self.target_yaw += 2.0*std::f32::consts::PI; }
+        self.clamp_targets();
+        if self.config.smoothing <= 0.0 { self.snap_to_targets(); }
+    }
 
+    /// En ortográfica el zoom escala el alto visible en vez de la distancia.
+    pub fn zoom(&mut self, amount: f32) {
+        if let Projection::Orthographic { height } = &mut self.projection {
+            *height = (*height * (1.0 + 0.05 * amount * self.config.zoom_sensitivity)).max(0.5);
+            return;
+        }
+        self.target_distance += amount * self.config.zoom_sensitivity;
+        self.clamp_targets();
+        if self.config.smoothing <= 0.0 { self.snap_to_targets(); }
+    }
+
+    /// Acerca yaw/pitch/distancia a sus objetivos (suavizado exponencial, yaw por el camino corto).
+    /// Con `smoothing` = 0 o ya en el objetivo no hace nada.
+    pub fn update(&mut self, dt: f32) {
+        let tau = 2.0 * std::f32::consts::PI;
+        let dyaw = (self.target_yaw - self.yaw + std::f32::consts::PI).rem_euclid(tau) - std::f32::consts::PI;
+        let dpitch = self.target_pitch - self.pitch;
+        let ddist = self.target_distance - self.distance;
+        if dyaw.abs() < 1e-5 && dpitch.abs() < 1e-5 && ddist.abs() < 1e-4 * self.distance.max(1.0) {
+            if dyaw != 0.0 || dpitch != 0.0 || ddist != 0.0 { self.snap_to_targets(); }
+            return;
+        }
+        let k = if self.config.smoothing > 0.0 { 1.0 - (-self.config.smoothing * dt).exp() } else { 1.0 };
+        self.yaw = (self.yaw + dyaw * k + std::f32::consts::PI).rem_euclid(tau) - std::f32::consts::PI;
+        self.pitch += dpitch * k;
+        self.distance += ddist * k;
         self.clamp_angles_and_distance();
         self.update_eye_from_spherical();
         self.update_basis_vectors();
     }
 
-    pub fn zoom(&mut self, amount: f32) {
-        self.distance += amount * self.config.zoom_sensitivity;
+    /// FOV vertical (radianes). Render y picking lo leen de la misma `Projection` vía `basis()`.
+    #[inline]
+    pub fn fov(&self) -> f32 { self.fov }
+
+    /// Cambia el FOV dentro de [`MIN_FOV`, `MAX_FOV`]; en ortográfica queda guardado para después.
+    pub fn set_fov(&mut self, fov: f32) {
+        self.fov = fov.clamp(MIN_FOV, MAX_FOV);
+        if let Projection::Perspective { fov } = &mut self.projection {
+            *fov = self.fov;
+        }
+    }
+
+    /// Dolly zoom: cambia el FOV y acerca/aleja la cámara para que el plano del centro
+    /// conserve su tamaño en pantalla (el fondo se estira o se comprime).
+    pub fn dolly_zoom(&mut self, fov: f32) {
+        if !matches!(self.projection, Projection::Perspective { .. }) { return; }
+        let old = self.fov;
+        self.set_fov(fov);
+        self.distance *= (old * 0.5).tan() / (self.fov * 0.5).tan();
+        self.clamp_angles_and_distance();
+        self.sync_targets();
+        self.update_eye_from_spherical();
+        self.update_basis_vectors();
+    }
+
+    pub fn save_preset(&self) -> CameraPreset {
+        CameraPreset { center: self.center, distance: self.distance, yaw: self.yaw, pitch: self.pitch, fov: self.fov }
+    }
+
+    /// Restaura una vista guardada (respeta los límites de `config`).
+    pub fn load_preset(&mut self, p: &CameraPreset) {
+        self.center = p.center;
+        self.distance = p.distance;
+        self.yaw = (p.yaw + std::f32::consts::PI).rem_euclid(2.0 * std::f32::consts::PI) - std::f32::consts::PI;
+        self.pitch = p.pitch;
+        self.set_fov(p.fov);
         self.clamp_angles_and_distance();
+        self.sync_targets();
         self.update_eye_from_spherical();
         self.update_basis_vectors();
     }
 
+    /// Vuelve a perspectiva con el FOV guardado.
+    #[inline]
+    pub fn perspective(&self) -> Projection { Projection::Perspective { fov: self.fov } }
+
     pub fn zoom_exp(&mut self, amount: f32) {
         let factor = (1.0 + 0.2 * amount).max(0.05);
         self.distance *= factor;
         self.clamp_angles_and_distance();
+        self.sync_targets();
         self.update_eye_from_spherical();
         self.update_basis_vectors();
     }
 
+    /// Coloca el ojo en `eye` mirando hacia `forward` (modo caminar).
+    /// No toca yaw/pitch/distancia orbitales.
+    pub fn look_from(&mut self, eye: Vector3, forward: Vector3) {
+        self.eye = eye;
+        self.center = eye + forward.normalized();
+        self.update_basis_vectors();
+    }
+
     #[inline]
     fn update_eye_from_spherical(&mut self) {
         let cp = self.pitch.cos();
@@ -156,6 +376,30 @@ impl Camera {
         self.distance = self.distance.clamp(self.config.min_distance, self.config.max_distance);
     }
 
+    /// Mismos límites que `clamp_angles_and_distance`, sobre los objetivos: nunca se suaviza
+    /// hacia un pitch o una distancia ilegal.
+    #[inline]
+    fn clamp_targets(&mut self) {
+        self.target_pitch = self.target_pitch.clamp(self.config.min_pitch, self.config.max_pitch);
+        self.target_distance = self.target_distance.clamp(self.config.min_distance, self.config.max_distance);
+    }
+
+    /// Los cambios directos (presets, dolly, config) dejan la cámara quieta donde quedó.
+    #[inline]
+    fn sync_targets(&mut self) {
+        self.target_yaw = self.yaw;
+        self.target_pitch = self.pitch;
+        self.target_distance = self.distance;
+    }
+
+    fn snap_to_targets(&mut self) {
+        self.yaw = self.target_yaw;
+        self.pitch = self.target_pitch;
+        self.distance = self.target_distance;
+        self.update_eye_from_spherical();
+        self.update_basis_vectors();
+    }
+
     pub fn update_basis_vectors(&mut self) {
         self.forward = (self.center - self.eye).normalized();
         let world_up = Vector3::new(0.0, 1.0, 0.0);
@@ -184,6 +428,7 @@ impl Camera {
             forward: self.forward,
             right: self.right,
             up: self.up,
+            projection: self.projection,
         }
     }
 }
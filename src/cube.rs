@@ -3,15 +3,29 @@ use raylib::prelude::Vector3;
 
 use crate::material::Material;
 use crate::ray_intersect::{Intersect, RayIntersect};
-use crate::texture::Texture;
-use crate::palette::{FaceStyle, TexStyle};
+use crate::texture::{SampleMode, Texture};
+use crate::gamma;
+use crate::palette::{Displacement, FaceStyle, TexStyle};
 
 #[derive(Clone, Copy)]
 pub enum Face { PosX, NegX, PosY, NegY, PosZ, NegZ }
 impl Face {
-    #[inline] fn idx(self) -> usize {
+    #[inline] pub(crate) fn idx(self) -> usize {
         match self { Face::PosX=>0, Face::NegX=>1, Face::PosY=>2, Face::NegY=>3, Face::PosZ=>4, Face::NegZ=>5 }
     }
+
+    /// Direcciones de mundo en que crecen u y v sobre la cara (las del mapeo UV de `Cube`).
+    #[inline] pub(crate) fn uv_axes(self) -> (Vector3, Vector3) {
+        let (x, y, z) = (Vector3::new(1.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, 1.0));
+        match self {
+            Face::PosX => (z, -y),
+            Face::NegX => (-z, -y),
+            Face::PosY => (x, z),
+            Face::NegY => (x, -z),
+            Face::PosZ => (x, -y),
+            Face::NegZ => (-x, -y),
+        }
+    }
 }
 
 /// AABB con texturas por cara
@@ -20,6 +34,8 @@ pub struct Cube {
     pub max: Vector3,
     pub material: Material,
     face_textures: [Option<FaceStyle>; 6],
+    /// Carácter de paleta de origen (eyedropper, sonido al quitar).
+    pub block: Option<char>,
 }
 
 impl Cube {
@@ -30,19 +46,20 @@ impl Cube {
             max: center + half,
             material,
             face_textures: [None, None, None, None, None, None],
+            block: None,
         }
     }
 
     pub fn new(min: Vector3, max: Vector3, material: Material) -> Self {
-        Cube { min, max, material, face_textures: [None, None, None, None, None, None] }
+        Cube { min, max, material, face_textures: [None, None, None, None, None, None], block: None }
     }
 
     pub fn set_face_texture(&mut self, face: Face, tex: Arc<Texture>) {
-        self.face_textures[face.idx()] = Some(FaceStyle { tex, style: TexStyle::Normal });
+        self.face_textures[face.idx()] = Some(FaceStyle::new(tex, TexStyle::Normal));
     }
 
     pub fn set_face_texture_styled(&mut self, face: Face, tex: Arc<Texture>, style: TexStyle) {
-        self.face_textures[face.idx()] = Some(FaceStyle { tex, style });
+        self.face_textures[face.idx()] = Some(FaceStyle::new(tex, style));
     }
 
     pub fn set_face_textures_from_template(&mut self, tpl: &[Option<FaceStyle>; 6]) {
@@ -58,56 +75,57 @@ fn luminance(rgb: Vector3) -> f32 {
     (rgb.x * 0.2126 + rgb.y * 0.7152 + rgb.z * 0.0722).clamp(0.0, 1.0)
 }
 
-fn sample_with_style(tex: &Texture, u: f32, v: f32, style: &TexStyle) -> Option<(Vector3, f32)> {
+pub(crate) fn sample_with_style(tex: &Texture, u: f32, v: f32, style: &TexStyle, mode: SampleMode) -> Option<(Vector3, f32)> {
     match style {
         TexStyle::Normal => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             Some((base, 1.0))
         }
         TexStyle::GrayscaleTint { color } => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             let a = luminance(base);
-            Some((Vector3::new(color.x * a, color.y * a, color.z * a), 1.0))
+            Some((gamma::srgb_vec_to_linear(*color) * a, 1.0))
         }
         TexStyle::BlackIsTransparent { threshold } => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             let a = luminance(base);
-            if a <= *threshold { None } else { Some((base, 1.0)) }
+            if a <= gamma::srgb_to_linear(*threshold) { None } else { Some((base, 1.0)) }
         }
         TexStyle::GrayscaleTintBlackTransparent { color, threshold } => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             let a = luminance(base);
-            if a <= *threshold { None } else {
-                Some((Vector3::new(color.x * a, color.y * a, color.z * a), 1.0))
+            if a <= gamma::srgb_to_linear(*threshold) { None } else {
+                Some((gamma::srgb_vec_to_linear(*color) * a, 1.0))
             }
         }
         TexStyle::ImageAlphaCutout { threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             if alpha <= *threshold { None } else { Some((base, 1.0)) }
         }
         TexStyle::GrayscaleTintImageAlphaCutout { color, threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             if alpha <= *threshold { None } else {
                 let l = luminance(base);
-                Some((Vector3::new(color.x * l, color.y * l, color.z * l), 1.0))
+                Some((gamma::srgb_vec_to_linear(*color) * l, 1.0))
             }
         }
         TexStyle::ImageAlphaWindow { threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             let cov = if alpha <= *threshold { 0.0 } else { alpha };
             Some((base, cov))
         }
         TexStyle::GrayscaleTintImageAlphaWindow { color, threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             let cov = if alpha <= *threshold { 0.0 } else { alpha };
             let l = luminance(base);
-            Some((Vector3::new(color.x * l, color.y * l, color.z * l), cov))
+            Some((gamma::srgb_vec_to_linear(*color) * l, cov))
         }
     }
 }
 
-impl RayIntersect for Cube {
-    fn ray_intersect(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+impl Cube {
+    fn intersect_impl(&self, ro: &Vector3, rd: &Vector3, flat: bool) -> Intersect {
+        profile_scope!(Cube);
         // Slabs
         let inv = Vector3::new(1.0 / rd.x, 1.0 / rd.y, 1.0 / rd.z);
         let (tx1, tx2) = ((self.min.x - ro.x) * inv.x, (self.max.x - ro.x) * inv.x);
@@ -129,8 +147,6 @@ impl RayIntersect for Cube {
         let t_hit = if t_enter > 0.0 { t_enter } else { t_exit };
         if !t_hit.is_finite() { return Intersect::empty(); }
 
-        let p = *ro + *rd * t_hit;
-
         let face = if t_enter == tmin_x || (tmin_x > tmin_y && tmin_x > tmin_z) {
             if rd.x > 0.0 { Face::NegX } else { Face::PosX }
         } else if t_enter == tmin_y || (tmin_y > tmin_z) {
@@ -139,6 +155,30 @@ impl RayIntersect for Cube {
             if rd.z > 0.0 { Face::NegZ } else { Face::PosZ }
         };
 
+        if let Some(hit) = self.face_hit(ro, rd, t_hit, face, flat) { return hit; }
+        // Texel recortado en la cara de entrada: el rayo sigue por adentro hasta la cara de
+        // salida, vista del lado de adentro (normales hacia el rayo). Así un bloque de hojas tapa
+        // con sus dos capas y la sombra sale salpicada en vez de un cuadrado lleno.
+        if t_enter <= 0.0 || !t_exit.is_finite() { return Intersect::empty(); }
+        let exit = if tmax_x <= tmax_y && tmax_x <= tmax_z {
+            if rd.x > 0.0 { Face::PosX } else { Face::NegX }
+        } else if tmax_y <= tmax_z {
+            if rd.y > 0.0 { Face::PosY } else { Face::NegY }
+        } else if rd.z > 0.0 { Face::PosZ } else { Face::NegZ };
+        match self.face_hit(ro, rd, t_exit, exit, flat) {
+            Some(mut hit) => {
+                hit.normal = -hit.normal;
+                hit.geometric_normal = -hit.geometric_normal;
+                hit
+            }
+            None => Intersect::empty(),
+        }
+    }
+
+    /// Hit sobre la cara `face` a distancia `t`; None si su texel es recorte (cutout).
+    fn face_hit(&self, ro: &Vector3, rd: &Vector3, t_hit: f32, face: Face, flat: bool) -> Option<Intersect> {
+        let p = *ro + *rd * t_hit;
+
         let normal = match face {
             Face::PosX => Vector3::new( 1.0, 0.0, 0.0),
             Face::NegX => Vector3::new(-1.0, 0.0, 0.0),
@@ -163,21 +203,135 @@ impl RayIntersect for Cube {
         u = u.clamp(0.0 + tiny, 1.0 - tiny);
         v = v.clamp(0.0 + tiny, 1.0 - tiny);
 
+        let mut shading_normal = normal;
         let (final_material, coverage) = if let Some(face_layer) = &self.face_textures[face.idx()] {
-            match sample_with_style(&face_layer.tex, u, v, &face_layer.style) {
-                Some((tex_color, cov)) => {
-                    (Material { diffuse: tex_color, ..self.material }, cov)
-                }
-                None => {
-                    return Intersect::empty();
-                }
+            let (u, v) = face_layer.rotate_uv(u, v);
+            if flat {
+                let mut m = Material { diffuse: face_layer.avg_color, ..self.material };
+                face_layer.apply_overrides(&mut m);
+                if let Some(map) = &face_layer.specular { map.apply_value(&mut m, map.avg); }
+                (m, face_layer.avg_coverage)
+            } else {
+                let (tex_color, cov) = sample_with_style(face_layer.texture(), u, v, &face_layer.style, face_layer.sample)?;
+                let mut m = Material { diffuse: tex_color, ..self.material };
+                face_layer.apply_overrides(&mut m);
+                if let Some(map) = &face_layer.specular { map.apply(&mut m, u, v); }
+                shading_normal = face_layer.shading_normal(normal, face.uv_axes(), u, v);
+                (m, cov)
             }
         } else { (self.material, 1.0) };
 
-        Intersect::with_coverage(p, normal, t_hit, final_material, coverage)
+        let mut hit = Intersect::with_coverage(p, normal, t_hit, final_material, coverage);
+        hit.normal = shading_normal;
+        hit.textured = self.face_textures[face.idx()].is_some();
+        Some(hit)
+    }
+
+    /// Relieve de la cara superior, si tiene.
+    #[inline]
+    fn top_displacement(&self) -> Option<&Displacement> {
+        self.face_textures[Face::PosY.idx()].as_ref().and_then(|f| f.displacement.as_ref())
+    }
+
+    /// Primario con relieve: la franja [max.y, max.y + amplitude] se recorre a pasos fijos contra
+    /// la altura; el primer paso bajo la superficie se refina por bisección.
+    fn intersect_displaced(&self, ro: &Vector3, rd: &Vector3, disp: &Displacement) -> Intersect {
+        profile_scope!(Cube);
+        const MARCH_STEPS: u32 = 16;
+        const REFINE_STEPS: u32 = 5;
+
+        let top = self.max.y;
+        let size = self.max - self.min;
+        let height_at = |p: Vector3| -> f32 {
+            top + disp.height.sample_height(p.x / size.x, p.z / size.z) * disp.amplitude
+        };
+
+        // Caja expandida
+        let hi = Vector3::new(self.max.x, top + disp.amplitude, self.max.z);
+        let inv = Vector3::new(1.0 / rd.x, 1.0 / rd.y, 1.0 / rd.z);
+        let (tx1, tx2) = ((self.min.x - ro.x) * inv.x, (hi.x - ro.x) * inv.x);
+        let (ty1, ty2) = ((self.min.y - ro.y) * inv.y, (hi.y - ro.y) * inv.y);
+        let (tz1, tz2) = ((self.min.z - ro.z) * inv.z, (hi.z - ro.z) * inv.z);
+        let t_enter = tx1.min(tx2).max(ty1.min(ty2)).max(tz1.min(tz2)).max(0.0);
+        let t_exit = tx1.max(tx2).min(ty1.max(ty2)).min(tz1.max(tz2));
+        if !t_exit.is_finite() || t_enter > t_exit { return Intersect::empty(); }
+
+        // Entra por debajo de la franja: es un lado del cubo normal
+        if (*ro + *rd * t_enter).y < top - 1e-5 {
+            return self.intersect_impl(ro, rd, false);
+        }
+
+        let dt = (t_exit - t_enter) / MARCH_STEPS as f32;
+        let below = |t: f32| { let p = *ro + *rd * t; p.y <= height_at(p) };
+        let mut t_prev = t_enter;
+        let mut t_hit = below(t_enter).then_some(t_enter);
+        for i in 1..=MARCH_STEPS {
+            if t_hit.is_some() { break; }
+            let t = t_enter + dt * i as f32;
+            if below(t) { t_hit = Some(t); break; }
+            t_prev = t;
+        }
+        let Some(mut t_in) = t_hit else { return Intersect::empty(); };
+        let mut t_out = t_prev;
+        for _ in 0..REFINE_STEPS {
+            let mid = 0.5 * (t_in + t_out);
+            if below(mid) { t_in = mid; } else { t_out = mid; }
+        }
+        let t = 0.5 * (t_in + t_out);
+        let p = *ro + *rd * t;
+
+        // Normal desde el gradiente de la altura
+        let e = 0.5 / disp.height.width().max(1) as f32 * size.x;
+        let dhdx = (height_at(p + Vector3::new(e, 0.0, 0.0)) - height_at(p - Vector3::new(e, 0.0, 0.0))) / (2.0 * e);
+        let dhdz = (height_at(p + Vector3::new(0.0, 0.0, e)) - height_at(p - Vector3::new(0.0, 0.0, e))) / (2.0 * e);
+        let normal = Vector3::new(-dhdx, 1.0, -dhdz).normalized();
+
+        let face_layer = match &self.face_textures[Face::PosY.idx()] { Some(f) => f, None => return Intersect::empty() };
+        let tiny = 1e-6f32;
+        let u = ((p.x - self.min.x) / size.x).clamp(tiny, 1.0 - tiny);
+        let v = ((p.z - self.min.z) / size.z).clamp(tiny, 1.0 - tiny);
+        let (u, v) = face_layer.rotate_uv(u, v);
+        let Some((tex_color, coverage)) = sample_with_style(face_layer.texture(), u, v, &face_layer.style, face_layer.sample) else {
+            return Intersect::empty();
+        };
+
+        let mut m = Material { diffuse: tex_color, ..self.material };
+        face_layer.apply_overrides(&mut m);
+        if let Some(map) = &face_layer.specular { map.apply(&mut m, u, v); }
+        let mut hit = Intersect::with_coverage(p, normal, t, m, coverage);
+        hit.textured = true;
+        hit.geometric_normal = Vector3::new(0.0, 1.0, 0.0);
+        hit
     }
+}
 
+impl RayIntersect for Cube {
+    fn ray_intersect(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_impl(ro, rd, false)
+    }
+
+    fn ray_intersect_flat(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_impl(ro, rd, true)
+    }
+
+    /// Con relieve solo los primarios marchan el heightfield; sombras y rebotes usan la cara plana
+    /// (aproximación: el relieve no proyecta sombra propia ni aparece en reflejos).
+    fn ray_intersect_detailed(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        match self.top_displacement() {
+            Some(disp) => self.intersect_displaced(ro, rd, disp),
+            None => self.intersect_impl(ro, rd, false),
+        }
+    }
+
+    /// Incluye la franja del relieve para que la grilla lo encuentre.
     fn aabb(&self) -> (Vector3, Vector3) {
+        let amp = self.top_displacement().map_or(0.0, |d| d.amplitude);
+        (self.min, self.max + Vector3::new(0.0, amp, 0.0))
+    }
+
+    fn solid_aabb(&self) -> (Vector3, Vector3) {
         (self.min, self.max)
     }
+
+    fn block_id(&self) -> Option<char> { self.block }
 }
@@ -0,0 +1,108 @@
+// daycycle.rs
+use std::f32::consts::PI;
+use raylib::prelude::*;
+
+use crate::light::{Light, LightKind};
+
+/// Estado de la luz en una hora del día. El ciclo interpola linealmente entre claves vecinas.
+#[derive(Clone, Copy, Debug)]
+pub struct DayKey {
+    /// Hora 0..24.
+    pub hour: f32,
+    /// Color de la luz (0..1).
+    pub color: Vector3,
+    pub intensity: f32,
+    /// Multiplicador del cielo (skybox o procedural).
+    pub sky: Vector3,
+}
+
+/// Claves por defecto: luna azulada de noche, amanecer cálido, mediodía blanco y atardecer azul.
+/// La intensidad pasa por 0 a las 6 y a las 18: ahí la luz salta del sol a la luna (o al revés)
+/// sin que se note en las sombras.
+pub const DEFAULT_KEYS: [DayKey; 9] = [
+    DayKey { hour: 0.0,  color: Vector3 { x: 0.55, y: 0.65, z: 1.0 },  intensity: 0.25, sky: Vector3 { x: 0.10, y: 0.12, z: 0.25 } },
+    DayKey { hour: 5.0,  color: Vector3 { x: 0.55, y: 0.65, z: 1.0 },  intensity: 0.25, sky: Vector3 { x: 0.15, y: 0.15, z: 0.30 } },
+    DayKey { hour: 6.0,  color: Vector3 { x: 1.0,  y: 0.55, z: 0.30 }, intensity: 0.0,  sky: Vector3 { x: 0.55, y: 0.40, z: 0.40 } },
+    DayKey { hour: 7.0,  color: Vector3 { x: 1.0,  y: 0.62, z: 0.35 }, intensity: 0.7,  sky: Vector3 { x: 1.0,  y: 0.70, z: 0.55 } },
+    DayKey { hour: 12.0, color: Vector3 { x: 1.0,  y: 1.0,  z: 1.0 },  intensity: 1.2,  sky: Vector3 { x: 1.0,  y: 1.0,  z: 1.0 } },
+    DayKey { hour: 16.0, color: Vector3 { x: 1.0,  y: 0.93, z: 0.85 }, intensity: 1.0,  sky: Vector3 { x: 0.95, y: 0.95, z: 1.0 } },
+    DayKey { hour: 17.0, color: Vector3 { x: 0.65, y: 0.72, z: 1.0 },  intensity: 0.6,  sky: Vector3 { x: 0.60, y: 0.62, z: 0.95 } },
+    DayKey { hour: 18.0, color: Vector3 { x: 0.45, y: 0.55, z: 1.0 },  intensity: 0.0,  sky: Vector3 { x: 0.30, y: 0.30, z: 0.55 } },
+    DayKey { hour: 19.0, color: Vector3 { x: 0.55, y: 0.65, z: 1.0 },  intensity: 0.25, sky: Vector3 { x: 0.12, y: 0.14, z: 0.28 } },
+];
+
+/// Ciclo día/noche: mueve la luz direccional por el cielo y tiñe el fondo según la hora.
+pub struct DayCycle {
+    pub enabled: bool,
+    pub paused: bool,
+    /// Hora actual 0..24.
+    pub time: f32,
+    /// Horas de juego por segundo real.
+    pub speed: f32,
+    /// Ordenadas por hora; la última se une con la primera al pasar de las 24.
+    keys: Vec<DayKey>,
+    /// Inclinación de la trayectoria respecto de la vertical (el sol no pasa justo por el cenit).
+    tilt: f32,
+}
+
+impl DayCycle {
+    pub fn new() -> Self {
+        DayCycle { enabled: false, paused: false, time: 9.0, speed: 0.5, keys: DEFAULT_KEYS.to_vec(), tilt: 0.35 }
+    }
+
+    /// Avanza el reloj (si no está en pausa) `dt` segundos reales.
+    pub fn update(&mut self, dt: f32) {
+        if self.enabled && !self.paused {
+            self.scrub(dt * self.speed);
+        }
+    }
+
+    /// Mueve la hora `hours` (negativo = atrás), con vuelta en 24.
+    pub fn scrub(&mut self, hours: f32) {
+        self.time = (self.time + hours).rem_euclid(24.0);
+    }
+
+    /// Clave interpolada para la hora actual.
+    pub fn sample(&self) -> DayKey {
+        let t = self.time;
+        let n = self.keys.len();
+        let next = self.keys.iter().position(|k| k.hour > t).unwrap_or(n);
+        let a = self.keys[(next + n - 1) % n];
+        let b = self.keys[next % n];
+        let span = (b.hour - a.hour).rem_euclid(24.0).max(1e-4);
+        let k = ((t - a.hour).rem_euclid(24.0) / span).clamp(0.0, 1.0);
+        let mix = |x: Vector3, y: Vector3| x + (y - x) * k;
+        DayKey {
+            hour: t,
+            color: mix(a.color, b.color),
+            intensity: a.intensity + (b.intensity - a.intensity) * k,
+            sky: mix(a.sky, b.sky),
+        }
+    }
+
+    /// Dirección en que viaja la luz: el sol de 6 a 18 (sale por +X, se pone por -X) y la luna
+    /// el resto, en el mismo arco. Sube y baja continua; el cambio sol/luna cae en intensidad 0.
+    pub fn light_direction(&self) -> Vector3 {
+        let phase = (self.time - 6.0).rem_euclid(12.0) / 12.0 * PI;
+        let to_sky = Vector3::new(phase.cos(), phase.sin(), self.tilt).normalized();
+        // Un poco por encima del horizonte para que al salir no ilumine desde abajo del suelo
+        let to_sky = Vector3::new(to_sky.x, to_sky.y.max(0.02), to_sky.z).normalized();
+        -to_sky
+    }
+
+    /// Escribe dirección, color e intensidad en `light` (la pasa a direccional).
+    pub fn apply(&self, light: &mut Light) {
+        let key = self.sample();
+        let to_u8 = |c: f32| (c.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        light.kind = LightKind::Directional;
+        light.direction = self.light_direction();
+        light.color = Color::new(to_u8(key.color.x), to_u8(key.color.y), to_u8(key.color.z), 255);
+        light.intensity = key.intensity;
+    }
+
+    /// "HH:MM" de la hora actual (HUD).
+    pub fn clock(&self) -> String {
+        let minutes = (self.time * 60.0) as u32;
+        format!("{:02}:{:02}", minutes / 60 % 24, minutes % 60)
+    }
+}
@@ -0,0 +1,256 @@
+// feedback.rs
+use std::collections::HashMap;
+use std::path::Path;
+use raylib::prelude::*;
+
+use crate::camera::CameraBasis;
+use crate::framebuffer::Framebuffer;
+use crate::palette::SoundCategory;
+use crate::settings;
+
+/// Qué pasó en la celda.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EffectKind { Placed, Removed, Failed }
+
+/// Efecto de overlay sobre una celda: se dibuja encima del frame, no re-renderiza.
+pub struct CellEffect {
+    pub center: Vector3,
+    pub size: Vector3,
+    pub kind: EffectKind,
+    age: f32,
+}
+
+impl CellEffect {
+    pub fn new(center: Vector3, size: Vector3, kind: EffectKind) -> Self {
+        CellEffect { center, size, kind, age: 0.0 }
+    }
+
+    #[inline]
+    fn duration(&self) -> f32 {
+        match self.kind {
+            EffectKind::Failed => 0.3,
+            _ => 0.2,
+        }
+    }
+}
+
+/// Avanza la cola con el frame time y descarta los efectos terminados.
+pub fn update_effects(effects: &mut Vec<CellEffect>, dt: f32) {
+    for e in effects.iter_mut() { e.age += dt; }
+    effects.retain(|e| e.age < e.duration());
+}
+
+/// Proyecta un punto a pixeles de pantalla (None si queda detrás de la cámara).
+#[inline]
+pub fn to_screen(cam: &CameraBasis, p: Vector3, w: f32, h: f32) -> Option<Vector2> {
+    to_screen_depth(cam, p, w, h).map(|s| Vector2::new(s.x, s.y))
+}
+
+/// `to_screen` con la profundidad de vista en `z` (para el test contra el framebuffer).
+#[inline]
+pub fn to_screen_depth(cam: &CameraBasis, p: Vector3, w: f32, h: f32) -> Option<Vector3> {
+    let (sx, sy, z) = cam.project_depth(p, w / h)?;
+    Some(Vector3::new((sx + 1.0) * 0.5 * w, (1.0 - sy) * 0.5 * h, z))
+}
+
+/// Recorta un segmento de mundo contra el plano cercano de la cámara (None si queda entero detrás).
+fn clip_near(cam: &CameraBasis, a: Vector3, b: Vector3) -> Option<(Vector3, Vector3)> {
+    const NEAR: f32 = 0.05;
+    let (za, zb) = ((a - cam.eye).dot(cam.forward), (b - cam.eye).dot(cam.forward));
+    if za < NEAR && zb < NEAR { return None; }
+    let clip = |p: Vector3, zp: Vector3, z0: f32, z1: f32| -> Vector3 {
+        if z0 >= NEAR { p } else { p + (zp - p) * ((NEAR - z0) / (z1 - z0)) }
+    };
+    Some((clip(a, b, za, zb), clip(b, a, zb, za)))
+}
+
+/// Segmento de mundo a pantalla recortado contra el plano cercano de la cámara
+/// (None si queda entero detrás).
+pub fn segment_to_screen(cam: &CameraBasis, a: Vector3, b: Vector3, w: f32, h: f32) -> Option<(Vector2, Vector2)> {
+    let (ca, cb) = clip_near(cam, a, b)?;
+    Some((to_screen(cam, ca, w, h)?, to_screen(cam, cb, w, h)?))
+}
+
+/// Segmento de mundo dibujado en el framebuffer con test de profundidad. Se recorta contra el
+/// plano cercano (una arista que pasa por detrás de la cámara se dibuja hasta el borde) y se
+/// parte en tramos de ≤ 0.25 unidades para que la z interpolada en pantalla siga la perspectiva.
+pub fn draw_line_3d(fb: &mut Framebuffer, cam: &CameraBasis, a: Vector3, b: Vector3, thick: f32, color: Color) {
+    let Some((a, b)) = clip_near(cam, a, b) else { return; };
+    let (w, h) = (fb.width as f32, fb.height as f32);
+    let pieces = ((b - a).length() / 0.25).ceil().max(1.0) as usize;
+    let mut prev = to_screen_depth(cam, a, w, h);
+    for i in 1..=pieces {
+        let cur = to_screen_depth(cam, a + (b - a) * (i as f32 / pieces as f32), w, h);
+        if let (Some(pa), Some(pb)) = (prev, cur) {
+            fb.draw_line_depth(pa, pb, thick, color);
+        }
+        prev = cur;
+    }
+}
+
+/// Aristas de una caja alineada (centro ± half), con test de profundidad.
+pub fn draw_box_wire(fb: &mut Framebuffer, cam: &CameraBasis, center: Vector3, half: Vector3, thick: f32, color: Color) {
+    let corner = |i: usize| -> Vector3 {
+        Vector3::new(
+            if i & 1 == 0 { -half.x } else { half.x },
+            if i & 2 == 0 { -half.y } else { half.y },
+            if i & 4 == 0 { -half.z } else { half.z },
+        ) + center
+    };
+    for a in 0..8usize {
+        for bit in [1usize, 2, 4] {
+            let b = a | bit;
+            if b == a { continue; }
+            draw_line_3d(fb, cam, corner(a), corner(b), thick, color);
+        }
+    }
+}
+
+/// Contorno del objeto bajo el cursor: las 12 aristas de su AABB, apenas agrandado para no
+/// pelear con sus propias caras. Oscuro debajo y claro encima, así se lee sobre cualquier textura.
+pub fn draw_hover_outline(fb: &mut Framebuffer, cam: &CameraBasis, (mn, mx): (Vector3, Vector3)) {
+    let half = (mx - mn) * 0.5 + Vector3::new(0.003, 0.003, 0.003);
+    let center = (mn + mx) * 0.5;
+    draw_box_wire(fb, cam, center, half, 3.0, Color::new(0, 0, 0, 120));
+    draw_box_wire(fb, cam, center, half, 1.0, Color::new(255, 255, 255, 235));
+}
+
+/// Leyenda de la vista de calor en la esquina inferior izquierda (sale en las capturas): la
+/// rampa de `heat_ramp` de 0 al fondo de escala, con un disco en `t` (0..1) si el mouse apunta
+/// a algo: el valor de su rayo.
+pub fn draw_heat_legend(fb: &mut Framebuffer, t: Option<f32>) {
+    let (w, h) = ((fb.width as i32 / 4).clamp(32, 256), 6);
+    let to_u8 = |v: f32| (v.clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+    let ramp: Vec<Color> = (0..w)
+        .map(|x| settings::heat_ramp(x as f32 / (w - 1) as f32))
+        .map(|c| Color::new(to_u8(c.x), to_u8(c.y), to_u8(c.z), 255))
+        .collect();
+    let strip: Vec<Color> = (0..h).flat_map(|_| ramp.iter().copied()).collect();
+    let (x, y) = (8, fb.height as i32 - h - 8);
+    fb.draw_rect(x - 1, y - 1, w + 2, h + 2, Color::new(0, 0, 0, 160));
+    fb.blit(x, y, &strip, w as usize);
+    if let Some(t) = t {
+        let mx = x + (t.clamp(0.0, 1.0) * (w - 1) as f32).round() as i32;
+        fb.draw_circle(mx, y + h / 2, h / 2 + 1, Color::new(0, 0, 0, 200));
+        fb.draw_circle(mx, y + h / 2, h / 2 - 1, Color::WHITE);
+    }
+}
+
+/// Efectos de celda, compuestos en el framebuffer antes de subirlo (se ocultan tras los bloques).
+pub fn draw_effects(fb: &mut Framebuffer, effects: &[CellEffect], cam: &CameraBasis) {
+    for e in effects.iter() {
+        let t = (e.age / e.duration()).clamp(0.0, 1.0);
+        let (base, grow) = match e.kind {
+            EffectKind::Placed => (Color::new(170, 255, 190, 255), 0.35),
+            EffectKind::Removed => (Color::new(255, 210, 140, 255), 0.35),
+            EffectKind::Failed => (Color::new(255, 60, 60, 255), 0.0),
+        };
+        // Falla: parpadeo rojo; éxito: anillo que se expande y se desvanece
+        let alpha = match e.kind {
+            EffectKind::Failed => if (t * 6.0) as i32 % 2 == 0 { 1.0 - t } else { 0.3 * (1.0 - t) },
+            _ => 1.0 - t,
+        };
+        let color = base.alpha(alpha);
+        let half = e.size * (0.5 * (1.0 + grow * t));
+        draw_box_wire(fb, cam, e.center, half, 2.0, color);
+    }
+}
+
+/// Sonidos de colocar/quitar por categoría + blip de error, con mute global.
+/// Usa `assets/sounds/<categoría>.wav` / `error.wav` si existen; si no, los sintetiza.
+pub struct Sfx<'aud> {
+    sounds: HashMap<SoundCategory, Sound<'aud>>,
+    error: Option<Sound<'aud>>,
+    pub muted: bool,
+}
+
+impl<'aud> Sfx<'aud> {
+    pub fn load(audio: &'aud RaylibAudio) -> Self {
+        let load = |name: &str, synth: &dyn Fn() -> Vec<u8>| -> Option<Sound<'aud>> {
+            let path = format!("assets/sounds/{}.wav", name);
+            let wave = if Path::new(&path).exists() {
+                audio.new_wave(&path).ok()
+            } else {
+                audio.new_wave_from_memory(".wav", &synth()).ok()
+            }?;
+            audio.new_sound_from_wave(&wave).ok()
+        };
+
+        let mut sounds = HashMap::new();
+        for cat in SoundCategory::ALL {
+            if let Some(s) = load(cat.name(), &|| synth_block_wav(cat)) { sounds.insert(cat, s); }
+        }
+        let error = load("error", &synth_error_wav);
+        Sfx { sounds, error, muted: false }
+    }
+
+    pub fn play(&self, cat: SoundCategory) {
+        if self.muted { return; }
+        if let Some(s) = self.sounds.get(&cat) { s.play(); }
+    }
+
+    pub fn play_error(&self) {
+        if self.muted { return; }
+        if let Some(s) = &self.error { s.play(); }
+    }
+}
+
+const SAMPLE_RATE: u32 = 22_050;
+
+/// WAV PCM 16-bit mono en memoria.
+fn wav_bytes(samples: &[f32]) -> Vec<u8> {
+    let data_len = (samples.len() * 2) as u32;
+    let mut out = Vec::with_capacity(44 + data_len as usize);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(36 + data_len).to_le_bytes());
+    out.extend_from_slice(b"WAVEfmt ");
+    out.extend_from_slice(&16u32.to_le_bytes());
+    out.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    out.extend_from_slice(&1u16.to_le_bytes()); // mono
+    out.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    out.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    out.extend_from_slice(&2u16.to_le_bytes());
+    out.extend_from_slice(&16u16.to_le_bytes());
+    out.extend_from_slice(b"data");
+    out.extend_from_slice(&data_len.to_le_bytes());
+    for s in samples {
+        out.extend_from_slice(&((s.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes());
+    }
+    out
+}
+
+/// “Toc” corto: ruido + tono con caída exponencial; cada categoría cambia tono/brillo/decay.
+fn synth_block_wav(cat: SoundCategory) -> Vec<u8> {
+    // (frecuencia, mezcla de tono, decay en s)
+    let (freq, tone, decay) = match cat {
+        SoundCategory::Stone => (180.0, 0.35, 0.045),
+        SoundCategory::Dirt => (110.0, 0.15, 0.050),
+        SoundCategory::Wood => (260.0, 0.60, 0.060),
+        SoundCategory::Glass => (1400.0, 0.80, 0.080),
+        SoundCategory::Foliage => (90.0, 0.05, 0.070),
+        SoundCategory::Metal => (620.0, 0.85, 0.120),
+    };
+    let n = (SAMPLE_RATE as f32 * 0.18) as usize;
+    let mut seed: u32 = 0x1234_5678;
+    let samples: Vec<f32> = (0..n).map(|i| {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+        let noise = (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0;
+        let sine = (t * freq * std::f32::consts::TAU).sin();
+        (noise * (1.0 - tone) + sine * tone) * (-t / decay).exp() * 0.6
+    }).collect();
+    wav_bytes(&samples)
+}
+
+/// Dos pitidos graves de onda cuadrada.
+fn synth_error_wav() -> Vec<u8> {
+    let n = (SAMPLE_RATE as f32 * 0.22) as usize;
+    let samples: Vec<f32> = (0..n).map(|i| {
+        let t = i as f32 / SAMPLE_RATE as f32;
+        let on = t < 0.08 || (0.12..0.20).contains(&t);
+        if !on { return 0.0; }
+        let sq = if (t * 220.0).fract() < 0.5 { 1.0 } else { -1.0 };
+        sq * 0.25
+    }).collect();
+    wav_bytes(&samples)
+}
@@ -0,0 +1,112 @@
+// flythrough.rs
+use raylib::prelude::Vector3;
+
+use crate::camera::CameraPreset;
+use crate::json::{self, Json};
+
+/// Punto de control de un recorrido de cámara. La vista usa las mismas unidades que
+/// `camera_presets.json` (radianes), así se pueden copiar vistas guardadas con `Ctrl+1..9`.
+#[derive(Clone, Debug)]
+pub struct Keyframe {
+    pub view: CameraPreset,
+    /// Frames hasta el keyframe siguiente (en el último no se usa).
+    pub frames: u32,
+    /// Dirección de la luz direccional (se interpola); None = la de arranque.
+    pub sun: Option<Vector3>,
+    pub sun_intensity: Option<f32>,
+    /// Carpeta del skybox desde este keyframe (no se interpola: cambia de golpe).
+    pub skybox: Option<String>,
+}
+
+/// Estado de un frame del recorrido.
+#[derive(Clone, Debug)]
+pub struct FrameState {
+    pub view: CameraPreset,
+    pub sun: Option<Vector3>,
+    pub sun_intensity: Option<f32>,
+    pub skybox: Option<String>,
+}
+
+/// Lee un recorrido:
+///
+/// ```text
+/// { "keyframes": [
+///     { "center": [0, 5, 0], "distance": 30, "yaw": 0, "pitch": 0.35, "fov": 1.05, "frames": 48,
+///       "sun": [-1, -1, 0.3], "sun_intensity": 1.2, "skybox": "assets/skyboxes/sky1" },
+///     { "center": [0, 5, 0], "distance": 30, "yaw": 3.1416, "pitch": 0.35, "fov": 1.05 }
+/// ] }
+/// ```
+///
+/// `sun`, `sun_intensity` y `skybox` son opcionales: si faltan, siguen los del keyframe anterior.
+pub fn load(path: &str) -> Result<Vec<Keyframe>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    parse(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+fn parse(text: &str) -> Result<Vec<Keyframe>, String> {
+    let root = json::parse(text)?;
+    let list = root.get("keyframes").and_then(Json::as_array).ok_or("falta el arreglo \"keyframes\"")?;
+    if list.len() < 2 { return Err("hacen falta al menos 2 keyframes".to_string()); }
+
+    let mut keys: Vec<Keyframe> = Vec::with_capacity(list.len());
+    for (i, k) in list.iter().enumerate() {
+        let num = |key: &str| -> Result<Option<f32>, String> {
+            k.get(key).map(|v| v.as_f32().ok_or_else(|| format!("keyframe {}: \"{}\" no es un número", i, key))).transpose()
+        };
+        let vec3 = |key: &str| -> Result<Option<Vector3>, String> {
+            let Some(v) = k.get(key) else { return Ok(None); };
+            match v.as_array().map(|a| a.iter().filter_map(Json::as_f32).collect::<Vec<_>>()).as_deref() {
+                Some(&[x, y, z]) => Ok(Some(Vector3::new(x, y, z))),
+                _ => Err(format!("keyframe {}: \"{}\" necesita 3 números", i, key)),
+            }
+        };
+        let required = |key: &str| -> Result<f32, String> {
+            num(key)?.ok_or_else(|| format!("keyframe {}: falta \"{}\"", i, key))
+        };
+        let prev = keys.last();
+        keys.push(Keyframe {
+            view: CameraPreset {
+                center: vec3("center")?.ok_or_else(|| format!("keyframe {}: falta \"center\"", i))?,
+                distance: required("distance")?,
+                yaw: required("yaw")?,
+                pitch: required("pitch")?,
+                fov: required("fov")?,
+            },
+            frames: num("frames")?.map_or(1, |f| f.max(1.0) as u32),
+            sun: vec3("sun")?.map(|d| d.normalized()).or(prev.and_then(|p| p.sun)),
+            sun_intensity: num("sun_intensity")?.or(prev.and_then(|p| p.sun_intensity)),
+            skybox: k.get("skybox").and_then(Json::as_str).map(str::to_string).or(prev.and_then(|p| p.skybox.clone())),
+        });
+    }
+    Ok(keys)
+}
+
+/// Todos los frames del recorrido: `frames` por tramo y el último keyframe al final.
+/// La cámara sigue una Catmull-Rom por los keyframes (los extremos se repiten como vecinos);
+/// el sol se interpola lineal y se normaliza.
+pub fn frames(keys: &[Keyframe]) -> Vec<FrameState> {
+    let mut out = Vec::new();
+    let Some(last) = keys.last() else { return out; };
+    for i in 0..keys.len() - 1 {
+        let (a, b) = (&keys[i], &keys[i + 1]);
+        let p0 = &keys[i.saturating_sub(1)].view;
+        let p3 = &keys[(i + 2).min(keys.len() - 1)].view;
+        for f in 0..a.frames {
+            let t = f as f32 / a.frames as f32;
+            out.push(FrameState {
+                view: CameraPreset::catmull_rom(p0, &a.view, &b.view, p3, t),
+                sun: match (a.sun, b.sun) {
+                    (Some(s0), Some(s1)) => Some((s0 + (s1 - s0) * t).normalized()),
+                    (s0, _) => s0,
+                },
+                sun_intensity: match (a.sun_intensity, b.sun_intensity) {
+                    (Some(i0), Some(i1)) => Some(i0 + (i1 - i0) * t),
+                    (i0, _) => i0,
+                },
+                skybox: a.skybox.clone(),
+            });
+        }
+    }
+    out.push(FrameState { view: last.view, sun: last.sun, sun_intensity: last.sun_intensity, skybox: last.skybox.clone() });
+    out
+}
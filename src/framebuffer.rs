@@ -2,14 +2,174 @@
 
 use raylib::prelude::*;
 
+/// Qué hacer con los tramos de overlay que quedan detrás de la geometría.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Occlusion {
+    /// No se dibujan.
+    Hide,
+    /// Se dibujan al 25% de opacidad.
+    Dim,
+}
+
+impl Occlusion {
+    pub fn name(self) -> &'static str {
+        match self {
+            Occlusion::Hide => "Ocultar",
+            Occlusion::Dim => "Atenuar",
+        }
+    }
+
+    #[inline]
+    fn alpha(self) -> f32 {
+        match self {
+            Occlusion::Hide => 0.0,
+            Occlusion::Dim => 0.25,
+        }
+    }
+}
+
+/// Fallas seguidas de subida (con su intento de recuperación) antes de rendirse.
+pub const MAX_UPLOAD_FAILURES: u32 = 5;
+
+/// Destino de la subida: recibe un rectángulo de pixeles RGBA8 empaquetados por filas. La
+/// textura GPU es el real; los tests usan uno en memoria.
+pub trait UploadTarget {
+    fn update_rect(&mut self, rect: PixelRect, bytes: &[u8]) -> Result<(), String>;
+}
+
+impl UploadTarget for Texture2D {
+    fn update_rect(&mut self, (x0, y0, x1, y1): PixelRect, bytes: &[u8]) -> Result<(), String> {
+        let rect = Rectangle::new(x0 as f32, y0 as f32, (x1 - x0) as f32, (y1 - y0) as f32);
+        self.update_texture_rec(rect, bytes).map_err(|e| e.to_string())
+    }
+}
+
+/// Sube el buffer CPU a su destino. Es un trait para poder reemplazar la subida real
+/// (p. ej. por una que falle a propósito y ejercitar la recuperación).
+pub trait TextureUploader {
+    fn upload(&mut self, target: &mut dyn UploadTarget, rect: PixelRect, bytes: &[u8]) -> Result<(), String>;
+}
+
+/// Subida real: pasa el rectángulo tal cual al destino.
+pub struct DirectUploader;
+
+impl TextureUploader for DirectUploader {
+    fn upload(&mut self, target: &mut dyn UploadTarget, rect: PixelRect, bytes: &[u8]) -> Result<(), String> {
+        target.update_rect(rect, bytes)
+    }
+}
+
+/// Subida real que falla a propósito una de cada `every` llamadas (`--simulate-upload-failures`),
+/// para probar la recuperación sin romper el driver. Con `every` = 1 falla siempre.
+pub struct FlakyUploader {
+    pub every: u32,
+    calls: u32,
+}
+
+impl FlakyUploader {
+    pub fn new(every: u32) -> Self { FlakyUploader { every: every.max(1), calls: 0 } }
+}
+
+impl TextureUploader for FlakyUploader {
+    fn upload(&mut self, target: &mut dyn UploadTarget, rect: PixelRect, bytes: &[u8]) -> Result<(), String> {
+        self.calls += 1;
+        if self.calls % self.every == 0 {
+            return Err(format!("falla simulada (subida #{})", self.calls));
+        }
+        DirectUploader.upload(target, rect, bytes)
+    }
+}
+
+/// Resultado de un frame que sí se mostró.
+#[derive(Debug)]
+pub enum SwapStatus {
+    Ok,
+    /// Falló la subida pero se recreó la textura desde el buffer CPU; lleva el motivo.
+    Recovered(String),
+}
+
+/// La subida falló y la recuperación también. `consecutive` cuenta frames seguidos así.
+#[derive(Debug)]
+pub struct SwapError {
+    pub reason: String,
+    pub consecutive: u32,
+}
+
+impl SwapError {
+    /// Ya no vale la pena seguir intentando.
+    #[inline]
+    pub fn is_fatal(&self) -> bool { self.consecutive >= MAX_UPLOAD_FAILURES }
+}
+
+/// Textura GPU negra de `w`×`h`, destino de las subidas.
+fn gpu_texture(window: &mut RaylibHandle, raylib_thread: &RaylibThread, w: u32, h: u32) -> Result<Texture2D, String> {
+    let img = Image::gen_image_color(w as i32, h as i32, Color::BLACK);
+    window.load_texture_from_image(raylib_thread, &img).map_err(|e| e.to_string())
+}
+
+/// Rectángulo de pixeles `(x0, y0, x1, y1)`, con x1/y1 exclusivos.
+pub type PixelRect = (u32, u32, u32, u32);
+
+#[inline]
+fn rect_union(a: Option<PixelRect>, b: PixelRect) -> PixelRect {
+    match a {
+        Some(a) => (a.0.min(b.0), a.1.min(b.1), a.2.max(b.2), a.3.max(b.3)),
+        None => b,
+    }
+}
+
+/// Recorta el segmento a `[0, w) × [0, h)` (Liang–Barsky) y redondea los extremos.
+/// None si queda entero afuera.
+fn clip_segment(x0: i32, y0: i32, x1: i32, y1: i32, w: i32, h: i32) -> Option<(i32, i32, i32, i32)> {
+    if w <= 0 || h <= 0 { return None; }
+    let (fx, fy) = (x0 as f32, y0 as f32);
+    let (dx, dy) = (x1 as f32 - fx, y1 as f32 - fy);
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (p, q) in [(-dx, fx), (dx, (w - 1) as f32 - fx), (-dy, fy), (dy, (h - 1) as f32 - fy)] {
+        if p == 0.0 {
+            if q < 0.0 { return None; }
+            continue;
+        }
+        let t = q / p;
+        if p < 0.0 { t0 = t0.max(t); } else { t1 = t1.min(t); }
+        if t0 > t1 { return None; }
+    }
+    let at = |t: f32| ((fx + dx * t).round() as i32, (fy + dy * t).round() as i32);
+    let ((ax, ay), (bx, by)) = (at(t0), at(t1));
+    Some((ax.clamp(0, w - 1), ay.clamp(0, h - 1), bx.clamp(0, w - 1), by.clamp(0, h - 1)))
+}
+
+/// Lo que subió el último `swap_buffers_with` (HUD): pixeles y tiempo; 0 si no había nada sucio.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UploadStats {
+    pub pixels: u64,
+    pub micros: u64,
+}
+
 /// Framebuffer CPU con textura GPU persistente (sin recreación por frame).
 pub struct Framebuffer {
     pub width: u32,
     pub height: u32,
     pixels: Vec<Color>,                               // buffer CPU: width*height
+    /// Profundidad de vista del hit primario por pixel (INFINITY = cielo); la llena `render`.
+    depth: Vec<f32>,
+    /// Copia del último render sin overlays (`save_render` / `restore_render`); vacía si no hay.
+    saved: Vec<Color>,
+    /// Región cambiada desde la última subida; None = la textura GPU ya está al día.
+    dirty: Option<PixelRect>,
+    /// Región que pintaron los overlays desde el último `save_render`/`restore_render`.
+    overlay_dirty: Option<PixelRect>,
+    /// Buffer para empaquetar las filas de un rectángulo sucio que no ocupa todo el ancho.
+    upload_scratch: Vec<Color>,
+    pub last_upload: UploadStats,
+    /// Overlays con test de profundidad: cómo se ven los tramos ocultos.
+    pub occlusion: Occlusion,
     texture_gpu: Option<raylib::texture::Texture2D>,  // textura persistente
     background_color: Color,
     current_color: Color,
+    uploader: Box<dyn TextureUploader>,
+    /// Frames seguidos en los que ni la subida ni la recuperación funcionaron.
+    upload_failures: u32,
 }
 
 impl Framebuffer {
@@ -19,9 +179,18 @@ impl Framebuffer {
             width,
             height,
             pixels: vec![Color::BLACK; n],
+            depth: vec![f32::INFINITY; n],
+            saved: Vec::new(),
+            dirty: Some((0, 0, width, height)),
+            overlay_dirty: None,
+            upload_scratch: Vec::new(),
+            last_upload: UploadStats::default(),
+            occlusion: Occlusion::Dim,
             texture_gpu: None,
             background_color: Color::BLACK,
             current_color: Color::WHITE,
+            uploader: Box::new(DirectUploader),
+            upload_failures: 0,
         }
     }
 
@@ -30,7 +199,8 @@ impl Framebuffer {
         self.texture_gpu = Some(tex);
     }
 
-    /// Acceso mutable al buffer para render paralelo.
+    /// Acceso mutable al buffer para render paralelo. Quien escriba tiene que avisar con
+    /// `mark_dirty` qué parte cambió (si no, la textura GPU no se entera).
     #[inline]
     pub fn pixels_mut(&mut self) -> &mut [Color] {
         &mut self.pixels
@@ -42,12 +212,218 @@ impl Framebuffer {
         &self.pixels
     }
 
+    /// Buffer de profundidad, mismo orden que `pixels`.
+    #[inline]
+    pub fn depth_mut(&mut self) -> &mut [f32] {
+        &mut self.depth
+    }
+
+    /// Marca `(x0, y0, x1, y1)` (exclusivo) para subirlo en el próximo `swap_buffers_with`.
+    pub fn mark_dirty(&mut self, x0: u32, y0: u32, x1: u32, y1: u32) {
+        let (x1, y1) = (x1.min(self.width), y1.min(self.height));
+        if x0 >= x1 || y0 >= y1 { return; }
+        self.dirty = Some(rect_union(self.dirty, (x0, y0, x1, y1)));
+    }
+
+    #[inline]
+    pub fn mark_all_dirty(&mut self) {
+        self.mark_dirty(0, 0, self.width, self.height);
+    }
+
     /// Limpia el buffer CPU sin recrearlo.
     pub fn clear(&mut self) {
         let bg = self.background_color;
         for px in self.pixels.iter_mut() {
             *px = bg;
         }
+        self.depth.fill(f32::INFINITY);
+        self.mark_all_dirty();
+    }
+
+    /// Guarda los pixeles recién renderizados, antes de componer overlays encima.
+    pub fn save_render(&mut self) {
+        self.saved.clone_from(&self.pixels);
+        self.overlay_dirty = None;
+    }
+
+    /// Vuelve al último render guardado (la profundidad no la tocan los overlays, sigue valiendo).
+    /// Solo copia (y marca sucio) lo que pintaron los overlays desde entonces.
+    /// false si no hay uno del tamaño actual: hay que renderizar.
+    pub fn restore_render(&mut self) -> bool {
+        if self.saved.len() != self.pixels.len() { return false; }
+        if let Some((x0, y0, x1, y1)) = self.overlay_dirty.take() {
+            let w = self.width as usize;
+            for y in y0 as usize..y1 as usize {
+                let row = y * w;
+                self.pixels[row + x0 as usize..row + x1 as usize].copy_from_slice(&self.saved[row + x0 as usize..row + x1 as usize]);
+            }
+            self.mark_dirty(x0, y0, x1, y1);
+        }
+        true
+    }
+
+    /// Mezcla `color` con opacidad `a` (0..1) sobre el pixel `idx`; el alpha guardado queda en 255.
+    /// No marca nada: el que llama junta la región y llama a `mark_overlay`.
+    #[inline]
+    fn mix_at(&mut self, idx: usize, color: Color, a: f32) {
+        self.pixels[idx] = if a >= 1.0 {
+            Color::new(color.r, color.g, color.b, 255)
+        } else {
+            let dst = self.pixels[idx];
+            let mix = |s: u8, d: u8| (s as f32 * a + d as f32 * (1.0 - a) + 0.5) as u8;
+            Color::new(mix(color.r, dst.r), mix(color.g, dst.g), mix(color.b, dst.b), 255)
+        };
+    }
+
+    /// Marca `rect` como sucio y como overlay (`restore_render` lo deshace).
+    #[inline]
+    fn mark_overlay(&mut self, rect: PixelRect) {
+        self.overlay_dirty = Some(rect_union(self.overlay_dirty, rect));
+        self.dirty = Some(rect_union(self.dirty, rect));
+    }
+
+    /// Mezcla `color` (con su alpha) sobre el pixel si pasa el test de profundidad;
+    /// si queda detrás de la geometría aplica `occlusion`.
+    #[inline]
+    fn blend_depth(&mut self, x: i32, y: i32, z: f32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 { return; }
+        let idx = y as usize * self.width as usize + x as usize;
+        // Sesgo: los contornos de un bloque caen casi sobre sus propias caras
+        let hidden = z > self.depth[idx] * 1.002 + 0.03;
+        let a = color.a as f32 / 255.0 * if hidden { self.occlusion.alpha() } else { 1.0 };
+        if a <= 0.0 { return; }
+        self.mix_at(idx, color, a);
+        self.mark_overlay((x as u32, y as u32, x as u32 + 1, y as u32 + 1));
+    }
+
+    // ==== Primitivas 2D sin profundidad (overlays que salen en las capturas) ====
+    // Coordenadas en pixeles del framebuffer; todo se recorta al buffer. Con `color.a` = 255
+    // escriben el color; con menos, lo mezclan encima (variante translúcida). Como los overlays
+    // con profundidad, marcan su región para que `restore_render` la limpie.
+
+    /// Pixel suelto con mezcla por alpha; fuera del buffer no hace nada.
+    #[inline]
+    pub fn blend_pixel(&mut self, x: i32, y: i32, color: Color) {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 || color.a == 0 { return; }
+        self.mix_at(y as usize * self.width as usize + x as usize, color, color.a as f32 / 255.0);
+        self.mark_overlay((x as u32, y as u32, x as u32 + 1, y as u32 + 1));
+    }
+
+    /// Segmento de 1 px (Bresenham) de `(x0, y0)` a `(x1, y1)`, ambos incluidos. Se recorta
+    /// primero contra el buffer (Liang–Barsky), así una línea casi toda afuera no se recorre entera.
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: Color) {
+        let Some((x0, y0, x1, y1)) = clip_segment(x0, y0, x1, y1, self.width as i32, self.height as i32) else { return; };
+        let (dx, dy) = ((x1 - x0).abs(), -(y1 - y0).abs());
+        let (sx, sy) = (if x0 < x1 { 1 } else { -1 }, if y0 < y1 { 1 } else { -1 });
+        let (mut x, mut y, mut err) = (x0, y0, dx + dy);
+        loop {
+            self.blend_pixel(x, y, color);
+            if x == x1 && y == y1 { break; }
+            let e2 = 2 * err;
+            if e2 >= dy { err += dy; x += sx; }
+            if e2 <= dx { err += dx; y += sy; }
+        }
+    }
+
+    /// Rectángulo lleno con esquina `(x, y)` y tamaño `w`×`h`.
+    pub fn draw_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: Color) {
+        let (x0, y0) = (x.max(0), y.max(0));
+        let (x1, y1) = (x.saturating_add(w).min(self.width as i32), y.saturating_add(h).min(self.height as i32));
+        if x0 >= x1 || y0 >= y1 || color.a == 0 { return; }
+        let (a, width) = (color.a as f32 / 255.0, self.width as usize);
+        for py in y0..y1 {
+            let row = py as usize * width;
+            for px in x0..x1 { self.mix_at(row + px as usize, color, a); }
+        }
+        self.mark_overlay((x0 as u32, y0 as u32, x1 as u32, y1 as u32));
+    }
+
+    /// Disco lleno de centro `(cx, cy)` y radio `r` (r = 0: un pixel). Cada fila es un tramo
+    /// recortado, así un círculo enorme fuera de pantalla no cuesta nada.
+    pub fn draw_circle(&mut self, cx: i32, cy: i32, r: i32, color: Color) {
+        if r < 0 { return; }
+        // En i64: centro y radio cerca de los extremos de i32 no desbordan al restar
+        let (cx, cy, r) = (cx as i64, cy as i64, r as i64);
+        let (w, h) = (self.width as i64, self.height as i64);
+        for py in (cy - r).max(0)..=(cy + r).min(h - 1) {
+            let dy = (py - cy) as f32;
+            let half = ((r as f32 + 0.5).powi(2) - dy * dy).max(0.0).sqrt() as i64;
+            let (x0, x1) = ((cx - half).max(0), (cx + half + 1).min(w));
+            if x0 < x1 { self.draw_rect(x0 as i32, py as i32, (x1 - x0) as i32, 1, color); }
+        }
+    }
+
+    /// Copia una imagen `src` (filas de `src_w` pixeles) con su esquina en `(x, y)`, mezclando
+    /// cada pixel por su alpha (0 = transparente).
+    pub fn blit(&mut self, x: i32, y: i32, src: &[Color], src_w: usize) {
+        if src_w == 0 { return; }
+        let (x, y, src_h) = (x as i64, y as i64, (src.len() / src_w) as i64);
+        let (x0, y0) = (x.max(0), y.max(0));
+        let (x1, y1) = ((x + src_w as i64).min(self.width as i64), (y + src_h).min(self.height as i64));
+        if x0 >= x1 || y0 >= y1 { return; }
+        let width = self.width as usize;
+        for py in y0..y1 {
+            let (row, src_row) = (py as usize * width, (py - y) as usize * src_w);
+            for px in x0..x1 {
+                let c = src[src_row + (px - x) as usize];
+                if c.a > 0 { self.mix_at(row + px as usize, c, c.a as f32 / 255.0); }
+            }
+        }
+        self.mark_overlay((x0 as u32, y0 as u32, x1 as u32, y1 as u32));
+    }
+
+    /// Mira en el centro del framebuffer: cruz de brazos `arm` px con un pixel de hueco en el
+    /// medio y contorno oscuro translúcido para que se lea sobre cielo y sobre bloques claros.
+    pub fn draw_crosshair(&mut self, arm: i32, color: Color) {
+        let (cx, cy) = (self.width as i32 / 2, self.height as i32 / 2);
+        let shadow = Color::new(0, 0, 0, 110);
+        self.draw_rect(cx - arm - 1, cy - 1, arm, 3, shadow);
+        self.draw_rect(cx + 2, cy - 1, arm, 3, shadow);
+        self.draw_rect(cx - 1, cy - arm - 1, 3, arm, shadow);
+        self.draw_rect(cx - 1, cy + 2, 3, arm, shadow);
+        self.draw_line(cx - arm, cy, cx - 2, cy, color);
+        self.draw_line(cx + 2, cy, cx + arm, cy, color);
+        self.draw_line(cx, cy - arm, cx, cy - 2, color);
+        self.draw_line(cx, cy + 2, cx, cy + arm, color);
+    }
+
+    /// Segmento en pantalla con profundidad: `a`/`b` = (x, y, z de vista). La z se interpola
+    /// lineal en pantalla, así que los segmentos largos conviene partirlos antes en mundo.
+    pub fn draw_line_depth(&mut self, a: Vector3, b: Vector3, thick: f32, color: Color) {
+        let steps = (b.x - a.x).abs().max((b.y - a.y).abs()).ceil().max(1.0) as i32;
+        let t = thick.round().max(1.0) as i32;
+        let (lo, hi) = (-(t - 1) / 2, t / 2);
+        let mut last: Option<(i32, i32)> = None;
+        for i in 0..=steps {
+            let k = i as f32 / steps as f32;
+            let (x, y) = ((a.x + (b.x - a.x) * k).round() as i32, (a.y + (b.y - a.y) * k).round() as i32);
+            if last == Some((x, y)) { continue; }
+            let z = a.z + (b.z - a.z) * k;
+            for oy in lo..=hi {
+                for ox in lo..=hi {
+                    let (px, py) = (x + ox, y + oy);
+                    // Ya lo cubrió el sello anterior
+                    if last.is_some_and(|(lx, ly)| (lo..=hi).contains(&(px - lx)) && (lo..=hi).contains(&(py - ly))) {
+                        continue;
+                    }
+                    self.blend_depth(px, py, z, color);
+                }
+            }
+            last = Some((x, y));
+        }
+    }
+
+    /// Disco lleno a profundidad constante `c.z`.
+    pub fn fill_circle_depth(&mut self, c: Vector3, radius: f32, color: Color) {
+        let r = radius.ceil() as i32;
+        let (cx, cy) = (c.x.round() as i32, c.y.round() as i32);
+        for oy in -r..=r {
+            for ox in -r..=r {
+                if (ox * ox + oy * oy) as f32 <= radius * radius {
+                    self.blend_depth(cx + ox, cy + oy, c.z, color);
+                }
+            }
+        }
     }
 
     /// Escritura de píxel directa (para usos puntuales).
@@ -56,6 +432,7 @@ impl Framebuffer {
         if x >= self.width || y >= self.height { return; }
         let idx = (y as usize) * (self.width as usize) + (x as usize);
         self.pixels[idx] = self.current_color;
+        self.mark_dirty(x, y, x + 1, y + 1);
     }
 
     pub fn set_background_color(&mut self, color: Color) {
@@ -66,34 +443,275 @@ impl Framebuffer {
         self.current_color = color;
     }
 
+    /// Color de `set_pixel`; también sirve como color de las primitivas 2D.
+    #[inline]
+    pub fn current_color(&self) -> Color { self.current_color }
+
+    /// Reemplaza la forma de subir a la GPU (por defecto `DirectUploader`).
+    pub fn set_uploader(&mut self, uploader: Box<dyn TextureUploader>) {
+        self.uploader = uploader;
+    }
+
+    /// Sube a la textura GPU solo el rectángulo sucio; sin nada sucio no llama al driver.
+    /// Si el rectángulo no ocupa todo el ancho, sus filas se empaquetan en `upload_scratch`.
+    fn upload_to<T: UploadTarget>(&mut self, target: &mut Option<T>) -> Result<(), String> {
+        let Some(target) = target.as_mut() else { return Err("no hay textura GPU".to_string()); };
+        let Some((x0, y0, x1, y1)) = self.dirty else {
+            self.last_upload = UploadStats::default();
+            return Ok(());
+        };
+        let started = std::time::Instant::now();
+        let w = self.width as usize;
+        let src: &[Color] = if x0 == 0 && x1 == self.width {
+            &self.pixels[y0 as usize * w..y1 as usize * w]
+        } else {
+            self.upload_scratch.clear();
+            for y in y0 as usize..y1 as usize {
+                self.upload_scratch.extend_from_slice(&self.pixels[y * w + x0 as usize..y * w + x1 as usize]);
+            }
+            &self.upload_scratch
+        };
+        let bytes: &[u8] = unsafe {
+            std::slice::from_raw_parts(src.as_ptr() as *const u8, std::mem::size_of_val(src))
+        };
+        self.uploader.upload(target, (x0, y0, x1, y1), bytes)?;
+        self.dirty = None;
+        self.last_upload = UploadStats { pixels: src.len() as u64, micros: started.elapsed().as_micros() as u64 };
+        Ok(())
+    }
+
+    /// Crea de nuevo la textura GPU (del tamaño actual del framebuffer) y le sube el buffer CPU.
+    /// Es la recuperación cuando falla una subida; sirve igual si algún día cambia el tamaño.
+    pub fn recreate_texture(&mut self, window: &mut RaylibHandle, raylib_thread: &RaylibThread) -> Result<(), String> {
+        self.texture_gpu = None; // libera la anterior antes de pedir otra
+        let mut tex = None;
+        let result = self.recreate_into(&mut tex, |w, h| gpu_texture(window, raylib_thread, w, h));
+        self.texture_gpu = tex;
+        result
+    }
+
+    /// Reemplaza `target` por uno nuevo de `create(ancho, alto)` y le sube todo el buffer.
+    fn recreate_into<T: UploadTarget>(
+        &mut self, target: &mut Option<T>, create: impl FnOnce(u32, u32) -> Result<T, String>,
+    ) -> Result<(), String> {
+        *target = None;
+        *target = Some(create(self.width, self.height)?);
+        // El destino nuevo está en negro: hay que subir todo
+        self.mark_all_dirty();
+        self.upload_to(target)
+    }
+
+    /// Sube lo sucio a `target`; si falla, lo recrea una vez con `create`. Si eso también falla
+    /// cuenta el frame en `upload_failures` (un frame que sale bien pone la cuenta en cero).
+    fn sync_target<T: UploadTarget>(
+        &mut self, target: &mut Option<T>, create: impl FnOnce(u32, u32) -> Result<T, String>,
+    ) -> Result<SwapStatus, SwapError> {
+        let status = match self.upload_to(target) {
+            Ok(()) => Ok(SwapStatus::Ok),
+            Err(first) => match self.recreate_into(target, create) {
+                Ok(()) => Ok(SwapStatus::Recovered(first)),
+                Err(second) => {
+                    self.upload_failures += 1;
+                    Err(SwapError { reason: format!("{}; al recrear: {}", first, second), consecutive: self.upload_failures })
+                }
+            },
+        };
+        if status.is_ok() { self.upload_failures = 0; }
+        status
+    }
+
+    /// Cambia la resolución interna: rehace los buffers CPU y la textura GPU al nuevo tamaño.
+    /// La ventana no cambia; `swap_buffers_with` estira la textura a pantalla completa.
+    pub fn resize(&mut self, window: &mut RaylibHandle, raylib_thread: &RaylibThread, width: u32, height: u32) -> Result<(), String> {
+        if width == self.width && height == self.height { return Ok(()); }
+        let n = (width as usize) * (height as usize);
+        self.width = width;
+        self.height = height;
+        self.pixels = vec![self.background_color; n];
+        self.depth = vec![f32::INFINITY; n];
+        self.saved.clear();
+        self.overlay_dirty = None;
+        self.recreate_texture(window, raylib_thread)
+    }
+
     /// Sube el buffer CPU a la textura persistente y **pinta**.
     /// Acepta un `draw_overlay` para que dibujes el HUD en el **mismo frame** (una sola Begin/End).
+    /// Si la subida falla intenta una vez recrear la textura; si eso también falla el frame se
+    /// muestra sin imagen (solo overlay) y se devuelve el error para que main decida.
     pub fn swap_buffers_with<F>(
         &mut self,
         window: &mut RaylibHandle,
         raylib_thread: &RaylibThread,
         mut draw_overlay: F,
-    )
+    ) -> Result<SwapStatus, SwapError>
     where
         F: FnMut(&mut RaylibDrawHandle),
     {
-        if let Some(tex) = &mut self.texture_gpu {
-            let byte_len = self.pixels.len() * std::mem::size_of::<Color>();
-            let bytes: &[u8] = unsafe {
-                std::slice::from_raw_parts(self.pixels.as_ptr() as *const u8, byte_len)
-            };
+        let mut tex = self.texture_gpu.take();
+        let status = self.sync_target(&mut tex, |w, h| gpu_texture(window, raylib_thread, w, h));
+        self.texture_gpu = tex;
+
+        // Dibuja frame + overlay en una sola pasada (aunque no haya imagen: la ventana
+        // sigue procesando eventos y el HUD muestra el aviso)
+        let mut d = window.begin_drawing(raylib_thread);
+        d.clear_background(Color::BLACK);
+        if let (Ok(_), Some(tex)) = (&status, &self.texture_gpu) {
+            // Con resolución interna menor que la ventana, la textura se estira a toda la pantalla
+            let src = Rectangle::new(0.0, 0.0, self.width as f32, self.height as f32);
+            let dst = Rectangle::new(0.0, 0.0, d.get_screen_width() as f32, d.get_screen_height() as f32);
+            d.draw_texture_pro(tex, src, dst, Vector2::zero(), 0.0, Color::WHITE);
+        }
+
+        // HUD/overlay del usuario
+        draw_overlay(&mut d);
+        drop(d);
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color { r: 255, g: 0, b: 0, a: 255 };
+
+    fn lit(fb: &Framebuffer) -> Vec<(i32, i32)> {
+        let w = fb.width as usize;
+        fb.pixels().iter().enumerate()
+            .filter(|(_, c)| c.r > 0 || c.g > 0 || c.b > 0)
+            .map(|(i, _)| ((i % w) as i32, (i / w) as i32))
+            .collect()
+    }
 
-            // Actualiza TODO el área de la textura (0,0, w, h)
-            let rect = Rectangle::new(0.0, 0.0, self.width as f32, self.height as f32);
-            tex.update_texture_rec(rect, bytes).expect("update_texture_rec failed");
+    #[test]
+    fn circle_clips_at_edges() {
+        let mut fb = Framebuffer::new(16, 12);
+        fb.draw_circle(0, 0, 2, RED);
+        // cuarto de disco de radio 2.5: filas de 3, 3 y 2 pixeles adentro
+        assert_eq!(lit(&fb), [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (0, 2), (1, 2)]);
 
-            // Dibuja frame + overlay en una sola pasada
-            let mut d = window.begin_drawing(raylib_thread);
-            d.clear_background(Color::BLACK);
-            d.draw_texture(tex, 0, 0, Color::WHITE);
+        let mut fb = Framebuffer::new(16, 12);
+        fb.draw_circle(17, 6, 1, RED);
+        fb.draw_circle(-40, -40, 10, RED);
+        assert!(lit(&fb).is_empty());
+        assert!(fb.overlay_dirty.is_none());
+
+        fb.draw_circle(15, 11, 1, RED);
+        assert_eq!(lit(&fb), [(14, 10), (15, 10), (14, 11), (15, 11)]);
+        assert_eq!(fb.overlay_dirty, Some((14, 10, 16, 12)));
+    }
+
+    #[test]
+    fn circle_extreme_coordinates() {
+        let mut fb = Framebuffer::new(8, 8);
+        fb.draw_circle(i32::MIN, i32::MAX, i32::MAX, RED);
+        fb.draw_circle(i32::MAX, i32::MIN, 5, RED);
+        assert!(lit(&fb).is_empty());
+        // radio enorme centrado adentro: cubre todo el buffer
+        fb.draw_circle(4, 4, i32::MAX, RED);
+        assert_eq!(lit(&fb).len(), 64);
+    }
 
-            // HUD/overlay del usuario
-            draw_overlay(&mut d);
+    #[test]
+    fn blit_clips_at_corners() {
+        // imagen 3×3 con el valor de rojo = índice + 1; el centro es transparente
+        let src: Vec<Color> = (0..9u8).map(|i| Color::new(i + 1, 0, 0, if i == 4 { 0 } else { 255 })).collect();
+
+        let mut fb = Framebuffer::new(6, 5);
+        fb.blit(-1, -1, &src, 3);
+        // entra la esquina inferior derecha de 2×2; el centro transparente no pinta (0, 0)
+        assert_eq!(lit(&fb), [(1, 0), (0, 1), (1, 1)]);
+        assert_eq!([fb.pixels()[1].r, fb.pixels()[6].r, fb.pixels()[7].r], [6, 8, 9]);
+        assert_eq!(fb.overlay_dirty, Some((0, 0, 2, 2)));
+
+        let mut fb = Framebuffer::new(6, 5);
+        fb.blit(5, 4, &src, 3);
+        assert_eq!(lit(&fb), [(5, 4)]);
+        assert_eq!(fb.pixels()[29].r, 1);
+        assert_eq!(fb.overlay_dirty, Some((5, 4, 6, 5)));
+
+        let mut fb = Framebuffer::new(6, 5);
+        fb.blit(6, 0, &src, 3);
+        fb.blit(0, -3, &src, 3);
+        fb.blit(i32::MIN, i32::MIN, &src, 3);
+        fb.blit(i32::MAX, i32::MAX, &src, 3);
+        fb.blit(0, 0, &[], 0);
+        assert!(lit(&fb).is_empty());
+        assert!(fb.overlay_dirty.is_none());
+    }
+
+    #[test]
+    fn line_clips_and_survives_extremes() {
+        let mut fb = Framebuffer::new(8, 8);
+        fb.draw_line(-10, 3, 20, 3, RED);
+        assert_eq!(lit(&fb).len(), 8);
+        fb.draw_line(i32::MIN, i32::MIN, i32::MAX, i32::MAX, RED);
+        fb.draw_line(-5, -1, 20, -1, RED);
+        assert!(lit(&fb).iter().all(|&(x, y)| (0..8).contains(&x) && (0..8).contains(&y)));
+    }
+
+    /// Destino en memoria: guarda los rectángulos recibidos; `broken` hace fallar cada subida.
+    #[derive(Default)]
+    struct MemTarget {
+        broken: bool,
+        uploads: Vec<PixelRect>,
+    }
+
+    impl UploadTarget for MemTarget {
+        fn update_rect(&mut self, rect: PixelRect, bytes: &[u8]) -> Result<(), String> {
+            if self.broken { return Err("destino perdido".into()); }
+            let (w, h) = (rect.2 - rect.0, rect.3 - rect.1);
+            assert_eq!(bytes.len(), (w * h * 4) as usize);
+            self.uploads.push(rect);
+            Ok(())
         }
     }
+
+    #[test]
+    fn failed_upload_recovers_by_recreating() {
+        let mut fb = Framebuffer::new(4, 3);
+        let mut target = Some(MemTarget { broken: true, ..MemTarget::default() });
+        fb.blend_pixel(1, 1, RED);
+        let status = fb.sync_target(&mut target, |_, _| Ok(MemTarget::default()));
+        assert!(matches!(status, Ok(SwapStatus::Recovered(ref why)) if why == "destino perdido"));
+        // el destino nuevo recibió el buffer entero
+        assert_eq!(target.as_ref().unwrap().uploads, [(0, 0, 4, 3)]);
+        assert_eq!(fb.upload_failures, 0);
+
+        // sin nada sucio no se llama al destino
+        assert!(matches!(fb.sync_target(&mut target, |_, _| Err("no".into())), Ok(SwapStatus::Ok)));
+        assert_eq!(target.as_ref().unwrap().uploads.len(), 1);
+    }
+
+    #[test]
+    fn consecutive_failures_become_fatal() {
+        let mut fb = Framebuffer::new(4, 3);
+        let mut target: Option<MemTarget> = None;
+        for n in 1..=MAX_UPLOAD_FAILURES {
+            let err = fb.sync_target(&mut target, |_, _| Err("sin memoria de video".into())).unwrap_err();
+            assert_eq!(err.consecutive, n);
+            assert_eq!(err.is_fatal(), n == MAX_UPLOAD_FAILURES);
+            assert!(err.reason.ends_with("al recrear: sin memoria de video"));
+        }
+        // un frame que sale bien pone la cuenta en cero
+        assert!(matches!(fb.sync_target(&mut target, |_, _| Ok(MemTarget::default())), Ok(SwapStatus::Recovered(_))));
+        assert_eq!(fb.upload_failures, 0);
+    }
+
+    #[test]
+    fn flaky_uploader_fails_every_nth() {
+        let mut fb = Framebuffer::new(4, 3);
+        fb.set_uploader(Box::new(FlakyUploader::new(2)));
+        let mut target = Some(MemTarget::default());
+        assert!(matches!(fb.sync_target(&mut target, |_, _| Ok(MemTarget::default())), Ok(SwapStatus::Ok)));
+        fb.blend_pixel(2, 0, RED);
+        // la 2.ª subida falla y la recreación (3.ª) entra
+        assert!(matches!(fb.sync_target(&mut target, |_, _| Ok(MemTarget::default())), Ok(SwapStatus::Recovered(_))));
+        // con every = 1 falla también la subida al destino recreado
+        fb.set_uploader(Box::new(FlakyUploader::new(1)));
+        fb.mark_all_dirty();
+        let err = fb.sync_target(&mut target, |_, _| Ok(MemTarget::default())).unwrap_err();
+        assert!(err.reason.starts_with("falla simulada"));
+        assert_eq!(err.consecutive, 1);
+    }
 }
@@ -0,0 +1,89 @@
+// gamma.rs
+use std::sync::OnceLock;
+use raylib::prelude::Vector3;
+
+use crate::ray_intersect::Intersect;
+
+/// Espacio de trabajo de un render, según `RenderSettings::gamma_correct`. Encendido: pipeline
+/// lineal (lo autorado en sRGB se decodifica al leer y el pixel final se codifica a sRGB).
+/// Apagado: los valores sRGB se usan tal cual. Viaja con los ajustes del render (`RenderSettings::gamma`)
+/// en lugar de ser global, así dos renders con modos distintos no se pisan.
+///
+/// Las texturas se decodifican siempre a lineal dentro de `ray_intersect` (el hit lo marca con
+/// `Intersect::textured`); `resolve` las lleva al espacio de trabajo antes de sombrear.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Gamma {
+    pub correct: bool,
+}
+
+impl Gamma {
+    /// Canal de 8 bits (color de luz) → espacio de trabajo.
+    #[inline]
+    pub fn decode_u8(self, c: u8) -> f32 {
+        if self.correct { srgb_u8_to_linear(c) } else { c as f32 / 255.0 }
+    }
+
+    /// Valor autorado en sRGB [0..1] (umbrales, cielos procedurales) → espacio de trabajo.
+    #[inline]
+    pub fn decode(self, c: f32) -> f32 {
+        if self.correct { srgb_to_linear(c) } else { c }
+    }
+
+    #[inline]
+    pub fn decode_vec(self, v: Vector3) -> Vector3 {
+        Vector3::new(self.decode(v.x), self.decode(v.y), self.decode(v.z))
+    }
+
+    /// Espacio de trabajo → valor para el framebuffer [0..1+].
+    #[inline]
+    pub fn encode(self, c: f32) -> f32 {
+        if self.correct { linear_to_srgb(c) } else { c }
+    }
+
+    /// Texeles y promedios precalculados (siempre en lineal) → espacio de trabajo.
+    #[inline]
+    pub fn from_linear(self, v: Vector3) -> Vector3 {
+        if self.correct { v } else { Vector3::new(linear_to_srgb(v.x), linear_to_srgb(v.y), linear_to_srgb(v.z)) }
+    }
+
+    /// Pasa el color de un hit texturado al espacio de trabajo (una sola vez: limpia la marca).
+    #[inline]
+    pub fn resolve(self, hit: &mut Intersect) {
+        if hit.textured {
+            hit.material.diffuse = self.from_linear(hit.material.diffuse);
+            hit.textured = false;
+        }
+    }
+}
+
+#[inline]
+pub fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+#[inline]
+pub fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.max(0.0);
+    if c <= 0.003_130_8 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+fn srgb_lut() -> &'static [f32; 256] {
+    static LUT: OnceLock<[f32; 256]> = OnceLock::new();
+    LUT.get_or_init(|| {
+        let mut t = [0.0; 256];
+        for (i, v) in t.iter_mut().enumerate() { *v = srgb_to_linear(i as f32 / 255.0); }
+        t
+    })
+}
+
+/// Canal de 8 bits de una textura → lineal (por tabla).
+#[inline]
+pub fn srgb_u8_to_linear(c: u8) -> f32 {
+    srgb_lut()[c as usize]
+}
+
+/// Color autorado en sRGB (tintes y umbrales de `TexStyle`) → lineal.
+#[inline]
+pub fn srgb_vec_to_linear(v: Vector3) -> Vector3 {
+    Vector3::new(srgb_to_linear(v.x), srgb_to_linear(v.y), srgb_to_linear(v.z))
+}
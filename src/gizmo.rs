@@ -0,0 +1,286 @@
+// gizmo.rs
+use raylib::prelude::*;
+
+use crate::build::find_object_index_by_center;
+use crate::camera::{CameraBasis, Projection};
+use crate::accel::UniformGridAccel;
+use crate::feedback::{draw_box_wire, draw_line_3d, segment_to_screen, to_screen, to_screen_depth};
+use crate::framebuffer::Framebuffer;
+use crate::light::{Light, LightKind};
+use crate::ray_intersect::RayIntersect;
+use crate::tools::{EditEvent, SceneEdit};
+
+/// Largo de las flechas del gizmo, en celdas.
+const ARROW_CELLS: f32 = 1.5;
+
+/// Distancia máxima (pixeles) del mouse a una flecha para agarrarla.
+const GRAB_PX: f32 = 8.0;
+
+const AXES: [Vector3; 3] = [
+    Vector3 { x: 1.0, y: 0.0, z: 0.0 },
+    Vector3 { x: 0.0, y: 1.0, z: 0.0 },
+    Vector3 { x: 0.0, y: 0.0, z: 1.0 },
+];
+
+#[derive(Clone, Copy, Debug)]
+struct Drag {
+    axis: usize,
+    /// Parámetro sobre el eje al empezar (unidades de mundo desde el centroide).
+    start_t: f32,
+    /// Desplazamiento actual en celdas enteras.
+    cells: i32,
+}
+
+/// Selección de bloques + gizmo de traslación de 3 ejes.
+/// La selección guarda centros de celda (los índices de `objects` cambian con cada edición).
+#[derive(Default)]
+pub struct MoveGizmo {
+    pub selection: Vec<Vector3>,
+    drag: Option<Drag>,
+}
+
+#[inline]
+fn same_cell(a: Vector3, b: Vector3) -> bool {
+    (a - b).length() < 1e-3
+}
+
+/// Distancia de `p` al segmento `a`-`b` en pantalla.
+fn segment_distance(p: Vector2, a: Vector2, b: Vector2) -> f32 {
+    let ab = b - a;
+    let len2 = ab.x * ab.x + ab.y * ab.y;
+    let t = if len2 > 0.0 { ((p - a).x * ab.x + (p - a).y * ab.y) / len2 } else { 0.0 };
+    let q = a + ab * t.clamp(0.0, 1.0);
+    (p - q).length()
+}
+
+/// Parámetro del punto del eje (`origin` + t·`axis`, `axis` unitario) más cercano al rayo del mouse.
+/// None si el rayo es casi paralelo al eje.
+fn closest_on_axis(origin: Vector3, axis: Vector3, ro: Vector3, rd: Vector3) -> Option<f32> {
+    let rd = rd.normalized();
+    let w0 = ro - origin;
+    let b = rd.dot(axis);
+    let denom = 1.0 - b * b;
+    if denom < 1e-4 { return None; }
+    Some((axis.dot(w0) - b * rd.dot(w0)) / denom)
+}
+
+impl MoveGizmo {
+    pub fn new() -> Self { Self::default() }
+
+    #[inline]
+    pub fn is_dragging(&self) -> bool { self.drag.is_some() }
+
+    /// Agrega o quita la celda de la selección.
+    pub fn toggle(&mut self, center: Vector3) {
+        match self.selection.iter().position(|c| same_cell(*c, center)) {
+            Some(i) => { self.selection.swap_remove(i); }
+            None => self.selection.push(center),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.selection.clear();
+        self.drag = None;
+    }
+
+    pub fn cancel(&mut self) { self.drag = None; }
+
+    /// Descarta celdas que quedaron vacías por otras ediciones.
+    pub fn retain_existing(&mut self, objects: &[Box<dyn RayIntersect>]) {
+        self.selection.retain(|c| find_object_index_by_center(objects, *c).is_some());
+    }
+
+    fn centroid(&self) -> Option<Vector3> {
+        if self.selection.is_empty() { return None; }
+        let sum = self.selection.iter().fold(Vector3::zero(), |acc, c| acc + *c);
+        Some(sum / self.selection.len() as f32)
+    }
+
+    fn delta(&self, cube_size: Vector3) -> Vector3 {
+        match self.drag {
+            Some(d) => {
+                let step = [cube_size.x, cube_size.y, cube_size.z][d.axis];
+                AXES[d.axis] * (d.cells as f32 * step)
+            }
+            None => Vector3::zero(),
+        }
+    }
+
+    /// Flechas en pantalla: (eje, inicio, punta).
+    fn arrows(&self, cam: &CameraBasis, w: f32, h: f32, cube_size: Vector3) -> Vec<(usize, Vector2, Vector2)> {
+        let Some(c) = self.centroid() else { return Vec::new(); };
+        let c = c + self.delta(cube_size);
+        (0..3).filter_map(|i| {
+            let len = [cube_size.x, cube_size.y, cube_size.z][i] * ARROW_CELLS;
+            let a = to_screen(cam, c, w, h)?;
+            let b = to_screen(cam, c + AXES[i] * len, w, h)?;
+            Some((i, a, b))
+        }).collect()
+    }
+
+    /// Eje bajo el mouse, si hay selección.
+    pub fn hovered_axis(&self, cam: &CameraBasis, mouse: Vector2, w: f32, h: f32, cube_size: Vector3) -> Option<usize> {
+        self.arrows(cam, w, h, cube_size).into_iter()
+            .map(|(i, a, b)| (i, segment_distance(mouse, a, b)))
+            .filter(|(_, dist)| *dist <= GRAB_PX)
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .map(|(i, _)| i)
+    }
+
+    pub fn begin_drag(&mut self, axis: usize, ro: Vector3, rd: Vector3) {
+        let Some(c) = self.centroid() else { return; };
+        if let Some(start_t) = closest_on_axis(c, AXES[axis], ro, rd) {
+            self.drag = Some(Drag { axis, start_t, cells: 0 });
+        }
+    }
+
+    /// Sigue al mouse en pasos de celda entera.
+    pub fn update_drag(&mut self, ro: Vector3, rd: Vector3, cube_size: Vector3) {
+        let (Some(c), Some(drag)) = (self.centroid(), self.drag.as_mut()) else { return; };
+        if let Some(t) = closest_on_axis(c, AXES[drag.axis], ro, rd) {
+            let step = [cube_size.x, cube_size.y, cube_size.z][drag.axis];
+            drag.cells = ((t - drag.start_t) / step).round() as i32;
+        }
+    }
+
+    /// Destinos del arrastre y si cada uno está ocupado por un bloque ajeno a la selección
+    /// (o fuera de los límites de construcción).
+    fn destinations(&self, objects: &[Box<dyn RayIntersect>], cube_size: Vector3) -> Vec<(Vector3, bool)> {
+        let delta = self.delta(cube_size);
+        self.selection.iter().map(|c| {
+            let dest = *c + delta;
+            let occupied = find_object_index_by_center(objects, dest).is_some()
+                && !self.selection.iter().any(|s| same_cell(*s, dest));
+            (dest, occupied || !SceneEdit::in_bounds(dest))
+        }).collect()
+    }
+
+    /// Suelta el arrastre: mueve todo el grupo de una vez o, si algún destino está ocupado,
+    /// no mueve nada y marca las celdas en conflicto.
+    pub fn commit(&mut self, edit: &mut SceneEdit) {
+        if self.drag.is_none_or(|d| d.cells == 0) {
+            self.drag = None;
+            return;
+        }
+        let dests = self.destinations(edit.objects, edit.cube_size);
+        self.drag = None;
+        if dests.iter().any(|(_, blocked)| *blocked) {
+            for (center, _) in dests.iter().filter(|(_, blocked)| *blocked) {
+                edit.events.push(EditEvent::Failed { center: *center });
+            }
+            return;
+        }
+
+        // Primero se quitan todos (los destinos pueden pisar celdas del propio grupo)
+        let mut moved = Vec::with_capacity(self.selection.len());
+        for (src, (dest, _)) in self.selection.iter().zip(dests.iter()) {
+            if let Some(idx) = find_object_index_by_center(edit.objects, *src) {
+                if let Some(ch) = edit.objects[idx].block_id() {
+                    edit.remove(idx);
+                    moved.push((*dest, ch));
+                }
+            }
+        }
+        for (dest, ch) in moved.iter() {
+            edit.place(*dest, *ch);
+        }
+        self.selection = moved.into_iter().map(|(dest, _)| dest).collect();
+    }
+
+    /// Selección (cian), destinos del arrastre (blanco libre / rojo ocupado) y flechas X/Y/Z.
+    /// Se compone en el framebuffer con test de profundidad: lo que queda detrás de bloques se
+    /// oculta o atenúa según `fb.occlusion`.
+    pub fn draw(&self, fb: &mut Framebuffer, cam: &CameraBasis, mouse: Vector2, objects: &[Box<dyn RayIntersect>], cube_size: Vector3) {
+        let Some(centroid) = self.centroid() else { return; };
+        let (w, h) = (fb.width as f32, fb.height as f32);
+        let half = cube_size * 0.5;
+
+        for c in self.selection.iter() {
+            draw_box_wire(fb, cam, *c, half * 1.02, 2.0, Color::new(90, 220, 255, 220));
+        }
+        if self.drag.is_some_and(|dr| dr.cells != 0) {
+            for (dest, blocked) in self.destinations(objects, cube_size) {
+                let color = if blocked { Color::new(255, 60, 60, 230) } else { Color::new(255, 255, 255, 200) };
+                draw_box_wire(fb, cam, dest, half * 0.98, 2.0, color);
+            }
+        }
+
+        let active = self.drag.map(|dr| dr.axis).or_else(|| self.hovered_axis(cam, mouse, w, h, cube_size));
+        let c = centroid + self.delta(cube_size);
+        for (i, axis) in AXES.iter().enumerate() {
+            let color = if active == Some(i) {
+                Color::new(255, 230, 80, 255)
+            } else {
+                [Color::new(230, 70, 70, 255), Color::new(80, 210, 90, 255), Color::new(80, 130, 240, 255)][i]
+            };
+            let tip = c + *axis * ([cube_size.x, cube_size.y, cube_size.z][i] * ARROW_CELLS);
+            draw_line_3d(fb, cam, c, tip, 3.0, color);
+            if let Some(t) = to_screen_depth(cam, tip, w, h) {
+                fb.fill_circle_depth(t, 6.0, color);
+            }
+        }
+    }
+}
+
+/// Ícono de la luz en el overlay: sol (círculo + rayos) en la posición de la luz puntual/foco,
+/// atenuado y tachado si hay geometría entre el ojo y la luz; nada si queda detrás de la cámara.
+/// Focos y direccionales dibujan además una línea corta con su dirección; las de área, su cuadrado.
+pub fn draw_light_gizmo(d: &mut RaylibDrawHandle, cam: &CameraBasis, light: &Light, objects: &[Box<dyn RayIntersect>], accel: &UniformGridAccel, screen_w: i32, screen_h: i32) {
+    let (w, h) = (screen_w as f32, screen_h as f32);
+    let base = Color::new(255, 220, 90, 255);
+
+    if let LightKind::Directional = light.kind {
+        // Flecha anclada delante de la cámara: hacia dónde viaja la luz
+        let anchor = cam.eye + cam.forward * 6.0;
+        if let Some((a, b)) = segment_to_screen(cam, anchor, anchor + light.direction * 1.5, w, h) {
+            d.draw_line_ex(a, b, 2.0, base);
+            d.draw_circle_v(a, 4.0, base);
+        }
+        return;
+    }
+
+    if let LightKind::Spot { direction, .. } = light.kind {
+        if let Some((a, b)) = segment_to_screen(cam, light.position, light.position + direction * 1.5, w, h) {
+            d.draw_line_ex(a, b, 2.0, base.alpha(0.8));
+        }
+    }
+
+    if let LightKind::Area { .. } = light.kind {
+        let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(u, v)| light.sample_point(u, v));
+        for i in 0..4 {
+            if let Some((a, b)) = segment_to_screen(cam, corners[i], corners[(i + 1) % 4], w, h) {
+                d.draw_line_ex(a, b, 2.0, base.alpha(0.8));
+            }
+        }
+    }
+
+    let Some(p) = to_screen(cam, light.position, w, h) else { return; };
+    if p.x < -16.0 || p.y < -16.0 || p.x > w + 16.0 || p.y > h + 16.0 { return; }
+
+    // Rayo de oclusión desde el ojo (en ortográfica, desde el plano de la cámara)
+    let (ro, rd, dist) = match cam.projection {
+        Projection::Perspective { .. } => {
+            let to = light.position - cam.eye;
+            let dist = to.length();
+            (cam.eye, to / dist.max(1e-6), dist)
+        }
+        Projection::Orthographic { .. } => {
+            let z = (light.position - cam.eye).dot(cam.forward);
+            (light.position - cam.forward * z, cam.forward, z)
+        }
+    };
+    let hidden = accel.occluded(&ro, &rd, dist, objects);
+    let color = if hidden { base.alpha(0.35) } else { base };
+
+    d.draw_circle_v(p, 6.0, color);
+    for k in 0..8 {
+        let ang = k as f32 * std::f32::consts::FRAC_PI_4;
+        let dir = Vector2::new(ang.cos(), ang.sin());
+        d.draw_line_ex(p + dir * 9.0, p + dir * 13.0, 2.0, color);
+    }
+    if hidden {
+        let x = Color::new(255, 80, 80, 200);
+        d.draw_line_ex(p + Vector2::new(-10.0, -10.0), p + Vector2::new(10.0, 10.0), 2.0, x);
+        d.draw_line_ex(p + Vector2::new(-10.0, 10.0), p + Vector2::new(10.0, -10.0), 2.0, x);
+    }
+}
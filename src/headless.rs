@@ -0,0 +1,204 @@
+// headless.rs
+//! Render sin ventana: la misma `render` del loop interactivo sobre un `Framebuffer` sin
+//! textura GPU, volcado a una `Image` de CPU. Nada acá toca `RaylibHandle` (ni HUD, ni íconos,
+//! ni audio, ni `Texture2D`), así que sirve desde scripts por lotes.
+
+use std::collections::HashMap;
+
+use raylib::prelude::*;
+
+use crate::accel::UniformGridAccel;
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::flythrough;
+use crate::light::{Light, LightKind};
+use crate::presets::{CameraPresets, PRESETS_FILE};
+use crate::ray_intersect::RayIntersect;
+use crate::settings::RenderSettings;
+use crate::skybox::Skybox;
+use crate::texture::TextureCache;
+use crate::ui;
+
+/// Tamaño por defecto: el de la ventana interactiva.
+const DEFAULT_SIZE: (u32, u32) = (1300, 900);
+
+const DEFAULT_SKYBOX: &str = "assets/skyboxes/sky1";
+
+/// Un frame de `width`×`height` de la escena, sin overlays ni acumulación (path tracing = 1 muestra).
+pub fn render_to_image(
+    objects: &[Box<dyn RayIntersect>], accel: &UniformGridAccel, camera: &Camera, lights: &[Light],
+    skybox: Option<&Skybox>, settings: &RenderSettings, width: u32, height: u32,
+) -> Image {
+    let mut fb = Framebuffer::new(width.max(1), height.max(1));
+    crate::render(&mut fb, objects, accel, camera, lights, None, skybox, settings, None, None, None);
+    framebuffer_image(&fb)
+}
+
+/// Copia los pixeles del framebuffer a una `Image` (CPU; exportable con `export_image`).
+/// Un solo `memcpy`: a 4K, `draw_pixel` por pixel serían millones de llamadas FFI.
+pub fn framebuffer_image(fb: &Framebuffer) -> Image {
+    let img = Image::gen_image_color(fb.width as i32, fb.height as i32, Color::BLACK);
+    let src = fb.pixels();
+    // `gen_image_color` crea RGBA8 de `width*height` texeles, el mismo layout que `Color`
+    unsafe {
+        std::ptr::copy_nonoverlapping(src.as_ptr(), img.data() as *mut Color, src.len());
+    }
+    img
+}
+
+/// Valor de `--flag`: los `n` argumentos siguientes como números (None si el flag no está).
+fn numbers(args: &[String], flag: &str, n: usize) -> Result<Option<Vec<f32>>, String> {
+    let Some(i) = args.iter().position(|a| a == flag) else { return Ok(None); };
+    let vals: Vec<f32> = args.iter().skip(i + 1).take(n).map_while(|s| s.parse().ok()).collect();
+    if vals.len() != n { return Err(format!("{} necesita {} número{}", flag, n, if n == 1 { "" } else { "s" })); }
+    Ok(Some(vals))
+}
+
+/// Cámara de `--camera-preset <slot>` (de `camera_presets.json`) o de `--eye x y z`,
+/// `--target x y z` y `--fov <grados>`; lo que falte sale de la cámara de arranque.
+fn camera_from_args(args: &[String]) -> Result<Camera, String> {
+    let mut camera = crate::startup::startup_camera();
+    if let Some(v) = numbers(args, "--camera-preset", 1)? {
+        let presets = CameraPresets::load(PRESETS_FILE)?;
+        let preset = presets.get(v[0] as usize).ok_or_else(|| format!("{}: no hay vista en el slot {}", PRESETS_FILE, v[0]))?;
+        camera.load_preset(&preset);
+    }
+    let eye = numbers(args, "--eye", 3)?;
+    let target = numbers(args, "--target", 3)?;
+    if eye.is_some() || target.is_some() {
+        let basis = camera.basis();
+        let eye = eye.map_or(basis.eye, |v| Vector3::new(v[0], v[1], v[2]));
+        let target = target.map_or(basis.eye + basis.forward, |v| Vector3::new(v[0], v[1], v[2]));
+        let fov = camera.fov();
+        camera = Camera::new(eye, target, Vector3::new(0.0, 1.0, 0.0));
+        camera.set_fov(fov);
+    }
+    if let Some(v) = numbers(args, "--fov", 1)? { camera.set_fov(v[0].to_radians()); }
+    Ok(camera)
+}
+
+/// Lo que comparten `run` y `run_flythrough`: escena cargada, grilla, ajustes y tamaño.
+struct Batch {
+    objects: Vec<Box<dyn RayIntersect>>,
+    accel: UniformGridAccel,
+    settings: RenderSettings,
+    textures: TextureCache,
+    size: (u32, u32),
+}
+
+/// Carga paleta y escena (`--scene`, `--run`, `--obj*`, `--vox`), arma la grilla y lee
+/// `--width`/`--height` y los flags de render. Err = mensaje para stderr (código 2).
+fn setup(args: &[String]) -> Result<Batch, String> {
+    let w = numbers(args, "--width", 1)?.map_or(DEFAULT_SIZE.0, |v| v[0] as u32);
+    let h = numbers(args, "--height", 1)?.map_or(DEFAULT_SIZE.1, |v| v[0] as u32);
+
+    let mut textures = TextureCache::new();
+    let (mut palette, stone_mat) = crate::startup::build_palette(&mut textures);
+    let mut params = crate::startup::startup_params(Vector3::new(1.0, 1.0, 1.0));
+    let mut notes = ui::Notifications::new();
+    let objects = crate::startup::load_startup_scene(args, &mut palette, stone_mat, &mut params, &mut textures, &mut notes);
+    let accel = UniformGridAccel::build(&objects, crate::startup::grid_cell_from_args(args, &objects, params.cube_size));
+
+    // Sin presupuesto de frame: calidad completa y el cielo en su resolución original
+    let mut settings = crate::startup::settings_from_args(args);
+    settings.sky_full_res = true;
+    Ok(Batch { objects, accel, settings, textures, size: (w, h) })
+}
+
+fn report_texture_errors(textures: &mut TextureCache) {
+    for e in textures.take_errors() {
+        eprintln!("Textura {} (se usa un tablero magenta)", e);
+    }
+}
+
+/// `export_image` no informa errores: se borra la salida vieja y se mira si apareció.
+fn write_png(img: &Image, path: &str) -> bool {
+    let _ = std::fs::remove_file(path);
+    img.export_image(path);
+    std::fs::metadata(path).is_ok()
+}
+
+/// `--headless --out <png>` o `--render <png>`: carga la escena (`setup`), la cámara
+/// (`camera_from_args`), luces de arranque y skybox (`--skybox <carpeta>`, por defecto `sky1`;
+/// con `--sky-preset <dia|atardecer|noche>`, el cielo procedural), renderiza un frame de `--width`×`--height` y lo escribe. Devuelve el código de salida.
+pub fn run(args: &[String]) -> i32 {
+    let out = ["--render", "--out"].iter()
+        .find_map(|flag| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)));
+    let Some(out) = out else {
+        eprintln!("uso: --render <archivo.png> [--width W --height H] [--scene <carpeta|json>] [--skybox <carpeta> | --sky-preset <dia|atardecer|noche>]");
+        eprintln!("     [--eye x y z] [--target x y z] [--fov grados] [--camera-preset N] [flags de escena y render]");
+        return 2;
+    };
+    let (camera, mut batch) = match camera_from_args(args).and_then(|c| Ok((c, setup(args)?))) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    let sky_dir = args.iter().position(|a| a == "--skybox").and_then(|i| args.get(i + 1)).map_or(DEFAULT_SKYBOX, |s| s.as_str());
+    let procedural = crate::startup::sky_preset_arg(args).is_some();
+    let skybox = (!procedural).then(|| Skybox::from_folder(sky_dir, &mut batch.textures));
+    report_texture_errors(&mut batch.textures);
+
+    let (w, h) = batch.size;
+    let started = std::time::Instant::now();
+    let img = render_to_image(&batch.objects, &batch.accel, &camera, &crate::startup::startup_lights(), skybox.as_ref(), &batch.settings, w, h);
+    if !write_png(&img, out) {
+        eprintln!("No se pudo escribir {}", out);
+        return 1;
+    }
+    println!("{}: {}x{}, {} objetos, {:.0} ms", out, w, h, batch.objects.len(), started.elapsed().as_secs_f32() * 1000.0);
+    0
+}
+
+/// `--flythrough <recorrido.json> [--out-dir <carpeta>]`: renderiza cada frame del recorrido
+/// (`flythrough::frames`) a `<carpeta>/frame_0001.png`, ... (por defecto `frames/`) con la misma
+/// escena y flags que `--render`. Imprime el avance por frame. Devuelve el código de salida.
+pub fn run_flythrough(args: &[String]) -> i32 {
+    let Some(path) = args.iter().position(|a| a == "--flythrough").and_then(|i| args.get(i + 1)) else {
+        eprintln!("uso: --flythrough <recorrido.json> [--out-dir <carpeta>] [--width W --height H] [flags de escena y render]");
+        return 2;
+    };
+    let out_dir = args.iter().position(|a| a == "--out-dir").and_then(|i| args.get(i + 1)).map_or("frames", |s| s.as_str());
+    let (frames, mut batch) = match flythrough::load(path).and_then(|k| Ok((flythrough::frames(&k), setup(args)?))) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("{}", e);
+            return 2;
+        }
+    };
+    if let Err(e) = std::fs::create_dir_all(out_dir) {
+        eprintln!("No se pudo crear {}: {}", out_dir, e);
+        return 1;
+    }
+
+    // Un skybox por carpeta: se arma la primera vez que un keyframe lo pide
+    let mut skyboxes: HashMap<String, Skybox> = HashMap::new();
+    let mut camera = crate::startup::startup_camera();
+    let (w, h) = batch.size;
+    let started = std::time::Instant::now();
+    for (i, f) in frames.iter().enumerate() {
+        let frame_started = std::time::Instant::now();
+        camera.load_preset(&f.view);
+        let mut lights = crate::startup::startup_lights();
+        if let Some(sun) = lights.iter_mut().find(|l| l.kind == LightKind::Directional) {
+            if let Some(d) = f.sun { sun.direction = d; }
+            if let Some(k) = f.sun_intensity { sun.intensity = k; }
+        }
+        let sky_dir = f.skybox.as_deref().unwrap_or(DEFAULT_SKYBOX);
+        if !skyboxes.contains_key(sky_dir) {
+            skyboxes.insert(sky_dir.to_string(), Skybox::from_folder(sky_dir, &mut batch.textures));
+            report_texture_errors(&mut batch.textures);
+        }
+        let img = render_to_image(&batch.objects, &batch.accel, &camera, &lights, skyboxes.get(sky_dir), &batch.settings, w, h);
+        let out = format!("{}/frame_{:04}.png", out_dir, i + 1);
+        if !write_png(&img, &out) {
+            eprintln!("No se pudo escribir {}", out);
+            return 1;
+        }
+        println!("{} ({}/{}): {:.0} ms", out, i + 1, frames.len(), frame_started.elapsed().as_secs_f32() * 1000.0);
+    }
+    println!("{} frames {}x{} en {:.1} s", frames.len(), w, h, started.elapsed().as_secs_f32());
+    0
+}
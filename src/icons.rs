@@ -0,0 +1,141 @@
+// icons.rs
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use raylib::prelude::*;
+
+use crate::build::{make_block_from_palette, slab_half};
+use crate::camera::{CameraBasis, Projection};
+use crate::gamma;
+use crate::palette::CubeTemplate;
+use crate::texture::SampleMode;
+use crate::ray_intersect::RayIntersect;
+
+/// Lado de los íconos generados (pixeles).
+pub const ICON_SIZE: i32 = 48;
+
+/// Carpeta de íconos ya renderizados (uno por hash de plantilla).
+pub const ICON_CACHE_DIR: &str = "cache/icons";
+
+/// Cambiar al modificar cómo se renderizan: invalida la caché.
+const ICON_VERSION: u32 = 2;
+
+/// Sub-muestras por lado (bordes suaves contra el fondo transparente).
+const ICON_SS: i32 = 3;
+
+/// Ícono de un bloque de la paleta: vista isométrica del bloque solo, fondo transparente.
+/// Se lee de la caché si ya existe; si no, se raytracea y se guarda como PNG.
+pub fn block_icon(block: char, tpl: &CubeTemplate) -> Image {
+    let path = format!("{}/{:016x}.png", ICON_CACHE_DIR, template_hash(block, tpl));
+    if Path::new(&path).exists() {
+        if let Ok(img) = Image::load_image(&path) { return img; }
+    }
+    let img = render_icon(block, tpl);
+    if std::fs::create_dir_all(ICON_CACHE_DIR).is_ok() {
+        img.export_image(&path);
+    }
+    img
+}
+
+/// FNV-1a de 64 bits. La clave de la caché vive en disco: `DefaultHasher` puede cambiar de
+/// algoritmo entre versiones de Rust y dejaría huérfanos todos los íconos.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self { Fnv1a(0xcbf2_9ce4_8422_2325) }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.0 = (self.0 ^ b as u64).wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 { self.0 }
+}
+
+/// Hash de todo lo que afecta al ícono: texturas (contenido), estilos, material y forma.
+fn template_hash(block: char, tpl: &CubeTemplate) -> u64 {
+    let mut h = Fnv1a::new();
+    ICON_VERSION.hash(&mut h);
+    ICON_SIZE.hash(&mut h);
+    slab_half(block).is_some().then_some(block).hash(&mut h);
+    // El ícono se sombrea con luz fija: del material solo cuenta el color difuso
+    let d = tpl.material.diffuse;
+    for c in [d.x, d.y, d.z] { c.to_bits().hash(&mut h); }
+    tpl.shape.hash(&mut h);
+    for face in tpl.face_textures.iter() {
+        match face {
+            Some(fs) => {
+                fs.tex.content_hash(&mut h);
+                if let Some(nm) = &fs.normal_map { nm.content_hash(&mut h); }
+                format!("{:?} {:?} {}", fs.style, fs.sample, fs.rotation).hash(&mut h);
+            }
+            None => 0u8.hash(&mut h),
+        }
+    }
+    h.finish()
+}
+
+fn render_icon(block: char, tpl: &CubeTemplate) -> Image {
+    let size = Vector3::one();
+    // El bloque va solo: sin vecinos con los que empalmar, el borde recortado de `Inset` se ve mejor
+    let tpl = tpl.clone().with_sample_mode(SampleMode::Inset);
+    let obj: Box<dyn RayIntersect> = make_block_from_palette(Vector3::zero(), size, block, &tpl);
+
+    // Isométrica: desde +X +Y +Z mirando al centro; alto visible ≈ diagonal proyectada del cubo
+    let forward = Vector3::new(-1.0, -0.8, -1.0).normalized();
+    let right = forward.cross(Vector3::new(0.0, 1.0, 0.0)).normalized();
+    let up = right.cross(forward).normalized();
+    let cam = CameraBasis {
+        eye: -forward * 4.0,
+        forward,
+        right,
+        up,
+        projection: Projection::Orthographic { height: 1.75 },
+    };
+    let light = Vector3::new(0.35, 1.0, 0.55).normalized();
+
+    let mut img = Image::gen_image_color(ICON_SIZE, ICON_SIZE, Color::BLANK);
+    let n = ICON_SS as f32;
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let mut rgb = Vector3::zero();
+            let mut alpha = 0.0;
+            for sj in 0..ICON_SS {
+                for si in 0..ICON_SS {
+                    let fx = x as f32 + (si as f32 + 0.5) / n;
+                    let fy = y as f32 + (sj as f32 + 0.5) / n;
+                    let sx = 2.0 * fx / ICON_SIZE as f32 - 1.0;
+                    let sy = 1.0 - 2.0 * fy / ICON_SIZE as f32;
+                    let (ro, rd) = cam.ray(sx, sy, 1.0);
+                    let hit = obj.ray_intersect(&ro, &rd);
+                    if !hit.is_intersecting { continue; }
+                    // Caras con brillo fijo tipo inventario: arriba clara, lados más oscuros
+                    let shade = 0.55 + 0.45 * hit.normal.dot(light).max(0.0);
+                    rgb += hit.material.diffuse * shade * hit.coverage;
+                    alpha += hit.coverage;
+                }
+            }
+            if alpha <= 0.0 { continue; }
+            let c = rgb / alpha;
+            let to_u8 = |v: f32| (gamma::linear_to_srgb(v).clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+            let a = (alpha / (n * n) * 255.0 + 0.5) as u8;
+            img.draw_pixel(x, y, Color::new(to_u8(c.x), to_u8(c.y), to_u8(c.z), a));
+        }
+    }
+    img
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fnv1a_matches_reference_vectors() {
+        let fnv = |s: &str| { let mut h = Fnv1a::new(); h.write(s.as_bytes()); h.finish() };
+        assert_eq!(fnv(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv("foobar"), 0x8594_4171_f739_67e8);
+    }
+}
@@ -0,0 +1,194 @@
+// json.rs
+//! Lector JSON mínimo (sin dependencias) para archivos de escena: objetos, arreglos, strings
+//! con escapes, números, booleanos y null. Los errores indican línea y columna.
+
+use std::fmt::Write as _;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Num(f64),
+    Str(String),
+    Arr(Vec<Json>),
+    /// Pares en el orden del archivo (las claves repetidas se quedan con la primera en `get`).
+    Obj(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Obj(pairs) => pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self { Json::Num(n) => Some(*n as f32), _ => None }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self { Json::Str(s) => Some(s), _ => None }
+    }
+
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self { Json::Arr(a) => Some(a), _ => None }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, Json)]> {
+        match self { Json::Obj(o) => Some(o), _ => None }
+    }
+}
+
+/// String JSON entre comillas con los escapes necesarios.
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(out, "\\u{:04x}", c as u32); }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+pub fn parse(text: &str) -> Result<Json, String> {
+    let mut p = Parser { src: text, chars: text.char_indices().peekable() };
+    let value = p.value()?;
+    p.skip_ws();
+    if p.chars.peek().is_some() { return Err(p.error("sobra texto después del valor")); }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn offset(&mut self) -> usize {
+        self.chars.peek().map_or(self.src.len(), |&(i, _)| i)
+    }
+
+    fn error(&mut self, msg: &str) -> String {
+        let before = &self.src[..self.offset()];
+        let line = before.matches('\n').count() + 1;
+        let col = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+        format!("línea {}, columna {}: {}", line, col, msg)
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.chars.peek(), Some((_, c)) if c.is_whitespace()) { self.chars.next(); }
+    }
+
+    fn expect(&mut self, want: char) -> Result<(), String> {
+        self.skip_ws();
+        match self.chars.peek() {
+            Some(&(_, c)) if c == want => { self.chars.next(); Ok(()) }
+            _ => Err(self.error(&format!("se esperaba '{}'", want))),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_ws();
+        match self.chars.peek().map(|&(_, c)| c) {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => Ok(Json::Str(self.string()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let start = self.offset();
+                while matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_alphabetic()) { self.chars.next(); }
+                match &self.src[start..self.offset()] {
+                    "true" => Ok(Json::Bool(true)),
+                    "false" => Ok(Json::Bool(false)),
+                    "null" => Ok(Json::Null),
+                    w => { let w = w.to_string(); Err(self.error(&format!("palabra desconocida '{}'", w))) }
+                }
+            }
+            Some(_) => Err(self.error("se esperaba un valor")),
+            None => Err(self.error("el archivo termina antes de tiempo")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        self.expect('{')?;
+        let mut pairs = Vec::new();
+        self.skip_ws();
+        if matches!(self.chars.peek(), Some((_, '}'))) { self.chars.next(); return Ok(Json::Obj(pairs)); }
+        loop {
+            self.skip_ws();
+            if !matches!(self.chars.peek(), Some((_, '"'))) { return Err(self.error("se esperaba una clave entre comillas")); }
+            let key = self.string()?;
+            self.expect(':')?;
+            pairs.push((key, self.value()?));
+            self.skip_ws();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(Json::Obj(pairs)),
+                _ => return Err(self.error("se esperaba ',' o '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.skip_ws();
+        if matches!(self.chars.peek(), Some((_, ']'))) { self.chars.next(); return Ok(Json::Arr(items)); }
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(Json::Arr(items)),
+                _ => return Err(self.error("se esperaba ',' o ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.chars.next(); // comilla de apertura
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(out),
+                Some((_, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    Some((_, '/')) => out.push('/'),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, 'b')) => out.push('\u{8}'),
+                    Some((_, 'f')) => out.push('\u{c}'),
+                    Some((_, 'u')) => {
+                        let hex: String = (0..4).filter_map(|_| self.chars.next().map(|(_, c)| c)).collect();
+                        let c = u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32)
+                            .ok_or_else(|| self.error("escape \\u inválido"))?;
+                        out.push(c);
+                    }
+                    _ => return Err(self.error("escape inválido")),
+                },
+                Some((_, c)) => out.push(c),
+                None => return Err(self.error("string sin cerrar")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.offset();
+        while matches!(self.chars.peek(), Some((_, c)) if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            self.chars.next();
+        }
+        let raw = &self.src[start..self.offset()];
+        raw.parse::<f64>().map(Json::Num).map_err(|_| { let raw = raw.to_string(); self.error(&format!("número inválido '{}'", raw)) })
+    }
+}
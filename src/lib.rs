@@ -0,0 +1,56 @@
+// lib.rs
+//! Raytracer de bloques como biblioteca: escena, aceleración, cámara y `render`. `main.rs` es el
+//! loop interactivo de raylib encima; un script por lotes puede quedarse con `render_to_image`.
+
+#[macro_use]
+pub mod profile;
+pub mod framebuffer;
+pub mod ray_intersect;
+pub mod sphere;
+pub mod camera;
+pub mod light;
+pub mod material;
+pub mod cube;
+pub mod slab;
+pub mod stairs;
+pub mod texture;
+pub mod scene;
+pub mod palette;
+pub mod accel;
+pub mod build;
+pub mod skybox;
+pub mod settings;
+pub mod gamma;
+pub mod post;
+pub mod walk;
+pub mod tools;
+pub mod mesh;
+pub mod feedback;
+pub mod pathtrace;
+pub mod icons;
+pub mod script;
+pub mod gizmo;
+pub mod ui;
+pub mod stats;
+pub mod daycycle;
+pub mod presets;
+pub mod headless;
+pub mod json;
+pub mod vox;
+pub mod flythrough;
+pub mod transform;
+pub mod perf;
+pub mod render;
+pub mod startup;
+
+pub use accel::UniformGridAccel;
+pub use camera::{Camera, Projection};
+pub use framebuffer::Framebuffer;
+pub use headless::render_to_image;
+pub use light::Light;
+pub use material::Material;
+pub use palette::Palette;
+pub use ray_intersect::{Intersect, RayIntersect};
+pub use render::render;
+pub use scene::{Cell, SceneParams};
+pub use settings::RenderSettings;
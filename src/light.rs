@@ -1,19 +1,59 @@
 use raylib::prelude::*;
 
 /// Tipo de luz
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LightKind {
     Point,      
     Directional,
+    /// Foco: como Point pero limitado a un cono (ángulos en radianes, medidos desde el eje).
+    Spot { direction: Vector3, inner_angle: f32, outer_angle: f32 },
+    /// Cuadrado de lado `size` centrado en `position`, perpendicular a `direction`. Ilumina como
+    /// Point desde el centro; las sombras muestrean `samples` puntos de la superficie (penumbra).
+    Area { size: f32, samples: u32 },
 }
 
-#[derive(Clone, Copy)]
+/// Atenuación por distancia de luces Point/Spot: 1 / (constante + lineal·d + cuadrática·d²).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Attenuation {
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+}
+
+impl Attenuation {
+    /// Sin caída (comportamiento histórico: la intensidad no dependía de la distancia).
+    pub const NONE: Attenuation = Attenuation { constant: 1.0, linear: 0.0, quadratic: 0.0 };
+
+    /// Niveles que recorren las teclas , y . (de menos a más caída; alcance aprox. 200, 100, 50 y 32 unidades).
+    pub const PRESETS: [Attenuation; 5] = [
+        Attenuation::NONE,
+        Attenuation { constant: 1.0, linear: 0.022, quadratic: 0.0019 },
+        Attenuation { constant: 1.0, linear: 0.045, quadratic: 0.0075 },
+        Attenuation { constant: 1.0, linear: 0.09, quadratic: 0.032 },
+        Attenuation { constant: 1.0, linear: 0.14, quadratic: 0.07 },
+    ];
+
+    #[inline]
+    pub fn factor(&self, dist: f32) -> f32 {
+        1.0 / (self.constant + self.linear * dist + self.quadratic * dist * dist).max(1e-4)
+    }
+}
+
+impl Default for Attenuation {
+    fn default() -> Self { Attenuation::NONE }
+}
+
+#[derive(Clone, Copy, PartialEq)]
 pub struct Light {
     pub kind: LightKind,
     pub position: Vector3,
     pub direction: Vector3,
     pub color: Color,
     pub intensity: f32,
+    /// Si genera glints especulares (cuestan un rayo de oclusión extra por punto).
+    pub glints: bool,
+    /// Caída con la distancia; no afecta a las direccionales.
+    pub attenuation: Attenuation,
 }
 
 impl Light {
@@ -24,6 +64,8 @@ impl Light {
             direction: Vector3::new(-1.0, -1.0, -1.0).normalized(),
             color,
             intensity,
+            glints: true,
+            attenuation: Attenuation::NONE,
         }
     }
 
@@ -35,12 +77,71 @@ impl Light {
             direction: d,
             color,
             intensity,
+            glints: true,
+            attenuation: Attenuation::NONE,
+        }
+    }
+
+    /// Foco en `position` apuntando a `dir`. Entre `inner_angle` y `outer_angle` la luz cae suave.
+    pub fn spot(position: Vector3, dir: Vector3, inner_angle: f32, outer_angle: f32, color: Color, intensity: f32) -> Self {
+        let d = if dir.length() > 0.0 { dir.normalized() } else { Vector3::new(0.0, -1.0, 0.0) };
+        let outer = outer_angle.max(1e-3);
+        Self {
+            kind: LightKind::Spot { direction: d, inner_angle: inner_angle.clamp(0.0, outer), outer_angle: outer },
+            position,
+            direction: d,
+            color,
+            intensity,
+            glints: true,
+            attenuation: Attenuation::NONE,
+        }
+    }
+
+    /// Luz de área: cuadrado de lado `size` en `position` mirando hacia `dir`.
+    pub fn area(position: Vector3, dir: Vector3, size: f32, samples: u32, color: Color, intensity: f32) -> Self {
+        let d = if dir.length() > 0.0 { dir.normalized() } else { Vector3::new(0.0, -1.0, 0.0) };
+        Self {
+            kind: LightKind::Area { size: size.max(0.0), samples: samples.max(1) },
+            position,
+            direction: d,
+            color,
+            intensity,
+            glints: true,
+            attenuation: Attenuation::NONE,
+        }
+    }
+
+    /// Punto de la superficie para (u, v) en [0, 1)² (luces de área); `position` en las demás.
+    pub fn sample_point(&self, u: f32, v: f32) -> Vector3 {
+        match self.kind {
+            LightKind::Area { size, .. } => {
+                let n = self.direction;
+                let helper = if n.y.abs() > 0.9 { Vector3::new(1.0, 0.0, 0.0) } else { Vector3::new(0.0, 1.0, 0.0) };
+                let t = helper.cross(n).normalized();
+                let b = n.cross(t);
+                self.position + t * ((u - 0.5) * size) + b * ((v - 0.5) * size)
+            }
+            _ => self.position,
         }
     }
 
+    /// Como `at`, pero hacia el punto (u, v) de la superficie (ver `sample_point`); en las luces
+    /// sin superficie es lo mismo que `at`.
+    pub fn at_sample(&self, point: Vector3, u: f32, v: f32) -> (Vector3, f32) {
+        match self.kind {
+            LightKind::Area { .. } => {
+                let to = self.sample_point(u, v) - point;
+                let dist = to.length();
+                if dist > 0.0 { (to / dist, dist) } else { (Vector3::new(0.0, -1.0, 0.0), 0.0) }
+            }
+            _ => self.at(point),
+        }
+    }
+
+    /// Dirección y distancia hacia la luz (el centro en las de área).
     pub fn at(&self, point: Vector3) -> (Vector3, f32) {
         match self.kind {
-            LightKind::Point => {
+            LightKind::Point | LightKind::Spot { .. } | LightKind::Area { .. } => {
                 let to = self.position - point;
                 let dist = to.length();
                 if dist > 0.0 { (to / dist, dist) } else { (Vector3::new(0.0, -1.0, 0.0), 0.0) }
@@ -51,30 +152,51 @@ impl Light {
         }
     }
 
+    /// Atenuación del cono (0..1). 1 para Point/Directional/Area; exactamente 0 fuera del cono externo.
+    pub fn cone(&self, point: Vector3) -> f32 {
+        match self.kind {
+            LightKind::Spot { direction, inner_angle, outer_angle } => {
+                let to_point = point - self.position;
+                let dist = to_point.length();
+                if dist <= 0.0 { return 1.0; }
+                let cos_theta = (to_point / dist).dot(direction);
+                let cos_outer = outer_angle.cos();
+                let cos_inner = inner_angle.cos();
+                if cos_theta <= cos_outer { return 0.0; }
+                if cos_theta >= cos_inner { return 1.0; }
+                let t = (cos_theta - cos_outer) / (cos_inner - cos_outer).max(1e-6);
+                t * t * (3.0 - 2.0 * t)
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Factor de atenuación para una distancia devuelta por `at` (1 para Directional).
+    #[inline]
+    pub fn falloff(&self, distance: f32) -> f32 {
+        match self.kind {
+            LightKind::Point | LightKind::Spot { .. } | LightKind::Area { .. } => self.attenuation.factor(distance),
+            LightKind::Directional => 1.0,
+        }
+    }
+
     pub fn translate(&mut self, delta: Vector3) {
-        if matches!(self.kind, LightKind::Point) {
+        if matches!(self.kind, LightKind::Point | LightKind::Spot { .. } | LightKind::Area { .. }) {
             self.position += delta;
         }
     }
 
     pub fn yaw_pitch(&mut self, yaw: f32, pitch: f32) {
-        if !matches!(self.kind, LightKind::Directional) { return; }
-        let mut dir = self.direction;
-        let r = dir.length();
-        if r == 0.0 { dir = Vector3::new(-1.0,-1.0,-1.0).normalized(); }
-
-        let mut cur_yaw   = dir.z.atan2(dir.x);
-        let mut cur_pitch = (dir.y).asin().clamp(-0.999, 0.999);
-
-        cur_yaw += yaw;
-        cur_pitch = (cur_pitch + pitch).clamp(-1.3, 1.3);
-
-        let cp = cur_pitch.cos();
-        let x = cp * cur_yaw.cos();
-        let y = cur_pitch.sin();
-        let z = cp * cur_yaw.sin();
-
-        self.direction = Vector3::new(x, y, z).normalized();
+        match &mut self.kind {
+            LightKind::Directional | LightKind::Area { .. } => {
+                self.direction = rotate_yaw_pitch(self.direction, yaw, pitch);
+            }
+            LightKind::Spot { direction, .. } => {
+                *direction = rotate_yaw_pitch(*direction, yaw, pitch);
+                self.direction = *direction;
+            }
+            LightKind::Point => {}
+        }
     }
 
     pub fn clone_light_readonly(&self) -> Light {
@@ -84,6 +206,26 @@ impl Light {
             direction: self.direction,
             color: self.color,
             intensity: self.intensity,
+            glints: self.glints,
+            attenuation: self.attenuation,
         }
     }
 }
+
+fn rotate_yaw_pitch(mut dir: Vector3, yaw: f32, pitch: f32) -> Vector3 {
+    let r = dir.length();
+    if r == 0.0 { dir = Vector3::new(-1.0,-1.0,-1.0).normalized(); }
+
+    let mut cur_yaw   = dir.z.atan2(dir.x);
+    let mut cur_pitch = (dir.y).asin().clamp(-0.999, 0.999);
+
+    cur_yaw += yaw;
+    cur_pitch = (cur_pitch + pitch).clamp(-1.3, 1.3);
+
+    let cp = cur_pitch.cos();
+    let x = cp * cur_yaw.cos();
+    let y = cur_pitch.sin();
+    let z = cp * cur_yaw.sin();
+
+    Vector3::new(x, y, z).normalized()
+}
@@ -1,357 +1,203 @@
 use raylib::prelude::*;
 use std::f32::consts::PI;
-
-mod framebuffer;
-mod ray_intersect;
-mod sphere;
-mod camera;
-mod light;
-mod material;
-mod cube;
-mod slab;
-mod texture;
-mod scene;
-mod palette;
-mod accel;
-mod build;
-mod skybox;
-
-use framebuffer::Framebuffer;
-use ray_intersect::{Intersect, RayIntersect};
-use camera::Camera;
+use std::time::Instant;
+
+use computer_graphics_v3::{
+    profile, framebuffer, ray_intersect, camera, light, texture, scene, accel, build, skybox, settings, walk,
+    tools, feedback, pathtrace, icons, script, gizmo, ui, stats, daycycle, presets, headless, perf,
+};
+use computer_graphics_v3::render::{render, DiffOverlay, GhostBlock, Preview};
+use computer_graphics_v3::startup::{
+    build_palette, flood_max_arg, grid_cell_from_args, load_startup_scene, scene_json_path, settings_from_args,
+    sky_preset_arg, startup_camera, startup_lights, startup_params,
+};
+
+use framebuffer::{FlakyUploader, Framebuffer, Occlusion, SwapStatus};
+use ray_intersect::RayIntersect;
+use camera::{Camera, CameraBasis, Projection};
 use light::LightKind;
-use material::{Material, vector3_to_color};
-use palette::{Palette, CubeTemplate};
 use accel::UniformGridAccel;
 
-use crate::texture::Texture;
-use crate::build::*;
-use crate::skybox::Skybox;
-
-const ORIGIN_BIAS: f32 = 1e-3;
-
-#[inline]
-fn lerp(a: Vector3, b: Vector3, t: f32) -> Vector3 { a * (1.0 - t) + b * t }
-
-#[inline]
-fn smooth5(t: f32) -> f32 {
-    let t = t.clamp(0.0, 1.0);
-    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
-}
-
-/// FONDO fallback
-fn procedural_sky(dir: Vector3) -> Vector3 {
-    let d = dir.normalized();
-    let t = ((d.y) * 0.5 + 0.5).clamp(0.0, 1.0);
-
-    let horizon = Vector3::new(0.08, 0.04, 0.12);
-    let mid     = Vector3::new(0.03, 0.015, 0.06);
-    let top     = Vector3::new(0.015, 0.010, 0.030);
-
-    let c = if t < 0.6 {
-        let k = smooth5(t / 0.6);
-        lerp(horizon, mid, k)
-    } else {
-        let k = smooth5((t - 0.6) / 0.4);
-        lerp(mid, top, k)
-    };
-
-    let h = (1.0 - t).clamp(0.0, 1.0);
-    let glow = h.powf(5.0);
-    let glow_col = Vector3::new(0.20, 0.05, 0.15);
-    let c = c + glow_col * (0.08 * glow);
-
-    let haze = (1.0 - t).powf(2.0) * 0.03;
-    let c = c + Vector3::new(haze * 0.6, haze * 0.3, haze);
-
-    Vector3::new(c.x.clamp(0.0, 1.0), c.y.clamp(0.0, 1.0), c.z.clamp(0.0, 1.0))
-}
-
-fn offset_origin(intersect: &Intersect, direction: &Vector3) -> Vector3 {
-    let offset = intersect.normal * ORIGIN_BIAS;
-    if direction.dot(intersect.normal) < 0.0 { intersect.point - offset } else { intersect.point + offset }
-}
-
-fn reflect(incident: &Vector3, normal: &Vector3) -> Vector3 {
-    *incident - *normal * 2.0 * incident.dot(*normal)
-}
-
-fn refract(incident: &Vector3, normal: &Vector3, refractive_index: f32) -> Option<Vector3> {
-    let mut cosi = incident.dot(*normal).max(-1.0).min(1.0);
-    let mut etai = 1.0;
-    let mut etat = refractive_index;
-    let mut n = *normal;
-
-    if cosi > 0.0 {
-        std::mem::swap(&mut etai, &mut etat);
-        n = -n;
-    } else {
-        cosi = -cosi;
+use texture::TextureCache;
+use build::*;
+use skybox::Skybox;
+use settings::{DebugView, Integrator, LightingPreset, QualityProfile, RenderSettings, RENDER_SCALES};
+use scene::SceneIndex;
+use walk::{WalkInput, WalkState};
+use tools::{EditEvent, Pick, RegionPreview, SceneEdit, SlotContent, ToolConfig, ToolKind};
+use feedback::{CellEffect, EffectKind, Sfx};
+use pathtrace::Accumulation;
+use stats::TileStats;
+
+/// Reinicio parcial de la acumulación: cuánto se extruye la celda editada alejándose de cada
+/// luz (sombra) y cuántos pixeles se agranda el rectángulo (reflejos cercanos).
+const ACCUM_SHADOW_REACH: f32 = 12.0;
+const ACCUM_MARGIN_PX: usize = 48;
+
+/// Lado (unidades de mundo) de la luz de área que crea la tecla 6.
+const AREA_LIGHT_SIZE: f32 = 2.0;
+
+/// Whitted progresivo: pasadas acumuladas tras las que la imagen se da por convergida y el render
+/// adaptativo vuelve a reusar el último frame.
+const PROGRESSIVE_MAX_FRAMES: u32 = 64;
+
+/// Frames acumulados (path tracing) tras los que el skybox pasa a las caras originales.
+const SKY_FULL_AFTER_FRAMES: u32 = 16;
+
+/// Órbita rápida: segundos con la vista quieta tras los que se vuelve a la resolución completa.
+const STILL_FULL_RES_SECS: f32 = 0.25;
+
+/// Tope de FPS del loop: con el render adaptativo los frames sin cambios son solo HUD, y sin tope
+/// el loop giraría a miles de FPS igual.
+const LOOP_MAX_FPS: u32 = 60;
+
+/// Reposo: tras este número de frames reusados seguidos (1 s a 60 FPS) el tope baja a
+/// `IDLE_FPS`; el primer frame que hay que trazar, o mover el mouse, lo devuelve a `LOOP_MAX_FPS`.
+const IDLE_AFTER_FRAMES: u32 = 60;
+const IDLE_FPS: u32 = 15;
+
+/// Eventos de edición por frame con sonido y contorno (un relleno puede generar miles).
+const FEEDBACK_MAX_EVENTS: usize = 64;
+
+/// `--diff escena_a escena_b`: resumen + diff ASCII por capa. Devuelve el código de salida.
+fn run_diff_cli(args: &[String]) -> i32 {
+    if args.len() < 2 {
+        eprintln!("uso: diorama --diff <carpeta_escena_a> <carpeta_escena_b>");
+        return 2;
     }
-
-    let eta = etai / etat;
-    let k = 1.0 - eta * eta * (1.0 - cosi * cosi);
-    if k < 0.0 { None } else { Some(*incident * eta + n * (eta * cosi - k.sqrt())) }
-}
-
-fn cast_shadow(
-    intersect: &Intersect,
-    light: &light::Light,
-    objects: &[Box<dyn RayIntersect>],
-    accel: &UniformGridAccel,
-) -> f32 {
-    let (light_dir, light_distance) = light.at(intersect.point);
-    let shadow_ray_origin = offset_origin(intersect, &light_dir);
-    if accel.occluded(&shadow_ray_origin, &light_dir, light_distance, objects) { 1.0 } else { 0.0 }
-}
-
-// ==== PREVIEW ====
-#[derive(Clone, Copy)]
-struct Preview { hovered_idx: usize }
-
-#[inline]
-fn sample_background(ray_direction: &Vector3, skybox: Option<&Skybox>) -> Vector3 {
-    if let Some(sb) = skybox {
-        sb.sample(*ray_direction)
-    } else {
-        procedural_sky(*ray_direction)
+    let (palette, _) = build_palette(&mut TextureCache::new());
+    let params = scene::default_params(Vector3::new(1.0, 1.0, 1.0));
+    let load = |dir: &str| scene::load_ascii_cells(dir, &params, &palette)
+        .map_err(|e| eprintln!("No se pudo leer {}: {}", dir, e));
+    let (a, b) = match (load(&args[0]), load(&args[1])) {
+        (Ok(a), Ok(b)) => (a, b),
+        _ => return 1,
+    };
+    let d = scene::diff(&a, &b);
+    println!("{} → {}: {}", args[0], args[1], d.summary());
+    if !d.is_empty() {
+        print!("{}", scene::diff_report(&a, &b, &d));
     }
+    0
 }
 
-pub fn cast_ray(
-    ray_origin: &Vector3,
-    ray_direction: &Vector3,
-    objects: &[Box<dyn RayIntersect>],
-    accel: &UniformGridAccel,
-    lights: &[light::Light], // <-- múltiple luces
-    depth: u32,
-    preview: Option<Preview>, 
-    skybox: Option<&Skybox>,  
-) -> Vector3 {
-    if depth > 3 {
-        return sample_background(ray_direction, skybox);
-    }
-
-    let mut intersect = accel.trace(ray_origin, ray_direction, objects);
-
-    if let Some(pv) = preview {
-        if intersect.is_intersecting && intersect.object_index == Some(pv.hovered_idx) {
-            let preview_mat = Material::new(
-                Vector3::new(0.9, 0.3, 0.3),
-                8.0,
-                [1.0, 0.0, 0.0, 0.0],
-                1.0
-            );
-            intersect.material = preview_mat;
-            intersect.coverage = 1.0;
-        }
-    }
-
-    if !intersect.is_intersecting {
-        return sample_background(ray_direction, skybox);
-    }
-
-    // Si no hay luces, usa solo background/ambient
-    if lights.is_empty() {
-        return sample_background(ray_direction, skybox);
-    }
-
-    let view_dir   = (*ray_origin - intersect.point).normalized();
-
-    // Acumuladores por-luz
-    let mut diffuse_sum  = Vector3::zero();
-    let mut specular_sum = Vector3::zero();
-    let mut glint_sum    = Vector3::zero();
-
-    let light_color_from = |c: Color| -> Vector3 {
-        Vector3::new(c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0)
+/// `--script <archivo>`: ejecuta un script sin ventana e imprime las ediciones resultantes.
+fn run_script_cli(args: &[String]) -> i32 {
+    let Some(path) = args.first() else {
+        eprintln!("uso: diorama --script <archivo>");
+        return 2;
     };
-
-    for l in lights.iter() {
-        let (light_dir, _light_distance) = l.at(intersect.point);
-
-        let shadow_intensity = cast_shadow(&intersect, l, objects, accel);
-        let light_intensity  = l.intensity * (1.0 - shadow_intensity);
-
-        let light_color_v3 = light_color_from(l.color);
-
-        let diffuse_intensity = ((intersect.normal.dot(light_dir) + 0.3) / 1.3)
-            .clamp(0.0, 1.0) * light_intensity;
-        diffuse_sum += intersect.material.diffuse * diffuse_intensity;
-
-        let refl_light = reflect(&-light_dir, &intersect.normal).normalized();
-        let specular_intensity = view_dir
-            .dot(refl_light)
-            .max(0.0)
-            .powf(intersect.material.specular) * light_intensity;
-        specular_sum += light_color_v3 * specular_intensity;
-
-        // Glint por-luz
-        let mirror_dir    = reflect(ray_direction, &intersect.normal).normalized();
-        let mirror_origin = offset_origin(&intersect, &mirror_dir);
-
-        let hardness_point = 800.0;
-        let hardness_dir   = 800.0;
-        let gain           = 1.0;
-        let refl_bias      = (intersect.material.albedo[2] + 0.05).min(1.0);
-
-        match l.kind {
-            LightKind::Point => {
-                let to_l = l.position - mirror_origin;
-                let dist = to_l.length();
-                if dist > 0.0 {
-                    let ldir  = to_l / dist;
-                    let align = mirror_dir.dot(ldir).max(0.0);
-                    if align > 0.0 && !accel.occluded(&mirror_origin, &ldir, dist, objects) {
-                        let falloff = 1.0 / (1.0 + dist * dist);
-                        let s = gain * l.intensity * falloff * align.powf(hardness_point) * refl_bias;
-                        glint_sum += light_color_v3 * s;
-                    }
-                }
-            }
-            LightKind::Directional => {
-                let ldir  = -l.direction;
-                let align = mirror_dir.dot(ldir).max(0.0);
-                if align > 0.0 && !accel.occluded(&mirror_origin, &ldir, f32::INFINITY, objects) {
-                    let s = gain * l.intensity * align.powf(hardness_dir) * refl_bias;
-                    glint_sum += light_color_v3 * s;
-                }
+    match script::run_file(path, script::ScriptConfig::default()) {
+        Ok(edits) => {
+            for ((x, y, z), ch) in edits.iter() {
+                println!("{} {} {} {}", x, y, z, ch);
             }
+            eprintln!("{}: {} ediciones", path, edits.len());
+            0
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            1
         }
     }
+}
 
-    let coverage = intersect.coverage;
-    let albedo   = intersect.material.albedo;
-
-    let ambient = intersect.material.diffuse * 0.15;
-
-    let phong_color =
-        (diffuse_sum + ambient) * (albedo[0] * coverage) +
-        specular_sum * (albedo[1] * coverage);
-
-    let reflectivity = albedo[2];
-
-    let mut transparency = (1.0 - coverage) + albedo[3] * coverage;
-    transparency = transparency.clamp(0.0, 1.0);
-
-    let reflect_color = if reflectivity > 0.0 {
-        let rdir = reflect(ray_direction, &intersect.normal).normalized();
-        let ro   = offset_origin(&intersect, &rdir);
-        cast_ray(&ro, &rdir, objects, accel, lights, depth + 1, preview, skybox)
-    } else {
-        Vector3::zero()
-    };
-
-    let refract_color = if transparency > 0.0 {
-        if let Some(tdir) = refract(ray_direction, &intersect.normal, intersect.material.refractive_index) {
-            let ro = offset_origin(&intersect, &tdir);
-            cast_ray(&ro, &tdir, objects, accel, lights, depth + 1, preview, skybox)
-        } else {
-            let rdir = reflect(ray_direction, &intersect.normal).normalized();
-            let ro   = offset_origin(&intersect, &rdir);
-            cast_ray(&ro, &rdir, objects, accel, lights, depth + 1, preview, skybox)
+/// Teclas 1..9 en orden (slots de vistas guardadas y casillas del hotbar con Alt).
+const DIGIT_KEYS: [KeyboardKey; presets::PRESET_SLOTS] = [
+    KeyboardKey::KEY_ONE, KeyboardKey::KEY_TWO, KeyboardKey::KEY_THREE,
+    KeyboardKey::KEY_FOUR, KeyboardKey::KEY_FIVE, KeyboardKey::KEY_SIX,
+    KeyboardKey::KEY_SEVEN, KeyboardKey::KEY_EIGHT, KeyboardKey::KEY_NINE,
+];
+
+/// FPS interactivos que busca la calibración de arranque.
+const CALIBRATION_TARGET_FPS: f32 = 30.0;
+/// Ancho del render oculto de calibración (el alto sigue el aspecto de la ventana).
+const CALIBRATION_WIDTH: u32 = 160;
+
+/// Renderiza la escena cargada desde la cámara inicial a baja resolución con cada perfil (del
+/// mejor al peor), extrapola el tiempo a la resolución de la ventana y se queda con el primero
+/// que entra en `CALIBRATION_TARGET_FPS`. Devuelve el perfil, los rayos/s medidos (primarios +
+/// sombra, con el perfil completo) y los ms por frame estimados del perfil elegido.
+fn calibrate_quality(
+    objects: &[Box<dyn RayIntersect>], accel: &UniformGridAccel, camera: &Camera, lights: &[light::Light],
+    skybox: Option<&Skybox>, base: &RenderSettings, window_w: u32, window_h: u32,
+) -> (QualityProfile, f64, f32) {
+    let (w, h) = (CALIBRATION_WIDTH, (CALIBRATION_WIDTH * window_h / window_w.max(1)).max(1));
+    let scale = (window_w * window_h) as f32 / (w * h) as f32;
+    let mut fb = Framebuffer::new(w, h);
+    let mut rays_per_sec = 0.0;
+    let mut chosen = (QualityProfile::Low, f32::INFINITY);
+    for profile in QualityProfile::ALL {
+        let settings = profile.apply(*base);
+        // El primer frame calienta cachés; se mide el segundo
+        render(&mut fb, objects, accel, camera, lights, None, skybox, &settings, None, None, None);
+        let mut ts = TileStats::new(w as usize, h as usize);
+        let started = Instant::now();
+        render(&mut fb, objects, accel, camera, lights, None, skybox, &settings, None, None, Some(&mut ts));
+        let secs = started.elapsed().as_secs_f32();
+        let est_ms = secs * scale * 1000.0;
+        if profile == QualityProfile::Full {
+            let total = ts.total();
+            rays_per_sec = (total.primary + total.shadow) as f64 / secs.max(1e-6) as f64;
         }
-    } else {
-        Vector3::zero()
-    };
-
-    let k_phong = (1.0 - reflectivity - transparency).max(0.0);
-    phong_color * k_phong + reflect_color * reflectivity + refract_color * transparency + glint_sum
+        println!("Calibración {}: {:.1} ms a {}x{} → ~{:.0} ms a {}x{}", profile.name(), secs * 1000.0, w, h, est_ms, window_w, window_h);
+        chosen = (profile, est_ms);
+        if est_ms <= 1000.0 / CALIBRATION_TARGET_FPS { break; }
+    }
+    (chosen.0, rays_per_sec, chosen.1)
 }
 
-pub fn render(
-    framebuffer: &mut Framebuffer,
-    objects: &[Box<dyn RayIntersect>],
-    accel: &UniformGridAccel,
-    camera: &Camera,
-    lights: &[light::Light], // <-- múltiples luces
-    preview: Option<Preview>,
-    skybox: Option<&Skybox>,
+/// Escribe `stats/frame_<unix>.csv` con los agregados por tile y una cabecera de parámetros (más
+/// los contadores de `profiling` si la feature está activa), e imprime el resumen con los 5 tiles más caros.
+fn dump_tile_stats(
+    ts: &TileStats, camera: &Camera, accel: &UniformGridAccel, object_count: usize,
+    settings: &RenderSettings, profile_frame: &[(&'static str, u64, u64)], notes: &mut ui::Notifications,
 ) {
-    let w = framebuffer.width as usize;
-    let h = framebuffer.height as usize;
-
+    let secs = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let path = format!("stats/frame_{}.csv", secs);
     let cam = camera.basis();
+    let mut header = vec![
+        ("resolution", format!("{}x{}", ts.width, ts.height)),
+        ("objects", object_count.to_string()),
+        ("accel", accel.describe()),
+        ("grid", accel.stats().summary()),
+        ("eye", format!("{:.3} {:.3} {:.3}", cam.eye.x, cam.eye.y, cam.eye.z)),
+        ("forward", format!("{:.3} {:.3} {:.3}", cam.forward.x, cam.forward.y, cam.forward.z)),
+        ("projection", format!("{:?}", cam.projection)),
+        ("integrator", settings.integrator.name().to_string()),
+        ("samples_per_pixel", settings.samples_per_pixel.to_string()),
+        ("max_depth", settings.max_depth.to_string()),
+        ("lod", settings.lod.enabled.to_string()),
+        ("sky_full_res", settings.sky_full_res.to_string()),
+    ];
+    for &(name, calls, nanos) in profile_frame.iter() {
+        header.push((name, format!("{} llamadas {:.3} ms", calls, nanos as f64 / 1e6)));
+    }
+    ts.print_summary(5);
+    match ts.write_csv(&path, &header) {
+        Ok(()) => notes.info(format!("Estadísticas por tile en {}", path)),
+        Err(e) => notes.error(format!("No se pudo escribir {}: {}", path, e)),
+    }
+}
 
-    let width_f = framebuffer.width as f32;
-    let height_f = framebuffer.height as f32;
-    let aspect_ratio = width_f / height_f;
-    let fov = PI / 3.0;
-    let perspective_scale = (fov * 0.5).tan();
-
-    let threads = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
-    let rows_per = (h + threads - 1) / threads;
-
-    let pixels = framebuffer.pixels_mut();
-
-    std::thread::scope(|scope| {
-        let mut joins = Vec::with_capacity(threads);
-        let mut results: Vec<(usize, Vec<Color>)> = Vec::with_capacity(threads);
-
-        for t in 0..threads {
-            let y_start = t * rows_per;
-            if y_start >= h { break; }
-            let y_end = ((t + 1) * rows_per).min(h);
-
-            // let light_c = *light;                    // (antes)
-            let lights_c: Vec<light::Light> = lights.to_vec(); // NEW
-            let aspect_ratio_c = aspect_ratio;
-            let perspective_scale_c = perspective_scale;
-            let width_f_c = width_f;
-            let height_f_c = height_f;
-            let cam_c = cam;
-            let span_w = w;
-            let preview_c = preview;
-            let skybox_c = skybox;
-
-            let handle = scope.spawn(move || {
-                let span_h = y_end - y_start;
-                let mut local = vec![Color::BLACK; span_h * span_w];
-
-                for (row_off, y) in (y_start..y_end).enumerate() {
-                    let fy = y as f32;
-                    for x in 0..span_w {
-                        let fx = x as f32;
-
-                        let mut sx = (2.0 * fx) / width_f_c - 1.0;
-                        let mut sy = -(2.0 * fy) / height_f_c + 1.0;
-
-                        sx = sx * aspect_ratio_c * perspective_scale_c;
-                        sy = sy * perspective_scale_c;
-
-                        let v_cam = Vector3::new(sx, sy, -1.0).normalized();
-                        let ray_dir = Vector3::new(
-                            v_cam.x * cam_c.right.x + v_cam.y * cam_c.up.x - v_cam.z * cam_c.forward.x,
-                            v_cam.x * cam_c.right.y + v_cam.y * cam_c.up.y - v_cam.z * cam_c.forward.y,
-                            v_cam.x * cam_c.right.z + v_cam.y * cam_c.up.z - v_cam.z * cam_c.forward.z,
-                        );
-
-                        let rgb = cast_ray(&cam_c.eye, &ray_dir, objects, accel, &lights_c, 0, preview_c, skybox_c);
-                        local[row_off * span_w + x] = vector3_to_color(rgb);
-                    }
-                }
-
-                (y_start, local)
-            });
-
-            joins.push(handle);
-        }
-
-        for j in joins {
-            let (y_start, local) = j.join().expect("Hilo de render falló");
-            results.push((y_start, local));
-        }
+/// Carpeta de las capturas de Ctrl+F12.
+const SCREENSHOT_DIR: &str = "screenshots";
 
-        for (y_start, local) in results {
-            let span_h = local.len() / w;
-            for row_off in 0..span_h {
-                let dst_start = (y_start + row_off) * w;
-                let src_start = row_off * w;
-                pixels[dst_start..dst_start + w]
-                    .copy_from_slice(&local[src_start..src_start + w]);
-            }
-        }
-    });
+/// Escribe `screenshots/diorama_<unix ms>[_hud].png`; los errores van a las notificaciones.
+fn save_screenshot(img: &Image, hud: bool, notes: &mut ui::Notifications) {
+    let millis = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_millis());
+    let path = format!("{}/diorama_{}{}.png", SCREENSHOT_DIR, millis, if hud { "_hud" } else { "" });
+    if let Err(e) = std::fs::create_dir_all(SCREENSHOT_DIR) {
+        notes.error(format!("No se pudo crear {}: {}", SCREENSHOT_DIR, e));
+        return;
+    }
+    // `export_image` no informa errores: se mira si el archivo apareció
+    img.export_image(&path);
+    if std::fs::metadata(&path).is_err() {
+        notes.error(format!("No se pudo escribir {}", path));
+        return;
+    }
+    notes.info(format!("Captura {}x{} en {}", img.width(), img.height(), path));
 }
 
 #[inline]
@@ -381,6 +227,24 @@ fn neighbor_cell_center_from_face_hit(
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|a| a == "--diff").unwrap_or(false) {
+        std::process::exit(run_diff_cli(&args[2..]));
+    }
+    if args.get(1).map(|a| a == "--script").unwrap_or(false) {
+        std::process::exit(run_script_cli(&args[2..]));
+    }
+    // `--flythrough <recorrido.json>`: secuencia de PNG a lo largo de un recorrido de cámara
+    if args.iter().any(|a| a == "--flythrough") {
+        std::process::exit(headless::run_flythrough(&args));
+    }
+    // `--headless --out <png>` / `--render <png>`: mismo render, sin abrir ventana
+    if args.iter().any(|a| a == "--headless" || a == "--render") {
+        std::process::exit(headless::run(&args));
+    }
+    // `--compare <carpeta>`: superpone el diff contra otra escena guardada
+    let compare_dir = args.iter().position(|a| a == "--compare").and_then(|i| args.get(i + 1).cloned());
+
     let window_width = 1300;
     let window_height = 900;
 
@@ -389,110 +253,101 @@ fn main() {
         .title("Diorama - Kevin Villagrán 23584")
         .log_level(TraceLogLevel::LOG_WARNING)
         .build();
-
-    let mut framebuffer = Framebuffer::new(window_width as u32, window_height as u32);
-
-    let mut tmp_img = Image::gen_image_color(window_width, window_height, Color::BLACK);
+    window.set_target_fps(LOOP_MAX_FPS);
+
+    // Resolución interna: el render traza a `render_scale` del tamaño de la ventana y la textura
+    // se estira al dibujar. `--render-scale <0.1..1>` fija la inicial; Y recorre RENDER_SCALES.
+    let mut render_scale = args.iter().position(|a| a == "--render-scale")
+        .and_then(|i| args.get(i + 1)).and_then(|s| s.parse::<f32>().ok())
+        .map_or(1.0, |s| s.clamp(0.1, 1.0));
+    // Órbita rápida (Shift+Y o `--fast-orbit`): `render_scale` solo mientras la vista se mueve;
+    // quieta, cuadros a resolución completa
+    let mut fast_orbit = args.iter().any(|a| a == "--fast-orbit");
+    if fast_orbit && render_scale >= 1.0 { render_scale = 0.5; }
+    let mut last_view: Option<CameraBasis> = None;
+    let mut still_secs = 0.0f32;
+    let (fb_w, fb_h) = settings::scaled_size(window_width, window_height, render_scale);
+    let mut framebuffer = Framebuffer::new(fb_w, fb_h);
+
+    let mut tmp_img = Image::gen_image_color(fb_w as i32, fb_h as i32, Color::BLACK);
     let texture = window
         .load_texture_from_image(&thread, &tmp_img)
         .expect("No se pudo crear la textura persistente");
     framebuffer.attach_texture(texture);
+    // `--simulate-upload-failures <N>`: una de cada N subidas a la GPU falla (prueba la recuperación)
+    if let Some(n) = args.iter().position(|a| a == "--simulate-upload-failures")
+        .and_then(|i| args.get(i + 1)).and_then(|n| n.parse::<u32>().ok())
+    {
+        framebuffer.set_uploader(Box::new(FlakyUploader::new(n)));
+    }
 
     // ======= PALETA (MATERIALES) =======
-    let stone_mat = Material::new(Vector3::new(0.55, 0.55, 0.55), 20.0, [0.90, 0.10, 0.0, 0.0], 0.0);
-    let grass_mat = Material::new(Vector3::new(1.0, 1.0, 1.0), 10.0, [0.95, 0.05, 0.0, 0.0], 0.0);
-    let dirt_mat  = Material::new(Vector3::new(1.0, 1.0, 1.0), 8.0,  [0.98, 0.02, 0.0, 0.0], 0.0);
-    let log_mat   = Material::new(Vector3::new(1.0, 1.0, 1.0), 15.0, [0.92, 0.08, 0.0, 0.0], 0.0);
-    let planks_mat= Material::new(Vector3::new(1.0, 1.0, 1.0), 12.0, [0.90, 0.10, 0.0, 0.0], 0.0);
-    let glass_mat = Material::new(Vector3::new(1.0, 1.0, 1.0),120.0,[0.80, 0.15, 0.06, 0.0], 1.5);
-    let leaves_mat= Material::new(Vector3::new(1.0, 1.0, 1.0), 35.0, [0.92, 0.08, 0.0, 0.0], 0.0);
-    let ice_mat   = Material::new(Vector3::new(1.0, 1.0, 1.0), 10.0, [0.80, 0.10, 0.20, 0.05], 1.31);
-
-    let diamond_mat = Material::new(Vector3::new(1.0, 1.0, 1.0), 140.0, [0.88, 0.12, 0.10, 0.0], 0.0);
-    let gold_mat    = Material::new(Vector3::new(1.0, 1.0, 1.0), 120.0, [0.85, 0.15, 0.12, 0.0], 0.0);
-    let iron_mat    = Material::new(Vector3::new(1.0, 1.0, 1.0),  60.0, [0.90, 0.10, 0.08, 0.0], 0.0);
-
-    let lava_mat    = Material::new(Vector3::new(1.0, 1.0, 1.0),   0.0, [1.00, 0.00, 0.00, 0.0], 0.0);
-
-    use std::sync::Arc;
-    let grass_top    = Arc::new(Texture::from_file("assets/snow_grass/posy.png"));
-    let grass_side   = Arc::new(Texture::from_file("assets/snow_grass/posx.png"));
-    let grass_bottom = Arc::new(Texture::from_file("assets/snow_grass/negy.png"));
-    let dirt_tex     = Arc::new(Texture::from_file("assets/dirt/dirt.png"));
-
-    let log_top     = Arc::new(Texture::from_file("assets/spruce_log/spruce_log_top.png"));
-    let log_bottom  = Arc::new(Texture::from_file("assets/spruce_log/spruce_log_top.png"));
-    let log_side    = Arc::new(Texture::from_file("assets/spruce_log/spruce_log.png"));
-
-    let planks = Arc::new(Texture::from_file("assets/spruce_planks/spruce_planks.png"));
-    let uslab_planks = Arc::new(Texture::from_file("assets/spruce_planks/spruce_planks.png"));
-    let lslab_planks = Arc::new(Texture::from_file("assets/spruce_planks/spruce_planks.png"));
-
-    let glass = Arc::new(Texture::from_file("assets/glass/glass.png"));
-    let glass_tpl = CubeTemplate::with_same_texture_image_alpha_window(glass_mat, glass.clone(), 0.05);
-
-    let leaves = Arc::new(Texture::from_file("assets/spruce_leaves/spruce_leaves.png"));
-    let leaves_tpl = CubeTemplate::with_same_texture_tinted_black_transparent(
-        leaves_mat, leaves.clone(), Vector3::new(0.2, 0.6, 0.25), 0.05,
-    );
-
-    let ice = Arc::new(Texture::from_file("assets/ice/ice.png"));
-
-    let diamond_tex = Arc::new(Texture::from_file("assets/diamond_block/diamond_block.png"));
-    let gold_tex    = Arc::new(Texture::from_file("assets/gold_block/gold_block.png"));
-    let iron_tex    = Arc::new(Texture::from_file("assets/iron_block/iron_block.png"));
-    let lava_tex    = Arc::new(Texture::from_file("assets/lava/lava.png"));
-
-    let diamond_ore_tex    = Arc::new(Texture::from_file("assets/diamond_ore/diamond_ore.png"));
-    let iron_ore_tex    = Arc::new(Texture::from_file("assets/iron_ore/iron_ore.png"));
-    let gold_ore_tex    = Arc::new(Texture::from_file("assets/gold_ore/gold_ore.png"));
-    
-    let stone    = Arc::new(Texture::from_file("assets/stone/stone.png"));
-
-    let mut palette = Palette::new();
-    palette.set('X', CubeTemplate::with_top_bottom_sides(grass_mat, grass_top, grass_bottom, grass_side));
-    palette.set('D', CubeTemplate::with_same_texture(dirt_mat,  dirt_tex));
-    palette.set('L', CubeTemplate::with_top_bottom_sides(log_mat,  log_top, log_bottom, log_side));
-    palette.set('P', CubeTemplate::with_same_texture(planks_mat,  planks));
-    palette.set('G', glass_tpl);
-    palette.set('l', leaves_tpl);
-    palette.set('H', CubeTemplate::with_same_texture(ice_mat,  ice));
-    palette.set('-', CubeTemplate::with_same_texture(planks_mat,  uslab_planks));
-    palette.set('_', CubeTemplate::with_same_texture(planks_mat,  lslab_planks));
-
-    palette.set('M', CubeTemplate::with_same_texture(diamond_mat, diamond_tex));
-    palette.set('O', CubeTemplate::with_same_texture(gold_mat,    gold_tex));   
-    palette.set('I', CubeTemplate::with_same_texture(iron_mat,    iron_tex));   
-    palette.set('V', CubeTemplate::with_same_texture(lava_mat,    lava_tex));
-
-    palette.set('m', CubeTemplate::with_same_texture(stone_mat, diamond_ore_tex));
-    palette.set('o', CubeTemplate::with_same_texture(stone_mat, gold_ore_tex));
-    palette.set('i', CubeTemplate::with_same_texture(stone_mat, iron_ore_tex));
-    
-    palette.set('S', CubeTemplate::with_same_texture(stone_mat, stone));
+    // Caché de texturas por ruta: paleta, texturas de mallas y skyboxes comparten los PNG repetidos
+    let mut textures = TextureCache::new();
+    let (mut palette, stone_mat) = build_palette(&mut textures);
+    // Con texturas animadas la acumulación del path tracer se reinicia en cada cambio de frame
+    let anim_frame_secs = palette.anim_frame_secs();
 
     // ===== CARGA ESCENA ASCII =====
-    let cube_size = Vector3::new(1.0, 1.0, 1.0);
-    let mut params = scene::default_params(cube_size);
-    params.gap = Vector3::new(0.0, 0.0, 0.0);
-    params.origin = Vector3::new(0.0, 0.0, 0.0);
-    params.y0 = -0.5;
-    params.y_step = 1.0;
-
-    let default_mat = stone_mat;
-
-    let mut objects: Vec<Box<dyn RayIntersect>> =
-        scene::load_ascii_layers_with_palette("assets/scene", &params, &palette, default_mat)
-            .expect("Error leyendo assets/scene");
-
-    let mut accel = UniformGridAccel::build(&objects, cube_size.x.max(0.01));
+    let mut params = startup_params(Vector3::new(1.0, 1.0, 1.0));
+
+    // Mensajes para el usuario (toasts en pantalla + stdout con hora)
+    let mut notes = ui::Notifications::new();
+
+    let mut objects = load_startup_scene(&args, &mut palette, stone_mat, &mut params, &mut textures, &mut notes);
+    // `--scene` puede traer su propio tamaño de celda
+    let cube_size = params.cube_size;
+    let scene_json = scene_json_path(&args);
+
+    // Lado de celda de la grilla: se ajusta en vivo con Shift/Ctrl + ` (ver README, "Rendimiento")
+    let mut grid_cell = grid_cell_from_args(&args, &objects, cube_size);
+    let mut accel = UniformGridAccel::build(&objects, grid_cell);
+
+    // Diff contra la escena de `--compare` (sobre los archivos; no refleja ediciones en vivo)
+    let mut diff_overlay = compare_dir.map(|dir| {
+        let base = scene::load_ascii_cells("assets/scene", &params, &palette).expect("Error leyendo assets/scene");
+        let other = scene::load_ascii_cells(&dir, &params, &palette)
+            .unwrap_or_else(|e| panic!("Error leyendo {}: {}", dir, e));
+        let d = scene::diff(&base, &other);
+        notes.info(format!("assets/scene → {}: {}", dir, d.summary()));
+        DiffOverlay::new(&d, &params)
+    });
+    let mut scene_index = SceneIndex::build(&objects, cube_size, params.origin);
+    let mut accum = Accumulation::new();
+    accum.partial_resets = !args.iter().any(|a| a == "--full-accum-reset");
+    let mut last_accum_key = None;
+    // Render adaptativo: si nada de lo que se ve cambió se reusa el último frame (`--always-render`
+    // traza todos). Lo que queda fuera de la clave de acumulación: post, nivel del cielo, resaltado.
+    let adaptive_render = !args.iter().any(|a| a == "--always-render");
+    let mut last_frame_key = None;
+    let mut scene_changed = false;
+    let mut reused_frames = 0u32;
+    let mut idle_throttled = false;
+    // Captura pedida con Ctrl+F12: Some(con_hud)
+    let mut screenshot_pending: Option<bool> = None;
+    // Segundos desde el arranque: reloj de las texturas animadas
+    let mut elapsed = 0.0f32;
+    // Celdas editadas este frame (reinicio parcial de la acumulación) y celda resaltada anterior
+    let mut edited_cells: Vec<Vector3> = Vec::new();
+    let mut last_preview_cells: [Option<Vector3>; 2] = [None; 2];
+    // Volcado de estadísticas por tile: F12 en el próximo frame, o `--dump-stats <N>` en el frame N
+    let mut dump_stats_in: Option<u32> = args.iter().position(|a| a == "--dump-stats")
+        .and_then(|i| args.get(i + 1)).and_then(|n| n.parse().ok());
+    // Contadores en vivo + ocupación de la grilla en el HUD (Home o `--live-stats`); End los loguea
+    // `--frame-stats` imprime por stdout un resumen de cada frame trazado (enciende los contadores)
+    let print_frame_stats = args.iter().any(|a| a == "--frame-stats");
+    stats::set_live(print_frame_stats || args.iter().any(|a| a == "--live-stats"));
+    let mut last_render = stats::RenderStats::default();
+    let mut live_counters = stats::RayCounters::default();
+    // Panel de rendimiento (Ctrl+F3): tiempos de los últimos frames
+    let mut perf = perf::PerfRing::new();
 
     // ===== Cámara =====
-    let mut camera = Camera::new(
-        Vector3::new(20.0, 12.0, 25.0),
-        Vector3::new(0.0, 5.0, 0.0),
-        Vector3::new(0.0, 1.0, 0.0),
-    );
+    let mut camera = startup_camera();
+    let mut camera_presets = presets::CameraPresets::load(presets::PRESETS_FILE).unwrap_or_else(|e| {
+        notes.warn(format!("Vistas guardadas ignoradas: {}", e));
+        presets::CameraPresets::new()
+    });
 
     camera.set_config(camera::CameraConfig {
         orbit_sensitivity_yaw:   1.0,
@@ -502,25 +357,50 @@ fn main() {
         max_pitch:   1.45,
         min_distance: 0.5,
         max_distance: 2000.0,
+        // `--camera-smoothing <1/s>`: qué tan rápido sigue la cámara al input (0 = al instante)
+        smoothing: args.iter().position(|a| a == "--camera-smoothing")
+            .and_then(|i| args.get(i + 1)).and_then(|v| v.parse::<f32>().ok())
+            .map_or(12.0, |v| v.max(0.0)),
     });
     let rotation_speed = PI / 100.0;
 
     // ===== Luz =====
-    let mut light = light::Light::directional(Vector3::new(-1.0, -1.0, 0.3), Color::new(255,255,255,255), 1.2);
-    let mut light2 = light::Light::new(Vector3::new(-8.0, 2.0, 5.0), Color::new(245, 149, 39,255), 1.2);
+    let [mut light, mut light2] = startup_lights();
     let dir_rot_speed = PI / 300.0;
+    let spot_inner = PI / 10.0;
+    let spot_outer = PI / 7.0;
+    // Nivel de `Attenuation::PRESETS` aplicado a las luces puntuales/foco (, y .)
+    let mut attenuation_level = 0usize;
+    // Color de la luz principal editable en vivo (T / G / ; / ')
+    let mut lighting_ui = LightingUi::new();
+    // Ciclo día/noche sobre la luz principal (N on/off, H pausa, V / B atrasar / adelantar)
+    let mut day = daycycle::DayCycle::new();
+    // Preset de las constantes de sombreado (U): plano / suave (el de siempre) / duro
+    let mut lighting_preset = LightingPreset::Soft;
     let move_speed = 0.15;
 
     // ===== Skyboxes =====
     // Estructura de carpetas/archivos requerida:
     // assets/skyboxes/sky2/{posx.png,negx.png,posy.png,negy.png,posz.png,negz.png}
-    let sky1 = Skybox::from_folder("assets/skyboxes/sky1");
-    let sky2 = Skybox::from_folder("assets/skyboxes/sky2");
+    let sky1 = Skybox::from_folder("assets/skyboxes/sky1", &mut textures);
+    let sky2 = Skybox::from_folder("assets/skyboxes/sky2", &mut textures);
+    let (tex_files, tex_requests) = textures.stats();
+    notes.info(format!("Texturas: {} archivos cargados para {} pedidos", tex_files, tex_requests));
+    for e in textures.take_errors() {
+        notes.error(format!("Textura {} (se usa un tablero magenta)", e));
+    }
     let skyboxes = vec![sky1, sky2];
-    let mut current_skybox: usize = 0; // 0 = sky1, 1 = sky2
+    // Memoria por nivel (reducido, original) para el HUD; `--sky-full` usa siempre el original
+    let sky_memory: Vec<(usize, usize)> = skyboxes.iter().map(|s| s.memory_bytes()).collect();
+    let sky_full_always = args.iter().any(|a| a == "--sky-full");
+    // 0 = sky1, 1 = sky2, skyboxes.len() = cielo procedural (`--sky-preset` arranca con él)
+    let mut current_skybox: usize = if sky_preset_arg(&args).is_some() { skyboxes.len() } else { 0 };
 
     // ===== Builder HUD/estado =====
-    let options = vec!['X', 'D', 'L', 'P', 'G', 'l', 'H', 'M', 'O', 'I', 'V', 'm', 'o', 'i', 'S'];
+    let block_ids = ['X', 'D', 'L', 'Y', 'P', '_', '>', 'G', 'l', 'Q', 'H', 'T', 'M', 'O', 'I', 'C', 'R', 'B', 'V', 'm', 'o', 'i', 'S'];
+    let tool_slots = [ToolKind::Eraser, ToolKind::Fill, ToolKind::Line, ToolKind::Replace, ToolKind::Flood];
+    let mut options: Vec<SlotContent> = block_ids.iter().map(|&b| SlotContent::Block(b)).collect();
+    options.extend(tool_slots.iter().map(|&t| SlotContent::Tool(t)));
 
     let hotbar_tex = window
         .load_texture(&thread, "assets/ui/hotbar.png")
@@ -529,30 +409,29 @@ fn main() {
         .load_texture(&thread, "assets/ui/hotbar_selection.png")
         .expect("No se pudo cargar assets/ui/hotbar_selection.png");
 
-    let icon_paths = vec![
-        "assets/snow_grass/posy.png",             // 'X'
-        "assets/dirt/dirt.png",                   // 'D'
-        "assets/spruce_log/spruce_log_top.png",   // 'L'
-        "assets/spruce_planks/spruce_planks.png", // 'P'
-        "assets/glass/glass.png",                 // 'G'
-        "assets/spruce_leaves/spruce_leaves.png", // 'l'
-        "assets/ice/ice.png",                     // 'H'
-        "assets/diamond_block/diamond_block.png", // 'M'
-        "assets/gold_block/gold_block.png",       // 'O'
-        "assets/iron_block/iron_block.png",       // 'I'
-        "assets/lava/lava.png",                   // 'V'
-        "assets/diamond_ore/diamond_ore.png",     // 'm'
-        "assets/gold_ore/gold_ore.png",           // 'o'
-        "assets/iron_ore/iron_ore.png",           // 'i'
-        "assets/stone/stone.png",                 // 'S'
+    // Íconos del hotbar en el orden de `options`: los bloques se generan (raytrace isométrico
+    // cacheado en `cache/icons`) salvo que tengan un PNG propio acá; las herramientas son procedurales.
+    let icon_overrides: &[(tools::BlockId, &str)] = &[
+        // p. ej. ('X', "assets/snow_grass/posy.png"),
     ];
-    let mut icons: Vec<Texture2D> = Vec::with_capacity(icon_paths.len());
-    for p in icon_paths {
-        icons.push(window.load_texture(&thread, p).expect(&format!("No se pudo cargar {}", p)));
+    let mut icons: Vec<Texture2D> = Vec::with_capacity(options.len());
+    for slot in options.iter() {
+        let img = match *slot {
+            SlotContent::Block(ch) => match icon_overrides.iter().find(|(b, _)| *b == ch) {
+                Some((_, p)) => Image::load_image(p).unwrap_or_else(|_| panic!("No se pudo cargar {}", p)),
+                None => match palette.get(ch) {
+                    Some(tpl) => icons::block_icon(ch, tpl),
+                    None => Image::gen_image_color(icons::ICON_SIZE, icons::ICON_SIZE, Color::MAGENTA),
+                },
+            },
+            SlotContent::Tool(t) => t.icon_image(),
+        };
+        icons.push(window.load_texture_from_image(&thread, &img).expect("No se pudo crear ícono del hotbar"));
     }
 
     let hud_cfg = build::HudConfig { scale: 2.6, bottom_margin: 10, icon_padding_px: 1.0 };
 
+    let grid_origin = params.origin;
     let mut builder = BuildState::new_with_sprites_and_cfg(
         options,
         cube_size,
@@ -560,32 +439,209 @@ fn main() {
         hotbar_sel_tex,
         icons,
         hud_cfg
-    );
-    let grid_origin = params.origin;
+    ).with_tool_config(ToolConfig { flood_max: flood_max_arg(&args), ..ToolConfig::new(cube_size, grid_origin) });
+
+    let mut render_settings = settings_from_args(&args);
+    // Calibración: perfil inicial según lo que tarda la escena real en esta máquina
+    if args.iter().any(|a| a == "--full-quality") {
+        println!("Calidad: completa (--full-quality, sin calibración)");
+    } else {
+        let cal_lights = [light, light2];
+        let (profile, rays_per_sec, est_ms) = calibrate_quality(
+            &objects, &accel, &camera, &cal_lights, skyboxes.get(current_skybox),
+            &render_settings, framebuffer.width, framebuffer.height,
+        );
+        render_settings = profile.apply(render_settings);
+        let msg = format!(
+            "Calidad inicial: {} (~{:.0} ms/frame estimados, {:.1} Mrayos/s). Cambiala con +/-, F1, F4, F7; --full-quality saltea la calibración",
+            profile.name(), est_ms, rays_per_sec / 1e6,
+        );
+        if profile == QualityProfile::Full { notes.info(msg); } else { notes.warn(msg); }
+    }
+
+    // Audio opcional: si no hay dispositivo, el feedback queda solo visual
+    let audio = RaylibAudio::init_audio_device().ok();
+    let mut sfx = audio.as_ref().map(Sfx::load);
+
+    // Modo caminar: estado + cámara orbital guardada para restaurarla al salir
+    let mut walk: Option<(WalkState, Camera)> = None;
+
+    // Esc cancela el arrastre / limpia la selección; sin nada de eso, sale como siempre
+    window.set_exit_key(None);
+    let mut mover = gizmo::MoveGizmo::new();
+    let mut pending_events: Vec<EditEvent> = Vec::new();
 
     while !window.window_should_close() {
-        // ====== INPUT Cámara ======
-        if window.is_key_down(KeyboardKey::KEY_LEFT)  { camera.orbit( rotation_speed, 0.0); }
-        if window.is_key_down(KeyboardKey::KEY_RIGHT) { camera.orbit(-rotation_speed, 0.0); }
-        if window.is_key_down(KeyboardKey::KEY_DOWN)  { camera.orbit(0.0, -rotation_speed); }
-        if window.is_key_down(KeyboardKey::KEY_UP)    { camera.orbit(0.0,  rotation_speed); }
-        if window.is_key_down(KeyboardKey::KEY_PAGE_UP)   { camera.zoom(-0.5); }
-        if window.is_key_down(KeyboardKey::KEY_PAGE_DOWN) { camera.zoom( 0.5); }
+        // ====== Modo caminar (TAB) ======
+        if window.is_key_pressed(KeyboardKey::KEY_TAB) {
+            match walk.take() {
+                Some((_, orbit_cam)) => {
+                    camera = orbit_cam;
+                    window.enable_cursor();
+                }
+                None => {
+                    let w = WalkState::spawn(camera.center, camera.forward, &scene_index);
+                    walk = Some((w, camera));
+                    camera.projection = camera.perspective();
+                    window.disable_cursor();
+                }
+            }
+        }
+
+        if let Some((w, _)) = walk.as_mut() {
+            let axis = |pos: KeyboardKey, neg: KeyboardKey| {
+                (window.is_key_down(pos) as i32 - window.is_key_down(neg) as i32) as f32
+            };
+            let input = WalkInput {
+                forward: axis(KeyboardKey::KEY_W, KeyboardKey::KEY_S),
+                strafe: axis(KeyboardKey::KEY_D, KeyboardKey::KEY_A),
+                jump: window.is_key_down(KeyboardKey::KEY_SPACE),
+                look_delta: window.get_mouse_delta(),
+            };
+            w.update(&input, window.get_frame_time(), &scene_index);
+            camera.look_from(w.eye(), w.forward());
+        } else {
+            // ====== INPUT Cámara ======
+            if window.is_key_down(KeyboardKey::KEY_LEFT)  { camera.orbit( rotation_speed, 0.0); }
+            if window.is_key_down(KeyboardKey::KEY_RIGHT) { camera.orbit(-rotation_speed, 0.0); }
+            if window.is_key_down(KeyboardKey::KEY_DOWN)  { camera.orbit(0.0, -rotation_speed); }
+            if window.is_key_down(KeyboardKey::KEY_UP)    { camera.orbit(0.0,  rotation_speed); }
+            if window.is_key_down(KeyboardKey::KEY_PAGE_UP)   { camera.zoom(-0.5); }
+            if window.is_key_down(KeyboardKey::KEY_PAGE_DOWN) { camera.zoom( 0.5); }
+
+            // Vistas guardadas: Ctrl+1..9 guarda, Shift+1..9 vuelve (con transición)
+            let ctrl = window.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || window.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+            let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            let moving = [KeyboardKey::KEY_LEFT, KeyboardKey::KEY_RIGHT, KeyboardKey::KEY_UP, KeyboardKey::KEY_DOWN,
+                KeyboardKey::KEY_PAGE_UP, KeyboardKey::KEY_PAGE_DOWN].iter().any(|&k| window.is_key_down(k));
+            if moving { camera_presets.cancel(); }
+            for (slot, key) in DIGIT_KEYS.iter().enumerate().map(|(i, k)| (i + 1, *k)) {
+                if !window.is_key_pressed(key) { continue; }
+                if ctrl {
+                    camera_presets.store(slot, &camera);
+                    match camera_presets.save(presets::PRESETS_FILE) {
+                        Ok(()) => notes.info(format!("Vista {} guardada", slot)),
+                        Err(e) => notes.error(format!("Vista {} guardada, pero no se pudo escribir {}: {}", slot, presets::PRESETS_FILE, e)),
+                    }
+                } else if shift && !camera_presets.recall(slot, &camera) {
+                    notes.warn(format!("La vista {} está vacía (Ctrl+{} para guardarla)", slot, slot));
+                }
+            }
+            camera_presets.update(window.get_frame_time(), &mut camera);
+            camera.update(window.get_frame_time());
+        }
+        // Los números con Ctrl/Shift son de las vistas y con Alt del hotbar; sin modificador,
+        // luces y skybox
+        let alt = window.is_key_down(KeyboardKey::KEY_LEFT_ALT) || window.is_key_down(KeyboardKey::KEY_RIGHT_ALT);
+        let digit_mods = alt || [KeyboardKey::KEY_LEFT_CONTROL, KeyboardKey::KEY_RIGHT_CONTROL, KeyboardKey::KEY_LEFT_SHIFT, KeyboardKey::KEY_RIGHT_SHIFT]
+            .iter().any(|&k| window.is_key_down(k));
+
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_ONE) { light.kind = LightKind::Point; }
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_TWO) { light.kind = LightKind::Directional; }
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_FIVE) {
+            light = light::Light::spot(light.position, light.direction, spot_inner, spot_outer, light.color, light.intensity);
+        }
+        // Luz de área (6) y muestras de su sombra (7: 1 → 4 → 9 → 16)
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_SIX) {
+            light = light::Light::area(light.position, light.direction, AREA_LIGHT_SIZE, 4, light.color, light.intensity);
+        }
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_SEVEN) {
+            if let LightKind::Area { samples, .. } = &mut light.kind {
+                *samples = match *samples { 1 => 4, 4 => 9, 9 => 16, _ => 1 };
+            }
+        }
+
+        // Caída con la distancia de las luces puntuales/foco (, menos / . más)
+        // Solo al cambiar de nivel: no se pisan cada frame coeficientes ajustados a mano en `attenuation`
+        let (less, more) = (window.is_key_pressed(KeyboardKey::KEY_COMMA), window.is_key_pressed(KeyboardKey::KEY_PERIOD));
+        if less || more {
+            attenuation_level = if less {
+                attenuation_level.saturating_sub(1)
+            } else {
+                (attenuation_level + 1).min(light::Attenuation::PRESETS.len() - 1)
+            };
+            light.attenuation = light::Attenuation::PRESETS[attenuation_level];
+            light2.attenuation = light::Attenuation::PRESETS[attenuation_level];
+        }
+
+        // Color de la luz principal: T preset (Shift+T anterior), G canal, ; / ' bajar/subir canal
+        if window.is_key_pressed(KeyboardKey::KEY_T) {
+            let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            lighting_ui.cycle_preset(&mut light.color, shift);
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_G) { lighting_ui.next_channel(); }
+        if window.is_key_pressed(KeyboardKey::KEY_SEMICOLON) { lighting_ui.nudge(&mut light.color, -1); }
+        if window.is_key_pressed(KeyboardKey::KEY_APOSTROPHE) { lighting_ui.nudge(&mut light.color, 1); }
+
+        // Resolución interna: Y baja a la siguiente escala (de 0.25 vuelve a 1.0); Shift+Y alterna
+        // la órbita rápida (el tamaño del framebuffer se ajusta antes del render)
+        if window.is_key_pressed(KeyboardKey::KEY_Y) {
+            let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            if shift {
+                fast_orbit = !fast_orbit;
+                // A escala completa la órbita rápida no cambiaría nada: arranca en 50%
+                if fast_orbit && render_scale >= 1.0 { render_scale = 0.5; }
+            } else {
+                render_scale = RENDER_SCALES.iter().copied().find(|&s| s < render_scale - 1e-3).unwrap_or(RENDER_SCALES[0]);
+            }
+        }
+
+        // Whitted progresivo on/off (Delete)
+        if window.is_key_pressed(KeyboardKey::KEY_DELETE) { render_settings.progressive = !render_settings.progressive; }
+
+        // Paquetes 2×2 de rayos primarios (misma imagen; para comparar tiempos)
+        if window.is_key_pressed(KeyboardKey::KEY_INSERT) {
+            render_settings.packets = !render_settings.packets;
+        }
+
+        // Constantes de sombreado (debug): U recorre plano → suave → duro
+        if window.is_key_pressed(KeyboardKey::KEY_U) {
+            lighting_preset = lighting_preset.next();
+            render_settings.lighting = lighting_preset.params();
+        }
 
-        if window.is_key_pressed(KeyboardKey::KEY_ONE) { light.kind = LightKind::Point; }
-        if window.is_key_pressed(KeyboardKey::KEY_TWO) { light.kind = LightKind::Directional; }
+        // Ciclo día/noche: maneja dirección, color e intensidad de la luz principal y tiñe el cielo
+        if window.is_key_pressed(KeyboardKey::KEY_N) { day.enabled = !day.enabled; }
+        if window.is_key_pressed(KeyboardKey::KEY_H) { day.paused = !day.paused; }
+        if day.enabled {
+            let scrub = window.get_frame_time() * 2.0;
+            if window.is_key_down(KeyboardKey::KEY_V) { day.scrub(-scrub); }
+            if window.is_key_down(KeyboardKey::KEY_B) { day.scrub(scrub); }
+            day.update(window.get_frame_time());
+            day.apply(&mut light);
+            render_settings.sky_tint = day.sample().sky;
+        } else {
+            render_settings.sky_tint = Vector3::new(1.0, 1.0, 1.0);
+        }
 
         // Cambiar skybox con 3/4
-        if window.is_key_pressed(KeyboardKey::KEY_THREE) { current_skybox = 0; }
-        if window.is_key_pressed(KeyboardKey::KEY_FOUR)  { current_skybox = 1; }
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_THREE) { current_skybox = 0; }
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_FOUR)  { current_skybox = 1; }
+        // 8: cielo procedural; si ya está, pasa al preset siguiente (día → atardecer → noche)
+        if !digit_mods && window.is_key_pressed(KeyboardKey::KEY_EIGHT) {
+            if current_skybox == skyboxes.len() {
+                render_settings.procedural_sky = render_settings.procedural_sky.next();
+            }
+            current_skybox = skyboxes.len();
+        }
 
-        if matches!(light.kind, LightKind::Directional) {
+        if matches!(light.kind, LightKind::Directional | LightKind::Spot { .. } | LightKind::Area { .. }) {
             if window.is_key_down(KeyboardKey::KEY_J) { light.yaw_pitch( dir_rot_speed, 0.0); }
             if window.is_key_down(KeyboardKey::KEY_L) { light.yaw_pitch(-dir_rot_speed, 0.0); }
             if window.is_key_down(KeyboardKey::KEY_I) { light.yaw_pitch(0.0,  dir_rot_speed); }
             if window.is_key_down(KeyboardKey::KEY_K) { light.yaw_pitch(0.0, -dir_rot_speed); }
         }
-        if matches!(light.kind, LightKind::Point) {
+        // Guardar la escena a JSON (Ctrl+S)
+        let ctrl_down = window.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || window.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+        if ctrl_down && window.is_key_pressed(KeyboardKey::KEY_S) {
+            let cells = scene::cells_from_objects(&objects, &params);
+            match scene::save_json(&scene_json, &cells, &params, &palette) {
+                Ok(()) => notes.info(format!("Escena guardada en {} ({} bloques)", scene_json, cells.len())),
+                Err(e) => notes.error(format!("No se pudo guardar {}: {}", scene_json, e)),
+            }
+        }
+        // En modo caminar WASD mueve al jugador, no a la luz (con Ctrl tampoco: Ctrl+S guarda)
+        if walk.is_none() && !ctrl_down && matches!(light.kind, LightKind::Point | LightKind::Spot { .. } | LightKind::Area { .. }) {
             if window.is_key_down(KeyboardKey::KEY_W) { light.translate(Vector3::new( 0.0, 0.0, -move_speed)); }
             if window.is_key_down(KeyboardKey::KEY_S) { light.translate(Vector3::new( 0.0, 0.0,  move_speed)); }
             if window.is_key_down(KeyboardKey::KEY_A) { light.translate(Vector3::new(-move_speed, 0.0, 0.0)); }
@@ -594,67 +650,656 @@ fn main() {
             if window.is_key_down(KeyboardKey::KEY_F) { light.translate(Vector3::new( 0.0, -move_speed, 0.0)); }
         }
 
+        // Profundidad máxima de rebotes (+/-)
+        if window.is_key_pressed(KeyboardKey::KEY_EQUAL) || window.is_key_pressed(KeyboardKey::KEY_KP_ADD) {
+            render_settings.max_depth = (render_settings.max_depth + 1).min(settings::MAX_DEPTH_LIMIT);
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_MINUS) || window.is_key_pressed(KeyboardKey::KEY_KP_SUBTRACT) {
+            render_settings.max_depth = render_settings.max_depth.saturating_sub(1);
+        }
+
+        // Proyección perspectiva/ortográfica (P)
+        if walk.is_none() && window.is_key_pressed(KeyboardKey::KEY_P) {
+            camera.projection = match camera.projection {
+                Projection::Perspective { .. } => Projection::Orthographic { height: camera.distance * 0.8 },
+                Projection::Orthographic { .. } => camera.perspective(),
+            };
+        }
+
+        // FOV (Z más angosto / X más ancho); con Shift, dolly zoom (el centro conserva su tamaño)
+        let fov_step = window.get_frame_time() * 0.6;
+        let fov_delta = (window.is_key_down(KeyboardKey::KEY_X) as i32 - window.is_key_down(KeyboardKey::KEY_Z) as i32) as f32 * fov_step;
+        if fov_delta != 0.0 {
+            let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            if shift && walk.is_none() { camera.dolly_zoom(camera.fov() + fov_delta); } else { camera.set_fov(camera.fov() + fov_delta); }
+        }
+
+        // Mute global de efectos de sonido (M)
+        if window.is_key_pressed(KeyboardKey::KEY_M) {
+            if let Some(sfx) = sfx.as_mut() { sfx.muted = !sfx.muted; }
+        }
+
+        // Overlays 3D detrás de bloques: ocultar / atenuar (O)
+        if window.is_key_pressed(KeyboardKey::KEY_O) {
+            framebuffer.occlusion = match framebuffer.occlusion {
+                Occlusion::Dim => Occlusion::Hide,
+                Occlusion::Hide => Occlusion::Dim,
+            };
+        }
+
+        // Overlay del diff on/off
+        if window.is_key_pressed(KeyboardKey::KEY_C) {
+            if let Some(ov) = diff_overlay.as_mut() { ov.visible = !ov.visible; }
+        }
+
+        // LOD por distancia on/off
+        if window.is_key_pressed(KeyboardKey::KEY_F1) { render_settings.lod.enabled = !render_settings.lod.enabled; }
+        // Exposición ([ / ]) y operador de tone mapping (F3)
+        if window.is_key_pressed(KeyboardKey::KEY_RIGHT_BRACKET) {
+            render_settings.post.exposure = (render_settings.post.exposure * 1.25).min(16.0);
+        }
+        if window.is_key_pressed(KeyboardKey::KEY_LEFT_BRACKET) {
+            render_settings.post.exposure = (render_settings.post.exposure / 1.25).max(1.0 / 16.0);
+        }
+        // Tone map (F3); Ctrl+F3 muestra el panel de rendimiento
+        if window.is_key_pressed(KeyboardKey::KEY_F3) {
+            if window.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || window.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL) {
+                perf.visible = !perf.visible;
+            } else {
+                render_settings.post.tone_map = render_settings.post.tone_map.next();
+            }
+        }
+
+        // Supersampling: 1 → 4 → 9 → 16 muestras por pixel (F4)
+        if window.is_key_pressed(KeyboardKey::KEY_F4) {
+            render_settings.samples_per_pixel = match render_settings.samples_per_pixel {
+                1 => 4,
+                4 => 9,
+                9 => 16,
+                _ => 1,
+            };
+        }
+
+        // Debug del reinicio parcial de la acumulación (F11)
+        if window.is_key_pressed(KeyboardKey::KEY_F11) { accum.show_resets = !accum.show_resets; }
+        // Vista de debug: calor del recorrido de grilla (celdas / candidatos) y AABB apuntado (`).
+        // Shift+` duplica el lado de celda, Ctrl+` lo divide a la mitad y Ctrl+Shift+` usa el
+        // sugerido; los tres reconstruyen la grilla.
+        if window.is_key_pressed(KeyboardKey::KEY_GRAVE) {
+            let ctrl = window.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || window.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+            let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            let new_cell = match (ctrl, shift) {
+                (false, false) => { render_settings.debug_view = render_settings.debug_view.next(); None }
+                (false, true) => Some(grid_cell * 2.0),
+                (true, false) => Some(grid_cell * 0.5),
+                (true, true) => Some(UniformGridAccel::suggest_cell_size(&objects)),
+            };
+            if let Some(cell) = new_cell.map(|c| c.clamp(0.01, 1000.0)) {
+                grid_cell = cell;
+                accel = UniformGridAccel::build(&objects, grid_cell);
+                // La imagen no cambia (salvo la vista de calor), pero el frame reusado sería viejo
+                scene_changed = true;
+                notes.info(format!("Grilla: {}", accel.stats().summary()));
+            }
+        }
+        // Volcado de estadísticas de recorrido del próximo frame (F12); Ctrl+F12 captura el render
+        // a PNG (Ctrl+Shift+F12: con HUD y overlays)
+        if window.is_key_pressed(KeyboardKey::KEY_F12) {
+            let ctrl = window.is_key_down(KeyboardKey::KEY_LEFT_CONTROL) || window.is_key_down(KeyboardKey::KEY_RIGHT_CONTROL);
+            let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            if ctrl { screenshot_pending = Some(shift); } else { dump_stats_in = Some(0); }
+        }
+        // Estadísticas en vivo on/off (Home) y log de la grilla + último frame (End)
+        if window.is_key_pressed(KeyboardKey::KEY_HOME) { stats::set_live(!stats::live()); }
+        if window.is_key_pressed(KeyboardKey::KEY_END) {
+            let frame_txt = if stats::live() { live_counters.summary() } else { "contadores apagados (Home)".to_string() };
+            notes.info(format!("Grilla: {} | Frame: {} | {}", accel.stats().summary(), last_render.summary(), frame_txt));
+        }
+
+        // AA adaptativo en bordes (F5) y visualización de pixeles refinados (F6)
+        if window.is_key_pressed(KeyboardKey::KEY_F5) { render_settings.adaptive_aa.enabled = !render_settings.adaptive_aa.enabled; }
+        if window.is_key_pressed(KeyboardKey::KEY_F6) { render_settings.adaptive_aa.show_refined = !render_settings.adaptive_aa.show_refined; }
+
+        // Ambiente desde el cielo (IBL) on/off
+        if window.is_key_pressed(KeyboardKey::KEY_F8) { render_settings.ibl.enabled = !render_settings.ibl.enabled; }
+
+        // Niebla por distancia on/off
+        // Shift+F10: color de la niebla fijo / tomado del cielo detrás
+        if window.is_key_pressed(KeyboardKey::KEY_F10) {
+            let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
+            if shift {
+                render_settings.fog.from_sky = !render_settings.fog.from_sky;
+            } else {
+                render_settings.fog.enabled = !render_settings.fog.enabled;
+            }
+        }
+
+        // Integrador: Whitted / path tracing (GI)
+        if window.is_key_pressed(KeyboardKey::KEY_F9) { render_settings.integrator = render_settings.integrator.next(); }
+
+        // Calidad de glints (Off / Primario / Completo)
+        if window.is_key_pressed(KeyboardKey::KEY_F7) { render_settings.glints.quality = render_settings.glints.quality.next(); }
+
+        // Corrección gamma on/off (comparar contra el pipeline sin linealizar)
+        if window.is_key_pressed(KeyboardKey::KEY_F2) { render_settings.gamma_correct = !render_settings.gamma_correct; }
+
         // ====== INPUT Builder ======
         if window.is_key_pressed(KeyboardKey::KEY_Q) { builder.prev(); }
         if window.is_key_pressed(KeyboardKey::KEY_E) { builder.next(); }
+        // Alt+rueda y Alt+1..9: slot vecino / casilla directa de la página visible del hotbar
+        if alt {
+            let wheel = window.get_mouse_wheel_move();
+            if wheel < 0.0 { builder.next(); } else if wheel > 0.0 { builder.prev(); }
+            for (n, &key) in DIGIT_KEYS.iter().enumerate() {
+                if window.is_key_pressed(key) { builder.select_on_page(n); }
+            }
+        }
 
         // ====== PICK / PREVIEW ======
-        let mouse = window.get_mouse_position();
+        // En modo caminar se apunta con la mira del centro de la pantalla
+        let mouse = if walk.is_some() {
+            Vector2::new(window_width as f32 * 0.5, window_height as f32 * 0.5)
+        } else {
+            window.get_mouse_position()
+        };
         let basis = camera.basis();
-        let fov = PI / 3.0;
-        let ray_dir = mouse_ray_dir(
-            mouse,
-            window_width as f32,
-            window_height as f32,
-            fov,
-            basis.right, basis.up, basis.forward,
-        );
-        let ray_origin = basis.eye;
+        let (ray_origin, ray_dir) = mouse_ray(mouse, window_width as f32, window_height as f32, &basis);
+
+        // ====== GIZMO DE MOVIMIENTO (selección con Shift + click izq) ======
+        // Esc primero descarta lo que la herramienta activa tenga a medias
+        let esc = window.is_key_pressed(KeyboardKey::KEY_ESCAPE) && !builder.active_tool().cancel();
+        // `\` cambia la variante de la herramienta activa (se puede cambiar a medias)
+        if window.is_key_pressed(KeyboardKey::KEY_BACKSLASH) {
+            builder.active_tool().cycle_mode();
+        }
+        let mut gizmo_busy = false;
+        if mover.is_dragging() {
+            gizmo_busy = true;
+            if esc {
+                mover.cancel();
+            } else if window.is_mouse_button_down(MouseButton::MOUSE_BUTTON_LEFT) {
+                mover.update_drag(ray_origin, ray_dir, cube_size);
+            } else {
+                // Soltar: todo el grupo en una sola edición (la grilla se actualiza por bloque)
+                let mut edit = SceneEdit::new(&mut objects, &palette, builder.cube_size).with_accel(&mut accel);
+                mover.commit(&mut edit);
+                let (dirty, rebuild_grid, events) = (edit.dirty, edit.rebuild_grid, edit.events);
+                let blocked = events.iter().filter(|e| matches!(e, EditEvent::Failed { .. })).count();
+                if blocked > 0 {
+                    notes.warn(format!("Movimiento cancelado: {} destino(s) ocupado(s) o fuera de límites", blocked));
+                }
+                if rebuild_grid {
+                    accel = UniformGridAccel::build(&objects, grid_cell);
+                }
+                if dirty {
+                    scene_changed = true;
+                    scene_index = SceneIndex::build(&objects, cube_size, grid_origin);
+                }
+                pending_events.extend(events);
+            }
+        } else if walk.is_none() && window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            if let Some(axis) = mover.hovered_axis(&basis, mouse, window_width as f32, window_height as f32, cube_size) {
+                mover.begin_drag(axis, ray_origin, ray_dir);
+                gizmo_busy = true;
+            }
+        }
+        if esc && !gizmo_busy {
+            if mover.selection.is_empty() { break; }
+            mover.clear();
+        }
+        let shift = window.is_key_down(KeyboardKey::KEY_LEFT_SHIFT) || window.is_key_down(KeyboardKey::KEY_RIGHT_SHIFT);
 
         let hit = accel.trace(&ray_origin, &ray_dir, &objects);
+        // Objeto bajo el mouse (vista de debug: su AABB)
+        let hovered_obj = hit.object_index.filter(|_| hit.is_intersecting);
 
         let mut preview: Option<Preview> = None;
-        if hit.is_intersecting {
-            if let Some(idx) = hit.object_index {
-                preview = Some(Preview { hovered_idx: idx });
-            }
-        }
-
-        if hit.is_intersecting {
-            let target_center = neighbor_cell_center_from_face_hit(
-                hit.point, hit.normal, builder.cube_size, grid_origin
-            );
+        // Objeto a contornear (el resaltado de la herramienta)
+        let mut outline_idx: Option<usize> = None;
+        builder.ghost_center = None;
+        let mut region: Option<RegionPreview> = None;
+        if hit.is_intersecting && !gizmo_busy {
+            let pick = Pick {
+                point: hit.point,
+                normal: hit.normal,
+                object_index: hit.object_index,
+                place_center: neighbor_cell_center_from_face_hit(
+                    hit.point, hit.normal, builder.cube_size, grid_origin
+                ),
+            };
+            let tool = builder.active_tool();
+            // Fantasma solo en celdas libres (la cara apuntada puede ser de una malla o un escalón)
+            let ghost_center = tool.ghost(&pick).filter(|&c| !scene_index.is_occupied(scene_index.cell_of(c)));
+            outline_idx = tool.preview(&pick);
+            builder.ghost_center = ghost_center;
+            let ghost = builder.ghost_center.map(|center| GhostBlock {
+                center, half: builder.cube_size * 0.5, color: render_settings.gamma().decode_vec(builder.ghost_mat.diffuse),
+            });
+            // El tinte de material es debug: lo normal es solo el contorno (no cambia la imagen)
+            let hovered_idx = outline_idx.filter(|_| render_settings.preview.tint_hovered);
+            preview = (hovered_idx.is_some() || ghost.is_some()).then_some(Preview { hovered_idx, ghost });
 
+            let mut edit = SceneEdit::new(&mut objects, &palette, builder.cube_size)
+                .with_accel(&mut accel).with_brush(builder.brush());
             if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
-                if let Some(tpl) = palette.get(builder.current_block_char()) {
-                    let block = make_block_from_palette(target_center, builder.cube_size, tpl);
-                    objects.push(block);
-                    accel = UniformGridAccel::build(&objects, cube_size.x.max(0.01));
+                if shift {
+                    // Solo bloques de paleta (las mallas no se pueden mover por celdas)
+                    if let Some(i) = pick.object_index.filter(|&i| edit.objects[i].block_id().is_some()) {
+                        let (mn, mx) = edit.objects[i].aabb();
+                        mover.toggle((mn + mx) * 0.5);
+                    }
+                } else {
+                    builder.active_tool().on_primary(&pick, &mut edit);
                 }
             }
-
             if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_RIGHT) {
-                if let Some(idx) = hit.object_index {
-                    if idx < objects.len() {
-                        objects.swap_remove(idx);
-                        accel = UniformGridAccel::build(&objects, cube_size.x.max(0.01));
+                builder.active_tool().on_secondary(&pick, &mut edit);
+            }
+            // Pick-block: click medio toma el bloque apuntado (si no está en el hotbar, presta un slot)
+            if window.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_MIDDLE) {
+                if let Some(ch) = pick.object_index.and_then(|i| edit.objects[i].block_id()) {
+                    let make_icon = || palette.get(ch)
+                        .and_then(|tpl| window.load_texture_from_image(&thread, &icons::block_icon(ch, tpl)).ok());
+                    if let Some(displaced) = builder.pick_block(ch, make_icon) {
+                        notes.info(format!("Bloque '{}' en el slot de {} (hasta el próximo bloque fuera del hotbar)", ch, displaced.label()));
                     }
                 }
             }
+            let (dirty, rebuild_grid, events) = (edit.dirty, edit.rebuild_grid, edit.events);
+            for (level, text) in edit.notes { notes.notify(level, text); }
+            region = builder.active_tool().region(Some(&pick));
+            if rebuild_grid {
+                accel = UniformGridAccel::build(&objects, grid_cell);
+            }
+            if dirty {
+                scene_changed = true;
+                // Los índices pueden haber cambiado (`swap_remove`)
+                preview = None;
+                outline_idx = None;
+                scene_index = SceneIndex::build(&objects, cube_size, grid_origin);
+                mover.retain_existing(&objects);
+            }
+            if events.iter().any(|e| matches!(e, EditEvent::Failed { .. })) {
+                notes.warn("No se puede colocar ahí: celda ocupada o fuera de límites");
+            }
+            pending_events.extend(events);
+        }
+
+        // Feedback: sonido + efecto sobre la celda. En ediciones masivas (relleno) solo las
+        // primeras FEEDBACK_MAX_EVENTS tienen efecto; el resto solo marca la celda editada.
+        for (i, ev) in pending_events.drain(..).enumerate() {
+            if let EditEvent::Placed { center, .. } | EditEvent::Removed { center, .. } = ev {
+                edited_cells.push(center);
+            }
+            if i >= FEEDBACK_MAX_EVENTS { continue; }
+            let (center, kind) = match ev {
+                EditEvent::Placed { center, sound } => {
+                    if let Some(sfx) = &sfx { sfx.play(sound); }
+                    (center, EffectKind::Placed)
+                }
+                EditEvent::Removed { center, sound } => {
+                    if let Some(sfx) = &sfx { sfx.play(sound); }
+                    (center, EffectKind::Removed)
+                }
+                EditEvent::Failed { center } => {
+                    if let Some(sfx) = &sfx { sfx.play_error(); }
+                    (center, EffectKind::Failed)
+                }
+            };
+            builder.effects.push(CellEffect::new(center, builder.cube_size, kind));
         }
+        feedback::update_effects(&mut builder.effects, window.get_frame_time());
+        notes.update(window.get_frame_time());
+        elapsed += window.get_frame_time();
+        texture::set_anim_time(elapsed);
 
         // ===== Render =====
-        framebuffer.clear();
-        let sky_ref = Some(&skyboxes[current_skybox]);
-        render(&mut framebuffer, &objects, &accel, &camera, &[light, light2], preview, sky_ref);
+        // Escala efectiva: con órbita rápida, completa cuando la vista lleva un rato quieta
+        let view = camera.basis();
+        if last_view == Some(view) { still_secs += window.get_frame_time(); } else { still_secs = 0.0; }
+        last_view = Some(view);
+        let frame_scale = if fast_orbit && still_secs >= STILL_FULL_RES_SECS { 1.0 } else { render_scale };
+        let (fb_w, fb_h) = settings::scaled_size(window_width, window_height, frame_scale);
+        if let Err(e) = framebuffer.resize(&mut window, &thread, fb_w, fb_h) {
+            notes.error(format!("No se pudo cambiar la resolución interna: {}", e));
+        }
+        let sky_ref = skyboxes.get(current_skybox);
+        let overlay = diff_overlay.as_ref().filter(|o| o.visible);
+        let lights = [light, light2];
+        // Cualquier cambio en lo que se ve reinicia la acumulación (la exposición/tone map no: van después,
+        // salvo en Whitted progresivo, que acumula muestras ya con post)
+        // Las ediciones de escena (y el resaltado del borrador) solo reinician los tiles que pueden
+        // haber cambiado, salvo `--full-accum-reset`.
+        let post_key = if render_settings.progressive && render_settings.integrator == Integrator::Whitted {
+            render_settings.post
+        } else {
+            Default::default()
+        };
+        let accum_key = (
+            camera.basis(), lights, current_skybox,
+            RenderSettings { post: post_key, sky_full_res: false, ..render_settings }, overlay.is_some(),
+            anim_frame_secs.map(|secs| (elapsed / secs) as u64),
+        );
+        // Celdas del resaltado y del fantasma: al moverse reinician sus tiles como una edición
+        let preview_cells = [
+            preview.and_then(|p| p.hovered_idx).map(|i| {
+                let (mn, mx) = objects[i].aabb();
+                (mn + mx) * 0.5
+            }),
+            preview.and_then(|p| p.ghost).map(|g| g.center),
+        ];
+        if preview_cells != last_preview_cells {
+            edited_cells.extend(preview_cells.iter().chain(last_preview_cells.iter()).flatten().copied());
+            last_preview_cells = preview_cells;
+        }
+        let view_changed = last_accum_key.as_ref() != Some(&accum_key);
+        if view_changed {
+            accum.reset();
+            last_accum_key = Some(accum_key);
+        } else if !edited_cells.is_empty() {
+            let footprint = accum.partial_resets.then(|| pathtrace::edit_footprint(
+                &camera.basis(), &edited_cells, builder.cube_size * 0.5, &lights,
+                framebuffer.width as usize, framebuffer.height as usize, ACCUM_SHADOW_REACH, ACCUM_MARGIN_PX,
+            )).flatten();
+            match footprint {
+                Some(rects) => accum.reset_tiles(&rects),
+                None => accum.reset(),
+            }
+        }
+        let edited_cells_any = !edited_cells.is_empty();
+        edited_cells.clear();
+        // Nivel del skybox: el original en path tracing una vez que la imagen lleva unos frames
+        // quieta. Queda fuera de la clave: los frames con el cielo reducido se diluyen en el
+        // promedio sin salto, y se vuelve al reducido solo en frames que ya reiniciaron.
+        let accumulating = render_settings.integrator == Integrator::PathTraced || render_settings.progressive;
+        render_settings.sky_full_res = sky_full_always || (accumulating && accum.frames() >= SKY_FULL_AFTER_FRAMES);
+        let mut tile_stats = (dump_stats_in == Some(0))
+            .then(|| TileStats::new(framebuffer.width as usize, framebuffer.height as usize));
+        dump_stats_in = dump_stats_in.and_then(|n| n.checked_sub(1));
+        // El resaltado cambia el material del objeto apuntado: el índice cuenta aunque la celda no cambie
+        let frame_key = Some((render_settings.post, render_settings.sky_full_res, preview.map(|p| (p.hovered_idx, p.ghost.map(|g| g.center)))));
+        let unchanged = adaptive_render && !view_changed && !scene_changed && !edited_cells_any && frame_key == last_frame_key
+            // El path tracing sigue acumulando con la vista quieta; el progresivo, hasta converger
+            && render_settings.integrator == Integrator::Whitted && tile_stats.is_none()
+            && !(render_settings.progressive && accum.min_tile_frames() < PROGRESSIVE_MAX_FRAMES);
+        last_frame_key = frame_key;
+        scene_changed = false;
+        let render_started = Instant::now();
+        let mut primary_rays = 0u64;
+        if unchanged && framebuffer.restore_render() {
+            reused_frames += 1;
+        } else {
+            reused_frames = 0;
+            framebuffer.clear();
+            last_render = render(&mut framebuffer, &objects, &accel, &camera, &lights, preview, sky_ref, &render_settings, overlay, Some(&mut accum), tile_stats.as_mut());
+            framebuffer.save_render();
+            if print_frame_stats { println!("frame: {}", last_render.summary()); }
+            // Aproximado: sin las muestras extra del AA adaptativo; la vista de calor traza 1 por pixel
+            let spp = if render_settings.debug_view == DebugView::Off { render_settings.samples_per_pixel as u64 } else { 1 };
+            primary_rays = framebuffer.width as u64 * framebuffer.height as u64 * spp;
+        }
+        let render_ms = render_started.elapsed().as_secs_f32() * 1000.0;
+        // Reposo: con la imagen quieta solo quedan HUD y toasts, que no necesitan 60 FPS
+        let idle = reused_frames >= IDLE_AFTER_FRAMES && window.get_mouse_delta() == Vector2::zero();
+        if idle != idle_throttled {
+            window.set_target_fps(if idle { IDLE_FPS } else { LOOP_MAX_FPS });
+            idle_throttled = idle;
+        }
+        // Contadores de `--features profiling` del frame (vacío sin la feature)
+        let profile_frame = profile::take_frame();
+        // Lo contado en `render` más lo del resto del frame (picking, gizmos de luz), que es poco
+        if stats::live() {
+            live_counters = stats::take_live();
+            if reused_frames == 0 { live_counters += last_render.counters; }
+        }
+        if let Some(ts) = tile_stats {
+            dump_tile_stats(&ts, &camera, &accel, objects.len(), &render_settings, &profile_frame, &mut notes);
+        }
+        // Sin HUD: el framebuffer recién trazado, antes de componer overlays
+        if screenshot_pending == Some(false) {
+            screenshot_pending = None;
+            save_screenshot(&headless::framebuffer_image(&framebuffer), false, &mut notes);
+        }
 
-        framebuffer.swap_buffers_with(&mut window, &thread, |d| {
+        // Overlays 3D con test de profundidad: se componen en el framebuffer antes de subirlo
+        let effects_cam = camera.basis();
+        feedback::draw_effects(&mut framebuffer, &builder.effects, &effects_cam);
+        // El mouse está en pixeles de ventana; el framebuffer puede ser más chico
+        let fb_mouse = Vector2::new(
+            mouse.x * framebuffer.width as f32 / window_width as f32,
+            mouse.y * framebuffer.height as f32 / window_height as f32,
+        );
+        mover.draw(&mut framebuffer, &effects_cam, fb_mouse, &objects, cube_size);
+        // Mira del modo caminar, en pixeles del framebuffer (8 px de ventana por brazo)
+        if walk.is_some() {
+            let arm = (8 * framebuffer.width as i32 / window_width).max(3);
+            framebuffer.draw_crosshair(arm, Color::WHITE);
+        }
+        // Región a medias de la herramienta: caja de la esquina fijada a la celda apuntada, o celdas sueltas
+        let region = region.or_else(|| builder.active_tool().region(None));
+        let region_color = |removing: bool| if removing { Color::new(255, 90, 80, 255) } else { Color::new(255, 230, 120, 255) };
+        match region {
+            Some(RegionPreview::Box { a, b, removing }) => {
+                let (a, b) = (cell_index_center(a, cube_size, grid_origin), cell_index_center(b, cube_size, grid_origin));
+                let half = Vector3::new((a.x - b.x).abs(), (a.y - b.y).abs(), (a.z - b.z).abs()) * 0.5 + cube_size * 0.5;
+                feedback::draw_box_wire(&mut framebuffer, &effects_cam, (a + b) * 0.5, half, 2.0, region_color(removing));
+            }
+            Some(RegionPreview::Cells { cells, removing }) => {
+                for c in cells {
+                    let center = cell_index_center(c, cube_size, grid_origin);
+                    feedback::draw_box_wire(&mut framebuffer, &effects_cam, center, cube_size * 0.5, 1.0, region_color(removing));
+                }
+            }
+            None => {}
+        }
+        if let Some(o) = outline_idx.and_then(|i| objects.get(i)) {
+            feedback::draw_hover_outline(&mut framebuffer, &effects_cam, o.aabb());
+        }
+        let hovered_aabb = hovered_obj.filter(|_| render_settings.debug_view != DebugView::Off)
+            .and_then(|i| objects.get(i)).map(|o| o.aabb());
+        if let Some((mn, mx)) = hovered_aabb {
+            feedback::draw_box_wire(&mut framebuffer, &effects_cam, (mn + mx) * 0.5, (mx - mn) * 0.5, 2.0, Color::new(255, 80, 255, 255));
+        }
+        if render_settings.debug_view != DebugView::Off {
+            let view = render_settings.debug_view;
+            let (mouse_hit, cost) = accel.trace_cost(&ray_origin, &ray_dir, &objects, f32::INFINITY, false);
+            let value = if view == DebugView::Refs { cost.refs } else { cost.cells };
+            let t = mouse_hit.is_intersecting.then(|| value as f32 / view.full_scale() as f32);
+            feedback::draw_heat_legend(&mut framebuffer, t);
+        }
+        let framebuffer_occlusion = framebuffer.occlusion;
+        let framebuffer_size = (framebuffer.width, framebuffer.height);
+        // La subida de este frame ocurre dentro de `swap_buffers_with`: el HUD muestra la anterior
+        let last_upload = framebuffer.last_upload;
+        let grid_shape = accel.shape();
+        let mut hud_ms = 0.0f32;
+        let swap = framebuffer.swap_buffers_with(&mut window, &thread, |d| {
+            let hud_started = Instant::now();
             draw_hud_hotbar(d, &builder, window_width, window_height);
+            for l in lights.iter() {
+                gizmo::draw_light_gizmo(d, &effects_cam, l, &objects, &accel, window_width, window_height);
+            }
+            notes.draw(d, window_width);
 
             d.draw_text("Click izq: colocar", 12, window_height - 100, 14, Color::LIGHTGRAY);
             d.draw_text("Click der: quitar", 12, window_height - 80, 14, Color::LIGHTGRAY);
-            d.draw_text("Light [1: Point, 2: Dir]", 12, window_height - 60, 14, Color::LIGHTGRAY);
-            d.draw_text("Skybox [3: Sky1, 4: Sky2]", 12, window_height - 40, 14, Color::LIGHTGRAY);
+            let light_txt = match light.kind {
+                LightKind::Area { samples, .. } => format!(
+                    "Light [1: Point, 2: Dir, 5: Spot, 6: Área]  muestras de sombra [7]: {}{}", samples,
+                    if samples > render_settings.area_shadow_samples {
+                        format!(" (la calidad usa {})", render_settings.area_shadow_samples)
+                    } else { String::new() },
+                ),
+                _ => "Light [1: Point, 2: Dir, 5: Spot, 6: Área]".to_string(),
+            };
+            d.draw_text(&light_txt, 12, window_height - 60, 14, Color::LIGHTGRAY);
+            let sky_txt = match sky_memory.get(current_skybox) {
+                Some(&(sky_small, sky_full)) => format!(
+                    "Skybox [3: Sky1, 4: Sky2, 8: procedural]: caras {} — reducido {:.1} MB, original {:.1} MB",
+                    if render_settings.sky_full_res { "originales" } else { "reducidas" },
+                    sky_small as f64 / 1048576.0, sky_full as f64 / 1048576.0,
+                ),
+                None => format!("Skybox [3: Sky1, 4: Sky2, 8: procedural]: procedural, {} [8: siguiente]", render_settings.procedural_sky.name),
+            };
+            d.draw_text(&sky_txt, 12, window_height - 40, 14, Color::LIGHTGRAY);
+            let lod_txt = format!("LOD [F1]: {}", if render_settings.lod.enabled { "ON" } else { "OFF" });
+            d.draw_text(&lod_txt, 12, window_height - 120, 14, Color::LIGHTGRAY);
+            let depth_txt = format!("Rebotes [+/-]: {}", render_settings.max_depth);
+            d.draw_text(&depth_txt, 12, window_height - 140, 14, Color::LIGHTGRAY);
+            let gamma_txt = format!("Gamma sRGB [F2]: {}", if render_settings.gamma_correct { "ON" } else { "OFF" });
+            d.draw_text(&gamma_txt, 12, window_height - 180, 14, Color::LIGHTGRAY);
+            let proj_txt = match camera.projection {
+                Projection::Perspective { fov } => format!("Proyección [P]: Perspectiva  FOV [Z/X, Shift: dolly]: {:.0}°", fov.to_degrees()),
+                Projection::Orthographic { .. } => "Proyección [P]: Ortográfica".to_string(),
+            };
+            d.draw_text(&proj_txt, 12, window_height - 200, 14, Color::LIGHTGRAY);
+            let post_txt = format!(
+                "Tone map [F3]: {}  Exposición [ [ / ] ]: {:.2}",
+                render_settings.post.tone_map.name(), render_settings.post.exposure
+            );
+            d.draw_text(&post_txt, 12, window_height - 220, 14, Color::LIGHTGRAY);
+            let ssaa_txt = format!(
+                "SSAA [F4]: {} spp  AA adaptativo [F5]: {}{}  Progresivo [Supr]: {}",
+                render_settings.samples_per_pixel,
+                if render_settings.adaptive_aa.enabled { "ON" } else { "OFF" },
+                if render_settings.adaptive_aa.show_refined { " (debug [F6])" } else { "" },
+                match (render_settings.progressive, render_settings.integrator) {
+                    (false, _) => "OFF".to_string(),
+                    (true, Integrator::PathTraced) => "ON (sin efecto en path tracing)".to_string(),
+                    (true, Integrator::Whitted) => format!("{}/{} pasadas", accum.min_tile_frames().min(PROGRESSIVE_MAX_FRAMES), PROGRESSIVE_MAX_FRAMES),
+                },
+            );
+            d.draw_text(&ssaa_txt, 12, window_height - 240, 14, Color::LIGHTGRAY);
+            let mute_txt = match &sfx {
+                Some(s) => format!("Sonido [M]: {}", if s.muted { "OFF" } else { "ON" }),
+                None => "Sonido: sin dispositivo".to_string(),
+            };
+            d.draw_text(&mute_txt, 12, window_height - 260, 14, Color::LIGHTGRAY);
+            let glint_txt = format!("Glints [F7]: {}", render_settings.glints.quality.name());
+            d.draw_text(&glint_txt, 12, window_height - 280, 14, Color::LIGHTGRAY);
+            let ibl_txt = format!("Ambiente del cielo (IBL) [F8]: {}", if render_settings.ibl.enabled { "ON" } else { "OFF" });
+            d.draw_text(&ibl_txt, 12, window_height - 300, 14, Color::LIGHTGRAY);
+            let gi_txt = match render_settings.integrator {
+                Integrator::Whitted => format!("Integrador [F9]: {}", Integrator::Whitted.name()),
+                Integrator::PathTraced => format!(
+                    "Integrador [F9]: {} ({} frames, reinicio {}{})", Integrator::PathTraced.name(), accum.frames(),
+                    if accum.partial_resets { "por tiles" } else { "total" },
+                    if accum.show_resets { ", debug [F11]" } else { "" },
+                ),
+            };
+            d.draw_text(&gi_txt, 12, window_height - 320, 14, Color::LIGHTGRAY);
+            let fog_txt = format!(
+                "Niebla [F10]: {}  color [Shift+F10]: {}",
+                if render_settings.fog.enabled { "ON" } else { "OFF" },
+                if render_settings.fog.from_sky { "cielo" } else { "fijo" },
+            );
+            d.draw_text(&fog_txt, 12, window_height - 340, 14, Color::LIGHTGRAY);
+            let sel_txt = if mover.selection.is_empty() {
+                "Selección [Shift + click izq]: vacía".to_string()
+            } else {
+                format!("Selección: {} bloques (arrastrar flechas: mover, Esc: cancelar/limpiar)", mover.selection.len())
+            };
+            d.draw_text(&sel_txt, 12, window_height - 360, 14, Color::LIGHTGRAY);
+            let att = light.attenuation;
+            let att_txt = if att == light::Attenuation::NONE {
+                "Atenuación luces [, / .]: sin caída".to_string()
+            } else {
+                format!("Atenuación luces [, / .]: nivel {} (c {:.1}, l {:.3}, q {:.4})", attenuation_level, att.constant, att.linear, att.quadratic)
+            };
+            d.draw_text(&att_txt, 12, window_height - 380, 14, Color::LIGHTGRAY);
+            let occl_txt = format!("Overlays ocultos [O]: {}", framebuffer_occlusion.name());
+            d.draw_text(&occl_txt, 12, window_height - 400, 14, Color::LIGHTGRAY);
+            lighting_ui.draw(d, light.color, 12, window_height - 420);
+            let day_txt = if day.enabled {
+                format!("Día/noche [N, H pausa, V/B hora]: {}{}", day.clock(), if day.paused { " (pausa)" } else { "" })
+            } else {
+                "Día/noche [N, H pausa, V/B hora]: OFF".to_string()
+            };
+            d.draw_text(&day_txt, 12, window_height - 440, 14, Color::LIGHTGRAY);
+            if let Some(status) = builder.tool_status() {
+                let tool_txt = format!("{} [\\ variante, Esc cancela]: {}", builder.active_slot().label(), status);
+                d.draw_text(&tool_txt, 12, window_height - 520, 14, Color::LIGHTGRAY);
+            }
+            let scale_txt = format!(
+                "Resolución interna [Y]: {:.0}% ({}×{}){}  Paquetes 2×2 [Insert]: {}",
+                frame_scale * 100.0, framebuffer_size.0, framebuffer_size.1,
+                if fast_orbit { format!(", órbita rápida [Shift+Y]: {:.0}% al mover", render_scale * 100.0) } else { String::new() },
+                if render_settings.packets { "ON" } else { "OFF" },
+            );
+            d.draw_text(&scale_txt, 12, window_height - 500, 14, Color::LIGHTGRAY);
+            let shading_txt = format!("Sombreado [U]: {}", lighting_preset.name());
+            d.draw_text(&shading_txt, 12, window_height - 460, 14, Color::LIGHTGRAY);
+            let slots = camera_presets.occupied();
+            let views_txt = if slots.is_empty() {
+                "Vistas [Ctrl+1..9 guardar, Shift+1..9 ir]: ninguna".to_string()
+            } else {
+                format!("Vistas [Ctrl+1..9 guardar, Shift+1..9 ir]: {:?}", slots)
+            };
+            d.draw_text(&views_txt, 12, window_height - 480, 14, Color::LIGHTGRAY);
+            if !profile_frame.is_empty() {
+                let prof_txt = format!("Perfil: {}", profile::summary(&profile_frame, 4));
+                d.draw_text(&prof_txt, 12, window_height - 540, 14, Color::LIGHTGRAY);
+            }
+            let stats_txt = if stats::live() {
+                format!("Estadísticas [Home, End: log]: {}\n  {}", accel.stats().summary(), live_counters.summary())
+            } else {
+                "Estadísticas [Home, End: log]: OFF".to_string()
+            };
+            d.draw_text(&stats_txt, 12, window_height - 580, 14, Color::LIGHTGRAY);
+            let frame_txt = if reused_frames > 0 {
+                format!(
+                    "Render: sin cambios, último frame reusado ({} frames){}", reused_frames,
+                    if idle_throttled { format!(", reposo a {} FPS", IDLE_FPS) } else { String::new() },
+                )
+            } else {
+                "Render: trazado".to_string()
+            };
+            let full_px = (framebuffer_size.0 as u64 * framebuffer_size.1 as u64).max(1);
+            let frame_txt = format!(
+                "{} · subida {:.0}% ({} KB, {:.2} ms)", frame_txt,
+                last_upload.pixels as f64 * 100.0 / full_px as f64, last_upload.pixels * 4 / 1024, last_upload.micros as f64 / 1000.0,
+            );
+            d.draw_text(&frame_txt, 12, window_height - 600, 14, Color::LIGHTGRAY);
+            let debug_view = render_settings.debug_view;
+            let mut debug_txt = format!("Debug [`]: {} · celda {:.2} [Shift/Ctrl+`]", debug_view.name(), grid_cell);
+            if debug_view != DebugView::Off {
+                debug_txt += &format!(" (rojo = {}+)", debug_view.full_scale());
+                if let Some((mn, mx)) = hovered_aabb {
+                    debug_txt += &format!(" · AABB ({:.2}, {:.2}, {:.2})–({:.2}, {:.2}, {:.2})", mn.x, mn.y, mn.z, mx.x, mx.y, mx.z);
+                }
+            }
+            d.draw_text(&debug_txt, 12, window_height - 620, 14, Color::LIGHTGRAY);
+            let walk_txt = format!("Caminar [TAB]: {}", if walk.is_some() { "ON (WASD, Espacio)" } else { "OFF" });
+            d.draw_text(&walk_txt, 12, window_height - 160, 14, Color::LIGHTGRAY);
+            perf.draw(d, objects.len(), grid_shape);
+            hud_ms = hud_started.elapsed().as_secs_f32() * 1000.0;
+        });
+        perf.push(perf::FrameTiming {
+            render_ms,
+            upload_ms: framebuffer.last_upload.micros as f32 / 1000.0,
+            hud_ms,
+            frame_ms: window.get_frame_time() * 1000.0,
+            primary_rays,
         });
+        // Subida a la GPU: una falla se recupera recreando la textura; solo se aborta tras
+        // varias fallas seguidas sin recuperación
+        match swap {
+            Ok(SwapStatus::Ok) => {}
+            Ok(SwapStatus::Recovered(reason)) => {
+                notes.warn(format!("Falló la subida del frame a la GPU ({}); textura recreada", reason));
+            }
+            Err(e) if e.is_fatal() => {
+                eprintln!("La textura GPU no se pudo recuperar tras {} intentos: {}", e.consecutive, e.reason);
+                break;
+            }
+            Err(e) => {
+                notes.error(format!("No se pudo mostrar el frame ({}); intento {}/{}", e.reason, e.consecutive, framebuffer::MAX_UPLOAD_FAILURES));
+            }
+        }
+        // Con HUD: lo que quedó en pantalla al cerrar el dibujo (a resolución de ventana)
+        if screenshot_pending == Some(true) {
+            screenshot_pending = None;
+            save_screenshot(&window.load_image_from_screen(&thread), true, &mut notes);
+        }
     }
 }
@@ -1,4 +1,14 @@
 use raylib::prelude::{Color, Vector3};
+use crate::gamma::Gamma;
+
+/// Modelo del brillo especular directo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpecularModel {
+    /// `powf(specular)` clásico (por defecto; las paletas viejas se ven igual).
+    Phong,
+    /// Microfacetas GGX/Trowbridge-Reitz con Smith-Schlick y Fresnel (F0 = albedo[2], mín. 0.04).
+    Ggx { roughness: f32 },
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct Material {
@@ -6,6 +16,12 @@ pub struct Material {
     pub albedo: [f32; 4],
     pub specular: f32,
     pub refractive_index: f32,
+    /// Materiales opacos con reflectividad: refleja más a ángulos rasantes (Schlick con F0 = albedo[2]).
+    /// Los transparentes siempre usan Fresnel con su índice de refracción.
+    pub fresnel: bool,
+    pub specular_model: SpecularModel,
+    /// Absorción de Beer-Lambert por unidad de distancia recorrida adentro (por canal; cero = ninguna).
+    pub absorption: Vector3,
 }
 
 impl Material {
@@ -15,24 +31,55 @@ impl Material {
             albedo,
             specular,
             refractive_index,
+            fresnel: false,
+            specular_model: SpecularModel::Phong,
+            absorption: Vector3::zero(),
         }
     }
 
+    pub fn with_fresnel(mut self) -> Self {
+        self.fresnel = true;
+        self
+    }
+
+    /// Especular GGX con `roughness` 0..1 (0 = espejo, 1 = mate).
+    pub fn with_ggx(mut self, roughness: f32) -> Self {
+        self.specular_model = SpecularModel::Ggx { roughness: roughness.clamp(0.02, 1.0) };
+        self
+    }
+
     pub fn black() -> Self {
         Material {
             diffuse: Vector3::zero(),
             albedo: [0.0, 0.0, 0.0, 0.0],
             specular: 0.0,
             refractive_index: 0.0,
+            fresnel: false,
+            specular_model: SpecularModel::Phong,
+            absorption: Vector3::zero(),
         }
     }
+
+    pub fn with_absorption(mut self, absorption: Vector3) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    /// Fracción por canal que sobrevive a `distance` unidades adentro del material: e^(-σ·d).
+    #[inline]
+    pub fn transmittance(&self, distance: f32) -> Vector3 {
+        if self.absorption == Vector3::zero() { return Vector3::one(); }
+        let a = self.absorption * distance.max(0.0);
+        Vector3::new((-a.x).exp(), (-a.y).exp(), (-a.z).exp())
+    }
 }
 
-pub fn vector3_to_color(v: Vector3) -> Color {
+/// Espacio de trabajo → `Color` (codifica a sRGB si `gamma` corrige).
+pub fn vector3_to_color(v: Vector3, gamma: Gamma) -> Color {
     Color::new(
-        (v.x * 255.0).min(255.0) as u8,
-        (v.y * 255.0).min(255.0) as u8,
-        (v.z * 255.0).min(255.0) as u8,
+        (gamma.encode(v.x) * 255.0).min(255.0) as u8,
+        (gamma.encode(v.y) * 255.0).min(255.0) as u8,
+        (gamma.encode(v.z) * 255.0).min(255.0) as u8,
         255,
     )
 }
@@ -0,0 +1,216 @@
+// mesh.rs
+use std::sync::Arc;
+use std::{fs, io};
+use raylib::prelude::{Vector2, Vector3};
+
+use crate::material::Material;
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::texture::Texture;
+
+/// Cómo se sombrea la malla.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalMode {
+    /// Normal de la cara (facetado).
+    Geometric,
+    /// Normales por vértice interpoladas con baricéntricas.
+    Smooth,
+}
+
+/// Triángulo con normal y UV por vértice (UV con origen arriba-izquierda, como `Texture`).
+#[derive(Clone, Copy, Debug)]
+pub struct Triangle {
+    pub v: [Vector3; 3],
+    pub n: [Vector3; 3],
+    pub uv: [Vector2; 3],
+}
+
+impl Triangle {
+    #[inline]
+    fn face_normal(&self) -> Vector3 {
+        (self.v[1] - self.v[0]).cross(self.v[2] - self.v[0]).normalized()
+    }
+
+    /// Möller–Trumbore: devuelve (t, u, v) con baricéntricas (1-u-v, u, v).
+    #[inline]
+    fn intersect(&self, ro: &Vector3, rd: &Vector3) -> Option<(f32, f32, f32)> {
+        let e1 = self.v[1] - self.v[0];
+        let e2 = self.v[2] - self.v[0];
+        let p = rd.cross(e2);
+        let det = e1.dot(p);
+        if det.abs() < 1e-8 { return None; }
+        let inv = 1.0 / det;
+        let s = *ro - self.v[0];
+        let u = s.dot(p) * inv;
+        if !(0.0..=1.0).contains(&u) { return None; }
+        let q = s.cross(e1);
+        let v = rd.dot(q) * inv;
+        if v < 0.0 || u + v > 1.0 { return None; }
+        let t = e2.dot(q) * inv;
+        if t > 1e-4 { Some((t, u, v)) } else { None }
+    }
+}
+
+/// Malla de triángulos (sin estructura interna: pensada para decoraciones chicas).
+pub struct Mesh {
+    pub triangles: Vec<Triangle>,
+    pub material: Material,
+    pub normal_mode: NormalMode,
+    /// Si está, el difuso sale de la textura en el UV interpolado (repetida fuera de 0..1).
+    pub texture: Option<Arc<Texture>>,
+    min: Vector3,
+    max: Vector3,
+}
+
+impl Mesh {
+    pub fn new(triangles: Vec<Triangle>, material: Material, normal_mode: NormalMode) -> Self {
+        let mut mesh = Mesh { triangles, material, normal_mode, texture: None, min: Vector3::zero(), max: Vector3::zero() };
+        mesh.update_bounds();
+        mesh
+    }
+
+    fn update_bounds(&mut self) {
+        let mut min = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = Vector3::new(-f32::INFINITY, -f32::INFINITY, -f32::INFINITY);
+        for tri in self.triangles.iter() {
+            for p in tri.v.iter() {
+                min = Vector3::new(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z));
+                max = Vector3::new(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z));
+            }
+        }
+        if self.triangles.is_empty() { min = Vector3::zero(); max = Vector3::zero(); }
+        self.min = min;
+        self.max = max;
+    }
+
+    pub fn with_texture(mut self, texture: Arc<Texture>) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    pub fn translate(&mut self, offset: Vector3) {
+        for tri in self.triangles.iter_mut() {
+            for p in tri.v.iter_mut() { *p += offset; }
+        }
+        self.update_bounds();
+    }
+
+    /// Carga un OBJ (solo `v`, `vt`, `vn` y `f`; caras de más de 3 vértices se triangulan en abanico).
+    /// Si una cara no trae `vn` se usa la normal de la cara en sus tres vértices; sin `vt`, UV (0, 0).
+    pub fn from_obj(path: &str, material: Material, normal_mode: NormalMode) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        let bad = |line: usize, what: &str| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("{}:{}: {}", path, line + 1, what))
+        };
+
+        let mut positions: Vec<Vector3> = Vec::new();
+        let mut normals: Vec<Vector3> = Vec::new();
+        let mut uvs: Vec<Vector2> = Vec::new();
+        let mut triangles: Vec<Triangle> = Vec::new();
+
+        let parse_vec3 = |parts: &mut std::str::SplitWhitespace, line: usize| -> io::Result<Vector3> {
+            let mut c = [0.0f32; 3];
+            for slot in c.iter_mut() {
+                *slot = parts.next().and_then(|s| s.parse().ok()).ok_or_else(|| bad(line, "número inválido"))?;
+            }
+            Ok(Vector3::new(c[0], c[1], c[2]))
+        };
+
+        // Índices OBJ: base 1, negativos = relativos al final
+        let resolve = |idx: &str, len: usize, line: usize| -> io::Result<usize> {
+            let i: i64 = idx.parse().map_err(|_| bad(line, "índice inválido"))?;
+            let r = if i < 0 { len as i64 + i } else { i - 1 };
+            if r < 0 || r as usize >= len { return Err(bad(line, "índice fuera de rango")); }
+            Ok(r as usize)
+        };
+
+        for (ln, raw) in text.lines().enumerate() {
+            let line = raw.split('#').next().unwrap_or("").trim();
+            let mut parts = line.split_whitespace();
+            match parts.next() {
+                Some("v") => positions.push(parse_vec3(&mut parts, ln)?),
+                Some("vn") => normals.push(parse_vec3(&mut parts, ln)?.normalized()),
+                Some("vt") => {
+                    let mut c = [0.0f32; 2];
+                    for slot in c.iter_mut() {
+                        *slot = parts.next().and_then(|s| s.parse().ok()).ok_or_else(|| bad(ln, "número inválido"))?;
+                    }
+                    // OBJ tiene v hacia arriba; las texturas se muestrean desde arriba
+                    uvs.push(Vector2::new(c[0], 1.0 - c[1]));
+                }
+                Some("f") => {
+                    let mut corners: Vec<(usize, Option<usize>, Option<usize>)> = Vec::new();
+                    for tok in parts {
+                        let mut fields = tok.split('/');
+                        let vi = resolve(fields.next().unwrap_or(""), positions.len(), ln)?;
+                        let ti = match fields.next() {
+                            Some(t) if !t.is_empty() => Some(resolve(t, uvs.len(), ln)?),
+                            _ => None,
+                        };
+                        let ni = match fields.next() {
+                            Some(n) if !n.is_empty() => Some(resolve(n, normals.len(), ln)?),
+                            _ => None,
+                        };
+                        corners.push((vi, ti, ni));
+                    }
+                    if corners.len() < 3 { return Err(bad(ln, "cara con menos de 3 vértices")); }
+                    for k in 1..corners.len() - 1 {
+                        let c = [corners[0], corners[k], corners[k + 1]];
+                        let v = [positions[c[0].0], positions[c[1].0], positions[c[2].0]];
+                        let mut tri = Triangle { v, n: [Vector3::zero(); 3], uv: [Vector2::zero(); 3] };
+                        let face_n = tri.face_normal();
+                        for (k, corner) in c.iter().enumerate() {
+                            tri.n[k] = corner.2.map(|i| normals[i]).unwrap_or(face_n);
+                            tri.uv[k] = corner.1.map(|i| uvs[i]).unwrap_or(Vector2::zero());
+                        }
+                        triangles.push(tri);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(Mesh::new(triangles, material, normal_mode))
+    }
+}
+
+impl RayIntersect for Mesh {
+    fn ray_intersect(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        profile_scope!(Mesh);
+        let mut best: Option<(f32, f32, f32, &Triangle)> = None;
+        for tri in self.triangles.iter() {
+            if let Some((t, u, v)) = tri.intersect(ro, rd) {
+                if best.is_none_or(|b| t < b.0) { best = Some((t, u, v, tri)); }
+            }
+        }
+        let Some((t, u, v, tri)) = best else { return Intersect::empty(); };
+
+        let point = *ro + *rd * t;
+        let geometric = tri.face_normal();
+        let shading = match self.normal_mode {
+            NormalMode::Geometric => geometric,
+            NormalMode::Smooth => {
+                let n = tri.n[0] * (1.0 - u - v) + tri.n[1] * u + tri.n[2] * v;
+                // Del mismo lado que la geométrica (normales de archivo pueden venir invertidas)
+                if n.dot(geometric) < 0.0 { -n.normalized() } else { n.normalized() }
+            }
+        };
+
+        let material = match &self.texture {
+            Some(tex) => {
+                let uv = tri.uv[0] * (1.0 - u - v) + tri.uv[1] * u + tri.uv[2] * v;
+                Material { diffuse: tex.sample_repeat(uv.x, uv.y), ..self.material }
+            }
+            None => self.material,
+        };
+        let mut hit = Intersect::new(point, shading, t, material);
+        hit.geometric_normal = geometric;
+        hit.textured = self.texture.is_some();
+        hit
+    }
+
+    fn aabb(&self) -> (Vector3, Vector3) {
+        (self.min, self.max)
+    }
+
+    fn prefer_sphere_prefilter(&self) -> bool { true }
+}
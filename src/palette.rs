@@ -4,8 +4,10 @@ use std::sync::Arc;
 
 use raylib::prelude::Vector3;
 
+use crate::cube::sample_with_style;
 use crate::material::Material;
-use crate::texture::Texture;
+use crate::stairs::StairFacing;
+use crate::texture::{AnimatedTexture, SampleMode, Texture};
 
 /// Estilo de muestreo por cara.
 /// - Normal: usa el color de la textura.
@@ -16,7 +18,7 @@ use crate::texture::Texture;
 /// - GrayscaleTintImageAlphaCutout: tinta + cutout por alpha.
 /// - ImageAlphaWindow: usa alpha como **coverage** (0..1), NO corta el rayo; ideal ventana.
 /// - GrayscaleTintImageAlphaWindow: igual que arriba con tinta para B/N.
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum TexStyle {
     Normal,
     GrayscaleTint { color: Vector3 },
@@ -28,11 +30,177 @@ pub enum TexStyle {
     GrayscaleTintImageAlphaWindow { color: Vector3, threshold: f32 },
 }
 
+/// Relieve por heightfield sobre la cara superior (sin geometría extra).
+#[derive(Clone)]
+pub struct Displacement {
+    /// Altura en escala de grises; se muestrea en coordenadas de mundo (x, z) para que los
+    /// bloques vecinos empalmen.
+    pub height: Arc<Texture>,
+    /// Altura máxima sobre la cara, en unidades de mundo.
+    pub amplitude: f32,
+}
+
+/// Mapa especular en escala de grises (dato, no color): blanco = pulido, negro = rayado/mate.
+#[derive(Clone)]
+pub struct SpecularMap {
+    pub tex: Arc<Texture>,
+    /// Si está, el gris también remapea el exponente especular: 0 → `.0`, 1 → `.1`.
+    pub exponent: Option<(f32, f32)>,
+    /// Promedio del mapa (LOD lejano).
+    pub avg: f32,
+}
+
+impl SpecularMap {
+    pub fn new(tex: Arc<Texture>, exponent: Option<(f32, f32)>) -> Self {
+        let (w, h) = (tex.width().max(1), tex.height().max(1));
+        let mut sum = 0.0;
+        for y in 0..h {
+            for x in 0..w {
+                sum += tex.sample_data((x as f32 + 0.5) / w as f32, (y as f32 + 0.5) / h as f32);
+            }
+        }
+        let avg = sum / (w * h) as f32;
+        SpecularMap { tex, exponent, avg }
+    }
+
+    /// Escala el especular (`albedo[1]`) y la reflexión (`albedo[2]`) del material por el texel.
+    #[inline]
+    pub fn apply(&self, m: &mut Material, u: f32, v: f32) {
+        self.apply_value(m, self.tex.sample_data(u, v));
+    }
+
+    #[inline]
+    pub fn apply_value(&self, m: &mut Material, s: f32) {
+        m.albedo[1] *= s;
+        m.albedo[2] *= s;
+        if let Some((lo, hi)) = self.exponent {
+            m.specular = lo + (hi - lo) * s;
+        }
+    }
+}
+
 /// Capa de cara: textura + estilo de muestreo.
+/// `avg_color`/`avg_coverage` se reducen una sola vez al crear la plantilla (LOD lejano).
 #[derive(Clone)]
 pub struct FaceStyle {
     pub tex: Arc<Texture>,
     pub style: TexStyle,
+    pub avg_color: Vector3,
+    pub avg_coverage: f32,
+    /// Reparto de texeles en la cara; `Edge` por defecto para que bloques vecinos no muestren costura.
+    /// Se cambia con `with_sample`, que recalcula el promedio de LOD con el modo nuevo.
+    pub sample: SampleMode,
+    /// Solo se usa en la cara superior de `Cube`.
+    pub displacement: Option<Displacement>,
+    /// Brillo por texel; sin mapa, toda la cara usa el material tal cual.
+    pub specular: Option<SpecularMap>,
+    /// Mapa de normales en espacio tangente de la cara (ver `shading_normal`).
+    pub normal_map: Option<Arc<Texture>>,
+    /// Cuartos de vuelta (horario) de la textura sobre la cara; 0 = sin rotar.
+    pub rotation: u8,
+    /// Frames animados; `tex` queda como el primero (promedio de LOD, íconos).
+    pub anim: Option<Arc<AnimatedTexture>>,
+    /// Reemplazos del material solo en esta cara (p. ej. tapa espejada con lados mate).
+    pub albedo_override: Option<[f32; 4]>,
+    pub specular_override: Option<f32>,
+    pub refractive_index_override: Option<f32>,
+}
+
+impl FaceStyle {
+    pub fn new(tex: Arc<Texture>, style: TexStyle) -> Self {
+        let sample = SampleMode::Edge;
+        let (avg_color, avg_coverage) = face_average(&tex, &style, sample);
+        FaceStyle {
+            tex, style, avg_color, avg_coverage, sample, displacement: None, specular: None, normal_map: None, rotation: 0, anim: None,
+            albedo_override: None, specular_override: None, refractive_index_override: None,
+        }
+    }
+
+    /// Misma cara muestreada con `mode`; `avg_color`/`avg_coverage` se vuelven a reducir con él.
+    pub fn with_sample(mut self, mode: SampleMode) -> Self {
+        if mode != self.sample {
+            (self.avg_color, self.avg_coverage) = face_average(&self.tex, &self.style, mode);
+            self.sample = mode;
+        }
+        self
+    }
+
+    /// Cara animada: el LOD y los íconos usan el primer frame.
+    pub fn animated(anim: Arc<AnimatedTexture>, style: TexStyle) -> Self {
+        FaceStyle { anim: Some(anim.clone()), ..Self::new(anim.first().clone(), style) }
+    }
+
+    /// Textura a muestrear ahora: el frame actual si la cara es animada.
+    #[inline]
+    pub fn texture(&self) -> &Texture {
+        match &self.anim {
+            Some(anim) => anim.current(),
+            None => &self.tex,
+        }
+    }
+
+    /// Gira `(u, v)` alrededor del centro de la cara según `rotation`. Se aplica después del clamp:
+    /// `1 - x` conserva el margen `[tiny, 1 - tiny]`, así que nunca cae fuera de la textura.
+    #[inline]
+    pub fn rotate_uv(&self, u: f32, v: f32) -> (f32, f32) {
+        match self.rotation % 4 {
+            0 => (u, v),
+            1 => (v, 1.0 - u),
+            2 => (1.0 - u, 1.0 - v),
+            _ => (1.0 - v, u),
+        }
+    }
+
+    /// Normal de sombreado en `(u, v)` (ya rotados) según el mapa de normales; sin mapa, `normal`.
+    /// `axes` son las direcciones de mundo en que crecen u y v sobre la cara sin rotar
+    /// (`Face::uv_axes`): con ellas y la normal de la cara se arma la base tangente.
+    #[inline]
+    pub fn shading_normal(&self, normal: Vector3, (du, dv): (Vector3, Vector3), u: f32, v: f32) -> Vector3 {
+        let Some(map) = &self.normal_map else { return normal; };
+        // Ejes de los UV girados (misma tabla que `rotate_uv`)
+        let (tu, tv) = match self.rotation % 4 {
+            0 => (du, dv),
+            1 => (dv, -du),
+            2 => (-du, -dv),
+            _ => (-dv, du),
+        };
+        let t = map.sample_normal(u, v);
+        // El verde apunta hacia arriba en la imagen, o sea contra v
+        let n = (tu * t.x - tv * t.y + normal * t.z).normalized();
+        // Una normal casi rasante haría que la cara se sombree como si mirara para atrás
+        if n.dot(normal) > 0.05 { n } else { normal }
+    }
+
+    /// Aplica los reemplazos de la cara sobre el material del bloque (antes del mapa especular,
+    /// que sigue modulando el brillo por texel).
+    #[inline]
+    pub fn apply_overrides(&self, m: &mut Material) {
+        if let Some(albedo) = self.albedo_override { m.albedo = albedo; }
+        if let Some(specular) = self.specular_override { m.specular = specular; }
+        if let Some(ior) = self.refractive_index_override { m.refractive_index = ior; }
+    }
+}
+
+/// Promedio de la textura ya aplicado el estilo: color ponderado por coverage y coverage medio
+/// (los huecos de cutout cuentan como 0).
+fn face_average(tex: &Texture, style: &TexStyle, sample: SampleMode) -> (Vector3, f32) {
+    let (w, h) = (tex.width().max(1), tex.height().max(1));
+    let mut color_sum = Vector3::zero();
+    let mut coverage_sum = 0.0;
+    for y in 0..h {
+        for x in 0..w {
+            let u = (x as f32 + 0.5) / w as f32;
+            let v = (y as f32 + 0.5) / h as f32;
+            if let Some((c, cov)) = sample_with_style(tex, u, v, style, sample) {
+                color_sum += c * cov;
+                coverage_sum += cov;
+            }
+        }
+    }
+    let n = (w * h) as f32;
+    let avg_color = if coverage_sum > 0.0 { color_sum / coverage_sum } else { Vector3::zero() };
+    // El muestreo ya decodifica a lineal: el promedio no depende del modo gamma del render.
+    (avg_color, coverage_sum / n)
 }
 
 /// Orden de caras (importante):
@@ -41,56 +209,157 @@ pub struct FaceStyle {
 pub struct CubeTemplate {
     pub material: Material,
     pub face_textures: [Option<FaceStyle>; 6],
+    /// Sonido al colocar/quitar (ver `feedback`).
+    pub sound: SoundCategory,
+    pub shape: BlockShape,
+}
+
+/// Geometría que arma la plantilla en una celda (los slabs salen del carácter `_`/`-`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BlockShape {
+    Cube,
+    /// Esfera inscrita en la celda; las texturas de la plantilla se proyectan como cubemap.
+    Sphere,
+    /// Escalera (losa + cuarto de bloque) que sube hacia el lado indicado.
+    Stairs(StairFacing),
+}
+
+/// Categoría de sonido de un bloque.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SoundCategory { Stone, Dirt, Wood, Glass, Foliage, Metal }
+
+impl SoundCategory {
+    pub const ALL: [SoundCategory; 6] = [
+        SoundCategory::Stone, SoundCategory::Dirt, SoundCategory::Wood,
+        SoundCategory::Glass, SoundCategory::Foliage, SoundCategory::Metal,
+    ];
+
+    /// Nombre de archivo en `assets/sounds/<nombre>.wav`.
+    pub fn name(self) -> &'static str {
+        match self {
+            SoundCategory::Stone => "stone",
+            SoundCategory::Dirt => "dirt",
+            SoundCategory::Wood => "wood",
+            SoundCategory::Glass => "glass",
+            SoundCategory::Foliage => "foliage",
+            SoundCategory::Metal => "metal",
+        }
+    }
 }
 
 impl CubeTemplate {
+    /// Relieve en la cara superior (nieve, arena...).
+    pub fn with_top_displacement(mut self, height: Arc<Texture>, amplitude: f32) -> Self {
+        if let Some(top) = self.face_textures[2].as_mut() {
+            top.displacement = Some(Displacement { height, amplitude });
+        }
+        self
+    }
+
+    /// Mapa especular en todas las caras texturizadas.
+    pub fn with_specular_map(mut self, tex: Arc<Texture>, exponent: Option<(f32, f32)>) -> Self {
+        let map = SpecularMap::new(tex, exponent);
+        for face in self.face_textures.iter_mut().flatten() {
+            face.specular = Some(map.clone());
+        }
+        self
+    }
+
+    /// Mapa de normales en todas las caras texturizadas.
+    pub fn with_normal_map(self, tex: Arc<Texture>) -> Self {
+        self.with_normal_map_on(tex, [true; 6])
+    }
+
+    /// Mapa de normales solo en las caras marcadas (mismo orden que `face_textures`), p. ej. la
+    /// corteza de un tronco y no sus anillos.
+    pub fn with_normal_map_on(mut self, tex: Arc<Texture>, faces: [bool; 6]) -> Self {
+        for (face, on) in self.face_textures.iter_mut().zip(faces) {
+            if let (Some(f), true) = (face, on) { f.normal_map = Some(tex.clone()); }
+        }
+        self
+    }
+
+    /// Duración de frame más corta entre sus caras animadas (None si no tiene).
+    pub fn anim_frame_secs(&self) -> Option<f32> {
+        self.face_textures.iter().flatten()
+            .filter_map(|f| f.anim.as_ref().map(|a| a.frame_secs()))
+            .reduce(f32::min)
+    }
+
+    /// Esfera inscrita en vez de cubo (copas de árbol, faroles...).
+    pub fn as_sphere(mut self) -> Self {
+        self.shape = BlockShape::Sphere;
+        self
+    }
+
+    /// Escalera en vez de cubo; cada orientación es su propio carácter de paleta.
+    pub fn as_stairs(mut self, facing: StairFacing) -> Self {
+        self.shape = BlockShape::Stairs(facing);
+        self
+    }
+
+    pub fn with_sound(mut self, sound: SoundCategory) -> Self {
+        self.sound = sound;
+        self
+    }
+
     pub fn material_only(material: Material) -> Self {
         CubeTemplate {
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
             face_textures: [None, None, None, None, None, None],
         }
     }
 
     pub fn with_same_texture(material: Material, tex: Arc<Texture>) -> Self {
-        let fs = FaceStyle { tex: tex.clone(), style: TexStyle::Normal };
+        let fs = FaceStyle::new(tex.clone(), TexStyle::Normal);
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
     pub fn with_same_texture_tinted(material: Material, tex: Arc<Texture>, color: Vector3) -> Self {
-        let fs = FaceStyle { tex: tex.clone(), style: TexStyle::GrayscaleTint { color } };
+        let fs = FaceStyle::new(tex.clone(), TexStyle::GrayscaleTint { color });
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
     pub fn with_same_texture_black_transparent(
         material: Material, tex: Arc<Texture>, threshold: f32,
     ) -> Self {
-        let fs = FaceStyle { tex: tex.clone(), style: TexStyle::BlackIsTransparent { threshold } };
+        let fs = FaceStyle::new(tex.clone(), TexStyle::BlackIsTransparent { threshold });
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
     pub fn with_same_texture_tinted_black_transparent(
         material: Material, tex: Arc<Texture>, color: Vector3, threshold: f32,
     ) -> Self {
-        let fs = FaceStyle {
-            tex: tex.clone(),
-            style: TexStyle::GrayscaleTintBlackTransparent { color, threshold },
-        };
+        let fs = FaceStyle::new(
+            tex.clone(),
+            TexStyle::GrayscaleTintBlackTransparent { color, threshold },
+        );
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
@@ -98,11 +367,13 @@ impl CubeTemplate {
     pub fn with_same_texture_image_alpha(
         material: Material, tex: Arc<Texture>, threshold: f32,
     ) -> Self {
-        let fs = FaceStyle { tex: tex.clone(), style: TexStyle::ImageAlphaCutout { threshold } };
+        let fs = FaceStyle::new(tex.clone(), TexStyle::ImageAlphaCutout { threshold });
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
@@ -110,14 +381,16 @@ impl CubeTemplate {
     pub fn with_same_texture_tinted_image_alpha(
         material: Material, tex: Arc<Texture>, color: Vector3, threshold: f32,
     ) -> Self {
-        let fs = FaceStyle {
-            tex: tex.clone(),
-            style: TexStyle::GrayscaleTintImageAlphaCutout { color, threshold },
-        };
+        let fs = FaceStyle::new(
+            tex.clone(),
+            TexStyle::GrayscaleTintImageAlphaCutout { color, threshold },
+        );
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
@@ -125,11 +398,13 @@ impl CubeTemplate {
     pub fn with_same_texture_image_alpha_window(
         material: Material, tex: Arc<Texture>, threshold: f32,
     ) -> Self {
-        let fs = FaceStyle { tex: tex.clone(), style: TexStyle::ImageAlphaWindow { threshold } };
+        let fs = FaceStyle::new(tex.clone(), TexStyle::ImageAlphaWindow { threshold });
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
@@ -137,14 +412,16 @@ impl CubeTemplate {
     pub fn with_same_texture_tinted_image_alpha_window(
         material: Material, tex: Arc<Texture>, color: Vector3, threshold: f32,
     ) -> Self {
-        let fs = FaceStyle {
-            tex: tex.clone(),
-            style: TexStyle::GrayscaleTintImageAlphaWindow { color, threshold },
-        };
+        let fs = FaceStyle::new(
+            tex.clone(),
+            TexStyle::GrayscaleTintImageAlphaWindow { color, threshold },
+        );
         CubeTemplate {
             face_textures: [Some(fs.clone()), Some(fs.clone()), Some(fs.clone()),
                             Some(fs.clone()), Some(fs.clone()), Some(fs)],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
@@ -154,16 +431,19 @@ impl CubeTemplate {
         bottom: Arc<Texture>,
         side: Arc<Texture>,
     ) -> Self {
+        let side = FaceStyle::new(side, TexStyle::Normal);
         CubeTemplate {
             face_textures: [
-                Some(FaceStyle { tex: side.clone(),   style: TexStyle::Normal }),
-                Some(FaceStyle { tex: side.clone(),   style: TexStyle::Normal }),
-                Some(FaceStyle { tex: top.clone(),    style: TexStyle::Normal }),
-                Some(FaceStyle { tex: bottom.clone(), style: TexStyle::Normal }),
-                Some(FaceStyle { tex: side.clone(),   style: TexStyle::Normal }),
-                Some(FaceStyle { tex: side,           style: TexStyle::Normal }),
+                Some(side.clone()),
+                Some(side.clone()),
+                Some(FaceStyle::new(top,    TexStyle::Normal)),
+                Some(FaceStyle::new(bottom, TexStyle::Normal)),
+                Some(side.clone()),
+                Some(side),
             ],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
@@ -173,16 +453,75 @@ impl CubeTemplate {
         bottom: Arc<Texture>, bottom_color: Vector3,
         side: Arc<Texture>, side_color: Vector3,
     ) -> Self {
+        let side = FaceStyle::new(side, TexStyle::GrayscaleTint { color: side_color });
         CubeTemplate {
             face_textures: [
-                Some(FaceStyle { tex: side.clone(),   style: TexStyle::GrayscaleTint { color: side_color } }),
-                Some(FaceStyle { tex: side.clone(),   style: TexStyle::GrayscaleTint { color: side_color } }),
-                Some(FaceStyle { tex: top.clone(),    style: TexStyle::GrayscaleTint { color: top_color } }),
-                Some(FaceStyle { tex: bottom.clone(), style: TexStyle::GrayscaleTint { color: bottom_color } }),
-                Some(FaceStyle { tex: side.clone(),   style: TexStyle::GrayscaleTint { color: side_color } }),
-                Some(FaceStyle { tex: side,           style: TexStyle::GrayscaleTint { color: side_color } }),
+                Some(side.clone()),
+                Some(side.clone()),
+                Some(FaceStyle::new(top,    TexStyle::GrayscaleTint { color: top_color })),
+                Some(FaceStyle::new(bottom, TexStyle::GrayscaleTint { color: bottom_color })),
+                Some(side.clone()),
+                Some(side),
             ],
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
+        }
+    }
+
+    /// Rotación por cara (cuartos de vuelta, mismo orden que `face_textures`).
+    /// El promedio de LOD no cambia al girar, así que no se recalcula.
+    pub fn with_face_rotations(mut self, rotations: [u8; 6]) -> Self {
+        for (face, r) in self.face_textures.iter_mut().zip(rotations) {
+            if let Some(f) = face { f.rotation = r % 4; }
+        }
+        self
+    }
+
+    /// Modo de muestreo de todas las caras (`Edge` para bloques que se repiten, `Inset` para íconos).
+    pub fn with_sample_mode(mut self, mode: SampleMode) -> Self {
+        for face in self.face_textures.iter_mut() {
+            *face = face.take().map(|f| f.with_sample(mode));
+        }
+        self
+    }
+
+    /// Pesos `albedo` propios de la cara `face` (orden de `face_textures`); la cara debe tener textura.
+    pub fn with_face_albedo(mut self, face: usize, albedo: [f32; 4]) -> Self {
+        if let Some(f) = self.face_textures[face].as_mut() { f.albedo_override = Some(albedo); }
+        self
+    }
+
+    /// Exponente especular propio de la cara `face`.
+    pub fn with_face_specular(mut self, face: usize, specular: f32) -> Self {
+        if let Some(f) = self.face_textures[face].as_mut() { f.specular_override = Some(specular); }
+        self
+    }
+
+    /// Índice de refracción propio de la cara `face`.
+    pub fn with_face_refractive_index(mut self, face: usize, ior: f32) -> Self {
+        if let Some(f) = self.face_textures[face].as_mut() { f.refractive_index_override = Some(ior); }
+        self
+    }
+
+    /// Todos los reemplazos de `o` sobre la cara `face`.
+    pub fn with_face_overrides(self, face: usize, o: FaceOverrides) -> Self {
+        let tpl = match o.albedo { Some(a) => self.with_face_albedo(face, a), None => self };
+        let tpl = match o.specular { Some(s) => tpl.with_face_specular(face, s), None => tpl };
+        match o.refractive_index { Some(ior) => tpl.with_face_refractive_index(face, ior), None => tpl }
+    }
+
+    /// Misma textura animada en las 6 caras.
+    pub fn with_animated_texture(material: Material, anim: Arc<AnimatedTexture>) -> Self {
+        let face = FaceStyle::animated(anim, TexStyle::Normal);
+        CubeTemplate {
+            face_textures: [
+                Some(face.clone()), Some(face.clone()), Some(face.clone()),
+                Some(face.clone()), Some(face.clone()), Some(face),
+            ],
+            material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
         }
     }
 
@@ -191,10 +530,12 @@ impl CubeTemplate {
         faces: [Option<(Arc<Texture>, TexStyle)>; 6],
     ) -> Self {
         let map = |opt: Option<(Arc<Texture>, TexStyle)>| {
-            opt.map(|(tex, style)| FaceStyle { tex, style })
+            opt.map(|(tex, style)| FaceStyle::new(tex, style))
         };
         CubeTemplate {
             material,
+            sound: SoundCategory::Stone,
+            shape: BlockShape::Cube,
             face_textures: [
                 map(faces[0].clone()),
                 map(faces[1].clone()),
@@ -207,13 +548,47 @@ impl CubeTemplate {
     }
 }
 
+/// Reemplazos de material de una cara (los campos `*_override` de `FaceStyle`).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FaceOverrides {
+    pub albedo: Option<[f32; 4]>,
+    pub specular: Option<f32>,
+    pub refractive_index: Option<f32>,
+}
+
+impl FaceOverrides {
+    pub fn is_empty(&self) -> bool {
+        self.albedo.is_none() && self.specular.is_none() && self.refractive_index.is_none()
+    }
+}
+
+/// Bloque derivado de otro de la paleta con reemplazos por cara (orden de `face_textures`).
+/// Lo declara el `"palette"` de una escena JSON y `save_json` lo vuelve a escribir.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockVariant {
+    pub base: char,
+    pub faces: [FaceOverrides; 6],
+}
+
 pub struct Palette {
     map: HashMap<char, CubeTemplate>,
+    variants: HashMap<char, BlockVariant>,
 }
 
 impl Palette {
     pub fn new() -> Self {
-        Palette { map: HashMap::new() }
+        Palette { map: HashMap::new(), variants: HashMap::new() }
+    }
+    /// Registra `ch` como `variant.base` con los reemplazos aplicados.
+    pub fn set_variant(&mut self, ch: char, variant: BlockVariant) -> Result<(), String> {
+        let base = self.get(variant.base).ok_or_else(|| format!("'{}' no está en la paleta", variant.base))?;
+        let tpl = (0..6).fold(base.clone(), |tpl, face| tpl.with_face_overrides(face, variant.faces[face]));
+        self.set(ch, tpl);
+        self.variants.insert(ch, variant);
+        Ok(())
+    }
+    pub fn variant(&self, ch: char) -> Option<&BlockVariant> {
+        self.variants.get(&ch)
     }
     pub fn set(&mut self, ch: char, tpl: CubeTemplate) {
         self.map.insert(ch, tpl);
@@ -221,4 +596,8 @@ impl Palette {
     pub fn get(&self, ch: char) -> Option<&CubeTemplate> {
         self.map.get(&ch)
     }
+    /// Duración de frame más corta de toda la paleta; None si nada está animado.
+    pub fn anim_frame_secs(&self) -> Option<f32> {
+        self.map.values().filter_map(|t| t.anim_frame_secs()).reduce(f32::min)
+    }
 }
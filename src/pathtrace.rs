@@ -0,0 +1,257 @@
+// pathtrace.rs
+use raylib::prelude::Vector3;
+
+use crate::camera::CameraBasis;
+use crate::light::{Light, LightKind};
+use crate::render::{cast_shadow, offset_origin, reflect, refract, sample_background, sample_sky, specular_term, surface_weights, medium_transmittance, transmission_tint, RenderCtx};
+
+/// Lado (pixeles) de los tiles en que se reinicia la acumulación tras una edición.
+pub const ACCUM_TILE: usize = 16;
+
+/// Acumulación entre frames para que el path tracing converja con la cámara quieta.
+/// Guarda radiancia lineal (sin post); se reinicia entera cuando cambia la vista y por tiles
+/// cuando solo cambian algunas celdas de la escena (`reset_tiles`).
+pub struct Accumulation {
+    sum: Vec<Vector3>,
+    /// Frames sumados en cada tile de `ACCUM_TILE`² pixeles.
+    tile_frames: Vec<u32>,
+    width: usize,
+    tiles_x: usize,
+    /// Frames desde el último reinicio total (HUD y semilla del jitter).
+    frames: u32,
+    /// Si es false, las ediciones reinician todo (capturas donde importa la exactitud).
+    pub partial_resets: bool,
+    /// Debug: tiñe de cian los tiles con menos frames que el resto (los reiniciados).
+    pub show_resets: bool,
+}
+
+impl Accumulation {
+    pub fn new() -> Self {
+        Accumulation { sum: Vec::new(), tile_frames: Vec::new(), width: 0, tiles_x: 0, frames: 0, partial_resets: true, show_resets: false }
+    }
+
+    pub fn reset(&mut self) { self.frames = 0; }
+
+    #[inline]
+    pub fn frames(&self) -> u32 { self.frames }
+
+    /// Frames del tile con menos (los reiniciados por una edición van por detrás del resto).
+    pub fn min_tile_frames(&self) -> u32 {
+        if self.frames == 0 { return 0; }
+        self.tile_frames.iter().copied().min().unwrap_or(0)
+    }
+
+    /// Reinicia solo los tiles que tocan los rectángulos de pixeles `(x0, y0, x1, y1)` (inclusivos).
+    pub fn reset_tiles(&mut self, rects: &[(usize, usize, usize, usize)]) {
+        if self.frames == 0 || self.tiles_x == 0 { return; }
+        let width = self.width;
+        let tiles_y = self.tile_frames.len() / self.tiles_x;
+        for &(x0, y0, x1, y1) in rects {
+            for ty in (y0 / ACCUM_TILE)..=(y1 / ACCUM_TILE).min(tiles_y - 1) {
+                for tx in (x0 / ACCUM_TILE)..=(x1 / ACCUM_TILE).min(self.tiles_x - 1) {
+                    let t = ty * self.tiles_x + tx;
+                    if self.tile_frames[t] == 0 { continue; }
+                    self.tile_frames[t] = 0;
+                    for y in ty * ACCUM_TILE..(ty + 1) * ACCUM_TILE {
+                        let row = y * width;
+                        for x in tx * ACCUM_TILE..((tx + 1) * ACCUM_TILE).min(width) {
+                            if let Some(acc) = self.sum.get_mut(row + x) { *acc = Vector3::zero(); }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Suma un frame de `width` pixeles de ancho y devuelve el promedio de cada tile.
+    pub fn add(&mut self, frame: &mut [Vector3], width: usize) {
+        let tiles_x = width.div_ceil(ACCUM_TILE);
+        let tiles_y = (frame.len() / width.max(1)).div_ceil(ACCUM_TILE);
+        if self.frames == 0 || self.sum.len() != frame.len() || self.width != width {
+            self.sum.clear();
+            self.sum.resize(frame.len(), Vector3::zero());
+            self.tile_frames.clear();
+            self.tile_frames.resize(tiles_x * tiles_y, 0);
+            self.width = width;
+            self.tiles_x = tiles_x;
+            self.frames = 0;
+        }
+        self.frames += 1;
+        for f in self.tile_frames.iter_mut() { *f += 1; }
+
+        let tint = Vector3::new(0.0, 1.0, 1.0);
+        for (i, (acc, c)) in self.sum.iter_mut().zip(frame.iter_mut()).enumerate() {
+            let n = self.tile_frames[(i / width / ACCUM_TILE) * tiles_x + (i % width) / ACCUM_TILE];
+            *acc += *c;
+            *c = *acc / n as f32;
+            if self.show_resets && n < self.frames {
+                *c = *c + (tint - *c) * 0.35;
+            }
+        }
+    }
+}
+
+/// Rectángulos de pantalla que una edición en las celdas `centers` puede cambiar: el AABB de
+/// cada celda proyectado, más su sombra (la celda extruida `shadow_reach` unidades alejándose de
+/// cada luz), agrandado `margin` pixeles por reflejos. None = no se puede acotar (algo queda
+/// detrás de la cámara): hay que reiniciar todo.
+pub fn edit_footprint(
+    cam: &CameraBasis, centers: &[Vector3], half: Vector3, lights: &[Light],
+    width: usize, height: usize, shadow_reach: f32, margin: usize,
+) -> Option<Vec<(usize, usize, usize, usize)>> {
+    let (w, h) = (width as f32, height as f32);
+    let mut rects = Vec::with_capacity(centers.len());
+    for c in centers.iter() {
+        let corners: Vec<Vector3> = (0..8).map(|i| *c + Vector3::new(
+            if i & 1 == 0 { -half.x } else { half.x },
+            if i & 2 == 0 { -half.y } else { half.y },
+            if i & 4 == 0 { -half.z } else { half.z },
+        )).collect();
+        let mut pts = corners.clone();
+        for l in lights.iter() {
+            for p in corners.iter() {
+                let away = match l.kind {
+                    LightKind::Directional => l.direction,
+                    LightKind::Point | LightKind::Spot { .. } | LightKind::Area { .. } => (*p - l.position).normalized(),
+                };
+                pts.push(*p + away * shadow_reach);
+            }
+        }
+
+        let (mut x0, mut y0, mut x1, mut y1) = (f32::INFINITY, f32::INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY);
+        for p in pts.iter() {
+            let (sx, sy) = cam.project(*p, w / h)?;
+            let (px, py) = ((sx + 1.0) * 0.5 * w, (1.0 - sy) * 0.5 * h);
+            x0 = x0.min(px); y0 = y0.min(py);
+            x1 = x1.max(px); y1 = y1.max(py);
+        }
+        let m = margin as f32;
+        if x1 + m < 0.0 || y1 + m < 0.0 || x0 - m >= w || y0 - m >= h { continue; }
+        rects.push((
+            (x0 - m).max(0.0) as usize, (y0 - m).max(0.0) as usize,
+            ((x1 + m).min(w - 1.0)) as usize, ((y1 + m).min(h - 1.0)) as usize,
+        ));
+    }
+    Some(rects)
+}
+
+/// xorshift32: barato y suficiente para direcciones de rebote.
+pub struct PathRng(u32);
+
+impl PathRng {
+    pub fn new(x: u32, y: u32, frame: u32) -> Self {
+        let (jx, jy) = crate::settings::jitter2(x, y, frame);
+        PathRng(((jx * 16_777_216.0) as u32 ^ ((jy * 16_777_216.0) as u32).rotate_left(16)) | 1)
+    }
+
+    #[inline]
+    pub fn next_f32(&mut self) -> f32 {
+        let mut s = self.0;
+        s ^= s << 13;
+        s ^= s >> 17;
+        s ^= s << 5;
+        self.0 = s;
+        (s >> 8) as f32 / 16_777_216.0
+    }
+}
+
+/// Dirección en el hemisferio de `n` con densidad cos(θ)/π.
+fn cosine_hemisphere(n: Vector3, rng: &mut PathRng) -> Vector3 {
+    let (u1, u2) = (rng.next_f32(), rng.next_f32());
+    let r = u1.sqrt();
+    let phi = std::f32::consts::TAU * u2;
+    let (x, y, z) = (r * phi.cos(), r * phi.sin(), (1.0 - u1).max(0.0).sqrt());
+
+    let helper = if n.x.abs() > 0.9 { Vector3::new(0.0, 1.0, 0.0) } else { Vector3::new(1.0, 0.0, 0.0) };
+    let t = helper.cross(n).normalized();
+    let b = n.cross(t);
+    (t * x + b * y + n * z).normalized()
+}
+
+/// Integrador de caminos: en cada hit se elige un lóbulo (difuso / espejo / transmisión) con
+/// probabilidad igual a su peso en `albedo`, así el estimador no necesita dividir por la pdf.
+/// El difuso suma luz directa (next-event estimation con el mismo rayo de sombra que Whitted)
+/// más un rebote con muestreo coseno; el skybox ilumina como entorno.
+/// `sun`: si el disco del sol se ve al fallar; los rebotes difusos no lo ven porque la luz
+/// directa ya cuenta esa luz.
+pub(crate) fn trace(ctx: &RenderCtx, ro: &Vector3, rd: &Vector3, depth: u32, sun: bool, rng: &mut PathRng) -> Vector3 {
+    let settings = ctx.settings;
+    let miss = |dir: &Vector3| -> Vector3 {
+        if sun { sample_sky(dir, ctx.skybox, ctx.lights, settings) } else { sample_background(dir, ctx.skybox, settings) }
+    };
+    if depth > settings.max_depth {
+        return miss(rd);
+    }
+    let can_bounce = depth < settings.max_depth;
+
+    let mut intersect = ctx.accel.trace_lod(ro, rd, ctx.objects, f32::INFINITY, depth == 0);
+    let gamma = settings.gamma();
+    gamma.resolve(&mut intersect);
+    if !intersect.is_intersecting {
+        return miss(rd);
+    }
+    if let Some(pv) = ctx.preview {
+        if pv.highlights(intersect.object_index) {
+            settings.preview.apply(&mut intersect.material);
+        }
+    }
+
+    profile_scope!(Shade);
+    let albedo = intersect.material.albedo;
+    // Sin presupuesto solo queda el lóbulo difuso, con todo el peso (como en Whitted)
+    let (reflectivity, transparency, pass) = if can_bounce { surface_weights(&intersect, rd) } else { (0.0, 0.0, 0.0) };
+    let k_diffuse = if can_bounce { (intersect.coverage - reflectivity - transparency).max(0.0) } else { 1.0 };
+    let total = k_diffuse + reflectivity + transparency + pass;
+
+    // Solo se llama con presupuesto: sin él los pesos de los lóbulos son cero y el indirecto no se traza
+    let bounce = |dir: Vector3, sees_sun: bool, rng: &mut PathRng| -> Vector3 {
+        profile_scope!(Bounce);
+        let o = offset_origin(&intersect, &dir);
+        trace(ctx, &o, &dir, depth + 1, sees_sun, rng)
+    };
+
+    // Lóbulo elegido con prob. peso/total: el estimador es su radiancia × total
+    let pick = rng.next_f32() * total;
+    // Beer-Lambert si el hit es de salida (se aplica a cualquier lóbulo)
+    let scale = medium_transmittance(&intersect, rd) * total;
+
+    if pick < reflectivity {
+        let rdir = reflect(rd, &intersect.normal).normalized();
+        return bounce(rdir, true, rng) * scale;
+    }
+    if pick < reflectivity + transparency {
+        let tdir = refract(rd, &intersect.geometric_normal, intersect.material.refractive_index)
+            .unwrap_or_else(|| reflect(rd, &intersect.normal).normalized());
+        return bounce(tdir, true, rng) * transmission_tint(&intersect, rd) * scale;
+    }
+    if pick < reflectivity + transparency + pass {
+        // Hueco de una textura ventana: sigue derecho
+        return bounce(*rd, true, rng) * scale;
+    }
+
+    // Difuso + especular directo
+    let n = intersect.normal;
+    let view_dir = (*ro - intersect.point).normalized();
+    let mut direct = Vector3::zero();
+    let mut specular = Vector3::zero();
+    for l in ctx.lights.iter() {
+        let cone = l.cone(intersect.point);
+        if cone <= 0.0 { continue; }
+        let (light_dir, light_distance) = l.at(intersect.point);
+        let cos = n.dot(light_dir);
+        if cos <= 0.0 { continue; }
+        let filter = cast_shadow(&intersect, l, ctx.objects, ctx.accel, ctx.settings);
+        let color = Vector3::new(gamma.decode_u8(l.color.r), gamma.decode_u8(l.color.g), gamma.decode_u8(l.color.b));
+        let li = color * filter * (l.intensity * cone * l.falloff(light_distance));
+        direct += li * cos;
+
+        specular += li * specular_term(&intersect.material, &n, &view_dir, &light_dir);
+    }
+
+    // Rebote difuso: con pdf coseno el estimador de Lambert es simplemente el color del rebote
+    let indirect = if can_bounce { bounce(cosine_hemisphere(n, rng), false, rng) } else { Vector3::zero() };
+
+    let diffuse_color = intersect.material.diffuse * albedo[0];
+    let lit = diffuse_color * (direct + indirect) + specular * albedo[1];
+    lit * scale
+}
@@ -0,0 +1,91 @@
+// perf.rs
+//! Panel de rendimiento (`Ctrl+F3`): tiempos por frame de render, subida y HUD en un anillo
+//! de los últimos `PERF_WINDOW` frames. Medir son tres `Instant::now` por frame; promediar
+//! recorre el anillo solo cuando el panel está visible.
+
+use raylib::prelude::*;
+
+/// Frames del promedio móvil.
+pub const PERF_WINDOW: usize = 60;
+
+const FONT: i32 = 14;
+const ROW_H: i32 = 18;
+
+/// Tiempos de un frame (ms). `frame_ms` es el tiempo entre frames (incluye la espera del tope
+/// de FPS), así que puede ser mayor que la suma de las partes.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrameTiming {
+    pub render_ms: f32,
+    pub upload_ms: f32,
+    pub hud_ms: f32,
+    pub frame_ms: f32,
+    /// Rayos primarios trazados (0 si el frame se reusó).
+    pub primary_rays: u64,
+}
+
+/// Anillo de los últimos `PERF_WINDOW` frames.
+pub struct PerfRing {
+    samples: [FrameTiming; PERF_WINDOW],
+    next: usize,
+    len: usize,
+    pub visible: bool,
+}
+
+impl PerfRing {
+    pub fn new() -> Self {
+        PerfRing { samples: [FrameTiming::default(); PERF_WINDOW], next: 0, len: 0, visible: false }
+    }
+
+    pub fn push(&mut self, t: FrameTiming) {
+        self.samples[self.next] = t;
+        self.next = (self.next + 1) % PERF_WINDOW;
+        self.len = (self.len + 1).min(PERF_WINDOW);
+    }
+
+    pub fn last(&self) -> FrameTiming {
+        if self.len == 0 { return FrameTiming::default(); }
+        self.samples[(self.next + PERF_WINDOW - 1) % PERF_WINDOW]
+    }
+
+    /// Promedio del anillo; `primary_rays` queda en la suma (para rayos/s con el render total).
+    fn totals(&self) -> (FrameTiming, f32) {
+        let mut sum = FrameTiming::default();
+        for s in self.samples.iter().take(self.len) {
+            sum.render_ms += s.render_ms;
+            sum.upload_ms += s.upload_ms;
+            sum.hud_ms += s.hud_ms;
+            sum.frame_ms += s.frame_ms;
+            sum.primary_rays += s.primary_rays;
+        }
+        let n = self.len.max(1) as f32;
+        let rays_per_sec = if sum.render_ms > 0.0 { sum.primary_rays as f32 / (sum.render_ms / 1000.0) } else { 0.0 };
+        (FrameTiming {
+            render_ms: sum.render_ms / n, upload_ms: sum.upload_ms / n, hud_ms: sum.hud_ms / n,
+            frame_ms: sum.frame_ms / n, primary_rays: sum.primary_rays,
+        }, rays_per_sec)
+    }
+
+    /// Panel arriba a la izquierda: último frame / promedio, rayos primarios por segundo de
+    /// render, objetos y forma de la grilla (`UniformGridAccel::shape`).
+    pub fn draw(&self, d: &mut RaylibDrawHandle, objects: usize, (dims, cell): ([i32; 3], Vector3)) {
+        if !self.visible { return; }
+        let (last, (avg, rays_per_sec)) = (self.last(), self.totals());
+        let row = |name: &str, l: f32, a: f32| format!("{:<7} {:6.2} ms   prom {:6.2} ms", name, l, a);
+        let lines = [
+            format!("Rendimiento [Ctrl+F3] (últimos {} frames)", self.len),
+            format!("{}  ({:.0} FPS)", row("frame", last.frame_ms, avg.frame_ms), 1000.0 / avg.frame_ms.max(1e-3)),
+            row("render", last.render_ms, avg.render_ms),
+            row("subida", last.upload_ms, avg.upload_ms),
+            row("HUD", last.hud_ms, avg.hud_ms),
+            format!("rayos primarios: {:.2} M/s de render", rays_per_sec / 1e6),
+            format!("objetos: {}", objects),
+            format!("grilla: {}x{}x{} celdas de {:.2}", dims[0], dims[1], dims[2], cell.x),
+        ];
+        let w = lines.iter().map(|l| d.measure_text(l, FONT)).max().unwrap_or(0) + 16;
+        let (x, y) = (12, 12);
+        d.draw_rectangle(x, y, w, ROW_H * lines.len() as i32 + 8, Color::new(20, 20, 24, 200));
+        for (i, l) in lines.iter().enumerate() {
+            d.draw_text(l, x + 8, y + 6 + ROW_H * i as i32, FONT, if i == 0 { Color::WHITE } else { Color::LIGHTGRAY });
+        }
+    }
+}
@@ -0,0 +1,77 @@
+// post.rs
+use raylib::prelude::Vector3;
+
+/// Operador de tone mapping (espacio lineal → [0, 1]).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMap {
+    /// Solo exposición + recorte que conserva el tono.
+    Clamp,
+    Reinhard,
+    /// Aproximación de ACES filmic (Narkowicz).
+    Aces,
+}
+
+impl ToneMap {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMap::Clamp => ToneMap::Reinhard,
+            ToneMap::Reinhard => ToneMap::Aces,
+            ToneMap::Aces => ToneMap::Clamp,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ToneMap::Clamp => "Clamp",
+            ToneMap::Reinhard => "Reinhard",
+            ToneMap::Aces => "ACES",
+        }
+    }
+}
+
+/// Post-proceso por pixel aplicado al color final, antes de `vector3_to_color`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostSettings {
+    /// Multiplicador lineal (1.0 = neutro).
+    pub exposure: f32,
+    pub tone_map: ToneMap,
+}
+
+impl Default for PostSettings {
+    fn default() -> Self {
+        Self { exposure: 1.0, tone_map: ToneMap::Aces }
+    }
+}
+
+#[inline]
+fn luminance(c: Vector3) -> f32 {
+    c.x * 0.2126 + c.y * 0.7152 + c.z * 0.0722
+}
+
+/// Si algún canal pasa de 1 escala los tres juntos: satura hacia el mismo tono en vez de
+/// recortar por canal (que corre el matiz en highlights).
+#[inline]
+fn hue_preserving_clip(c: Vector3) -> Vector3 {
+    let m = c.x.max(c.y).max(c.z);
+    if m > 1.0 { c / m } else { c }
+}
+
+#[inline]
+fn aces(x: f32) -> f32 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    ((x * (a * x + b)) / (x * (c * x + d) + e)).clamp(0.0, 1.0)
+}
+
+pub fn apply(rgb: Vector3, post: &PostSettings) -> Vector3 {
+    let c = Vector3::new(rgb.x.max(0.0), rgb.y.max(0.0), rgb.z.max(0.0)) * post.exposure;
+    let mapped = match post.tone_map {
+        ToneMap::Clamp => c,
+        ToneMap::Reinhard => {
+            // Sobre la luminancia para no alterar el tono
+            let l = luminance(c);
+            if l > 0.0 { c * (1.0 / (1.0 + l)) } else { c }
+        }
+        ToneMap::Aces => Vector3::new(aces(c.x), aces(c.y), aces(c.z)),
+    };
+    hue_preserving_clip(mapped)
+}
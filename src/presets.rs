@@ -0,0 +1,135 @@
+// presets.rs
+use std::io;
+use raylib::prelude::Vector3;
+
+use crate::camera::{Camera, CameraPreset};
+use crate::json::{self, Json};
+
+/// Slots de vistas (teclas 1..9 con modificador).
+pub const PRESET_SLOTS: usize = 9;
+
+/// Archivo donde se guardan entre sesiones (en el directorio de trabajo).
+pub const PRESETS_FILE: &str = "camera_presets.json";
+
+/// Duración de la transición al recuperar una vista.
+const TRANSITION_SECS: f32 = 0.35;
+
+/// Vistas guardadas + transición en curso hacia una de ellas.
+pub struct CameraPresets {
+    slots: [Option<CameraPreset>; PRESET_SLOTS],
+    /// (desde, hacia, progreso 0..1)
+    transition: Option<(CameraPreset, CameraPreset, f32)>,
+}
+
+impl CameraPresets {
+    pub fn new() -> Self {
+        CameraPresets { slots: [None; PRESET_SLOTS], transition: None }
+    }
+
+    /// Lee `path`; si no existe devuelve slots vacíos. Un archivo mal formado es error.
+    pub fn load(path: &str) -> Result<Self, String> {
+        let mut presets = Self::new();
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(presets),
+            Err(e) => return Err(format!("{}: {}", path, e)),
+        };
+        for (slot, p) in parse_presets(&text).map_err(|e| format!("{}: {}", path, e))? {
+            if (1..=PRESET_SLOTS).contains(&slot) { presets.slots[slot - 1] = Some(p); }
+        }
+        Ok(presets)
+    }
+
+    pub fn save(&self, path: &str) -> io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Guarda la vista actual en `slot` (1..=9).
+    pub fn store(&mut self, slot: usize, cam: &Camera) {
+        if let Some(s) = self.slots.get_mut(slot.wrapping_sub(1)) { *s = Some(cam.save_preset()); }
+    }
+
+    /// Empieza la transición hacia `slot`; false si está vacío.
+    pub fn recall(&mut self, slot: usize, cam: &Camera) -> bool {
+        let Some(Some(to)) = self.slots.get(slot.wrapping_sub(1)) else { return false; };
+        self.transition = Some((cam.save_preset(), *to, 0.0));
+        true
+    }
+
+    /// Corta la transición (el usuario tomó el control de la cámara).
+    pub fn cancel(&mut self) { self.transition = None; }
+
+    /// Avanza la transición y la aplica a `cam` (smoothstep).
+    pub fn update(&mut self, dt: f32, cam: &mut Camera) {
+        let Some((from, to, t)) = self.transition.as_mut() else { return; };
+        *t = (*t + dt / TRANSITION_SECS).min(1.0);
+        let k = *t * *t * (3.0 - 2.0 * *t);
+        cam.load_preset(&from.lerp(to, k));
+        if *t >= 1.0 { self.transition = None; }
+    }
+
+    /// Vista guardada en `slot` (1..=9), si hay.
+    pub fn get(&self, slot: usize) -> Option<CameraPreset> {
+        self.slots.get(slot.wrapping_sub(1)).copied().flatten()
+    }
+
+    /// Slots ocupados (HUD).
+    pub fn occupied(&self) -> Vec<usize> {
+        (1..=PRESET_SLOTS).filter(|&i| self.slots[i - 1].is_some()).collect()
+    }
+
+    fn to_json(&self) -> String {
+        let entries: Vec<String> = self.slots.iter().enumerate()
+            .filter_map(|(i, p)| p.map(|p| format!(
+                "    {{ \"slot\": {}, \"center\": [{}, {}, {}], \"distance\": {}, \"yaw\": {}, \"pitch\": {}, \"fov\": {} }}",
+                i + 1, p.center.x, p.center.y, p.center.z, p.distance, p.yaw, p.pitch, p.fov,
+            )))
+            .collect();
+        format!("{{\n  \"presets\": [\n{}\n  ]\n}}\n", entries.join(",\n"))
+    }
+}
+
+/// Lee el formato que escribe `to_json` con `json::parse`: cada objeto del arreglo `presets`
+/// con `slot`, `center` [x, y, z], `distance`, `yaw`, `pitch`, `fov`.
+fn parse_presets(text: &str) -> Result<Vec<(usize, CameraPreset)>, String> {
+    let root = json::parse(text)?;
+    let list = root.get("presets").and_then(Json::as_array).ok_or("falta el arreglo \"presets\"")?;
+    list.iter().enumerate().map(|(i, p)| {
+        let scalar = |key: &str| -> Result<f32, String> {
+            p.get(key).and_then(Json::as_f32).ok_or_else(|| format!("vista {}: \"{}\" no es un número", i, key))
+        };
+        let c = match p.get("center").and_then(Json::as_array).map(|a| a.iter().filter_map(Json::as_f32).collect::<Vec<_>>()).as_deref() {
+            Some(&[x, y, z]) => Vector3::new(x, y, z),
+            _ => return Err(format!("vista {}: \"center\" necesita 3 números", i)),
+        };
+        Ok((scalar("slot")? as usize, CameraPreset {
+            center: c,
+            distance: scalar("distance")?,
+            yaw: scalar("yaw")?,
+            pitch: scalar("pitch")?,
+            fov: scalar("fov")?,
+        }))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_round_trip_through_json() {
+        let mut presets = CameraPresets::new();
+        let preset = CameraPreset { center: Vector3::new(1.5, -2.0, 0.25), distance: 12.0, yaw: 0.75, pitch: -0.3, fov: 55.0 };
+        presets.slots[2] = Some(preset);
+        presets.slots[8] = Some(CameraPreset { fov: 90.0, ..preset });
+        let parsed = parse_presets(&presets.to_json()).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].0, 3);
+        assert_eq!(parsed[1].0, 9);
+        assert_eq!(parsed[1].1.fov, 90.0);
+        assert_eq!((parsed[0].1.center.x, parsed[0].1.center.z, parsed[0].1.pitch), (1.5, 0.25, -0.3));
+
+        assert!(parse_presets("{ \"presets\": [ { \"slot\": 1, \"center\": [0, 0] } ] }").unwrap_err().contains("center"));
+        assert!(parse_presets("{}").is_err());
+    }
+}
@@ -0,0 +1,157 @@
+// profile.rs
+//! Contadores del camino caliente (feature `profiling`): llamadas y nanosegundos por función,
+//! acumulados por hilo y sumados al cerrar cada frame. Sin la feature `profile_scope!` no
+//! genera código y `take_frame` devuelve una lista vacía.
+
+/// Abre un contador hasta el final del bloque: `profile_scope!(Grid);`.
+macro_rules! profile_scope {
+    ($probe:ident) => {
+        #[cfg(feature = "profiling")]
+        let _profile_scope = crate::profile::Scope::new(crate::profile::Probe::$probe);
+    };
+}
+
+/// (nombre, llamadas, nanosegundos) de cada contador desde el frame anterior, y los vacía.
+pub fn take_frame() -> Vec<(&'static str, u64, u64)> {
+    #[cfg(feature = "profiling")]
+    { imp::take_frame() }
+    #[cfg(not(feature = "profiling"))]
+    { Vec::new() }
+}
+
+/// Línea de HUD con los `n` contadores más caros (vacía si no hay datos).
+pub fn summary(frame: &[(&'static str, u64, u64)], n: usize) -> String {
+    let mut order: Vec<&(&str, u64, u64)> = frame.iter().filter(|p| p.1 > 0).collect();
+    order.sort_by(|a, b| b.2.cmp(&a.2));
+    order.iter().take(n)
+        .map(|(name, calls, nanos)| format!("{} {}× {:.1} ms", name, calls, *nanos as f64 / 1e6))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+#[cfg(feature = "profiling")]
+pub use imp::{Probe, Scope};
+
+#[cfg(feature = "profiling")]
+mod imp {
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Instant;
+
+    /// Funciones medidas. Los tiempos son inclusivos: `Grid` contiene a las primitivas y
+    /// `Shadow` a su recorrido de grilla.
+    #[derive(Clone, Copy)]
+    pub enum Probe {
+        /// Recorrido de la grilla (`trace`, `occluded`, `transmittance`).
+        Grid,
+        Cube,
+        Sphere,
+        Slab,
+        Stairs,
+        Mesh,
+        Texture,
+        /// Rayos de sombra (`cast_shadow`).
+        Shadow,
+        Background,
+        /// Hits sombreados (Whitted o path tracing).
+        Shade,
+        /// Rebotes de reflexión/refracción.
+        Bounce,
+    }
+
+    const COUNT: usize = 11;
+    const NAMES: [&str; COUNT] = ["grilla", "cubo", "esfera", "losa", "escalera", "malla", "textura", "sombra", "fondo", "sombreado", "rebote"];
+
+    /// [llamadas, nanos] por contador de un hilo; el hilo suma sin contención y el frame lo vacía.
+    type Slots = [[AtomicU64; 2]; COUNT];
+
+    static THREADS: Mutex<Vec<Arc<Slots>>> = Mutex::new(Vec::new());
+
+    thread_local! {
+        static LOCAL: Arc<Slots> = {
+            let slots: Arc<Slots> = Arc::new(std::array::from_fn(|_| [AtomicU64::new(0), AtomicU64::new(0)]));
+            THREADS.lock().unwrap().push(slots.clone());
+            slots
+        };
+    }
+
+    pub struct Scope { probe: Probe, started: Instant }
+
+    impl Scope {
+        #[inline]
+        pub fn new(probe: Probe) -> Self { Scope { probe, started: Instant::now() } }
+    }
+
+    impl Drop for Scope {
+        #[inline]
+        fn drop(&mut self) {
+            let nanos = self.started.elapsed().as_nanos() as u64;
+            LOCAL.with(|s| {
+                let slot = &s[self.probe as usize];
+                slot[0].fetch_add(1, Ordering::Relaxed);
+                slot[1].fetch_add(nanos, Ordering::Relaxed);
+            });
+        }
+    }
+
+    pub fn take_frame() -> Vec<(&'static str, u64, u64)> {
+        let mut out: Vec<(&'static str, u64, u64)> = NAMES.iter().map(|&n| (n, 0, 0)).collect();
+        for slots in THREADS.lock().unwrap().iter() {
+            for (o, s) in out.iter_mut().zip(slots.iter()) {
+                o.1 += s[0].swap(0, Ordering::Relaxed);
+                o.2 += s[1].swap(0, Ordering::Relaxed);
+            }
+        }
+        out
+    }
+}
+
+#[cfg(all(test, feature = "profiling"))]
+mod tests {
+    use raylib::prelude::{Color, Vector3};
+
+    use crate::accel::UniformGridAccel;
+    use crate::camera::Camera;
+    use crate::cube::Cube;
+    use crate::framebuffer::Framebuffer;
+    use crate::light::Light;
+    use crate::material::Material;
+    use crate::ray_intersect::RayIntersect;
+    use crate::settings::RenderSettings;
+    use crate::sphere::Sphere;
+
+    fn probe(frame: &[(&'static str, u64, u64)], name: &str) -> (u64, u64) {
+        frame.iter().find(|p| p.0 == name).map(|p| (p.1, p.2)).unwrap()
+    }
+
+    /// Frame chico (piso, esfera espejo, dos luces): los contadores tienen que moverse y cada
+    /// hit sombreado pide a lo sumo una consulta de sombra por luz.
+    #[test]
+    fn counters_after_small_frame() {
+        let floor = Material::new(Vector3::new(0.6, 0.6, 0.6), 10.0, [0.9, 0.1, 0.0, 0.0], 1.0);
+        let mirror = Material::new(Vector3::new(0.9, 0.9, 0.9), 50.0, [0.3, 0.3, 0.6, 0.0], 1.0);
+        let objects: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(Vector3::new(-3.0, -1.0, -3.0), Vector3::new(3.0, 0.0, 3.0), floor)),
+            Box::new(Sphere::new(Vector3::new(0.0, 1.0, 0.0), 1.0, mirror)),
+        ];
+        let accel = UniformGridAccel::build(&objects, 1.0);
+        let camera = Camera::new(Vector3::new(4.0, 3.0, 5.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let lights = [
+            Light::new(Vector3::new(2.0, 5.0, 2.0), Color::WHITE, 1.0),
+            Light::directional(Vector3::new(-1.0, -2.0, -0.5), Color::WHITE, 0.8),
+        ];
+        let mut fb = Framebuffer::new(32, 24);
+
+        super::take_frame();
+        crate::render(&mut fb, &objects, &accel, &camera, &lights, None, None, &RenderSettings::default(), None, None, None);
+        let frame = super::take_frame();
+
+        for name in ["grilla", "sombra", "sombreado", "fondo", "rebote"] {
+            let (calls, nanos) = probe(&frame, name);
+            assert!(calls > 0 && nanos > 0, "{}: {} llamadas, {} ns", name, calls, nanos);
+        }
+        assert!(probe(&frame, "cubo").0 > 0 && probe(&frame, "esfera").0 > 0);
+        let (shadow, shaded) = (probe(&frame, "sombra").0, probe(&frame, "sombreado").0);
+        assert!(shadow <= shaded * lights.len() as u64, "{} sombras para {} hits", shadow, shaded);
+    }
+}
@@ -5,14 +5,22 @@ use crate::material::Material;
 #[allow(dead_code)]
 pub struct Intersect {
     pub point: Vector3,
+    /// Normal de sombreado (interpolada en mallas suaves).
     pub normal: Vector3,
+    /// Normal real de la superficie: offsets de origen y refracción.
+    pub geometric_normal: Vector3,
     pub distance: f32,
     pub is_intersecting: bool,
     pub material: Material,
     /// Cobertura 0..1 del texel (1 = opaco, 0 = totalmente transparente).
     /// Se usa para sombreado y para que las sombras ignoren superficies “ventana”.
     pub coverage: f32,
+    /// Posición del objeto en la lista de la escena. Las primitivas devuelven None; la grilla
+    /// (`trace*`) lo completa en el mejor hit, y de ahí salen el resaltado y el borrado.
     pub object_index: Option<usize>,
+    /// `material.diffuse` salió de una textura y está en lineal; `Gamma::resolve` lo lleva al
+    /// espacio de trabajo del render antes de sombrear.
+    pub textured: bool,
 }
 
 impl Intersect {
@@ -20,11 +28,13 @@ impl Intersect {
         Intersect {
             point,
             normal,
+            geometric_normal: normal,
             distance,
             is_intersecting: true,
             material,
             coverage: 1.0,
             object_index: None,
+            textured: false,
         }
     }
 
@@ -35,11 +45,13 @@ impl Intersect {
         Intersect {
             point,
             normal,
+            geometric_normal: normal,
             distance,
             is_intersecting: true,
             material,
             coverage: coverage.clamp(0.0, 1.0),
             object_index: None,
+            textured: false,
         }
     }
 
@@ -47,11 +59,13 @@ impl Intersect {
         Intersect {
             point: Vector3::zero(),
             normal: Vector3::zero(),
+            geometric_normal: Vector3::zero(),
             distance: 0.0,
             is_intersecting: false,
             material: Material::black(),
             coverage: 0.0,
             object_index: None,
+            textured: false,
         }
     }
 }
@@ -60,6 +74,28 @@ impl Intersect {
 pub trait RayIntersect: Send + Sync {
     fn ray_intersect(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Intersect;
 
+    /// Variante LOD: sin muestreo por texel (color/coverage promedio por cara).
+    /// Por defecto es la intersección normal.
+    fn ray_intersect_flat(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Intersect {
+        self.ray_intersect(ray_origin, ray_direction)
+    }
+
+    /// Variante para rayos primarios: detalle caro que los secundarios/sombras se saltean
+    /// (relieve por heightfield). Por defecto es la intersección normal.
+    fn ray_intersect_detailed(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Intersect {
+        self.ray_intersect(ray_origin, ray_direction)
+    }
+
     /// AABB en espacio mundo para aceleración (grilla/BVH).
     fn aabb(&self) -> (Vector3, Vector3);
+
+    /// Caja de la parte sólida (ocupación de celdas, colisiones); sin relieves decorativos.
+    fn solid_aabb(&self) -> (Vector3, Vector3) { self.aabb() }
+
+    /// Si la grilla debe probar primero la esfera envolvente (centro/radio del AABB) antes de
+    /// `ray_intersect`. Conviene en mallas y esferas; en cubos el test de slabs ya es mínimo.
+    fn prefer_sphere_prefilter(&self) -> bool { false }
+
+    /// Carácter de paleta que generó el objeto (None si no viene de la paleta).
+    fn block_id(&self) -> Option<char> { None }
 }
@@ -0,0 +1,1001 @@
+// render.rs
+//! Núcleo del raytracer: rayos primarios, sombreado Whitted, sombras y el `render` por tiles que
+//! comparten el loop interactivo, el modo headless y los tests.
+
+use raylib::prelude::*;
+use rayon::prelude::*;
+use std::f32::consts::PI;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use crate::accel::UniformGridAccel;
+use crate::camera::{Camera, CameraBasis};
+use crate::framebuffer::Framebuffer;
+use crate::gamma::Gamma;
+use crate::light::{self, LightKind};
+use crate::material::{Material, SpecularModel, vector3_to_color};
+use crate::pathtrace::{Accumulation, PathRng};
+use crate::ray_intersect::{Intersect, RayIntersect};
+use crate::settings::{self, DebugView, Integrator, LodCtx, RenderSettings};
+use crate::skybox::Skybox;
+use crate::stats::{self, TileStats};
+use crate::{cube, pathtrace, post, scene};
+
+const ORIGIN_BIAS: f32 = 1e-3;
+
+#[inline]
+fn lerp(a: Vector3, b: Vector3, t: f32) -> Vector3 { a * (1.0 - t) + b * t }
+
+#[inline]
+fn smooth5(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+/// FONDO fallback (sin skybox): colores de `sky`
+fn procedural_sky(dir: Vector3, sky: &settings::ProceduralSky, gamma: Gamma) -> Vector3 {
+    let d = dir.normalized();
+    let t = ((d.y) * 0.5 + 0.5).clamp(0.0, 1.0);
+
+    let (horizon, mid, top) = (sky.horizon, sky.mid, sky.top);
+
+    let c = if t < 0.6 {
+        let k = smooth5(t / 0.6);
+        lerp(horizon, mid, k)
+    } else {
+        let k = smooth5((t - 0.6) / 0.4);
+        lerp(mid, top, k)
+    };
+
+    let h = (1.0 - t).clamp(0.0, 1.0);
+    let glow = h.powf(5.0);
+    let c = c + sky.glow * (0.08 * glow);
+
+    let haze = (1.0 - t).powf(2.0) * 0.03;
+    let c = c + sky.haze * haze;
+
+    // Colores autorados en sRGB
+    gamma.decode_vec(Vector3::new(c.x.clamp(0.0, 1.0), c.y.clamp(0.0, 1.0), c.z.clamp(0.0, 1.0)))
+}
+
+/// Usa la normal geométrica: con normales suaves la de sombreado puede “hundir” el origen.
+pub(crate) fn offset_origin(intersect: &Intersect, direction: &Vector3) -> Vector3 {
+    let offset = intersect.geometric_normal * ORIGIN_BIAS;
+    if direction.dot(intersect.geometric_normal) < 0.0 { intersect.point - offset } else { intersect.point + offset }
+}
+
+pub(crate) fn reflect(incident: &Vector3, normal: &Vector3) -> Vector3 {
+    *incident - *normal * 2.0 * incident.dot(*normal)
+}
+
+pub(crate) fn refract(incident: &Vector3, normal: &Vector3, refractive_index: f32) -> Option<Vector3> {
+    let mut cosi = incident.dot(*normal).max(-1.0).min(1.0);
+    let mut etai = 1.0;
+    let mut etat = refractive_index;
+    let mut n = *normal;
+
+    if cosi > 0.0 {
+        std::mem::swap(&mut etai, &mut etat);
+        n = -n;
+    } else {
+        cosi = -cosi;
+    }
+
+    let eta = etai / etat;
+    let k = 1.0 - eta * eta * (1.0 - cosi * cosi);
+    if k < 0.0 { None } else { Some(*incident * eta + n * (eta * cosi - k.sqrt())) }
+}
+
+/// Especular directo de una luz (sin color ni intensidad) según el modelo del material.
+/// `view` y `light_dir` apuntan desde el punto hacia el ojo / la luz.
+pub(crate) fn specular_term(material: &Material, normal: &Vector3, view: &Vector3, light_dir: &Vector3) -> f32 {
+    match material.specular_model {
+        SpecularModel::Phong => {
+            let refl_light = reflect(&-*light_dir, normal).normalized();
+            view.dot(refl_light).max(0.0).powf(material.specular)
+        }
+        SpecularModel::Ggx { roughness } => {
+            let n_dot_l = normal.dot(*light_dir);
+            let n_dot_v = normal.dot(*view);
+            if n_dot_l <= 0.0 || n_dot_v <= 0.0 { return 0.0; }
+            let h = (*light_dir + *view).normalized();
+            let n_dot_h = normal.dot(h).max(0.0);
+            let v_dot_h = view.dot(h).max(0.0);
+
+            // D: Trowbridge-Reitz con α = roughness²
+            let a2 = (roughness * roughness).powi(2);
+            let denom = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+            let d = a2 / (PI * denom * denom);
+            // G: Smith con Schlick-GGX (k para luz directa)
+            let k = (roughness + 1.0).powi(2) / 8.0;
+            let g1 = |x: f32| x / (x * (1.0 - k) + k);
+            let g = g1(n_dot_l) * g1(n_dot_v);
+            // F: Schlick sobre el medio vector
+            let f0 = material.albedo[2].max(0.04);
+            let f = f0 + (1.0 - f0) * (1.0 - v_dot_h).powi(5);
+
+            // BRDF·cosθl = D·G·F / (4·(n·v))
+            d * g * f / (4.0 * n_dot_v)
+        }
+    }
+}
+
+/// Reflectancia de Fresnel (aprox. de Schlick) para una interfaz aire/`refractive_index`.
+/// Desde adentro usa el ángulo transmitido; reflexión total interna → 1.
+fn fresnel_schlick(incident: &Vector3, normal: &Vector3, refractive_index: f32) -> f32 {
+    let (n1, n2) = (1.0, refractive_index.max(1.0));
+    let cos_i = incident.dot(*normal).clamp(-1.0, 1.0);
+    let (eta_i, eta_t) = if cos_i > 0.0 { (n2, n1) } else { (n1, n2) };
+    let mut cos = cos_i.abs();
+    if eta_i > eta_t {
+        let sin_t2 = (eta_i / eta_t).powi(2) * (1.0 - cos * cos);
+        if sin_t2 >= 1.0 { return 1.0; }
+        cos = (1.0 - sin_t2).sqrt();
+    }
+    let f0 = ((n1 - n2) / (n1 + n2)).powi(2);
+    f0 + (1.0 - f0) * (1.0 - cos).powi(5)
+}
+
+/// Pesos (reflexión, transmisión, paso) del hit; lo que sobra hasta 1 es Phong.
+/// La cobertura parte el pixel: en la fracción `coverage` hay superficie, que se reparte entre
+/// reflexión, transmisión (con Fresnel en transparentes) y Phong; el hueco `1 - coverage` deja
+/// pasar el rayo derecho, sin refractar ni reflejar. Nunca suman más de 1.
+pub(crate) fn surface_weights(intersect: &Intersect, ray_direction: &Vector3) -> (f32, f32, f32) {
+    let coverage = intersect.coverage;
+    let mat = &intersect.material;
+    let mut reflectivity = mat.albedo[2];
+    let mut transparency = mat.albedo[3].clamp(0.0, 1.0);
+
+    if transparency > 0.0 && mat.refractive_index > 0.0 {
+        let budget = (reflectivity + transparency).min(1.0);
+        let f = fresnel_schlick(ray_direction, &intersect.geometric_normal, mat.refractive_index);
+        reflectivity = budget * f;
+        transparency = budget * (1.0 - f);
+    } else if mat.fresnel && reflectivity > 0.0 {
+        let cos = ray_direction.dot(intersect.normal).abs();
+        reflectivity += (1.0 - reflectivity) * (1.0 - cos).powi(5);
+    }
+
+    let sum = (reflectivity + transparency).max(1.0);
+    (reflectivity / sum * coverage, transparency / sum * coverage, 1.0 - coverage)
+}
+
+/// Tinte de la luz refractada en el hit: el color de la superficie si el rayo entra al material,
+/// blanco si sale (el panel ya tiñó al entrar).
+#[inline]
+pub(crate) fn transmission_tint(intersect: &Intersect, ray_direction: &Vector3) -> Vector3 {
+    if ray_direction.dot(intersect.geometric_normal) < 0.0 { intersect.material.diffuse } else { Vector3::one() }
+}
+
+/// Beer-Lambert del tramo recorrido: si el rayo pega desde adentro (sale del material), todo lo
+/// que ve llegó atravesando `distance` unidades del medio.
+#[inline]
+pub(crate) fn medium_transmittance(intersect: &Intersect, ray_direction: &Vector3) -> Vector3 {
+    if ray_direction.dot(intersect.geometric_normal) > 0.0 {
+        intersect.material.transmittance(intersect.distance)
+    } else {
+        Vector3::one()
+    }
+}
+
+/// Filtro RGB de la luz que llega al punto: (1,1,1) sin sombra, (0,0,0) sombra total.
+/// Vidrios/ventanas tiñen la sombra en lugar de bloquearla.
+pub(crate) fn cast_shadow(
+    intersect: &Intersect,
+    light: &light::Light,
+    objects: &[Box<dyn RayIntersect>],
+    accel: &UniformGridAccel,
+    settings: &RenderSettings,
+) -> Vector3 {
+    profile_scope!(Shadow);
+    let gamma = settings.gamma();
+    if let LightKind::Area { samples, .. } = light.kind {
+        // Penumbra: grilla n×n estratificada sobre el cuadrado con jitter por punto de hit. Con
+        // SSAA/progresivo el punto cambia por sub-pixel, así las muestras varían sin estado extra.
+        // El perfil de calidad acota las muestras (`RenderSettings::area_shadow_samples`).
+        let n = ((samples.min(settings.area_shadow_samples).max(1) as f32).sqrt().floor() as u32).max(1);
+        let p = intersect.point;
+        let (sx, sy) = (p.x.to_bits() ^ p.z.to_bits().rotate_left(16), p.y.to_bits());
+        let mut sum = Vector3::zero();
+        for k in 0..n * n {
+            let (jx, jy) = settings::jitter2(sx, sy, k);
+            let (u, v) = (((k % n) as f32 + jx) / n as f32, ((k / n) as f32 + jy) / n as f32);
+            stats::count(|c| c.shadow += 1);
+            let (light_dir, light_distance) = light.at_sample(p, u, v);
+            let origin = offset_origin(intersect, &light_dir);
+            sum += accel.transmittance(&origin, &light_dir, light_distance, objects, gamma);
+        }
+        return sum / (n * n) as f32;
+    }
+    stats::count(|c| c.shadow += 1);
+    let (light_dir, light_distance) = light.at(intersect.point);
+    let shadow_ray_origin = offset_origin(intersect, &light_dir);
+    accel.transmittance(&shadow_ray_origin, &light_dir, light_distance, objects, gamma)
+}
+
+// ==== PREVIEW ====
+/// Objeto apuntado a resaltar y/o cubo fantasma donde caería el próximo bloque.
+#[derive(Clone, Copy)]
+pub struct Preview { pub hovered_idx: Option<usize>, pub ghost: Option<GhostBlock> }
+
+impl Preview {
+    #[inline]
+    pub(crate) fn highlights(&self, object_index: Option<usize>) -> bool {
+        self.hovered_idx.is_some() && object_index == self.hovered_idx
+    }
+}
+
+/// Cubo translúcido de la celda de colocación. No es un objeto de la escena: no tapa luz, no
+/// se refleja y la grilla no lo ve; se mezcla sobre el rayo primario si queda delante del hit.
+#[derive(Clone, Copy)]
+pub struct GhostBlock { pub center: Vector3, pub half: Vector3, pub color: Vector3 }
+
+impl GhostBlock {
+    /// Distancia de entrada y normal de la cara por la que entra el rayo.
+    fn hit(&self, ro: &Vector3, rd: &Vector3) -> Option<(f32, Vector3)> {
+        let (lo, hi) = (self.center - self.half, self.center + self.half);
+        let (mut t_enter, mut t_exit, mut axis) = (f32::NEG_INFINITY, f32::INFINITY, 0);
+        for (a, (o, d, l, h)) in [(ro.x, rd.x, lo.x, hi.x), (ro.y, rd.y, lo.y, hi.y), (ro.z, rd.z, lo.z, hi.z)].into_iter().enumerate() {
+            if d == 0.0 {
+                if o < l || o > h { return None; }
+                continue;
+            }
+            let (t1, t2) = ((l - o) / d, (h - o) / d);
+            if t1.min(t2) > t_enter { t_enter = t1.min(t2); axis = a; }
+            t_exit = t_exit.min(t1.max(t2));
+        }
+        if t_enter > t_exit || t_exit < 0.0 { return None; }
+        let sign = if [rd.x, rd.y, rd.z][axis] > 0.0 { -1.0 } else { 1.0 };
+        let normal = match axis { 0 => Vector3::new(sign, 0.0, 0.0), 1 => Vector3::new(0.0, sign, 0.0), _ => Vector3::new(0.0, 0.0, sign) };
+        Some((t_enter.max(0.0), normal))
+    }
+
+    /// `base` con el fantasma encima si está delante de `scene_t` (o a la par: comparte la cara
+    /// apuntada). Las caras se aclaran según miran a la cámara para que se lea la forma.
+    fn composite(&self, base: Vector3, ro: &Vector3, rd: &Vector3, scene_t: f32, opacity: f32) -> Vector3 {
+        match self.hit(ro, rd) {
+            Some((t, n)) if t <= scene_t + 1e-3 => {
+                let facing = n.dot(-*rd).max(0.0);
+                lerp(base, self.color * (0.55 + 0.45 * facing), opacity)
+            }
+            _ => base,
+        }
+    }
+}
+
+// ==== DIFF DE ESCENAS ====
+/// Bloques fantasma de un diff: verde = agregado, rojo = quitado, ámbar = cambiado.
+/// Se mezclan translúcidos sobre la imagen cuando quedan delante (o coinciden) con la escena.
+pub struct DiffOverlay {
+    objects: Vec<Box<dyn RayIntersect>>,
+    accel: UniformGridAccel,
+    pub visible: bool,
+}
+
+impl DiffOverlay {
+    pub fn new(diff: &scene::SceneDiff, params: &scene::SceneParams) -> Self {
+        // Apenas más grandes que un bloque para ganar el empate contra el bloque quitado.
+        let size = params.cube_size * 1.02;
+        let ghost = |cell: scene::Cell, color: Vector3| -> Box<dyn RayIntersect> {
+            let mat = Material::new(color, 0.0, [1.0, 0.0, 0.0, 0.0], 0.0);
+            Box::new(cube::Cube::from_center_size(scene::cell_center(params, cell), size, mat))
+        };
+        let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+        for &(c, _) in diff.added.iter() { objects.push(ghost(c, Vector3::new(0.2, 0.9, 0.3))); }
+        for &(c, _) in diff.removed.iter() { objects.push(ghost(c, Vector3::new(0.95, 0.2, 0.2))); }
+        for &(c, _, _) in diff.changed.iter() { objects.push(ghost(c, Vector3::new(0.95, 0.7, 0.15))); }
+        let accel = UniformGridAccel::build(&objects, params.cube_size.x.max(0.01));
+        DiffOverlay { objects, accel, visible: true }
+    }
+
+    fn composite(
+        &self,
+        base: Vector3,
+        ro: &Vector3,
+        rd: &Vector3,
+        objects: &[Box<dyn RayIntersect>],
+        accel: &UniformGridAccel,
+        gamma: Gamma,
+    ) -> Vector3 {
+        let ghost = self.accel.trace(ro, rd, &self.objects);
+        if !ghost.is_intersecting { return base; }
+        let hit = accel.trace(ro, rd, objects);
+        if hit.is_intersecting && hit.distance + 1e-3 < ghost.distance { return base; }
+        lerp(base, gamma.decode_vec(ghost.material.diffuse), 0.45)
+    }
+}
+
+#[inline]
+pub(crate) fn sample_background(ray_direction: &Vector3, skybox: Option<&Skybox>, settings: &RenderSettings) -> Vector3 {
+    profile_scope!(Background);
+    let gamma = settings.gamma();
+    let sky = if let Some(sb) = skybox {
+        gamma.from_linear(sb.sample(*ray_direction, settings.sky_full_res))
+    } else {
+        procedural_sky(*ray_direction, &settings.procedural_sky, gamma)
+    };
+    sky * settings.sky_tint
+}
+
+/// Fondo de un rayo que no pega: cielo + disco del sol de las luces direccionales.
+/// (El IBL usa `sample_background` sin sol: la luz directa ya lo cuenta.)
+#[inline]
+pub(crate) fn sample_sky(ray_direction: &Vector3, skybox: Option<&Skybox>, lights: &[light::Light], settings: &RenderSettings) -> Vector3 {
+    sample_background(ray_direction, skybox, settings) + settings.sun.radiance(*ray_direction, lights, settings.gamma())
+}
+
+/// Lo que vio el rayo primario de un pixel (el AA adaptativo busca bordes de objeto/normal).
+#[derive(Clone, Copy)]
+pub struct PrimaryHit {
+    pub object: Option<usize>,
+    pub normal: Vector3,
+    /// Distancia del hit sobre el rayo; `RenderCtx::sample` la pasa a profundidad de vista.
+    pub depth: f32,
+}
+
+impl PrimaryHit {
+    pub const MISS: PrimaryHit = PrimaryHit { object: None, normal: Vector3 { x: 0.0, y: 0.0, z: 0.0 }, depth: f32::INFINITY };
+
+    #[inline]
+    fn differs(&self, other: &PrimaryHit) -> bool {
+        self.object != other.object || self.normal.dot(other.normal) < 0.95
+    }
+}
+
+pub fn cast_ray(
+    ray_origin: &Vector3,
+    ray_direction: &Vector3,
+    objects: &[Box<dyn RayIntersect>],
+    accel: &UniformGridAccel,
+    lights: &[light::Light],
+    depth: u32,
+    preview: Option<Preview>,
+    skybox: Option<&Skybox>,
+    settings: &RenderSettings,
+    lod: LodCtx,
+) -> Vector3 {
+    cast_ray_hit(ray_origin, ray_direction, objects, accel, lights, depth, preview, skybox, settings, lod).0
+}
+
+/// `cast_ray` que además devuelve el hit primario.
+fn cast_ray_hit(
+    ray_origin: &Vector3,
+    ray_direction: &Vector3,
+    objects: &[Box<dyn RayIntersect>],
+    accel: &UniformGridAccel,
+    lights: &[light::Light], // <-- múltiple luces
+    depth: u32,
+    preview: Option<Preview>, 
+    skybox: Option<&Skybox>,  
+    settings: &RenderSettings,
+    lod: LodCtx,
+) -> (Vector3, PrimaryHit) {
+    if depth > settings.max_depth {
+        return (settings.fog.apply_sky(sample_sky(ray_direction, skybox, lights, settings), *ray_direction, depth, settings.gamma()), PrimaryHit::MISS);
+    }
+    let lod_cfg = &settings.lod;
+    let flat_from = lod.remaining(lod_cfg, lod_cfg.flat_texture_dist);
+    let intersect = accel.trace_lod(ray_origin, ray_direction, objects, flat_from, depth == 0);
+    shade_hit(intersect, ray_origin, ray_direction, objects, accel, lights, depth, preview, skybox, settings, lod)
+}
+
+/// Sombreado de `cast_ray_hit` a partir del hit ya trazado (los paquetes de rayos primarios
+/// trazan 4 juntos y sombrean cada uno por acá).
+fn shade_hit(
+    mut intersect: Intersect,
+    ray_origin: &Vector3,
+    ray_direction: &Vector3,
+    objects: &[Box<dyn RayIntersect>],
+    accel: &UniformGridAccel,
+    lights: &[light::Light],
+    depth: u32,
+    preview: Option<Preview>,
+    skybox: Option<&Skybox>,
+    settings: &RenderSettings,
+    lod: LodCtx,
+) -> (Vector3, PrimaryHit) {
+    let fog = &settings.fog;
+    let gamma = settings.gamma();
+    gamma.resolve(&mut intersect);
+    // Presupuesto de rebotes: sin él no hay reflexión/refracción trazada ni glints.
+    let can_bounce = depth < settings.max_depth;
+    let lod_cfg = &settings.lod;
+    let shading = &settings.lighting;
+
+    if let Some(pv) = preview {
+        if intersect.is_intersecting && pv.highlights(intersect.object_index) {
+            settings.preview.apply(&mut intersect.material);
+        }
+    }
+
+    if !intersect.is_intersecting {
+        return (fog.apply_sky(sample_sky(ray_direction, skybox, lights, settings), *ray_direction, depth, gamma), PrimaryHit::MISS);
+    }
+    let primary = PrimaryHit { object: intersect.object_index, normal: intersect.normal, depth: intersect.distance };
+
+    // Si no hay luces, usa solo background/ambient
+    if lights.is_empty() {
+        return (sample_background(ray_direction, skybox, settings), primary);
+    }
+
+    profile_scope!(Shade);
+    let view_dir   = (*ray_origin - intersect.point).normalized();
+
+    // LOD por distancia (respecto al ojo)
+    let skip_specular = lod.beyond(lod_cfg, lod_cfg.no_specular_dist, intersect.distance);
+    let skip_shadow   = lod.beyond(lod_cfg, lod_cfg.no_shadow_dist, intersect.distance);
+    let glints_here   = can_bounce && settings.glints.active(depth, intersect.material.albedo[2]);
+
+    // Acumuladores por-luz
+    let mut diffuse_sum  = Vector3::zero();
+    let mut specular_sum = Vector3::zero();
+    let mut glint_sum    = Vector3::zero();
+
+    let light_color_from = |c: Color| -> Vector3 {
+        Vector3::new(gamma.decode_u8(c.r), gamma.decode_u8(c.g), gamma.decode_u8(c.b))
+    };
+
+    for l in lights.iter() {
+        // Fuera del cono de un foco la contribución es exactamente cero: ni se traza la sombra.
+        let cone = l.cone(intersect.point);
+        if cone <= 0.0 { continue; }
+        let (light_dir, light_distance) = l.at(intersect.point);
+
+        let light_filter = if skip_shadow {
+            Vector3::one() * lod_cfg.shadow_estimate
+        } else {
+            cast_shadow(&intersect, l, objects, accel, settings)
+        };
+        let light_intensity = l.intensity * cone * l.falloff(light_distance);
+
+        let light_color_v3 = light_color_from(l.color);
+
+        let wrap = shading.diffuse_wrap;
+        let diffuse_intensity = ((intersect.normal.dot(light_dir) + wrap) / (1.0 + wrap))
+            .clamp(0.0, 1.0) * light_intensity;
+        diffuse_sum += intersect.material.diffuse * light_filter * diffuse_intensity;
+
+        if skip_specular { continue; }
+
+        let specular_intensity = specular_term(&intersect.material, &intersect.normal, &view_dir, &light_dir) * light_intensity;
+        specular_sum += light_color_v3 * light_filter * specular_intensity;
+
+        if !glints_here || !l.glints { continue; }
+
+        // Glint por-luz
+        let mirror_dir    = reflect(ray_direction, &intersect.normal).normalized();
+        let mirror_origin = offset_origin(&intersect, &mirror_dir);
+
+        let hardness_point = shading.glint_hardness_point;
+        let hardness_dir   = shading.glint_hardness_dir;
+        let gain           = shading.glint_gain;
+        let refl_bias      = (intersect.material.albedo[2] + shading.glint_bias).min(1.0);
+
+        match l.kind {
+            LightKind::Point | LightKind::Spot { .. } | LightKind::Area { .. } => {
+                let to_l = l.position - mirror_origin;
+                let dist = to_l.length();
+                if dist > 0.0 {
+                    let ldir  = to_l / dist;
+                    let align = mirror_dir.dot(ldir).max(0.0);
+                    if align > 0.0 {
+                        // Misma caída que difuso y especular, medida desde el origen del glint
+                        let s = gain * l.intensity * cone * l.falloff(dist) * align.powf(hardness_point) * refl_bias;
+                        glint_sum += light_color_v3 * accel.transmittance(&mirror_origin, &ldir, dist, objects, gamma) * s;
+                    }
+                }
+            }
+            LightKind::Directional => {
+                let ldir  = -l.direction;
+                let align = mirror_dir.dot(ldir).max(0.0);
+                if align > 0.0 {
+                    let s = gain * l.intensity * align.powf(hardness_dir) * refl_bias;
+                    glint_sum += light_color_v3 * accel.transmittance(&mirror_origin, &ldir, f32::INFINITY, objects, gamma) * s;
+                }
+            }
+        }
+    }
+
+    let albedo   = intersect.material.albedo;
+
+    // IBL: el cielo (skybox o procedural) en la dirección de la normal como irradiancia aproximada
+    let ambient = if settings.ibl.enabled {
+        intersect.material.diffuse * sample_background(&intersect.normal, skybox, settings) * settings.ibl.intensity
+    } else {
+        intersect.material.diffuse * shading.ambient
+    };
+
+    // Sin `coverage`: ya entra en los pesos (`k_phong` abajo)
+    let phong_color = (diffuse_sum + ambient) * albedo[0] + specular_sum * albedo[1];
+
+    // Agotado el presupuesto no hay rebotes: reflexión, transmisión y hueco pasan a Phong
+    // (no se muestrea el fondo en su lugar)
+    let (reflectivity, transparency, pass) = if can_bounce { surface_weights(&intersect, ray_direction) } else { (0.0, 0.0, 0.0) };
+
+    let lod_next = lod.after(intersect.distance);
+
+    // Rayo secundario (solo con presupuesto: sin él los pesos de arriba son cero)
+    let bounce = |ro: &Vector3, dir: &Vector3| -> Vector3 {
+        profile_scope!(Bounce);
+        cast_ray(ro, dir, objects, accel, lights, depth + 1, preview, skybox, settings, lod_next)
+    };
+
+    let reflect_color = if reflectivity > 0.0 {
+        let rdir = reflect(ray_direction, &intersect.normal).normalized();
+        let ro   = offset_origin(&intersect, &rdir);
+        bounce(&ro, &rdir)
+    } else {
+        Vector3::zero()
+    };
+
+    let refract_color = if transparency > 0.0 {
+        if let Some(tdir) = refract(ray_direction, &intersect.geometric_normal, intersect.material.refractive_index) {
+            let ro = offset_origin(&intersect, &tdir);
+            // Al entrar la luz transmitida toma el color de la superficie; al salir no (cada
+            // panel tiñe una vez). Paneles apilados se multiplican solos por la recursión.
+            bounce(&ro, &tdir) * transmission_tint(&intersect, ray_direction)
+        } else {
+            let rdir = reflect(ray_direction, &intersect.normal).normalized();
+            let ro   = offset_origin(&intersect, &rdir);
+            bounce(&ro, &rdir)
+        }
+    } else {
+        Vector3::zero()
+    };
+
+    // Hueco de una textura ventana: el rayo sigue derecho detrás del hit
+    let pass_color = if pass > 0.0 {
+        let ro = offset_origin(&intersect, ray_direction);
+        bounce(&ro, ray_direction)
+    } else {
+        Vector3::zero()
+    };
+
+    let k_phong = if can_bounce { (intersect.coverage - reflectivity - transparency).max(0.0) } else { 1.0 };
+    let shaded = (phong_color * k_phong + reflect_color * reflectivity + refract_color * transparency
+        + pass_color * pass + glint_sum * intersect.coverage) * medium_transmittance(&intersect, ray_direction);
+    let sky = || sample_background(ray_direction, skybox, settings);
+    (fog.apply(shaded, lod.travelled, intersect.distance, depth, gamma, sky), primary)
+}
+
+/// Lo que necesita un hilo de render para disparar rayos primarios.
+#[derive(Clone, Copy)]
+pub(crate) struct RenderCtx<'a> {
+    pub(crate) objects: &'a [Box<dyn RayIntersect>],
+    pub(crate) accel: &'a UniformGridAccel,
+    pub(crate) lights: &'a [light::Light],
+    pub(crate) preview: Option<Preview>,
+    pub(crate) skybox: Option<&'a Skybox>,
+    pub(crate) settings: &'a RenderSettings,
+    overlay: Option<&'a DiffOverlay>,
+    cam: CameraBasis,
+    width_f: f32,
+    height_f: f32,
+}
+
+impl RenderCtx<'_> {
+    /// Una muestra en coordenadas de pixel (fx, fy), ya con post-proceso.
+    fn sample(&self, fx: f32, fy: f32, lod: LodCtx) -> (Vector3, PrimaryHit) {
+        let (ray_origin, ray_dir) = self.primary_ray(fx, fy);
+        stats::count(|c| c.primary += 1);
+
+        let shaded = cast_ray_hit(
+            &ray_origin, &ray_dir, self.objects, self.accel, self.lights, 0,
+            self.preview, self.skybox, self.settings, lod,
+        );
+        self.finish(shaded, &ray_origin, &ray_dir)
+    }
+
+    /// `sample` de los 4 pixeles del quad con esquina (x, y), con un solo `trace_packet` para
+    /// los rayos primarios. Sin SSAA: mismas posiciones y mismo resultado que 4 `sample`.
+    fn sample_quad(&self, x: usize, y: usize) -> [(Vector3, PrimaryHit); 4] {
+        let at = |k: usize| (x + k % 2, y + k / 2);
+        let lod_cfg = &self.settings.lod;
+        let lods: [LodCtx; 4] = std::array::from_fn(|k| LodCtx::for_pixel(lod_cfg, at(k).0, at(k).1));
+        let rays: [(Vector3, Vector3); 4] = std::array::from_fn(|k| self.primary_ray(at(k).0 as f32, at(k).1 as f32));
+        stats::count(|c| c.primary += 4);
+
+        let flat_from = lods.map(|lod| lod.remaining(lod_cfg, lod_cfg.flat_texture_dist));
+        let hits = self.accel.trace_packet(&rays.map(|r| r.0), &rays.map(|r| r.1), self.objects, &flat_from, true);
+        std::array::from_fn(|k| {
+            let (ro, rd) = &rays[k];
+            let shaded = shade_hit(hits[k], ro, rd, self.objects, self.accel, self.lights, 0, self.preview, self.skybox, self.settings, lods[k]);
+            self.finish(shaded, ro, rd)
+        })
+    }
+
+    #[inline]
+    fn primary_ray(&self, fx: f32, fy: f32) -> (Vector3, Vector3) {
+        let sx = (2.0 * fx) / self.width_f - 1.0;
+        let sy = -(2.0 * fy) / self.height_f + 1.0;
+        self.cam.ray(sx, sy, self.width_f / self.height_f)
+    }
+
+    /// Profundidad de vista, fantasma de colocación, overlay de diff y post-proceso de una
+    /// muestra primaria.
+    fn finish(&self, (mut rgb, mut hit): (Vector3, PrimaryHit), ray_origin: &Vector3, ray_dir: &Vector3) -> (Vector3, PrimaryHit) {
+        if let Some(g) = self.preview.and_then(|p| p.ghost) {
+            rgb = g.composite(rgb, ray_origin, ray_dir, hit.depth, self.settings.preview.ghost_opacity);
+        }
+        hit.depth *= ray_dir.dot(self.cam.forward);
+        if let Some(ov) = self.overlay {
+            rgb = ov.composite(rgb, ray_origin, ray_dir, self.objects, self.accel, self.settings.gamma());
+        }
+        // Tone mapping por muestra: un highlight no “ensucia” el promedio del borde
+        (post::apply(rgb, &self.settings.post), hit)
+    }
+
+    /// Vista de debug: color de rampa según el costo del recorrido del rayo primario por el
+    /// centro del pixel (más oscuro si no pega). La profundidad sigue valiendo para los overlays.
+    fn sample_heat(&self, x: usize, y: usize) -> (Vector3, PrimaryHit) {
+        let (ray_origin, ray_dir) = self.primary_ray(x as f32, y as f32);
+        stats::count(|c| c.primary += 1);
+        let (hit, cost) = self.accel.trace_cost(&ray_origin, &ray_dir, self.objects, f32::INFINITY, false);
+        let view = self.settings.debug_view;
+        let value = if view == DebugView::Refs { cost.refs } else { cost.cells };
+        let heat = settings::heat_ramp(value as f32 / view.full_scale() as f32);
+        let depth = if hit.is_intersecting { hit.distance * ray_dir.dot(self.cam.forward) } else { f32::INFINITY };
+        // `vector3_to_color` codifica: la rampa está pensada en el espacio de salida
+        let rgb = self.settings.gamma().decode_vec(if hit.is_intersecting { heat } else { heat * 0.55 });
+        (rgb, PrimaryHit { depth, ..PrimaryHit::MISS })
+    }
+
+    /// Solo la profundidad de vista del hit primario por el centro del pixel (el path tracer
+    /// no devuelve hits; la usan los overlays con test de profundidad).
+    fn primary_depth(&self, fx: f32, fy: f32) -> f32 {
+        let (ray_origin, ray_dir) = self.primary_ray(fx, fy);
+        let hit = self.accel.trace_lod(&ray_origin, &ray_dir, self.objects, f32::INFINITY, false);
+        if hit.is_intersecting { hit.distance * ray_dir.dot(self.cam.forward) } else { f32::INFINITY }
+    }
+
+    /// Muestra del path tracer: radiancia lineal sin post (se aplica sobre el promedio acumulado).
+    fn sample_path(&self, fx: f32, fy: f32, rng: &mut PathRng) -> Vector3 {
+        let (ray_origin, ray_dir) = self.primary_ray(fx, fy);
+        stats::count(|c| c.primary += 1);
+
+        let mut rgb = pathtrace::trace(self, &ray_origin, &ray_dir, 0, true, rng);
+        if let Some(g) = self.preview.and_then(|p| p.ghost) {
+            // El integrador no devuelve el hit: solo se traza de nuevo si el rayo toca el fantasma
+            if g.hit(&ray_origin, &ray_dir).is_some() {
+                let hit = self.accel.trace_lod(&ray_origin, &ray_dir, self.objects, f32::INFINITY, false);
+                let scene_t = if hit.is_intersecting { hit.distance } else { f32::INFINITY };
+                rgb = g.composite(rgb, &ray_origin, &ray_dir, scene_t, self.settings.preview.ghost_opacity);
+            }
+        }
+        match self.overlay {
+            Some(ov) => ov.composite(rgb, &ray_origin, &ray_dir, self.objects, self.accel, self.settings.gamma()),
+            None => rgb,
+        }
+    }
+}
+
+#[inline]
+fn max_channel_diff(a: Vector3, b: Vector3) -> f32 {
+    (a.x - b.x).abs().max((a.y - b.y).abs()).max((a.z - b.z).abs())
+}
+
+pub fn render(
+    framebuffer: &mut Framebuffer,
+    objects: &[Box<dyn RayIntersect>],
+    accel: &UniformGridAccel,
+    camera: &Camera,
+    lights: &[light::Light], // <-- múltiples luces
+    preview: Option<Preview>,
+    skybox: Option<&Skybox>,
+    settings: &RenderSettings,
+    overlay: Option<&DiffOverlay>,
+    accum: Option<&mut Accumulation>,
+    tile_stats: Option<&mut TileStats>,
+) -> stats::RenderStats {
+    let started = Instant::now();
+    let w = framebuffer.width as usize;
+    let h = framebuffer.height as usize;
+
+    let ctx = RenderCtx {
+        objects, accel, lights, preview, skybox, settings, overlay,
+        cam: camera.basis(),
+        width_f: framebuffer.width as f32,
+        height_f: framebuffer.height as f32,
+    };
+
+    // SSAA: grilla n×n de sub-pixeles centrada en (x, y); n = 1 → mismo rayo que sin AA
+    let n = settings.ssaa_grid();
+    let inv_n = 1.0 / n as f32;
+    let base_samples = (n * n) as f32;
+
+    // Path tracing: muestras con jitter nuevo cada frame, promediadas en `accum`
+    let path = settings.integrator == Integrator::PathTraced;
+    let frame = accum.as_ref().map_or(0, |a| a.frames());
+    // Whitted progresivo: el primer frame usa los centros de siempre y los siguientes, jitter
+    // nuevo dentro de cada sub-pixel; se promedia en `accum` igual que el path tracing
+    let progressive = !path && settings.progressive && accum.is_some();
+
+    // Volcado de estadísticas: cada tarea de rayon suma por tile en un parcial propio (`fold`)
+    // y los parciales se fusionan al final
+    stats::set_recording(tile_stats.is_some());
+    let tile_stats = tile_stats.map(Mutex::new);
+    let tile_stats = &tile_stats;
+    let new_local = || tile_stats.as_ref().map(|_| TileStats::new(w, h));
+    let merge_local = |local: Option<TileStats>| {
+        if let (Some(ts), Some(shared)) = (local, tile_stats) { shared.lock().unwrap().merge(&ts); }
+    };
+
+    // ===== Pasada 1: color en float + hit primario por pixel, una fila por tarea =====
+    // Cada pixel depende solo de (x, y, frame): el reparto entre hilos no cambia el resultado
+    let mut color = vec![Vector3::zero(); w * h];
+    let mut hits = vec![PrimaryHit::MISS; w * h];
+
+    let heat = settings.debug_view != DebugView::Off;
+    let pixel = |x: usize, y: usize| -> (Vector3, PrimaryHit) {
+        if heat {
+            ctx.sample_heat(x, y)
+        } else if path {
+            let mut rng = PathRng::new(x as u32, y as u32, frame);
+            let mut sum = Vector3::zero();
+            for sj in 0..n {
+                for si in 0..n {
+                    let fx = x as f32 + (si as f32 + rng.next_f32()) * inv_n - 0.5;
+                    let fy = y as f32 + (sj as f32 + rng.next_f32()) * inv_n - 0.5;
+                    sum += ctx.sample_path(fx, fy, &mut rng);
+                }
+            }
+            (sum / base_samples, PrimaryHit { depth: ctx.primary_depth(x as f32, y as f32), ..PrimaryHit::MISS })
+        } else {
+            let lod = LodCtx::for_pixel(&ctx.settings.lod, x, y);
+            let mut sum = Vector3::zero();
+            let mut first = PrimaryHit::MISS;
+            for sj in 0..n {
+                for si in 0..n {
+                    let (jx, jy) = if progressive && frame > 0 {
+                        settings::jitter2(x as u32, y as u32, frame * (n * n) as u32 + (sj * n + si) as u32)
+                    } else {
+                        (0.5, 0.5)
+                    };
+                    let fx = x as f32 + (si as f32 + jx) * inv_n - 0.5;
+                    let fy = y as f32 + (sj as f32 + jy) * inv_n - 0.5;
+                    let (rgb, hit) = ctx.sample(fx, fy, lod);
+                    if si == 0 && sj == 0 { first = hit; }
+                    sum += rgb;
+                }
+            }
+            (sum / base_samples, first)
+        }
+    };
+
+    // Paquetes: quads 2×2 con un `trace_packet` (solo Whitted sin SSAA); las tareas toman
+    // pares de filas y lo que sobra (última columna/fila impar) va por `pixel`
+    let packets = settings.packets && !path && !progressive && !heat && n == 1;
+    let rows = if packets { 2 } else { 1 };
+    color.par_chunks_mut(rows * w).zip(hits.par_chunks_mut(rows * w)).enumerate()
+        .fold(new_local, |mut local, (band, (c_band, h_band))| {
+            let y0 = band * rows;
+            let band_h = c_band.len() / w;
+            let mut x0 = 0;
+            if packets && band_h == 2 {
+                while x0 + 1 < w {
+                    let started = local.is_some().then(Instant::now);
+                    for (k, (rgb, hit)) in ctx.sample_quad(x0, y0).into_iter().enumerate() {
+                        let i = (k / 2) * w + x0 + k % 2;
+                        c_band[i] = rgb;
+                        h_band[i] = hit;
+                    }
+                    // Los 4 pixeles caen en el mismo tile (quads alineados a pares)
+                    if let (Some(ts), Some(t0)) = (local.as_mut(), started) { ts.flush(x0, y0, t0); }
+                    x0 += 2;
+                }
+            }
+            for r in 0..band_h {
+                for x in x0..w {
+                    let started = local.is_some().then(Instant::now);
+                    (c_band[r * w + x], h_band[r * w + x]) = pixel(x, y0 + r);
+                    if let (Some(ts), Some(t0)) = (local.as_mut(), started) { ts.flush(x, y0 + r, t0); }
+                }
+            }
+            local
+        })
+        .for_each(merge_local);
+
+    if (path || progressive) && !heat {
+        if let Some(acc) = accum { acc.add(&mut color, w); }
+    }
+
+    // ===== Pasada 2: AA adaptativo en bordes + conversión a Color =====
+    // En path tracing el jitter por frame ya suaviza los bordes: solo post-proceso
+    let aa = settings.adaptive_aa;
+    let gamma = settings.gamma();
+    let color = &color;
+    let hits = &hits;
+    // Profundidad del hit primario para los overlays (copia directa del AOV de la pasada 1)
+    for (d, hit) in framebuffer.depth_mut().iter_mut().zip(hits.iter()) {
+        *d = hit.depth;
+    }
+    framebuffer.mark_all_dirty();
+    let pixels = framebuffer.pixels_mut();
+
+    pixels.par_chunks_mut(w).enumerate()
+        .fold(new_local, |mut local, (y, px_row)| {
+            for (x, px) in px_row.iter_mut().enumerate() {
+                let idx = y * w + x;
+                let mut c = color[idx];
+
+                // Vista de debug: la rampa tal cual, sin post ni AA
+                if heat {
+                    *px = vector3_to_color(c, gamma);
+                    continue;
+                }
+                if path {
+                    *px = vector3_to_color(post::apply(c, &settings.post), gamma);
+                    continue;
+                }
+                // Progresivo: el promedio ya suaviza los bordes (y las muestras ya tienen post)
+                if progressive {
+                    *px = vector3_to_color(c, gamma);
+                    continue;
+                }
+
+                if aa.enabled && aa.extra_samples > 0 {
+                    let is_edge = [(1i32, 0i32), (-1, 0), (0, 1), (0, -1)].iter().any(|&(dx, dy)| {
+                        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+                        if nx < 0 || ny < 0 || nx >= w as i32 || ny >= h as i32 { return false; }
+                        let nidx = ny as usize * w + nx as usize;
+                        max_channel_diff(c, color[nidx]) > aa.threshold || hits[idx].differs(&hits[nidx])
+                    });
+
+                    if is_edge {
+                        let started = local.is_some().then(Instant::now);
+                        let lod = LodCtx::for_pixel(&ctx.settings.lod, x, y);
+                        let mut sum = c * base_samples;
+                        for k in 0..aa.extra_samples {
+                            let (jx, jy) = settings::jitter2(x as u32, y as u32, k);
+                            sum += ctx.sample(x as f32 + jx - 0.5, y as f32 + jy - 0.5, lod).0;
+                        }
+                        c = sum / (base_samples + aa.extra_samples as f32);
+                        if aa.show_refined {
+                            c = lerp(c, Vector3::new(1.0, 0.0, 1.0), 0.6);
+                        }
+                        if let (Some(ts), Some(t0)) = (local.as_mut(), started) { ts.flush(x, y, t0); }
+                    }
+                }
+
+                *px = vector3_to_color(c, gamma);
+            }
+            local
+        })
+        .for_each(merge_local);
+    stats::set_recording(false);
+    stats::RenderStats {
+        counters: if stats::live() { stats::take_live() } else { stats::RayCounters::default() },
+        elapsed: started.elapsed(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::startup::tinted_glass;
+    use crate::cube::Cube;
+
+    fn luminance(c: Color) -> f32 { 0.2126 * c.r as f32 + 0.7152 * c.g as f32 + 0.0722 * c.b as f32 }
+
+    /// Panel rojo adelante y azul detrás, corridos para que se crucen en el centro: donde se
+    /// superponen los tintes se multiplican (violeta oscuro).
+    #[test]
+    fn red_over_blue_glass_multiplies() {
+        let [red, blue] = tinted_glass();
+        let white = Material::new(Vector3::new(1.0, 1.0, 1.0), 1.0, [1.0, 0.0, 0.0, 0.0], 0.0);
+        let objects: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(Vector3::new(-40.0, -20.0, 0.0), Vector3::new(1.5, 20.0, 0.6), red)),
+            Box::new(Cube::new(Vector3::new(-1.5, -20.0, -1.6), Vector3::new(40.0, 20.0, -1.0), blue)),
+            // pared blanca de fondo: el cielo procedural por defecto es casi negro
+            Box::new(Cube::new(Vector3::new(-40.0, -20.0, -6.0), Vector3::new(40.0, 20.0, -5.0), white)),
+        ];
+        let accel = UniformGridAccel::build(&objects, 2.0);
+        let camera = Camera::new(Vector3::new(0.0, 0.0, 8.0), Vector3::zero(), Vector3::new(0.0, 1.0, 0.0));
+        let lights = [light::Light::new(Vector3::new(6.0, 8.0, 10.0), Color::WHITE, 1.5)];
+        // dos paneles son cuatro caras: con la profundidad por defecto (3) el segundo queda opaco
+        let settings = RenderSettings { max_depth: 6, ..RenderSettings::default() };
+        let mut fb = Framebuffer::new(64, 32);
+        render(&mut fb, &objects, &accel, &camera, &lights, None, None, &settings, None, None, None);
+
+        // fila del centro: solo rojo a la izquierda, los dos en el medio, solo azul a la derecha
+        let at = |x: usize| fb.pixels()[16 * 64 + x];
+        let (only_red, both, only_blue) = (at(6), at(32), at(58));
+        assert!(only_red.r > only_red.g && only_red.r > only_red.b, "rojo solo: {:?}", only_red);
+        assert!(only_blue.b > only_blue.r && only_blue.b > only_blue.g, "azul solo: {:?}", only_blue);
+        assert!(both.r > both.g && both.b > both.g, "superpuestos: {:?}", both);
+        assert!(luminance(both) < luminance(only_red) && luminance(both) < luminance(only_blue),
+            "superpuestos {:?} no más oscuro que {:?} / {:?}", both, only_red, only_blue);
+    }
+
+    /// Panel ventana delante de un bloque espejo, con cielo de día: la mitad izquierda de la
+    /// textura es marco (cobertura parcial, gris oscuro) y la derecha vidrio abierto. El espejo
+    /// queda detrás del marco; el vidrio abierto da al cielo. El marco no puede quedar más claro
+    /// que el vidrio frente al cielo (el hueco del marco no entra al presupuesto de Fresnel).
+    #[test]
+    fn window_frame_over_mirror_not_brighter_than_open_glass() {
+        use std::sync::Arc;
+        use crate::palette::CubeTemplate;
+        use crate::texture::Texture;
+
+        let glass = Material::new(Vector3::new(1.0, 1.0, 1.0), 120.0, [0.80, 0.15, 0.06, 0.0], 1.5);
+        let texels = vec![Color::new(70, 70, 70, 160), Color::new(0, 0, 0, 0)];
+        let tpl = CubeTemplate::with_same_texture_image_alpha_window(glass, Arc::new(Texture::from_pixels(2, 1, texels)), 0.05);
+        let mut pane = Cube::new(Vector3::new(-4.0, -2.0, 0.0), Vector3::new(4.0, 2.0, 0.1), glass);
+        pane.set_face_textures_from_template(&tpl.face_textures);
+        let chrome = Material::new(Vector3::new(0.85, 0.86, 0.90), 0.0, [0.25, 0.9, 0.55, 0.0], 0.0).with_ggx(0.22).with_fresnel();
+        let objects: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(pane),
+            Box::new(Cube::new(Vector3::new(-3.5, -1.5, -2.0), Vector3::new(-0.5, 1.5, -1.0), chrome)),
+        ];
+        let accel = UniformGridAccel::build(&objects, 1.0);
+        // Mirando el panel de costado, donde Fresnel sube
+        let camera = Camera::new(Vector3::new(5.0, 0.5, 5.0), Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0));
+        let lights = [light::Light::new(Vector3::new(6.0, 8.0, 10.0), Color::WHITE, 1.5)];
+        let settings = RenderSettings { procedural_sky: settings::ProceduralSky::DAY, ..RenderSettings::default() };
+        let mut fb = Framebuffer::new(96, 64);
+        render(&mut fb, &objects, &accel, &camera, &lights, None, None, &settings, None, None, None);
+
+        // Qué ve cada pixel de la fila del centro (el mismo rayo que `primary_ray`): panel, y de
+        // qué lado de la textura, o nada
+        let cam = camera.basis();
+        let (mut frame, mut open) = (Vec::new(), Vec::new());
+        for x in 0..96 {
+            let (ro, rd) = cam.ray((2.0 * x as f32) / 96.0 - 1.0, 0.0, 96.0 / 64.0);
+            let hit = accel.trace(&ro, &rd, &objects);
+            if hit.object_index != Some(0) { continue; }
+            let lum = luminance(fb.pixels()[32 * 96 + x]);
+            if hit.point.x < -0.5 { frame.push(lum) } else if hit.point.x > 0.5 { open.push(lum) }
+        }
+        assert!(!frame.is_empty() && !open.is_empty(), "marco {} / abierto {} pixeles", frame.len(), open.len());
+        let brightest_frame = frame.iter().copied().fold(0.0, f32::max);
+        let darkest_open = open.iter().copied().fold(f32::INFINITY, f32::min);
+        assert!(brightest_frame <= darkest_open, "marco {} más claro que el vidrio abierto {}", brightest_frame, darkest_open);
+    }
+
+    /// `LightingParams::default()` reproduce los números que estaban escritos en `shade_hit`
+    /// (wrap `/1.3`, ambiente `*0.15`, glints de dureza 800, ganancia 1 y sesgo 0.05) byte a byte.
+    #[test]
+    fn default_lighting_matches_the_hard_coded_constants() {
+        use crate::settings::{GlintQuality, LightingParams, LightingPreset};
+
+        // El wrap pasó de `(n·l + 0.3) / 1.3` a `(n·l + wrap) / (1 + wrap)`: `1 + 0.3` en f32 es 1.3
+        for k in -1000..=1000 {
+            let ndl = k as f32 / 1000.0;
+            assert_eq!(((ndl + 0.3) / 1.3).to_bits(), ((ndl + 0.3) / (1.0 + 0.3)).to_bits(), "n·l = {}", ndl);
+        }
+
+        let hard_coded = LightingParams {
+            diffuse_wrap: 0.3, ambient: 0.15,
+            glint_hardness_point: 800.0, glint_hardness_dir: 800.0, glint_gain: 1.0, glint_bias: 0.05,
+        };
+        // Piso pulido; la direccional está justo en el reflejo del rayo del centro (glint)
+        let white = Material::new(Vector3::new(1.0, 1.0, 1.0), 30.0, [0.7, 0.2, 0.3, 0.0], 0.0);
+        let shiny = Material::new(Vector3::new(0.6, 0.6, 0.7), 80.0, [0.5, 0.5, 0.4, 0.0], 0.0);
+        let objects: Vec<Box<dyn RayIntersect>> = vec![
+            Box::new(Cube::new(Vector3::new(-6.0, -1.0, -6.0), Vector3::new(6.0, 0.0, 6.0), white)),
+            Box::new(Cube::new(Vector3::new(-4.0, 0.0, -1.0), Vector3::new(-2.0, 2.0, 1.0), shiny)),
+        ];
+        let accel = UniformGridAccel::build(&objects, 1.0);
+        let (eye, target) = (Vector3::new(4.0, 3.0, 6.0), Vector3::new(1.5, 0.0, 2.5));
+        let camera = Camera::new(eye, target, Vector3::new(0.0, 1.0, 0.0));
+        let mirrored = target - eye;
+        let lights = [
+            light::Light::new(Vector3::new(3.0, 5.0, 4.0), Color::WHITE, 1.0),
+            light::Light::directional(Vector3::new(-mirrored.x, mirrored.y, -mirrored.z), Color::new(255, 230, 200, 255), 0.6),
+        ];
+        let frame = |lighting: LightingParams| {
+            let mut settings = RenderSettings { lighting, ..RenderSettings::default() };
+            settings.glints.quality = GlintQuality::Full;
+            settings.ibl.enabled = false;
+            let mut fb = Framebuffer::new(48, 32);
+            render(&mut fb, &objects, &accel, &camera, &lights, None, None, &settings, None, None, None);
+            fb.pixels().to_vec()
+        };
+
+        let default = frame(LightingParams::default());
+        assert!(default == frame(hard_coded), "los pixeles cambian con LightingParams::default()");
+        // Y los parámetros se usan de verdad: otro preset cambia la imagen, y sin glints también
+        assert!(default != frame(LightingPreset::Harsh.params()));
+        assert!(default != frame(LightingParams { glint_gain: 0.0, ..hard_coded }));
+    }
+}
@@ -1,15 +1,23 @@
 // scene.rs
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
 use std::{fs, io};
 
 use raylib::prelude::Vector3;
 
 use crate::cube::Cube;
+use crate::gamma;
+use crate::json::{self, Json};
 use crate::material::Material;
-use crate::palette::{CubeTemplate, Palette};
+use crate::palette::{BlockShape, BlockVariant, FaceOverrides, Palette};
 use crate::ray_intersect::RayIntersect;
+use crate::sphere::Sphere;
+use crate::stairs::Stairs;
 use crate::slab::{Slab, SlabHalf, Face as SlabFace};
 
 /// Parámetros para construir la escena a partir de ASCII layers.
+#[derive(Clone)]
 pub struct SceneParams {
     pub cube_size: Vector3,
     pub gap: Vector3,
@@ -20,27 +28,107 @@ pub struct SceneParams {
     pub solid_chars: Vec<char>,
 }
 
-pub fn load_ascii_layers_with_palette(
-    dir: &str,
-    params: &SceneParams,
-    palette: &Palette,
-    default_material: Material,
-) -> io::Result<Vec<Box<dyn RayIntersect>>> {
-    let mut entries: Vec<_> = fs::read_dir(dir)?
+/// Celda de una escena ASCII: (columna, capa, fila) con los mismos índices enteros que usa el loader.
+pub type Cell = (i32, i32, i32);
+
+/// Reconstrucción de ocupación de una escena: celda → carácter de bloque
+/// (el carácter es la identidad del bloque y su forma: `_`/`-` son slabs).
+pub type CellMap = BTreeMap<Cell, char>;
+
+#[inline]
+fn is_solid_char(ch: char, params: &SceneParams, palette: &Palette) -> bool {
+    if params.any_non_whitespace_is_solid {
+        !ch.is_whitespace()
+    } else {
+        params.solid_chars.contains(&ch) || palette.get(ch).is_some() || ch == '_' || ch == '-'
+    }
+}
+
+/// Manifiesto opcional dentro de la carpeta de escena: `{ "layers": [...] }` fija el orden de las capas.
+pub const SCENE_MANIFEST: &str = "manifest.json";
+
+/// Compara nombres de archivo por trozos: las corridas de dígitos como números
+/// (`layer2` < `layer10`, `02` == `2` desempata por largo) y el resto lexicográfico.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut ia, mut ib) = (a.char_indices().peekable(), b.char_indices().peekable());
+    loop {
+        match (ia.peek().copied(), ib.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some((sa, ca)), Some((sb, cb))) if ca.is_ascii_digit() && cb.is_ascii_digit() => {
+                let run = |it: &mut std::iter::Peekable<std::str::CharIndices>, s: &str, start: usize| {
+                    let mut end = start;
+                    while let Some(&(i, c)) = it.peek() {
+                        if !c.is_ascii_digit() { break; }
+                        end = i + c.len_utf8();
+                        it.next();
+                    }
+                    s[start..end].to_string()
+                };
+                let (na, nb) = (run(&mut ia, a, sa), run(&mut ib, b, sb));
+                let (ta, tb) = (na.trim_start_matches('0'), nb.trim_start_matches('0'));
+                let ord = ta.len().cmp(&tb.len()).then_with(|| ta.cmp(tb));
+                if ord != Ordering::Equal { return ord; }
+            }
+            (Some((_, ca)), Some((_, cb))) => {
+                if ca != cb { return ca.cmp(&cb); }
+                ia.next();
+                ib.next();
+            }
+        }
+    }
+}
+
+/// Lee el arreglo `"layers"` de `dir/manifest.json` si existe, con el mismo `json::parse` que
+/// las escenas. None = no hay manifiesto o no declara capas: se descubren los `*.txt`.
+fn manifest_layers(dir: &Path) -> io::Result<Option<Vec<String>>> {
+    let text = match fs::read_to_string(dir.join(SCENE_MANIFEST)) {
+        Ok(t) => t,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let bad = |msg: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", SCENE_MANIFEST, msg));
+    let root = json::parse(&text).map_err(bad)?;
+    let Some(layers) = root.get("layers") else { return Ok(None); };
+    let list = layers.as_array().ok_or_else(|| bad("\"layers\" no es un arreglo".to_string()))?;
+    list.iter()
+        .map(|v| v.as_str().map(str::to_string).ok_or_else(|| bad("\"layers\" lleva nombres de archivo (strings)".to_string())))
+        .collect::<io::Result<Vec<_>>>()
+        .map(Some)
+}
+
+/// Archivos de capa de `dir` de abajo hacia arriba: la lista del manifiesto si la hay, si no
+/// los `*.txt` en orden natural (`layer2` antes que `layer10`).
+pub fn layer_files(dir: &str) -> io::Result<Vec<PathBuf>> {
+    let root = Path::new(dir);
+    if let Some(names) = manifest_layers(root)? {
+        return Ok(names.iter().map(|n| root.join(n)).collect());
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)?
         .filter_map(|e| e.ok())
-        .filter(|e| {
-            let p = e.path();
-            p.is_file() && p.extension().map(|ext| ext == "txt").unwrap_or(false)
-        })
+        .map(|e| e.path())
+        .filter(|p| p.is_file() && p.extension().map(|ext| ext == "txt").unwrap_or(false))
         .collect();
+    let name = |p: &PathBuf| p.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    files.sort_by(|a, b| natural_cmp(&name(a), &name(b)));
+    Ok(files)
+}
 
-    entries.sort_by_key(|e| e.path());
+/// Lee las capas de `dir` (ver `layer_files`) y devuelve las celdas sólidas.
+/// La capa i-ésima va al nivel Y `i`, aunque esté vacía.
+pub fn load_ascii_cells(dir: &str, params: &SceneParams, palette: &Palette) -> io::Result<CellMap> {
+    let entries = layer_files(dir)?;
+    let order: Vec<String> = entries.iter().enumerate()
+        .map(|(i, p)| format!("{}→y{}", p.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(), i))
+        .collect();
+    println!("Capas de {}: {}", dir, order.join(", "));
 
-    let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+    let mut cells = CellMap::new();
 
-    for (layer_idx, entry) in entries.into_iter().enumerate() {
-        let path = entry.path();
-        let text = fs::read_to_string(&path)?;
+    for (layer_idx, path) in entries.into_iter().enumerate() {
+        let text = fs::read_to_string(&path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
 
         let mut lines: Vec<String> = text
             .lines()
@@ -54,64 +142,395 @@ pub fn load_ascii_layers_with_palette(
         let rows = lines.len();
         let cols = lines.iter().map(|s| s.chars().count()).max().unwrap_or(0);
 
-        // pasos entre centros: SIN GAPS si gap = 0
-        let step_x = params.cube_size.x + params.gap.x;
-        let step_z = params.cube_size.z + params.gap.z;
-
         // Índice base ENTERO para alinear con la grilla del builder (centros en i+0.5)
         let base_ix: i32 = -((cols as i32) / 2);
         let base_iz: i32 = -((rows as i32) / 2);
 
-        let y_center = params.y0 + layer_idx as f32 * params.y_step;
-
         for (r, line) in lines.iter().enumerate() {
-            let mut chars = line.chars().collect::<Vec<char>>();
-            if chars.len() < cols { chars.resize(cols, ' '); }
-
-            for c in 0..cols {
-                let ch = chars[c];
-
-                // sólido...
-                let has_tpl = palette.get(ch).is_some();
-                let is_slab = ch == '_' || ch == '-';
-                let solid = if params.any_non_whitespace_is_solid {
-                    !ch.is_whitespace()
-                } else {
-                    params.solid_chars.contains(&ch) || has_tpl || is_slab
-                };
-                if !solid { continue; }
-
-                // ✅ usa índices de celda enteros y centros (i + 0.5) * step
-                let ix = base_ix + c as i32;
-                let iz = base_iz + r as i32;
-
-                let x = params.origin.x + (ix as f32 + 0.5) * step_x;
-                let z = params.origin.z + (iz as f32 + 0.5) * step_z;
-                let center = Vector3::new(x, y_center, z);
-
-                if is_slab {
-                    let half = if ch == '_' { SlabHalf::Bottom } else { SlabHalf::Top };
-                    let mut slab = Slab::from_block_center_size(center, params.cube_size, half, default_material);
-                    if let Some(tpl) = palette.get(ch) {
-                        slab.material = tpl.material;
-                        slab.set_face_textures_from_template(&tpl.face_textures);
-                    }
-                    objects.push(Box::new(slab));
-                } else {
-                    let mut cube = Cube::from_center_size(center, params.cube_size, default_material);
-                    if let Some(tpl) = palette.get(ch) {
-                        cube.material = tpl.material;
-                        cube.set_face_textures_from_template(&tpl.face_textures);
-                    }
-                    objects.push(Box::new(cube));
-                }
+            for (c, ch) in line.chars().enumerate() {
+                if !is_solid_char(ch, params, palette) { continue; }
+                cells.insert((base_ix + c as i32, layer_idx as i32, base_iz + r as i32), ch);
             }
         }
     }
 
+    Ok(cells)
+}
+
+/// Centro en mundo de una celda ASCII.
+pub fn cell_center(params: &SceneParams, cell: Cell) -> Vector3 {
+    // pasos entre centros: SIN GAPS si gap = 0
+    let step_x = params.cube_size.x + params.gap.x;
+    let step_z = params.cube_size.z + params.gap.z;
+    Vector3::new(
+        params.origin.x + (cell.0 as f32 + 0.5) * step_x,
+        params.y0 + cell.1 as f32 * params.y_step,
+        params.origin.z + (cell.2 as f32 + 0.5) * step_z,
+    )
+}
+
+pub fn load_ascii_layers_with_palette(
+    dir: &str,
+    params: &SceneParams,
+    palette: &Palette,
+    default_material: Material,
+) -> io::Result<Vec<Box<dyn RayIntersect>>> {
+    let cells = load_ascii_cells(dir, params, palette)?;
+    Ok(objects_from_cells(&cells, params, palette, default_material))
+}
+
+/// Un objeto por celda, con la forma que indica la plantilla de su carácter.
+pub fn objects_from_cells(
+    cells: &CellMap,
+    params: &SceneParams,
+    palette: &Palette,
+    default_material: Material,
+) -> Vec<Box<dyn RayIntersect>> {
+    let mut objects: Vec<Box<dyn RayIntersect>> = Vec::with_capacity(cells.len());
+
+    for (&cell, &ch) in cells.iter() {
+        let center = cell_center(params, cell);
+        let is_slab = ch == '_' || ch == '-';
+
+        if is_slab {
+            let half = if ch == '_' { SlabHalf::Bottom } else { SlabHalf::Top };
+            let mut slab = Slab::from_block_center_size(center, params.cube_size, half, default_material);
+            if let Some(tpl) = palette.get(ch) {
+                slab.material = tpl.material;
+                slab.set_face_textures_from_template(&tpl.face_textures);
+                slab.block = Some(ch);
+            }
+            objects.push(Box::new(slab));
+        } else if let Some(tpl) = palette.get(ch).filter(|t| t.shape == BlockShape::Sphere) {
+            // Esfera inscrita en la celda
+            let radius = params.cube_size.x.min(params.cube_size.y).min(params.cube_size.z) * 0.5;
+            let mut sphere = Sphere::new(center, radius, tpl.material);
+            sphere.set_face_textures_from_template(&tpl.face_textures);
+            sphere.block = Some(ch);
+            objects.push(Box::new(sphere));
+        } else if let Some((tpl, BlockShape::Stairs(facing))) = palette.get(ch).map(|t| (t, t.shape)) {
+            let mut stairs = Stairs::from_block_center_size(center, params.cube_size, facing, tpl.material);
+            stairs.set_face_textures_from_template(&tpl.face_textures);
+            stairs.block = Some(ch);
+            objects.push(Box::new(stairs));
+        } else {
+            let mut cube = Cube::from_center_size(center, params.cube_size, default_material);
+            if let Some(tpl) = palette.get(ch) {
+                cube.material = tpl.material;
+                cube.set_face_textures_from_template(&tpl.face_textures);
+                cube.block = Some(ch);
+            }
+            objects.push(Box::new(cube));
+        }
+    }
+
+    objects
+}
+
+/// Celdas de una lista de objetos: las que vienen de la paleta (`block_id`), por el centro de
+/// su caja. Mallas y objetos sin carácter quedan afuera.
+pub fn cells_from_objects(objects: &[Box<dyn RayIntersect>], params: &SceneParams) -> CellMap {
+    let mut cells = CellMap::new();
+    for obj in objects.iter() {
+        let Some(ch) = obj.block_id() else { continue; };
+        let (mn, mx) = obj.aabb();
+        cells.insert(cell_of_center(params, (mn + mx) * 0.5), ch);
+    }
+    cells
+}
+
+/// Inversa de `cell_center` (las losas quedan a ±¼ de celda del centro: redondean igual).
+pub fn cell_of_center(params: &SceneParams, c: Vector3) -> Cell {
+    let step_x = params.cube_size.x + params.gap.x;
+    let step_z = params.cube_size.z + params.gap.z;
+    (
+        ((c.x - params.origin.x) / step_x - 0.5).round() as i32,
+        ((c.y - params.y0) / params.y_step).round() as i32,
+        ((c.z - params.origin.z) / step_z - 0.5).round() as i32,
+    )
+}
+
+/// Celda de un objeto que cabe en una sola (bloques, losas, voxels); `None` para mallas u
+/// objetos más grandes que un bloque, que no se reemplazan por celda.
+pub fn block_cell(obj: &dyn RayIntersect, params: &SceneParams) -> Option<Cell> {
+    let (mn, mx) = obj.aabb();
+    let size = mx - mn;
+    let eps = 1e-3;
+    let fits = size.x <= params.cube_size.x + eps && size.y <= params.cube_size.y + eps && size.z <= params.cube_size.z + eps;
+    fits.then(|| cell_of_center(params, (mn + mx) * 0.5))
+}
+
+/// Modelo MagicaVoxel (`.vox`) en la grilla de las capas ASCII: cada voxel es una celda, con
+/// z de MagicaVoxel hacia arriba (capa) desde el nivel 0 y el modelo centrado en X/Z como una
+/// capa `.txt` (la y de MagicaVoxel crece hacia -Z, así no se espeja). Los índices de color que
+/// están en `palette_map` usan la plantilla de ese carácter; el resto, un cubo de color plano con
+/// el RGBA del archivo (alpha ignorado).
+pub fn load_vox(
+    path: &str,
+    params: &SceneParams,
+    palette: &Palette,
+    palette_map: &HashMap<u8, char>,
+    default_material: Material,
+) -> io::Result<Vec<Box<dyn RayIntersect>>> {
+    let model = crate::vox::load(path)?;
+    let (sx, sy, _) = model.size;
+    let (base_ix, base_iz) = (-((sx as i32) / 2), -((sy as i32) / 2));
+    let cell_of = |x: u8, y: u8, z: u8| (base_ix + x as i32, z as i32, base_iz + (sy as i32 - 1 - y as i32));
+
+    let mut mapped = CellMap::new();
+    let mut objects: Vec<Box<dyn RayIntersect>> = Vec::new();
+    for &(x, y, z, index) in model.voxels.iter() {
+        let cell = cell_of(x, y, z);
+        if let Some(&ch) = palette_map.get(&index).filter(|&&ch| palette.get(ch).is_some()) {
+            mapped.insert(cell, ch);
+            continue;
+        }
+        let [r, g, b, _] = model.palette[index as usize];
+        // El RGBA es sRGB; los materiales sin textura se usan tal cual en el espacio de trabajo (lineal)
+        let diffuse = Vector3::new(gamma::srgb_to_linear(r as f32 / 255.0), gamma::srgb_to_linear(g as f32 / 255.0), gamma::srgb_to_linear(b as f32 / 255.0));
+        let material = Material { diffuse, ..default_material };
+        objects.push(Box::new(Cube::from_center_size(cell_center(params, cell), params.cube_size, material)));
+    }
+    objects.extend(objects_from_cells(&mapped, params, palette, default_material));
     Ok(objects)
 }
 
+/// Escena en un solo archivo JSON:
+///
+/// ```text
+/// {
+///   "params":  { "cube_size": [1, 1, 1], "gap": [0, 0, 0], "origin": [0, 0, 0], "y0": -0.5, "y_step": 1 },
+///   "palette": { "a": "P",                    // alias: `a` se construye con la plantilla de `P`
+///                "t": { "base": "T", "faces": { "+y": { "albedo": [0.2, 0.1, 0.7, 0], "specular": 250 } } } },
+///   "layers":  [ ["SSS", "S.S"], ["  P"] ],   // capa i → nivel Y i, centradas como las .txt
+///   "blocks":  [ [3, 2, -1, "G"] ]            // celdas sueltas (pisan a las capas)
+/// }
+/// ```
+///
+/// Todo es opcional; los parámetros que falten salen de `defaults`. Devuelve los parámetros
+/// usados y las celdas (con los alias ya resueltos). Las variantes (`"base"` + reemplazos por cara
+/// `albedo`/`specular`/`refractive_index`, claves de `FACE_KEYS`) se agregan a `palette` con su carácter.
+pub fn load_json(path: &str, palette: &mut Palette, defaults: SceneParams) -> io::Result<(SceneParams, CellMap)> {
+    let bad = |msg: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, msg));
+    let text = fs::read_to_string(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+    let root = json::parse(&text).map_err(bad)?;
+
+    let mut params = defaults;
+    if let Some(p) = root.get("params") {
+        let vec3 = |key: &str, into: &mut Vector3| -> io::Result<()> {
+            let Some(v) = p.get(key) else { return Ok(()); };
+            match v.as_array().map(|a| a.iter().filter_map(Json::as_f32).collect::<Vec<_>>()).as_deref() {
+                Some(&[x, y, z]) => { *into = Vector3::new(x, y, z); Ok(()) }
+                _ => Err(bad(format!("\"params.{}\" necesita 3 números", key))),
+            }
+        };
+        vec3("cube_size", &mut params.cube_size)?;
+        vec3("gap", &mut params.gap)?;
+        vec3("origin", &mut params.origin)?;
+        for (key, into) in [("y0", &mut params.y0), ("y_step", &mut params.y_step)] {
+            if let Some(v) = p.get(key) {
+                *into = v.as_f32().ok_or_else(|| bad(format!("\"params.{}\" no es un número", key)))?;
+            }
+        }
+    }
+
+    let single_char = |v: &Json, what: &str| -> io::Result<char> {
+        let mut chars = v.as_str().unwrap_or("").chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(c),
+            _ => Err(bad(format!("{}: se esperaba un solo carácter", what))),
+        }
+    };
+    let mut alias: HashMap<char, char> = HashMap::new();
+    if let Some(map) = root.get("palette") {
+        let pairs = map.as_object().ok_or_else(|| bad("\"palette\" debe ser un objeto".to_string()))?;
+        for (from, to) in pairs.iter() {
+            let from = single_char(&Json::Str(from.clone()), "clave de \"palette\"")?;
+            if to.as_object().is_some() {
+                let variant = variant_from_json(to).map_err(|e| bad(format!("\"palette\".{}: {}", from, e)))?;
+                palette.set_variant(from, variant).map_err(|e| bad(format!("\"palette\".{}: {}", from, e)))?;
+                continue;
+            }
+            let to = single_char(to, "valor de \"palette\"")?;
+            if palette.get(to).is_none() { return Err(bad(format!("\"palette\": '{}' no está en la paleta", to))); }
+            alias.insert(from, to);
+        }
+    }
+    let resolve = |ch: char| alias.get(&ch).copied().unwrap_or(ch);
+
+    let mut cells = CellMap::new();
+    if let Some(layers) = root.get("layers") {
+        let layers = layers.as_array().ok_or_else(|| bad("\"layers\" debe ser un arreglo".to_string()))?;
+        for (y, layer) in layers.iter().enumerate() {
+            let rows: Vec<&str> = layer.as_array().map(|r| r.iter().filter_map(Json::as_str).collect())
+                .ok_or_else(|| bad(format!("\"layers\"[{}] debe ser un arreglo de strings", y)))?;
+            // Mismo centrado que `load_ascii_cells`
+            let cols = rows.iter().map(|s| s.chars().count()).max().unwrap_or(0);
+            let (base_ix, base_iz) = (-((cols as i32) / 2), -((rows.len() as i32) / 2));
+            for (r, line) in rows.iter().enumerate() {
+                for (c, ch) in line.chars().enumerate() {
+                    let ch = resolve(ch);
+                    if !is_solid_char(ch, &params, palette) { continue; }
+                    cells.insert((base_ix + c as i32, y as i32, base_iz + r as i32), ch);
+                }
+            }
+        }
+    }
+    if let Some(blocks) = root.get("blocks") {
+        let blocks = blocks.as_array().ok_or_else(|| bad("\"blocks\" debe ser un arreglo".to_string()))?;
+        for (i, b) in blocks.iter().enumerate() {
+            let what = format!("\"blocks\"[{}]", i);
+            let parts = b.as_array().filter(|a| a.len() == 4).ok_or_else(|| bad(format!("{}: se espera [x, y, z, \"c\"]", what)))?;
+            let coord = |v: &Json| v.as_f32().map(|f| f as i32).ok_or_else(|| bad(format!("{}: coordenada inválida", what)));
+            let ch = resolve(single_char(&parts[3], &what)?);
+            if !is_solid_char(ch, &params, palette) { return Err(bad(format!("{}: '{}' no está en la paleta", what, ch))); }
+            cells.insert((coord(&parts[0])?, coord(&parts[1])?, coord(&parts[2])?), ch);
+        }
+    }
+    Ok((params, cells))
+}
+
+/// Claves de cara en el JSON, en el orden de `CubeTemplate::face_textures`.
+pub const FACE_KEYS: [&str; 6] = ["+x", "-x", "+y", "-y", "+z", "-z"];
+
+fn variant_from_json(v: &Json) -> Result<BlockVariant, String> {
+    let mut base = v.get("base").and_then(Json::as_str).unwrap_or("").chars();
+    let base = match (base.next(), base.next()) {
+        (Some(c), None) => c,
+        _ => return Err("\"base\" debe ser un solo carácter".to_string()),
+    };
+    let mut faces = [FaceOverrides::default(); 6];
+    for (key, f) in v.get("faces").and_then(Json::as_object).unwrap_or(&[]) {
+        let idx = FACE_KEYS.iter().position(|k| k == key).ok_or_else(|| format!("cara desconocida \"{}\"", key))?;
+        let num = |name: &str| -> Result<Option<f32>, String> {
+            f.get(name).map(|n| n.as_f32().ok_or_else(|| format!("{}.{} no es un número", key, name))).transpose()
+        };
+        let albedo = match f.get("albedo").map(|a| a.as_array().map(|a| a.iter().filter_map(Json::as_f32).collect::<Vec<_>>())) {
+            None => None,
+            Some(Some(a)) if a.len() == 4 => Some([a[0], a[1], a[2], a[3]]),
+            Some(_) => return Err(format!("{}.albedo necesita 4 números", key)),
+        };
+        faces[idx] = FaceOverrides { albedo, specular: num("specular")?, refractive_index: num("refractive_index")? };
+    }
+    Ok(BlockVariant { base, faces })
+}
+
+fn variant_to_json(v: &BlockVariant) -> String {
+    let faces: Vec<String> = FACE_KEYS.iter().zip(v.faces.iter()).filter(|(_, f)| !f.is_empty())
+        .map(|(key, f)| {
+            let mut fields = Vec::new();
+            if let Some([a, b, c, d]) = f.albedo { fields.push(format!("\"albedo\": [{}, {}, {}, {}]", a, b, c, d)); }
+            if let Some(s) = f.specular { fields.push(format!("\"specular\": {}", s)); }
+            if let Some(ior) = f.refractive_index { fields.push(format!("\"refractive_index\": {}", ior)); }
+            format!("{}: {{ {} }}", json::quote(key), fields.join(", "))
+        })
+        .collect();
+    format!("{{ \"base\": {}, \"faces\": {{ {} }} }}", json::quote(&v.base.to_string()), faces.join(", "))
+}
+
+/// Escribe `cells` como lista de bloques (formato de `load_json`) con los parámetros de la grilla
+/// y las variantes de `palette` que usan.
+pub fn save_json(path: &str, cells: &CellMap, params: &SceneParams, palette: &Palette) -> io::Result<()> {
+    use std::fmt::Write as _;
+    let v3 = |v: Vector3| format!("[{}, {}, {}]", v.x, v.y, v.z);
+    let mut out = String::new();
+    let _ = writeln!(out, "{{");
+    let _ = writeln!(
+        out, "  \"params\": {{ \"cube_size\": {}, \"gap\": {}, \"origin\": {}, \"y0\": {}, \"y_step\": {} }},",
+        v3(params.cube_size), v3(params.gap), v3(params.origin), params.y0, params.y_step,
+    );
+    let used: BTreeSet<char> = cells.values().copied().collect();
+    let variants: Vec<String> = used.iter()
+        .filter_map(|&ch| palette.variant(ch).map(|v| format!("    {}: {}", json::quote(&ch.to_string()), variant_to_json(v))))
+        .collect();
+    if !variants.is_empty() {
+        let _ = writeln!(out, "  \"palette\": {{\n{}\n  }},", variants.join(",\n"));
+    }
+    let blocks: Vec<String> = cells.iter()
+        .map(|(&(x, y, z), &ch)| format!("    [{}, {}, {}, {}]", x, y, z, json::quote(&ch.to_string())))
+        .collect();
+    if blocks.is_empty() {
+        let _ = writeln!(out, "  \"blocks\": []");
+    } else {
+        let _ = writeln!(out, "  \"blocks\": [\n{}\n  ]", blocks.join(",\n"));
+    }
+    let _ = writeln!(out, "}}");
+    if let Some(dir) = Path::new(path).parent().filter(|d| !d.as_os_str().is_empty()) { fs::create_dir_all(dir)?; }
+    fs::write(path, out)
+}
+
+/// Diferencias entre dos escenas, por celda. Ordenadas por celda: no dependen del orden en los archivos.
+#[derive(Default, Debug)]
+pub struct SceneDiff {
+    pub added: Vec<(Cell, char)>,
+    pub removed: Vec<(Cell, char)>,
+    /// (celda, antes, después)
+    pub changed: Vec<(Cell, char, char)>,
+}
+
+impl SceneDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!("+{} agregados, -{} quitados, ~{} cambiados", self.added.len(), self.removed.len(), self.changed.len())
+    }
+}
+
+pub fn diff(a: &CellMap, b: &CellMap) -> SceneDiff {
+    let mut d = SceneDiff::default();
+    for (&cell, &ca) in a.iter() {
+        match b.get(&cell) {
+            None => d.removed.push((cell, ca)),
+            Some(&cb) if cb != ca => d.changed.push((cell, ca, cb)),
+            _ => {}
+        }
+    }
+    for (&cell, &cb) in b.iter() {
+        if !a.contains_key(&cell) { d.added.push((cell, cb)); }
+    }
+    d
+}
+
+/// Diff ASCII por capa (solo capas con cambios): `+` agregado, `-` quitado, `~` cambiado,
+/// `.` sin cambios, espacio = vacío en ambas.
+pub fn diff_report(a: &CellMap, b: &CellMap, d: &SceneDiff) -> String {
+    use std::fmt::Write;
+
+    let mut marks: BTreeMap<Cell, char> = BTreeMap::new();
+    for &(c, _) in d.added.iter() { marks.insert(c, '+'); }
+    for &(c, _) in d.removed.iter() { marks.insert(c, '-'); }
+    for &(c, _, _) in d.changed.iter() { marks.insert(c, '~'); }
+
+    let all = a.keys().chain(b.keys());
+    let (mut x0, mut x1, mut z0, mut z1) = (i32::MAX, i32::MIN, i32::MAX, i32::MIN);
+    for &(x, _, z) in all {
+        x0 = x0.min(x); x1 = x1.max(x);
+        z0 = z0.min(z); z1 = z1.max(z);
+    }
+
+    let layers: BTreeSet<i32> = marks.keys().map(|c| c.1).collect();
+    let mut out = String::new();
+    for layer in layers {
+        let _ = writeln!(out, "capa {:02}:", layer);
+        for z in z0..=z1 {
+            let mut line = String::new();
+            for x in x0..=x1 {
+                let cell = (x, layer, z);
+                let ch = match marks.get(&cell) {
+                    Some(&m) => m,
+                    None if a.contains_key(&cell) => '.',
+                    None => ' ',
+                };
+                line.push(ch);
+            }
+            let _ = writeln!(out, "  {}", line.trim_end());
+        }
+    }
+    out
+}
+
 pub fn default_params(cube_size: Vector3) -> SceneParams {
     SceneParams {
         cube_size,
@@ -123,3 +542,180 @@ pub fn default_params(cube_size: Vector3) -> SceneParams {
         solid_chars: vec!['X', '_', '-'],        // << incluye slabs por defecto
     }
 }
+
+/// Ocupación por celdas de la grilla del builder (colisiones a nivel de celda).
+/// Una celda cuenta como ocupada si el AABB de algún objeto la toca (slabs incluidos).
+pub struct SceneIndex {
+    origin: Vector3,
+    cell_size: Vector3,
+    occupied: HashSet<(i32, i32, i32)>,
+    min_iy: i32,
+}
+
+impl SceneIndex {
+    pub fn build(objects: &[Box<dyn RayIntersect>], cell_size: Vector3, origin: Vector3) -> Self {
+        let mut index = SceneIndex { origin, cell_size, occupied: HashSet::new(), min_iy: 0 };
+        let eps = 1e-3;
+        let mut min_iy = i32::MAX;
+        for obj in objects.iter() {
+            let (mn, mx) = obj.solid_aabb();
+            let (x0, y0, z0) = index.cell_of(mn + Vector3::new(eps, eps, eps));
+            let (x1, y1, z1) = index.cell_of(mx - Vector3::new(eps, eps, eps));
+            for iy in y0..=y1 {
+                for iz in z0..=z1 {
+                    for ix in x0..=x1 {
+                        index.occupied.insert((ix, iy, iz));
+                    }
+                }
+            }
+            min_iy = min_iy.min(y0);
+        }
+        index.min_iy = if min_iy == i32::MAX { 0 } else { min_iy };
+        index
+    }
+
+    #[inline]
+    pub fn cell_size(&self) -> Vector3 { self.cell_size }
+
+    #[inline]
+    pub fn cell_of(&self, p: Vector3) -> (i32, i32, i32) {
+        let rel = p - self.origin;
+        (
+            (rel.x / self.cell_size.x).floor() as i32,
+            (rel.y / self.cell_size.y).floor() as i32,
+            (rel.z / self.cell_size.z).floor() as i32,
+        )
+    }
+
+    #[inline]
+    pub fn is_occupied(&self, cell: (i32, i32, i32)) -> bool {
+        self.occupied.contains(&cell)
+    }
+
+    /// Cota superior (y) de las celdas de la fila `iy`.
+    #[inline]
+    fn cell_top(&self, iy: i32) -> f32 {
+        self.origin.y + (iy + 1) as f32 * self.cell_size.y
+    }
+
+    /// ¿Alguna celda ocupada toca la caja [min, max]?
+    pub fn box_occupied(&self, min: Vector3, max: Vector3) -> bool {
+        let (x0, y0, z0) = self.cell_of(min);
+        let (x1, y1, z1) = self.cell_of(max);
+        for iy in y0..=y1 {
+            for iz in z0..=z1 {
+                for ix in x0..=x1 {
+                    if self.is_occupied((ix, iy, iz)) { return true; }
+                }
+            }
+        }
+        false
+    }
+
+    /// Tope de la celda ocupada más alta bajo la huella (x±radius, z±radius) que no supere `feet.y`.
+    pub fn ground_below(&self, feet: Vector3, radius: f32) -> Option<f32> {
+        let eps = 1e-3;
+        let (x0, _, z0) = self.cell_of(Vector3::new(feet.x - radius + eps, feet.y, feet.z - radius + eps));
+        let (x1, _, z1) = self.cell_of(Vector3::new(feet.x + radius - eps, feet.y, feet.z + radius - eps));
+        let iy_max = ((feet.y + eps - self.origin.y) / self.cell_size.y).floor() as i32 - 1;
+        let mut iy = iy_max;
+        while iy >= self.min_iy {
+            for iz in z0..=z1 {
+                for ix in x0..=x1 {
+                    if self.is_occupied((ix, iy, iz)) { return Some(self.cell_top(iy)); }
+                }
+            }
+            iy -= 1;
+        }
+        None
+    }
+
+    /// Tope de la celda ocupada más alta de la columna que contiene (x, z).
+    pub fn column_top(&self, x: f32, z: f32) -> Option<f32> {
+        let (ix, _, iz) = self.cell_of(Vector3::new(x, 0.0, z));
+        self.occupied
+            .iter()
+            .filter(|c| c.0 == ix && c.2 == iz)
+            .map(|c| c.1)
+            .max()
+            .map(|iy| self.cell_top(iy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_cmp_orders_numbers_by_value() {
+        let mut names = vec!["layer10.txt", "layer2.txt", "layer1.txt", "layer02.txt", "layer001.txt", "base.txt"];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        // a igual valor el desempate es lexicográfico, así el orden no depende del listado
+        assert_eq!(names, ["base.txt", "layer001.txt", "layer1.txt", "layer02.txt", "layer2.txt", "layer10.txt"]);
+        assert_eq!(natural_cmp("layer9", "layer10"), Ordering::Less);
+        assert_eq!(natural_cmp("layer010", "layer9"), Ordering::Greater);
+        assert_eq!(natural_cmp("a", "a"), Ordering::Equal);
+    }
+
+    #[test]
+    fn layer_files_bottom_to_top() {
+        let dir = std::env::temp_dir().join(format!("diorama_layers_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for name in ["layer10.txt", "layer2.txt", "layer1.txt", "notas.md"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let files = layer_files(dir.to_str().unwrap()).unwrap();
+        let names: Vec<_> = files.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        fs::remove_dir_all(&dir).unwrap();
+        // el índice es la capa Y: layer1 abajo, layer10 arriba
+        assert_eq!(names, ["layer1.txt", "layer2.txt", "layer10.txt"]);
+    }
+
+    #[test]
+    fn manifest_fixes_layer_order() {
+        let dir = std::env::temp_dir().join(format!("diorama_manifest_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(SCENE_MANIFEST), "{ \"layers\": [\"piso.txt\",\n  \"muros.txt\"] }").unwrap();
+        let files = layer_files(dir.to_str().unwrap()).unwrap();
+        assert_eq!(files, [dir.join("piso.txt"), dir.join("muros.txt")]);
+
+        // sin "layers" se descubren los .txt; un nombre que no es string es error
+        fs::write(dir.join(SCENE_MANIFEST), "{ \"autor\": \"yo\" }").unwrap();
+        assert!(layer_files(dir.to_str().unwrap()).unwrap().is_empty());
+        fs::write(dir.join(SCENE_MANIFEST), "{ \"layers\": [1] }").unwrap();
+        let err = layer_files(dir.to_str().unwrap()).unwrap_err();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn palette_variants_round_trip() {
+        use std::sync::Arc;
+        use raylib::prelude::Color;
+        use crate::palette::CubeTemplate;
+        use crate::texture::Texture;
+
+        let base = CubeTemplate::with_same_texture(Material::black(), Arc::new(Texture::from_pixels(1, 1, vec![Color::WHITE])));
+        let mut palette = Palette::new();
+        palette.set('T', base.clone());
+        let path = std::env::temp_dir().join(format!("diorama_variants_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        fs::write(path, r#"{
+            "palette": { "t": { "base": "T", "faces": { "+y": { "albedo": [0.25, 0.1, 0.65, 0], "specular": 250, "refractive_index": 1.31 } } } },
+            "blocks": [ [0, 0, 0, "t"], [1, 0, 0, "T"] ]
+        }"#).unwrap();
+        let (params, cells) = load_json(path, &mut palette, default_params(Vector3::one())).unwrap();
+        let tpl = palette.get('t').unwrap();
+        let top = tpl.face_textures[2].as_ref().unwrap();
+        assert_eq!((top.specular_override, top.refractive_index_override), (Some(250.0), Some(1.31)));
+        assert!(tpl.face_textures[0].as_ref().unwrap().albedo_override.is_none());
+
+        save_json(path, &cells, &params, &palette).unwrap();
+        let mut fresh = Palette::new();
+        fresh.set('T', base);
+        let (_, reloaded) = load_json(path, &mut fresh, default_params(Vector3::one())).unwrap();
+        fs::remove_file(path).unwrap();
+        assert_eq!(reloaded, cells);
+        assert_eq!(fresh.variant('t'), palette.variant('t'));
+    }
+}
@@ -0,0 +1,500 @@
+// script.rs
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use crate::scene::Cell;
+
+/// Intérprete mínimo para construir por script (una instrucción por línea, `#` comenta):
+///
+/// ```text
+/// let i = 0                 # variable entera
+/// i = i + 1                 # reasignación
+/// repeat 8 { ... }          # bloque repetido N veces
+/// /set (x+i) y (z) P        # coloca un bloque (reemplaza lo que haya)
+/// /fill x1 y1 z1 x2 y2 z2 L # caja inclusiva
+/// /run otro.txt             # incluye otro script (relativo a este)
+/// ```
+///
+/// Las coordenadas son celdas (columna, capa, fila) como las de `assets/scene`. Los argumentos
+/// de los comandos son átomos: número, variable, `rand(a,b)` (inclusivo) o `( expr )`.
+/// El script no toca la escena: devuelve la lista de ediciones para aplicarlas de una vez.
+
+/// Posición en el fuente (1-based).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Pos {
+    pub line: usize,
+    pub col: usize,
+}
+
+#[derive(Debug)]
+pub struct ScriptError {
+    pub file: PathBuf,
+    pub pos: Pos,
+    pub msg: String,
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: línea {}, columna {}: {}", self.file.display(), self.pos.line, self.pos.col, self.msg)
+    }
+}
+
+/// Límites de ejecución: cada sentencia y cada celda de `/fill` gasta una instrucción.
+#[derive(Clone, Copy, Debug)]
+pub struct ScriptConfig {
+    pub budget: u64,
+    pub seed: u64,
+    /// Profundidad máxima de `/run` anidados (corta inclusiones recursivas).
+    pub max_include_depth: u32,
+}
+
+impl Default for ScriptConfig {
+    fn default() -> Self {
+        ScriptConfig { budget: 100_000, seed: 0x5EED, max_include_depth: 8 }
+    }
+}
+
+// ——— Tokens ———
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Num(i64),
+    Ident(String),
+    /// `/set`, `/fill`, `/run`
+    Command(String),
+    /// Resto crudo de la línea (argumento de `/run`).
+    Path(String),
+    Sym(char),
+    Newline,
+}
+
+fn tokenize(src: &str) -> Result<Vec<(Tok, Pos)>, (Pos, String)> {
+    let mut out = Vec::new();
+    for (ln, raw) in src.lines().enumerate() {
+        let line = raw.split('#').next().unwrap_or("");
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let pos = Pos { line: ln + 1, col: i + 1 };
+            if c.is_whitespace() {
+                i += 1;
+            } else if c.is_ascii_digit() {
+                let start = i;
+                while i < chars.len() && chars[i].is_ascii_digit() { i += 1; }
+                let text: String = chars[start..i].iter().collect();
+                let n = text.parse().map_err(|_| (pos, format!("número fuera de rango: {}", text)))?;
+                out.push((Tok::Num(n), pos));
+            } else if c.is_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') { i += 1; }
+                out.push((Tok::Ident(chars[start..i].iter().collect()), pos));
+            } else if c == '/' && out.last().is_none_or(|(t, _)| *t == Tok::Newline || *t == Tok::Sym('{')) {
+                // `/` al inicio de sentencia es comando; en otro lado es división
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i].is_alphabetic() { i += 1; }
+                let name: String = chars[start..i].iter().collect();
+                if name.is_empty() { return Err((pos, "falta el nombre del comando".into())); }
+                let is_run = name == "run";
+                out.push((Tok::Command(name), pos));
+                if is_run {
+                    let rest: String = chars[i..].iter().collect();
+                    let col = i + 1 + (rest.len() - rest.trim_start().len());
+                    out.push((Tok::Path(rest.trim().to_string()), Pos { line: ln + 1, col }));
+                    i = chars.len();
+                }
+            } else if "+-*/%(){}=,".contains(c) {
+                out.push((Tok::Sym(c), pos));
+                i += 1;
+            } else {
+                return Err((pos, format!("carácter inesperado '{}'", c)));
+            }
+        }
+        out.push((Tok::Newline, Pos { line: ln + 1, col: chars.len() + 1 }));
+    }
+    Ok(out)
+}
+
+// ——— AST ———
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Num(i64),
+    Var(String, Pos),
+    Neg(Box<Expr>),
+    Bin(char, Box<Expr>, Box<Expr>, Pos),
+    Rand(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Debug)]
+enum Stmt {
+    Let(String, Expr, Pos),
+    Assign(String, Expr, Pos),
+    Repeat(Expr, Vec<Stmt>, Pos),
+    Set([Expr; 3], char, Pos),
+    Fill([Expr; 6], char, Pos),
+    Run(String, Pos),
+}
+
+impl Stmt {
+    fn pos(&self) -> Pos {
+        match self {
+            Stmt::Let(_, _, p) | Stmt::Assign(_, _, p) | Stmt::Repeat(_, _, p)
+            | Stmt::Set(_, _, p) | Stmt::Fill(_, _, p) | Stmt::Run(_, p) => *p,
+        }
+    }
+}
+
+struct Parser {
+    toks: Vec<(Tok, Pos)>,
+    i: usize,
+}
+
+type ParseResult<T> = Result<T, (Pos, String)>;
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> { self.toks.get(self.i).map(|(t, _)| t) }
+
+    fn pos(&self) -> Pos {
+        self.toks.get(self.i).or(self.toks.last()).map(|(_, p)| *p).unwrap_or(Pos { line: 1, col: 1 })
+    }
+
+    fn next(&mut self) -> Option<(Tok, Pos)> {
+        let t = self.toks.get(self.i).cloned();
+        self.i += 1;
+        t
+    }
+
+    fn expect_sym(&mut self, c: char) -> ParseResult<()> {
+        let pos = self.pos();
+        match self.next() {
+            Some((Tok::Sym(s), _)) if s == c => Ok(()),
+            _ => Err((pos, format!("se esperaba '{}'", c))),
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek() == Some(&Tok::Newline) { self.i += 1; }
+    }
+
+    /// Sentencias hasta fin de archivo o hasta `}` (si `in_block`).
+    fn block(&mut self, in_block: bool) -> ParseResult<Vec<Stmt>> {
+        let mut stmts = Vec::new();
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                None if in_block => return Err((self.pos(), "falta '}'".into())),
+                None => return Ok(stmts),
+                Some(Tok::Sym('}')) if in_block => { self.i += 1; return Ok(stmts); }
+                _ => {}
+            }
+            stmts.push(self.statement()?);
+            // Fin de sentencia: salto de línea, `}` o fin de archivo
+            match self.peek() {
+                None | Some(Tok::Newline) | Some(Tok::Sym('}')) => {}
+                _ => return Err((self.pos(), "se esperaba fin de línea".into())),
+            }
+        }
+    }
+
+    fn statement(&mut self) -> ParseResult<Stmt> {
+        let pos = self.pos();
+        match self.next() {
+            Some((Tok::Ident(kw), _)) if kw == "let" => {
+                let name = self.ident()?;
+                self.expect_sym('=')?;
+                Ok(Stmt::Let(name, self.expr()?, pos))
+            }
+            Some((Tok::Ident(kw), _)) if kw == "repeat" => {
+                let count = self.expr()?;
+                self.expect_sym('{')?;
+                Ok(Stmt::Repeat(count, self.block(true)?, pos))
+            }
+            Some((Tok::Ident(name), _)) => {
+                self.expect_sym('=')?;
+                Ok(Stmt::Assign(name, self.expr()?, pos))
+            }
+            Some((Tok::Command(cmd), _)) => match cmd.as_str() {
+                "set" => {
+                    let c = [self.atom()?, self.atom()?, self.atom()?];
+                    Ok(Stmt::Set(c, self.block_char()?, pos))
+                }
+                "fill" => {
+                    let c = [self.atom()?, self.atom()?, self.atom()?, self.atom()?, self.atom()?, self.atom()?];
+                    Ok(Stmt::Fill(c, self.block_char()?, pos))
+                }
+                "run" => match self.next() {
+                    Some((Tok::Path(p), _)) if !p.is_empty() => Ok(Stmt::Run(p, pos)),
+                    _ => Err((pos, "/run necesita una ruta".into())),
+                },
+                other => Err((pos, format!("comando desconocido /{}", other))),
+            },
+            _ => Err((pos, "se esperaba una sentencia".into())),
+        }
+    }
+
+    fn ident(&mut self) -> ParseResult<String> {
+        let pos = self.pos();
+        match self.next() {
+            Some((Tok::Ident(n), _)) if n != "let" && n != "repeat" && n != "rand" => Ok(n),
+            _ => Err((pos, "se esperaba un nombre de variable".into())),
+        }
+    }
+
+    /// Carácter de bloque de la paleta (`P`, `l`, `_`, `-`, …): un solo carácter.
+    fn block_char(&mut self) -> ParseResult<char> {
+        let pos = self.pos();
+        let ch = match self.next() {
+            Some((Tok::Ident(s), _)) if s.chars().count() == 1 => s.chars().next(),
+            Some((Tok::Sym(c), _)) if c != '{' && c != '}' => Some(c),
+            _ => None,
+        };
+        ch.ok_or((pos, "se esperaba un carácter de bloque".into()))
+    }
+
+    fn expr(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.term()?;
+        while let Some(Tok::Sym(op @ ('+' | '-'))) = self.peek().cloned() {
+            let pos = self.pos();
+            self.i += 1;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.term()?), pos);
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> ParseResult<Expr> {
+        let mut lhs = self.atom()?;
+        while let Some(Tok::Sym(op @ ('*' | '/' | '%'))) = self.peek().cloned() {
+            let pos = self.pos();
+            self.i += 1;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(self.atom()?), pos);
+        }
+        Ok(lhs)
+    }
+
+    fn atom(&mut self) -> ParseResult<Expr> {
+        let pos = self.pos();
+        match self.next() {
+            Some((Tok::Num(n), _)) => Ok(Expr::Num(n)),
+            Some((Tok::Sym('-'), _)) => Ok(Expr::Neg(Box::new(self.atom()?))),
+            Some((Tok::Sym('('), _)) => {
+                let e = self.expr()?;
+                self.expect_sym(')')?;
+                Ok(e)
+            }
+            Some((Tok::Ident(n), _)) if n == "rand" => {
+                self.expect_sym('(')?;
+                let a = self.expr()?;
+                self.expect_sym(',')?;
+                let b = self.expr()?;
+                self.expect_sym(')')?;
+                Ok(Expr::Rand(Box::new(a), Box::new(b)))
+            }
+            Some((Tok::Ident(n), _)) if n != "let" && n != "repeat" => Ok(Expr::Var(n, pos)),
+            _ => Err((pos, "se esperaba un número, variable o '('".into())),
+        }
+    }
+}
+
+// ——— Ejecución ———
+
+struct Interp {
+    vars: HashMap<String, i64>,
+    rng: StdRng,
+    budget: u64,
+    max_include_depth: u32,
+    edits: Vec<(Cell, char)>,
+}
+
+impl Interp {
+    fn spend(&mut self, n: u64, pos: Pos) -> ParseResult<()> {
+        if n > self.budget {
+            self.budget = 0;
+            return Err((pos, "se agotó el presupuesto de instrucciones".into()));
+        }
+        self.budget -= n;
+        Ok(())
+    }
+
+    fn eval(&mut self, e: &Expr) -> ParseResult<i64> {
+        Ok(match e {
+            Expr::Num(n) => *n,
+            Expr::Var(name, pos) => *self.vars.get(name).ok_or((*pos, format!("variable no definida '{}'", name)))?,
+            Expr::Neg(a) => self.eval(a)?.wrapping_neg(),
+            Expr::Bin(op, a, b, pos) => {
+                let (a, b) = (self.eval(a)?, self.eval(b)?);
+                match op {
+                    '+' => a.wrapping_add(b),
+                    '-' => a.wrapping_sub(b),
+                    '*' => a.wrapping_mul(b),
+                    _ if b == 0 => return Err((*pos, "división por cero".into())),
+                    '/' => a.wrapping_div_euclid(b),
+                    _ => a.wrapping_rem_euclid(b),
+                }
+            }
+            Expr::Rand(a, b) => {
+                let (a, b) = (self.eval(a)?, self.eval(b)?);
+                self.rng.random_range(a.min(b)..=a.max(b))
+            }
+        })
+    }
+
+    fn eval_cell(&mut self, c: &[Expr], pos: Pos) -> ParseResult<Cell> {
+        let mut v = [0i32; 3];
+        for (out, e) in v.iter_mut().zip(c) {
+            let n = self.eval(e)?;
+            *out = i32::try_from(n).map_err(|_| (pos, format!("coordenada fuera de rango: {}", n)))?;
+        }
+        Ok((v[0], v[1], v[2]))
+    }
+
+    fn exec(&mut self, stmts: &[Stmt], file: &Path, depth: u32) -> Result<(), ScriptError> {
+        for s in stmts.iter() {
+            let wrap = |(pos, msg): (Pos, String)| ScriptError { file: file.to_path_buf(), pos, msg };
+            self.spend(1, s.pos()).map_err(wrap)?;
+            match s {
+                Stmt::Let(name, e, _) => {
+                    let v = self.eval(e).map_err(wrap)?;
+                    self.vars.insert(name.clone(), v);
+                }
+                Stmt::Assign(name, e, pos) => {
+                    if !self.vars.contains_key(name) {
+                        return Err(wrap((*pos, format!("variable no definida '{}' (usar let)", name))));
+                    }
+                    let v = self.eval(e).map_err(wrap)?;
+                    self.vars.insert(name.clone(), v);
+                }
+                Stmt::Repeat(count, body, _) => {
+                    let n = self.eval(count).map_err(wrap)?;
+                    for _ in 0..n.max(0) { self.exec(body, file, depth)?; }
+                }
+                Stmt::Set(c, ch, pos) => {
+                    let cell = self.eval_cell(c, *pos).map_err(wrap)?;
+                    self.edits.push((cell, *ch));
+                }
+                Stmt::Fill(c, ch, pos) => {
+                    let a = self.eval_cell(&c[0..3], *pos).map_err(wrap)?;
+                    let b = self.eval_cell(&c[3..6], *pos).map_err(wrap)?;
+                    // en i64 la diferencia no desborda; el producto satura y `spend` lo rechaza
+                    let span = |p: i32, q: i32| (p as i64 - q as i64).unsigned_abs() + 1;
+                    let cells = span(a.0, b.0).saturating_mul(span(a.1, b.1)).saturating_mul(span(a.2, b.2));
+                    self.spend(cells, *pos).map_err(wrap)?;
+                    for y in a.1.min(b.1)..=a.1.max(b.1) {
+                        for z in a.2.min(b.2)..=a.2.max(b.2) {
+                            for x in a.0.min(b.0)..=a.0.max(b.0) {
+                                self.edits.push(((x, y, z), *ch));
+                            }
+                        }
+                    }
+                }
+                Stmt::Run(path, pos) => {
+                    if depth >= self.max_include_depth {
+                        return Err(wrap((*pos, "demasiados /run anidados".into())));
+                    }
+                    let base = file.parent().unwrap_or(Path::new("."));
+                    self.run_file(&base.join(path), depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn run_file(&mut self, path: &Path, depth: u32) -> Result<(), ScriptError> {
+        let err = |pos: Pos, msg: String| ScriptError { file: path.to_path_buf(), pos, msg };
+        let src = fs::read_to_string(path).map_err(|e| err(Pos { line: 0, col: 0 }, e.to_string()))?;
+        let stmts = parse(&src).map_err(|(pos, msg)| err(pos, msg))?;
+        self.exec(&stmts, path, depth)
+    }
+}
+
+fn parse(src: &str) -> ParseResult<Vec<Stmt>> {
+    let toks = tokenize(src)?;
+    Parser { toks, i: 0 }.block(false)
+}
+
+/// Ejecuta un script y devuelve las ediciones en orden (una celda puede repetirse: gana la última).
+/// Con la misma semilla el resultado es idéntico.
+pub fn run_file(path: &str, config: ScriptConfig) -> Result<Vec<(Cell, char)>, ScriptError> {
+    let mut interp = Interp {
+        vars: HashMap::new(),
+        rng: StdRng::seed_from_u64(config.seed),
+        budget: config.budget,
+        max_include_depth: config.max_include_depth,
+        edits: Vec::new(),
+    };
+    interp.run_file(Path::new(path), 0)?;
+    Ok(interp.edits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> String {
+        format!("{}/assets/scripts/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    /// Escribe un script temporal (nombre único por proceso) y devuelve su ruta.
+    fn temp_script(name: &str, src: &str) -> String {
+        let path = std::env::temp_dir().join(format!("diorama_{}_{}.txt", std::process::id(), name));
+        fs::write(&path, src).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn spiral_staircase_edits() {
+        let edits = run_file(&asset("spiral_staircase.txt"), ScriptConfig::default()).unwrap();
+        // 3 vueltas × 8 escalones, columna de 25 troncos (capas 1..=25) y descanso de 3×3
+        assert_eq!(edits.len(), 24 + 25 + 9);
+        assert_eq!(edits.iter().filter(|(_, ch)| *ch == 'L').count(), 25);
+        assert_eq!(edits[0], ((15, 1, -1), 'P'));
+    }
+
+    #[test]
+    fn forest_edits_are_deterministic() {
+        let a = run_file(&asset("forest.txt"), ScriptConfig::default()).unwrap();
+        let b = run_file(&asset("forest.txt"), ScriptConfig::default()).unwrap();
+        assert_eq!(a, b);
+        // suelo 23×23 y 14 árboles de h + 20 celdas cada uno (tronco h, copa 18, punta y tronco)
+        let trees = a.len() - 23 * 23;
+        assert!((14 * 23..=14 * 25).contains(&trees), "{} ediciones de árboles", trees);
+        assert_eq!(a.iter().filter(|(_, ch)| *ch == 'X').count(), 23 * 23);
+    }
+
+    #[test]
+    fn parse_error_position() {
+        let err = run_file(&temp_script("parse", "let a = 1\n/set 1 2 3 P extra\n"), ScriptConfig::default()).unwrap_err();
+        assert_eq!(err.pos, Pos { line: 2, col: 14 });
+        assert_eq!(err.msg, "se esperaba fin de línea");
+
+        let err = run_file(&temp_script("token", "\n\nlet x = 3 $\n"), ScriptConfig::default()).unwrap_err();
+        assert_eq!(err.pos, Pos { line: 3, col: 11 });
+    }
+
+    #[test]
+    fn budget_cutoff() {
+        let path = temp_script("budget", "/set 0 0 0 P\n/fill 0 0 0 9 9 9 X\n");
+        let config = ScriptConfig { budget: 500, ..ScriptConfig::default() };
+        let err = run_file(&path, config).unwrap_err();
+        assert_eq!(err.pos, Pos { line: 2, col: 1 });
+        assert!(run_file(&path, ScriptConfig { budget: 1002, ..config }).is_ok());
+        assert!(run_file(&path, ScriptConfig { budget: 1001, ..config }).is_err());
+    }
+
+    #[test]
+    fn huge_fill_fails_instead_of_overflowing() {
+        let src = "/fill -2000000000 -2000000000 -2000000000 2000000000 2000000000 2000000000 X\n";
+        let err = run_file(&temp_script("huge", src), ScriptConfig::default()).unwrap_err();
+        assert_eq!(err.pos, Pos { line: 1, col: 1 });
+
+        let err = run_file(&temp_script("range", "let x = 1\n/set (x * 3000000000) 0 0 P\n"), ScriptConfig::default()).unwrap_err();
+        assert_eq!(err.pos, Pos { line: 2, col: 1 });
+        assert!(err.msg.contains("fuera de rango"));
+    }
+}
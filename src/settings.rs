@@ -0,0 +1,670 @@
+// settings.rs
+use raylib::prelude::Vector3;
+
+use crate::gamma::Gamma;
+use crate::light::{Light, LightKind};
+use crate::material::Material;
+use crate::post::PostSettings;
+
+/// Umbrales de nivel de detalle (LOD) según la distancia del hit primario.
+/// Desactivado por defecto.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LodSettings {
+    pub enabled: bool,
+    /// Más allá de esta distancia no se calculan especular ni glints.
+    pub no_specular_dist: f32,
+    /// Más allá: color promedio por cara en vez de muestrear la textura (sin coverage por texel).
+    pub flat_texture_dist: f32,
+    /// Más allá: no se lanzan rayos de sombra; se usa `shadow_estimate`.
+    pub no_shadow_dist: f32,
+    /// Fracción de luz que llega cuando se omite el rayo de sombra.
+    pub shadow_estimate: f32,
+    /// Variación relativa (0..1) por pixel de los umbrales, para que no se vean costuras.
+    pub dither: f32,
+}
+
+impl Default for LodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            no_specular_dist: 30.0,
+            flat_texture_dist: 60.0,
+            no_shadow_dist: 90.0,
+            shadow_estimate: 0.8,
+            dither: 0.15,
+        }
+    }
+}
+
+/// AA adaptativo: tras la pasada base se re-muestrean solo los pixeles de borde.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptiveAaSettings {
+    pub enabled: bool,
+    /// Diferencia máxima por canal (ya con tone mapping) contra un vecino para considerarlo borde.
+    pub threshold: f32,
+    /// Rayos extra con jitter por pixel de borde.
+    pub extra_samples: u32,
+    /// Debug: tiñe de magenta los pixeles refinados.
+    pub show_refined: bool,
+}
+
+impl Default for AdaptiveAaSettings {
+    fn default() -> Self {
+        Self { enabled: false, threshold: 0.1, extra_samples: 8, show_refined: false }
+    }
+}
+
+/// Calidad del pase de glints especulares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GlintQuality {
+    Off,
+    /// Solo en el hit primario (los rebotes casi nunca lo necesitan).
+    Primary,
+    Full,
+}
+
+impl GlintQuality {
+    pub fn next(self) -> Self {
+        match self {
+            GlintQuality::Off => GlintQuality::Primary,
+            GlintQuality::Primary => GlintQuality::Full,
+            GlintQuality::Full => GlintQuality::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GlintQuality::Off => "Off",
+            GlintQuality::Primary => "Primario",
+            GlintQuality::Full => "Completo",
+        }
+    }
+}
+
+/// Cuándo se calcula el glint (y su rayo de oclusión).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlintSettings {
+    pub quality: GlintQuality,
+    /// Reflectividad (`albedo[2]`) mínima del material; por debajo no hay glint.
+    pub min_reflectivity: f32,
+}
+
+impl Default for GlintSettings {
+    fn default() -> Self {
+        Self { quality: GlintQuality::Primary, min_reflectivity: 0.05 }
+    }
+}
+
+impl GlintSettings {
+    #[inline]
+    pub fn active(&self, depth: u32, reflectivity: f32) -> bool {
+        let by_depth = match self.quality {
+            GlintQuality::Off => false,
+            GlintQuality::Primary => depth == 0,
+            GlintQuality::Full => true,
+        };
+        by_depth && reflectivity >= self.min_reflectivity
+    }
+}
+
+/// Relleno ambiente tomado del cielo (una muestra en la dirección de la normal).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IblSettings {
+    /// Apagado: ambiente constante (`diffuse * 0.15`).
+    pub enabled: bool,
+    pub intensity: f32,
+}
+
+impl Default for IblSettings {
+    fn default() -> Self {
+        Self { enabled: false, intensity: 0.3 }
+    }
+}
+
+/// Curva de la niebla a partir de `start`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FogMode {
+    /// Llega a niebla total en `end`.
+    Linear { end: f32 },
+    /// 1 - e^(-density · d).
+    Exponential { density: f32 },
+}
+
+/// Niebla por distancia: el color sombreado se mezcla hacia `color`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FogSettings {
+    pub enabled: bool,
+    /// Color autorado en sRGB.
+    pub color: Vector3,
+    /// Usar el fondo en la dirección del rayo como color de la niebla (en vez de `color`):
+    /// lo lejano se funde con el cielo que tiene detrás.
+    pub from_sky: bool,
+    pub mode: FogMode,
+    /// Sin niebla antes de esta distancia.
+    pub start: f32,
+    /// Los rebotes usan la distancia acumulada desde el ojo (si no, solo rayos primarios).
+    pub secondary: bool,
+}
+
+impl Default for FogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: Vector3::new(0.62, 0.68, 0.78),
+            from_sky: false,
+            mode: FogMode::Exponential { density: 0.03 },
+            start: 15.0,
+            secondary: true,
+        }
+    }
+}
+
+impl FogSettings {
+    /// Fracción de niebla (0..1) a `distance` del ojo.
+    #[inline]
+    pub fn amount(&self, distance: f32) -> f32 {
+        let d = (distance - self.start).max(0.0);
+        match self.mode {
+            FogMode::Linear { end } => (d / (end - self.start).max(1e-3)).clamp(0.0, 1.0),
+            FogMode::Exponential { density } => 1.0 - (-density * d).exp(),
+        }
+    }
+
+    /// Mezcla un hit; `travelled` es lo recorrido antes de este rayo (0 en el primario).
+    /// `sky` da el fondo en la dirección del rayo (lineal); solo se evalúa con `from_sky`.
+    #[inline]
+    pub fn apply(&self, color: Vector3, travelled: f32, distance: f32, depth: u32, gamma: Gamma, sky: impl FnOnce() -> Vector3) -> Vector3 {
+        if !self.enabled || (depth > 0 && !self.secondary) { return color; }
+        let k = self.amount(travelled + distance);
+        // Densidad 0 (o antes de `start`): sin costo extra ni muestreo del cielo
+        if k <= 0.0 { return color; }
+        let fog = if self.from_sky { sky() } else { gamma.decode_vec(self.color) };
+        color + (fog - color) * k
+    }
+
+    /// Rayos que no pegan: solo bruma cerca del horizonte, el cielo alto queda intacto.
+    /// Con `from_sky` el fondo ya es el color lejano: no se toca.
+    #[inline]
+    pub fn apply_sky(&self, color: Vector3, dir: Vector3, depth: u32, gamma: Gamma) -> Vector3 {
+        if !self.enabled || self.from_sky || (depth > 0 && !self.secondary) { return color; }
+        let k = (1.0 - dir.normalized().y.abs()).powf(8.0);
+        color + (gamma.decode_vec(self.color) - color) * k
+    }
+}
+
+/// Constantes del sombreado Whitted. Los valores por defecto son los de siempre (preset "suave").
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LightingParams {
+    /// Wrap del difuso: `(n·l + wrap) / (1 + wrap)`; 0 = Lambert puro, más = sombra propia más suave.
+    pub diffuse_wrap: f32,
+    /// Ambiente plano (`diffuse * ambient`) cuando el IBL está apagado.
+    pub ambient: f32,
+    /// Exponente del glint para luces puntuales/foco y para direccionales.
+    pub glint_hardness_point: f32,
+    pub glint_hardness_dir: f32,
+    pub glint_gain: f32,
+    /// Se suma a la reflectividad del material para pesar el glint.
+    pub glint_bias: f32,
+}
+
+impl Default for LightingParams {
+    fn default() -> Self { LightingPreset::Soft.params() }
+}
+
+/// Ánimos de iluminación para las teclas de debug (y escenas que quieran el suyo).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LightingPreset {
+    /// Mucho wrap y ambiente alto: casi sin sombra propia.
+    Flat,
+    /// El sombreado original.
+    Soft,
+    /// Lambert puro, ambiente bajo y glints más duros.
+    Harsh,
+}
+
+impl LightingPreset {
+    pub fn next(self) -> Self {
+        match self {
+            LightingPreset::Flat => LightingPreset::Soft,
+            LightingPreset::Soft => LightingPreset::Harsh,
+            LightingPreset::Harsh => LightingPreset::Flat,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LightingPreset::Flat => "Plana",
+            LightingPreset::Soft => "Suave",
+            LightingPreset::Harsh => "Dura",
+        }
+    }
+
+    pub fn params(self) -> LightingParams {
+        match self {
+            LightingPreset::Flat => LightingParams {
+                diffuse_wrap: 1.0, ambient: 0.35,
+                glint_hardness_point: 400.0, glint_hardness_dir: 400.0, glint_gain: 0.6, glint_bias: 0.05,
+            },
+            LightingPreset::Soft => LightingParams {
+                diffuse_wrap: 0.3, ambient: 0.15,
+                glint_hardness_point: 800.0, glint_hardness_dir: 800.0, glint_gain: 1.0, glint_bias: 0.05,
+            },
+            LightingPreset::Harsh => LightingParams {
+                diffuse_wrap: 0.0, ambient: 0.04,
+                glint_hardness_point: 1600.0, glint_hardness_dir: 1600.0, glint_gain: 1.5, glint_bias: 0.05,
+            },
+        }
+    }
+}
+
+/// Resaltado del bloque bajo el cursor. Se mezcla sobre el color ya muestreado de la cara en vez
+/// de reemplazar el material: la cobertura (recortes y ventanas de alpha) queda intacta, así una
+/// hoja o un vidrio resaltado se ven como quedarían puestos.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PreviewSettings {
+    /// Color del resaltado (lineal 0..1).
+    pub tint: Vector3,
+    /// 0 = sin resaltado, 1 = color plano del tinte.
+    pub strength: f32,
+    /// Opacidad del cubo fantasma de colocación (0 = invisible).
+    pub ghost_opacity: f32,
+    /// Debug (`--hover-tint`): además del contorno, tiñe el material del objeto apuntado
+    /// (se ve en reflejos y obliga a re-trazar al mover el cursor).
+    pub tint_hovered: bool,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self { tint: Vector3::new(0.9, 0.3, 0.3), strength: 0.55, ghost_opacity: 0.4, tint_hovered: false }
+    }
+}
+
+impl PreviewSettings {
+    /// Tiñe `m` hacia `tint`. La parte `strength` del peso de reflejo/transmisión pasa al difuso
+    /// para que el tinte también se note en espejos y vidrio, que siguen siendo translúcidos.
+    pub fn apply(&self, m: &mut Material) {
+        let k = self.strength.clamp(0.0, 1.0);
+        m.diffuse = m.diffuse + (self.tint - m.diffuse) * k;
+        let moved = (m.albedo[2] + m.albedo[3]) * k;
+        m.albedo[2] *= 1.0 - k;
+        m.albedo[3] *= 1.0 - k;
+        m.albedo[0] += moved;
+    }
+}
+
+/// Disco del sol en el cielo, en la dirección de cada luz direccional (las puntuales no dibujan).
+/// Se suma al fondo de los rayos que no pegan, así que también aparece en reflejos y refracciones.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SunSettings {
+    pub enabled: bool,
+    /// Radio angular del disco (radianes).
+    pub angular_radius: f32,
+    /// Color autorado en sRGB; se multiplica por el color de la luz.
+    pub color: Vector3,
+    /// Brillo del disco (HDR: el tone mapping lo recorta).
+    pub intensity: f32,
+    /// Radio angular del halo (caída gaussiana) y su brillo.
+    pub halo_radius: f32,
+    pub halo_intensity: f32,
+}
+
+impl Default for SunSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            angular_radius: 0.026,
+            color: Vector3::new(1.0, 0.95, 0.82),
+            intensity: 12.0,
+            halo_radius: 0.15,
+            halo_intensity: 0.35,
+        }
+    }
+}
+
+impl SunSettings {
+    /// Cambia el radio angular del disco (radianes; 0 o menos lo apaga). El halo crece con el
+    /// disco para no quedar tapado por él: al menos 3 radios.
+    pub fn set_disk_angle(&mut self, radius: f32) {
+        self.enabled = radius > 0.0;
+        self.angular_radius = radius.max(0.0);
+        self.halo_radius = self.halo_radius.max(self.angular_radius * 3.0);
+    }
+
+    /// Radiancia del sol (disco + halo) vista en `dir`, en el espacio de trabajo de `gamma`.
+    pub fn radiance(&self, dir: Vector3, lights: &[Light], gamma: Gamma) -> Vector3 {
+        if !self.enabled { return Vector3::zero(); }
+        let d = dir.normalized();
+        let mut sum = Vector3::zero();
+        for l in lights.iter().filter(|l| l.kind == LightKind::Directional) {
+            let angle = d.dot(-l.direction).clamp(-1.0, 1.0).acos();
+            if angle > self.halo_radius * 3.0 { continue; }
+            // Borde del disco suavizado en el último 15% del radio
+            let edge = ((self.angular_radius - angle) / (self.angular_radius * 0.15)).clamp(0.0, 1.0);
+            let h = angle / self.halo_radius;
+            let k = self.intensity * edge + self.halo_intensity * (-h * h).exp();
+            let tint = Vector3::new(
+                gamma.decode_u8(l.color.r), gamma.decode_u8(l.color.g), gamma.decode_u8(l.color.b),
+            );
+            sum += gamma.decode_vec(self.color) * tint * (k * l.intensity);
+        }
+        sum
+    }
+}
+
+/// Integrador de `render`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Integrator {
+    /// Phong + reflexión/refracción recursivas (por defecto).
+    Whitted,
+    /// Path tracing con luz directa + rebote difuso; converge acumulando frames.
+    PathTraced,
+}
+
+impl Integrator {
+    pub fn next(self) -> Self {
+        match self {
+            Integrator::Whitted => Integrator::PathTraced,
+            Integrator::PathTraced => Integrator::Whitted,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Integrator::Whitted => "Whitted",
+            Integrator::PathTraced => "Path tracing (GI)",
+        }
+    }
+}
+
+/// Vista de debug de `render` (tecla `` ` ``): en vez de sombrear, colorea cada pixel por el costo
+/// del recorrido de grilla de su rayo primario.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugView {
+    Off,
+    /// Celdas visitadas por el rayo primario.
+    Cells,
+    /// Referencias a objetos en esas celdas (candidatos a prueba).
+    Refs,
+}
+
+impl DebugView {
+    pub fn next(self) -> Self {
+        match self {
+            DebugView::Off => DebugView::Cells,
+            DebugView::Cells => DebugView::Refs,
+            DebugView::Refs => DebugView::Off,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DebugView::Off => "OFF",
+            DebugView::Cells => "calor: celdas por rayo",
+            DebugView::Refs => "calor: candidatos por rayo",
+        }
+    }
+
+    /// Valor de la vista que satura la rampa (rojo).
+    pub fn full_scale(self) -> u32 {
+        match self {
+            DebugView::Off => 1,
+            DebugView::Cells => 64,
+            DebugView::Refs => 32,
+        }
+    }
+}
+
+/// Rampa azul → cian → verde → amarillo → rojo para `t` en 0..1 (en el espacio de salida).
+pub fn heat_ramp(t: f32) -> Vector3 {
+    const STOPS: [(f32, f32, f32); 5] = [(0.05, 0.05, 0.45), (0.0, 0.7, 0.9), (0.1, 0.8, 0.2), (0.95, 0.9, 0.1), (0.9, 0.1, 0.05)];
+    let x = t.clamp(0.0, 1.0) * (STOPS.len() - 1) as f32;
+    let i = (x as usize).min(STOPS.len() - 2);
+    let (a, b, k) = (STOPS[i], STOPS[i + 1], x - i as f32);
+    Vector3::new(a.0 + (b.0 - a.0) * k, a.1 + (b.1 - a.1) * k, a.2 + (b.2 - a.2) * k)
+}
+
+/// Tope para `max_depth` desde el teclado.
+pub const MAX_DEPTH_LIMIT: u32 = 12;
+
+/// Colores del cielo procedural (fondo sin skybox), autorados en sRGB: degradé horizonte →
+/// medio → cenit, brillo cerca del horizonte y tinte de la bruma. La mezcla es la de
+/// `procedural_sky`; acá solo están los colores.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ProceduralSky {
+    pub name: &'static str,
+    pub horizon: Vector3,
+    pub mid: Vector3,
+    pub top: Vector3,
+    pub glow: Vector3,
+    pub haze: Vector3,
+}
+
+impl ProceduralSky {
+    pub const DAY: ProceduralSky = ProceduralSky {
+        name: "día",
+        horizon: Vector3 { x: 0.78, y: 0.86, z: 0.95 },
+        mid: Vector3 { x: 0.47, y: 0.66, z: 0.90 },
+        top: Vector3 { x: 0.22, y: 0.42, z: 0.80 },
+        glow: Vector3 { x: 1.00, y: 0.95, z: 0.80 },
+        haze: Vector3 { x: 1.0, y: 1.0, z: 1.0 },
+    };
+    /// El de siempre: violeta de atardecer.
+    pub const DUSK: ProceduralSky = ProceduralSky {
+        name: "atardecer",
+        horizon: Vector3 { x: 0.08, y: 0.04, z: 0.12 },
+        mid: Vector3 { x: 0.03, y: 0.015, z: 0.06 },
+        top: Vector3 { x: 0.015, y: 0.010, z: 0.030 },
+        glow: Vector3 { x: 0.20, y: 0.05, z: 0.15 },
+        haze: Vector3 { x: 0.6, y: 0.3, z: 1.0 },
+    };
+    pub const NIGHT: ProceduralSky = ProceduralSky {
+        name: "noche",
+        horizon: Vector3 { x: 0.035, y: 0.045, z: 0.08 },
+        mid: Vector3 { x: 0.015, y: 0.02, z: 0.045 },
+        top: Vector3 { x: 0.004, y: 0.006, z: 0.018 },
+        glow: Vector3 { x: 0.06, y: 0.09, z: 0.16 },
+        haze: Vector3 { x: 0.3, y: 0.4, z: 1.0 },
+    };
+    pub const PRESETS: [ProceduralSky; 3] = [Self::DAY, Self::DUSK, Self::NIGHT];
+
+    /// El preset siguiente en `PRESETS` (uno editado a mano vuelve al primero).
+    pub fn next(&self) -> ProceduralSky {
+        let i = Self::PRESETS.iter().position(|p| p == self).map_or(0, |i| i + 1);
+        Self::PRESETS[i % Self::PRESETS.len()]
+    }
+
+    pub fn by_name(name: &str) -> Option<ProceduralSky> {
+        Self::PRESETS.iter().copied().find(|p| p.name == name || (name == "dia" && p.name == "día"))
+    }
+}
+
+impl Default for ProceduralSky {
+    fn default() -> Self { Self::DUSK }
+}
+
+/// Parámetros de render que viajan a `render`/`cast_ray`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSettings {
+    /// Rebotes máximos (reflexión/refracción/glint). 0 = solo rayos primarios.
+    pub max_depth: u32,
+    /// Trabajar en lineal (texturas/skybox decodificados de sRGB) y codificar a sRGB al escribir.
+    pub gamma_correct: bool,
+    /// Exposición + tone mapping del color final.
+    pub post: PostSettings,
+    /// Muestras por pixel (SSAA en grilla n×n; se usa el cuadrado perfecto más cercano por debajo).
+    pub samples_per_pixel: u32,
+    pub adaptive_aa: AdaptiveAaSettings,
+    pub lod: LodSettings,
+    pub glints: GlintSettings,
+    pub ibl: IblSettings,
+    pub integrator: Integrator,
+    pub fog: FogSettings,
+    pub sun: SunSettings,
+    pub lighting: LightingParams,
+    pub preview: PreviewSettings,
+    /// Multiplicador del fondo (skybox o procedural); el ciclo día/noche lo mueve. Blanco = tal cual.
+    pub sky_tint: Vector3,
+    /// Colores del fondo cuando no hay skybox.
+    pub procedural_sky: ProceduralSky,
+    /// Skybox con las caras originales en vez del nivel reducido (ver `Skybox::sample`).
+    pub sky_full_res: bool,
+    /// Rayos primarios en paquetes de 2×2 (`UniformGridAccel::trace_packet`); misma imagen.
+    pub packets: bool,
+    /// Whitted progresivo: con la vista quieta cada frame suma una pasada de muestras con jitter
+    /// nuevo a la acumulación (AA limpio sin pagar todas las muestras en un frame).
+    pub progressive: bool,
+    /// Tope de rayos de sombra por luz de área (se usa el menor entre este y sus `samples`).
+    pub area_shadow_samples: u32,
+    pub debug_view: DebugView,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            max_depth: 3,
+            gamma_correct: true,
+            post: PostSettings::default(),
+            samples_per_pixel: 1,
+            adaptive_aa: AdaptiveAaSettings::default(),
+            lod: LodSettings::default(),
+            glints: GlintSettings::default(),
+            ibl: IblSettings::default(),
+            integrator: Integrator::Whitted,
+            fog: FogSettings::default(),
+            sun: SunSettings::default(),
+            lighting: LightingParams::default(),
+            preview: PreviewSettings::default(),
+            sky_tint: Vector3::new(1.0, 1.0, 1.0),
+            procedural_sky: ProceduralSky::default(),
+            sky_full_res: false,
+            packets: false,
+            progressive: false,
+            area_shadow_samples: 16,
+            debug_view: DebugView::Off,
+        }
+    }
+}
+
+/// Escalas de resolución interna que recorre la tecla `Y` (1.0 = la de la ventana).
+pub const RENDER_SCALES: [f32; 4] = [1.0, 0.75, 0.5, 0.25];
+
+/// Tamaño del framebuffer interno para una ventana de `w`×`h` a escala `scale` (mínimo 1 px).
+pub fn scaled_size(w: i32, h: i32, scale: f32) -> (u32, u32) {
+    let side = |v: i32| ((v as f32 * scale).round() as u32).max(1);
+    (side(w), side(h))
+}
+
+/// Perfil de calidad inicial; la calibración de arranque elige uno midiendo la escena real.
+/// Después cada ajuste se sigue cambiando con su tecla.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QualityProfile {
+    /// Los ajustes por defecto, sin recortes.
+    Full,
+    /// Un rebote menos, LOD por distancia y hasta 4 muestras de sombra por luz de área.
+    Balanced,
+    /// Sin rebotes extra, sin glints, sin AA, LOD agresivo y sombras duras en luces de área.
+    Low,
+}
+
+impl QualityProfile {
+    /// Del mejor al peor: la calibración prueba en este orden.
+    pub const ALL: [QualityProfile; 3] = [QualityProfile::Full, QualityProfile::Balanced, QualityProfile::Low];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            QualityProfile::Full => "Completa",
+            QualityProfile::Balanced => "Balanceada",
+            QualityProfile::Low => "Baja",
+        }
+    }
+
+    /// `base` con los recortes del perfil (lo que el perfil no toca queda igual).
+    pub fn apply(self, base: RenderSettings) -> RenderSettings {
+        match self {
+            QualityProfile::Full => base,
+            QualityProfile::Balanced => RenderSettings {
+                max_depth: base.max_depth.min(2),
+                lod: LodSettings { enabled: true, ..base.lod },
+                area_shadow_samples: base.area_shadow_samples.min(4),
+                ..base
+            },
+            QualityProfile::Low => RenderSettings {
+                max_depth: base.max_depth.min(1),
+                samples_per_pixel: 1,
+                area_shadow_samples: 1,
+                adaptive_aa: AdaptiveAaSettings { enabled: false, ..base.adaptive_aa },
+                glints: GlintSettings { quality: GlintQuality::Off, ..base.glints },
+                lod: LodSettings {
+                    enabled: true,
+                    no_specular_dist: 15.0,
+                    flat_texture_dist: 30.0,
+                    no_shadow_dist: 45.0,
+                    ..base.lod
+                },
+                ..base
+            },
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Espacio de trabajo de este render (ver `Gamma`).
+    #[inline]
+    pub fn gamma(&self) -> Gamma {
+        Gamma { correct: self.gamma_correct }
+    }
+
+    /// Lado de la grilla de sub-pixeles (1 = sin AA).
+    #[inline]
+    pub fn ssaa_grid(&self) -> usize {
+        ((self.samples_per_pixel.max(1) as f32).sqrt().floor() as usize).max(1)
+    }
+}
+
+/// Estado LOD que acompaña a cada rayo: escala por pixel (dither) y distancia ya recorrida
+/// desde el ojo (los rebotes heredan la distancia del hit primario).
+#[derive(Clone, Copy)]
+pub struct LodCtx {
+    pub scale: f32,
+    pub travelled: f32,
+}
+
+#[inline]
+fn hash2(x: u32, y: u32) -> f32 {
+    let mut h = x.wrapping_mul(0x8da6_b343) ^ y.wrapping_mul(0xd816_3841);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 16;
+    (h & 0x00ff_ffff) as f32 / 16_777_216.0
+}
+
+/// Offset pseudoaleatorio estable en [0, 1)² para la muestra `k` del pixel (x, y).
+#[inline]
+pub fn jitter2(x: u32, y: u32, k: u32) -> (f32, f32) {
+    let kx = x.wrapping_mul(0x9e37_79b9).wrapping_add(k.wrapping_mul(0x27d4_eb2f));
+    (hash2(kx, y ^ 0x5bd1_e995), hash2(y.wrapping_add(k.wrapping_mul(0x1656_67b1)), kx))
+}
+
+impl LodCtx {
+    pub fn for_pixel(lod: &LodSettings, x: usize, y: usize) -> Self {
+        let jitter = (hash2(x as u32, y as u32) * 2.0 - 1.0) * lod.dither.clamp(0.0, 1.0);
+        LodCtx { scale: 1.0 + jitter, travelled: 0.0 }
+    }
+
+    /// Distancia restante (desde el origen del rayo actual) hasta el umbral; INF si LOD está apagado.
+    #[inline]
+    pub fn remaining(&self, lod: &LodSettings, threshold: f32) -> f32 {
+        if !lod.enabled { return f32::INFINITY; }
+        (threshold * self.scale - self.travelled).max(0.0)
+    }
+
+    #[inline]
+    pub fn beyond(&self, lod: &LodSettings, threshold: f32, distance: f32) -> bool {
+        lod.enabled && self.travelled + distance > threshold * self.scale
+    }
+
+    #[inline]
+    pub fn after(&self, distance: f32) -> Self {
+        LodCtx { scale: self.scale, travelled: self.travelled + distance }
+    }
+}
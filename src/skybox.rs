@@ -1,7 +1,7 @@
 use std::sync::Arc;
 use raylib::prelude::Vector3;
 
-use crate::texture::Texture;
+use crate::texture::{SampleMode, Texture, TextureCache};
 
 /// Orden y nombres de archivo requeridos en la carpeta:
 /// posx.png (Right), negx.png (Left), posy.png (Top), negy.png (Bottom), posz.png (Front), negz.png (Back)
@@ -16,31 +16,43 @@ use crate::texture::Texture;
 ///
 /// Nota: nuestras texturas se muestrean con origen **arriba-izquierda** (top-left),
 /// por lo que invertimos v: v = 1 - v_raw, para que no aparezca verticalmente volteado.
+///
+/// Dos niveles: las caras originales (`full`) y copias reducidas a `SKY_SMALL_SIZE`² (`small`)
+/// para el render interactivo, donde el detalle extra no se ve y solo cuesta caché.
 pub struct Skybox {
-    posx: Arc<Texture>,
-    negx: Arc<Texture>,
-    posy: Arc<Texture>,
-    negy: Arc<Texture>,
-    posz: Arc<Texture>,
-    negz: Arc<Texture>,
+    /// posx, negx, posy, negy, posz, negz.
+    full: [Arc<Texture>; 6],
+    small: [Arc<Texture>; 6],
 }
 
+/// Lado de las caras del nivel reducido.
+pub const SKY_SMALL_SIZE: i32 = 256;
+
 impl Skybox {
     /// Carga un skybox desde una carpeta con archivos:
     /// posx.png, negx.png, posy.png, negy.png, posz.png, negz.png
-    pub fn from_folder(folder: &str) -> Self {
-        let join = |name: &str| -> String { format!("{}/{}", folder, name) };
-        let posx = Arc::new(Texture::from_file(&join("posx.png")));
-        let negx = Arc::new(Texture::from_file(&join("negx.png")));
-        let posy = Arc::new(Texture::from_file(&join("posy.png")));
-        let negy = Arc::new(Texture::from_file(&join("negy.png")));
-        let posz = Arc::new(Texture::from_file(&join("posz.png")));
-        let negz = Arc::new(Texture::from_file(&join("negz.png")));
-        Skybox { posx, negx, posy, negy, posz, negz }
+    /// Las texturas pasan por `cache` (dos skyboxes que comparten caras no las duplican).
+    /// El nivel reducido se promedia en lineal; caras que ya son chicas se comparten tal cual.
+    pub fn from_folder(folder: &str, cache: &mut TextureCache) -> Self {
+        let full = ["posx.png", "negx.png", "posy.png", "negy.png", "posz.png", "negz.png"]
+            .map(|name| cache.get(&format!("{}/{}", folder, name)));
+        let small = full.clone().map(|tex| {
+            if tex.width() <= SKY_SMALL_SIZE && tex.height() <= SKY_SMALL_SIZE { tex }
+            else { Arc::new(tex.resized_srgb(SKY_SMALL_SIZE, SKY_SMALL_SIZE)) }
+        });
+        Skybox { full, small }
+    }
+
+    /// Memoria de cada nivel en bytes: (reducido, completo). Las caras compartidas cuentan en ambos.
+    pub fn memory_bytes(&self) -> (usize, usize) {
+        let sum = |faces: &[Arc<Texture>; 6]| faces.iter().map(|t| t.bytes()).sum();
+        (sum(&self.small), sum(&self.full))
     }
 
-    /// Devuelve el color RGB [0..1] para un rayo (dirección en mundo).
-    pub fn sample(&self, dir: Vector3) -> Vector3 {
+    /// Devuelve el color RGB [0..1] para un rayo (dirección en mundo). Por defecto se usa el nivel
+    /// reducido; `full_res` elige las caras originales (capturas, acumulación convergida).
+    pub fn sample(&self, dir: Vector3, full_res: bool) -> Vector3 {
+        let [posx, negx, posy, negy, posz, negz] = if full_res { &self.full } else { &self.small };
         let r = dir.normalized();
         let ax = r.x.abs();
         let ay = r.y.abs();
@@ -50,39 +62,39 @@ impl Skybox {
         if ax >= ay && ax >= az {
             // Cara X
             let (tex, sc, tc, ma) = if r.x > 0.0 {
-                (&self.posx, -r.z, -r.y, ax) // +X
+                (posx, -r.z, -r.y, ax) // +X
             } else {
-                (&self.negx,  r.z, -r.y, ax) // -X
+                (negx,  r.z, -r.y, ax) // -X
             };
             let u = (sc / ma + 1.0) * 0.5;
             let v_raw = (tc / ma + 1.0) * 0.5;
             let u = (sc / ma + 1.0) * 0.5;
             let v = v_raw; // invertir v por origen top-left
             let u = 1.0 - u;
-            tex.sample_clamp(u, v)
+            tex.sample_mode(u, v, SampleMode::Inset)
         } else if ay >= ax && ay >= az {
             // Cara Y
             let (tex, sc, tc, ma) = if r.y > 0.0 {
-                (&self.posy,  r.x,  r.z, ay) // +Y (top)
+                (posy,  r.x,  r.z, ay) // +Y (top)
             } else {
-                (&self.negy,  r.x, -r.z, ay) // -Y (bottom)
+                (negy,  r.x, -r.z, ay) // -Y (bottom)
             };
             let u = (sc / ma + 1.0) * 0.5;
             let v_raw = (tc / ma + 1.0) * 0.5;
             let v = 1.0 - v_raw;
-            tex.sample_clamp(u, v)
+            tex.sample_mode(u, v, SampleMode::Inset)
         } else {
             // Cara Z
             let (tex, sc, tc, ma) = if r.z > 0.0 {
-                (&self.posz,  r.x, -r.y, az) // +Z (front)
+                (posz,  r.x, -r.y, az) // +Z (front)
             } else {
-                (&self.negz, -r.x, -r.y, az) // -Z (back)
+                (negz, -r.x, -r.y, az) // -Z (back)
             };
             let u = (sc / ma + 1.0) * 0.5;
             let v_raw = (tc / ma + 1.0) * 0.5;
             let v = v_raw;
             let u = 1.0 - u;
-            tex.sample_clamp(u, v)
+            tex.sample_mode(u, v, SampleMode::Inset)
         }
     }
 }
@@ -3,19 +3,15 @@ use raylib::prelude::Vector3;
 
 use crate::material::Material;
 use crate::ray_intersect::{Intersect, RayIntersect};
-use crate::texture::Texture;
+use crate::texture::{SampleMode, Texture};
+use crate::gamma;
 use crate::palette::{FaceStyle, TexStyle};
 
 #[derive(Clone, Copy)]
 pub enum SlabHalf { Bottom, Top }
 
-#[derive(Clone, Copy)]
-pub enum Face { PosX, NegX, PosY, NegY, PosZ, NegZ }
-impl Face {
-    #[inline] fn idx(self) -> usize {
-        match self { Face::PosX=>0, Face::NegX=>1, Face::PosY=>2, Face::NegY=>3, Face::PosZ=>4, Face::NegZ=>5 }
-    }
-}
+/// Las mismas caras que `Cube` (mismo orden, mismos ejes UV).
+pub use crate::cube::Face;
 
 pub struct Slab {
     pub min: Vector3,
@@ -23,6 +19,8 @@ pub struct Slab {
     pub half: SlabHalf,
     pub material: Material,
     face_textures: [Option<FaceStyle>; 6],
+    /// Carácter de paleta de origen.
+    pub block: Option<char>,
 }
 
 impl Slab {
@@ -43,15 +41,16 @@ impl Slab {
             min, max, half,
             material,
             face_textures: [None, None, None, None, None, None],
+            block: None,
         }
     }
 
     pub fn set_face_texture(&mut self, face: Face, tex: Arc<Texture>) {
-        self.face_textures[face.idx()] = Some(FaceStyle { tex, style: TexStyle::Normal });
+        self.face_textures[face.idx()] = Some(FaceStyle::new(tex, TexStyle::Normal));
     }
 
     pub fn set_face_texture_styled(&mut self, face: Face, tex: Arc<Texture>, style: TexStyle) {
-        self.face_textures[face.idx()] = Some(FaceStyle { tex, style });
+        self.face_textures[face.idx()] = Some(FaceStyle::new(tex, style));
     }
 
     pub fn set_face_textures_from_template(&mut self, tpl: &[Option<FaceStyle>; 6]) {
@@ -68,56 +67,57 @@ fn luminance(rgb: Vector3) -> f32 {
 }
 
 /// Igual que en cube.rs, pero con control de v_tex para caras laterales (media textura).
-fn sample_with_style(tex: &Texture, u: f32, v: f32, style: &TexStyle) -> Option<(Vector3, f32)> {
+fn sample_with_style(tex: &Texture, u: f32, v: f32, style: &TexStyle, mode: SampleMode) -> Option<(Vector3, f32)> {
     match style {
         TexStyle::Normal => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             Some((base, 1.0))
         }
         TexStyle::GrayscaleTint { color } => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             let a = luminance(base);
-            Some((Vector3::new(color.x * a, color.y * a, color.z * a), 1.0))
+            Some((gamma::srgb_vec_to_linear(*color) * a, 1.0))
         }
         TexStyle::BlackIsTransparent { threshold } => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             let a = luminance(base);
-            if a <= *threshold { None } else { Some((base, 1.0)) }
+            if a <= gamma::srgb_to_linear(*threshold) { None } else { Some((base, 1.0)) }
         }
         TexStyle::GrayscaleTintBlackTransparent { color, threshold } => {
-            let base = tex.sample_clamp(u, v);
+            let base = tex.sample_mode(u, v, mode);
             let a = luminance(base);
-            if a <= *threshold { None } else {
-                Some((Vector3::new(color.x * a, color.y * a, color.z * a), 1.0))
+            if a <= gamma::srgb_to_linear(*threshold) { None } else {
+                Some((gamma::srgb_vec_to_linear(*color) * a, 1.0))
             }
         }
         TexStyle::ImageAlphaCutout { threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             if alpha <= *threshold { None } else { Some((base, 1.0)) }
         }
         TexStyle::GrayscaleTintImageAlphaCutout { color, threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             if alpha <= *threshold { None } else {
                 let l = luminance(base);
-                Some((Vector3::new(color.x * l, color.y * l, color.z * l), 1.0))
+                Some((gamma::srgb_vec_to_linear(*color) * l, 1.0))
             }
         }
         TexStyle::ImageAlphaWindow { threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             let cov = if alpha <= *threshold { 0.0 } else { alpha };
             Some((base, cov))
         }
         TexStyle::GrayscaleTintImageAlphaWindow { color, threshold } => {
-            let (base, alpha) = tex.sample_clamp_rgba(u, v);
+            let (base, alpha) = tex.sample_mode_rgba(u, v, mode);
             let cov = if alpha <= *threshold { 0.0 } else { alpha };
             let l = luminance(base);
-            Some((Vector3::new(color.x * l, color.y * l, color.z * l), cov))
+            Some((gamma::srgb_vec_to_linear(*color) * l, cov))
         }
     }
 }
 
-impl RayIntersect for Slab {
-    fn ray_intersect(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+impl Slab {
+    fn intersect_impl(&self, ro: &Vector3, rd: &Vector3, flat: bool) -> Intersect {
+        profile_scope!(Slab);
         // Intersección tipo slabs (AABB)
         let inv = Vector3::new(1.0 / rd.x, 1.0 / rd.y, 1.0 / rd.z);
         let (tx1, tx2) = ((self.min.x - ro.x) * inv.x, (self.max.x - ro.x) * inv.x);
@@ -173,6 +173,11 @@ impl RayIntersect for Slab {
         u = u.clamp(0.0 + tiny, 1.0 - tiny);
         v = v.clamp(0.0 + tiny, 1.0 - tiny);
 
+        // Rotación de la cara antes de elegir la media franja
+        if let Some(face_layer) = &self.face_textures[face.idx()] {
+            (u, v) = face_layer.rotate_uv(u, v);
+        }
+
         // === Ajuste “media textura” SOLO en LATERALES ===
         let is_side = matches!(face, Face::PosX | Face::NegX | Face::PosZ | Face::NegZ);
         if is_side {
@@ -185,21 +190,48 @@ impl RayIntersect for Slab {
         // En top/bottom NO se remapea: usan la imagen completa.
 
         // Material final + coverage
+        let mut shading_normal = normal;
         let (final_material, coverage) = if let Some(face_layer) = &self.face_textures[face.idx()] {
-            match sample_with_style(&face_layer.tex, u, v, &face_layer.style) {
-                Some((tex_color, cov)) => {
-                    (Material { diffuse: tex_color, ..self.material }, cov)
-                }
-                None => {
-                    return Intersect::empty();
+            if flat {
+                let mut m = Material { diffuse: face_layer.avg_color, ..self.material };
+                face_layer.apply_overrides(&mut m);
+                if let Some(map) = &face_layer.specular { map.apply_value(&mut m, map.avg); }
+                (m, face_layer.avg_coverage)
+            } else {
+                match sample_with_style(face_layer.texture(), u, v, &face_layer.style, face_layer.sample) {
+                    Some((tex_color, cov)) => {
+                        let mut m = Material { diffuse: tex_color, ..self.material };
+                        face_layer.apply_overrides(&mut m);
+                        if let Some(map) = &face_layer.specular { map.apply(&mut m, u, v); }
+                        shading_normal = face_layer.shading_normal(normal, face.uv_axes(), u, v);
+                        (m, cov)
+                    }
+                    None => {
+                        return Intersect::empty();
+                    }
                 }
             }
         } else { (self.material, 1.0) };
 
-        Intersect::with_coverage(p, normal, t_hit, final_material, coverage)
+        let mut hit = Intersect::with_coverage(p, normal, t_hit, final_material, coverage);
+        hit.normal = shading_normal;
+        hit.textured = self.face_textures[face.idx()].is_some();
+        hit
+    }
+}
+
+impl RayIntersect for Slab {
+    fn ray_intersect(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_impl(ro, rd, false)
+    }
+
+    fn ray_intersect_flat(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_impl(ro, rd, true)
     }
 
     fn aabb(&self) -> (Vector3, Vector3) {
         (self.min, self.max)
     }
+
+    fn block_id(&self) -> Option<char> { self.block }
 }
@@ -1,4 +1,6 @@
+use crate::cube::sample_with_style;
 use crate::material::Material;
+use crate::palette::FaceStyle;
 use crate::ray_intersect::{Intersect, RayIntersect};
 use raylib::prelude::Vector3;
 
@@ -6,10 +8,51 @@ pub struct Sphere {
     pub center: Vector3,
     pub radius: f32,
     pub material: Material,
+    /// Mismo orden que `Cube`: [PosX, NegX, PosY, NegY, PosZ, NegZ].
+    face_textures: [Option<FaceStyle>; 6],
+    /// Carácter de paleta de origen (eyedropper, sonido al quitar).
+    pub block: Option<char>,
 }
 
-impl RayIntersect for Sphere {
-    fn ray_intersect(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Intersect {
+impl Sphere {
+    pub fn new(center: Vector3, radius: f32, material: Material) -> Self {
+        Sphere { center, radius, material, face_textures: [None, None, None, None, None, None], block: None }
+    }
+
+    pub fn set_face_textures_from_template(&mut self, tpl: &[Option<FaceStyle>; 6]) {
+        self.face_textures = [
+            tpl[0].clone(), tpl[1].clone(), tpl[2].clone(),
+            tpl[3].clone(), tpl[4].clone(), tpl[5].clone(),
+        ];
+    }
+
+    /// UV de cubemap: el punto se proyecta desde el centro sobre el cubo circunscrito y se usa
+    /// la cara y los UV que tendría un `Cube` (la tapa de arriba toma la textura de arriba, etc.).
+    #[inline]
+    fn face_uv(n: Vector3) -> (usize, f32, f32) {
+        let (ax, ay, az) = (n.x.abs(), n.y.abs(), n.z.abs());
+        let (face, q) = if ax >= ay && ax >= az {
+            (if n.x > 0.0 { 0 } else { 1 }, n / ax)
+        } else if ay >= az {
+            (if n.y > 0.0 { 2 } else { 3 }, n / ay)
+        } else {
+            (if n.z > 0.0 { 4 } else { 5 }, n / az)
+        };
+        // q en [-1, 1]³ sobre la cara; mismas convenciones que `Cube`
+        let (u, v) = match face {
+            0 => (( q.z + 1.0) * 0.5, (1.0 - q.y) * 0.5),
+            1 => ((1.0 - q.z) * 0.5, (1.0 - q.y) * 0.5),
+            2 => (( q.x + 1.0) * 0.5, ( q.z + 1.0) * 0.5),
+            3 => (( q.x + 1.0) * 0.5, (1.0 - q.z) * 0.5),
+            4 => (( q.x + 1.0) * 0.5, (1.0 - q.y) * 0.5),
+            _ => ((1.0 - q.x) * 0.5, (1.0 - q.y) * 0.5),
+        };
+        let tiny = 1e-6f32;
+        (face, u.clamp(tiny, 1.0 - tiny), v.clamp(tiny, 1.0 - tiny))
+    }
+
+    fn intersect_impl(&self, ray_origin: &Vector3, ray_direction: &Vector3, flat: bool) -> Intersect {
+        profile_scope!(Sphere);
         let oc = *ray_origin - self.center;
 
         let a = ray_direction.dot(*ray_direction);
@@ -17,23 +60,61 @@ impl RayIntersect for Sphere {
         let c = oc.dot(oc) - self.radius * self.radius;
 
         let discriminant = b * b - 4.0 * a * c;
+        if discriminant <= 0.0 { return Intersect::empty(); }
 
-        if discriminant > 0.0 {
-            let t = (-b - discriminant.sqrt()) / (2.0 * a);
-            if t > 0.0 {
-                let point = *ray_origin + *ray_direction * t;
-                let normal = (point - self.center).normalized();
-                let distance = t;
+        // Raíz cercana; desde adentro (refracción) la lejana
+        let sq = discriminant.sqrt();
+        let t0 = (-b - sq) / (2.0 * a);
+        let t1 = (-b + sq) / (2.0 * a);
+        let t = if t0 > 1e-4 { t0 } else if t1 > 1e-4 { t1 } else { return Intersect::empty(); };
 
-                return Intersect::new(point, normal, distance, self.material);
+        let point = *ray_origin + *ray_direction * t;
+        let normal = (point - self.center).normalized();
+
+        let (face, u, v) = Self::face_uv(normal);
+        let (material, coverage) = match &self.face_textures[face] {
+            Some(layer) if flat => {
+                let mut m = Material { diffuse: layer.avg_color, ..self.material };
+                layer.apply_overrides(&mut m);
+                if let Some(map) = &layer.specular { map.apply_value(&mut m, map.avg); }
+                (m, layer.avg_coverage)
+            }
+            Some(layer) => {
+                let (u, v) = layer.rotate_uv(u, v);
+                match sample_with_style(layer.texture(), u, v, &layer.style, layer.sample) {
+                    Some((tex_color, cov)) => {
+                        let mut m = Material { diffuse: tex_color, ..self.material };
+                        layer.apply_overrides(&mut m);
+                        if let Some(map) = &layer.specular { map.apply(&mut m, u, v); }
+                        (m, cov)
+                    }
+                    None => return Intersect::empty(),
+                }
             }
-        }
+            None => (self.material, 1.0),
+        };
 
-        Intersect::empty()
+        let mut hit = Intersect::with_coverage(point, normal, t, material, coverage);
+        hit.textured = self.face_textures[face].is_some();
+        hit
+    }
+}
+
+impl RayIntersect for Sphere {
+    fn ray_intersect(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Intersect {
+        self.intersect_impl(ray_origin, ray_direction, false)
+    }
+
+    fn ray_intersect_flat(&self, ray_origin: &Vector3, ray_direction: &Vector3) -> Intersect {
+        self.intersect_impl(ray_origin, ray_direction, true)
     }
 
     fn aabb(&self) -> (Vector3, Vector3) {
         let r = Vector3::new(self.radius, self.radius, self.radius);
         (self.center - r, self.center + r)
     }
-}
\ No newline at end of file
+
+    fn block_id(&self) -> Option<char> { self.block }
+
+    fn prefer_sphere_prefilter(&self) -> bool { true }
+}
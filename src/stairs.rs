@@ -0,0 +1,166 @@
+// stairs.rs
+use raylib::prelude::Vector3;
+
+use crate::cube::{sample_with_style, Face};
+use crate::material::Material;
+use crate::palette::FaceStyle;
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+/// Lado hacia el que sube la escalera (donde queda el cuarto de bloque alto).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StairFacing { PosX, NegX, PosZ, NegZ }
+
+/// Escalera de una celda: losa inferior + cuarto de bloque arriba, del lado de `facing`.
+/// Los UV salen de la celda entera, así cada cara muestra su recorte de la textura del bloque
+/// (el escalón y la contrahuella siguen la misma imagen que un cubo completo).
+pub struct Stairs {
+    /// Celda completa.
+    pub min: Vector3,
+    pub max: Vector3,
+    pub facing: StairFacing,
+    pub material: Material,
+    face_textures: [Option<FaceStyle>; 6],
+    /// Carácter de paleta de origen.
+    pub block: Option<char>,
+}
+
+impl Stairs {
+    pub fn from_block_center_size(center: Vector3, cube_size: Vector3, facing: StairFacing, material: Material) -> Self {
+        let h = cube_size * 0.5;
+        Stairs {
+            min: center - h,
+            max: center + h,
+            facing,
+            material,
+            face_textures: [None, None, None, None, None, None],
+            block: None,
+        }
+    }
+
+    pub fn set_face_textures_from_template(&mut self, tpl: &[Option<FaceStyle>; 6]) {
+        self.face_textures = [
+            tpl[0].clone(), tpl[1].clone(), tpl[2].clone(),
+            tpl[3].clone(), tpl[4].clone(), tpl[5].clone(),
+        ];
+    }
+
+    /// Las dos cajas: (losa inferior, cuarto superior).
+    fn boxes(&self) -> [(Vector3, Vector3); 2] {
+        let mid = (self.min + self.max) * 0.5;
+        let bottom = (self.min, Vector3::new(self.max.x, mid.y, self.max.z));
+        let (mut lo, mut hi) = (Vector3::new(self.min.x, mid.y, self.min.z), self.max);
+        match self.facing {
+            StairFacing::PosX => lo.x = mid.x,
+            StairFacing::NegX => hi.x = mid.x,
+            StairFacing::PosZ => lo.z = mid.z,
+            StairFacing::NegZ => hi.z = mid.z,
+        }
+        [bottom, (lo, hi)]
+    }
+
+    fn intersect_impl(&self, ro: &Vector3, rd: &Vector3, flat: bool) -> Intersect {
+        profile_scope!(Stairs);
+        let [a, b] = self.boxes();
+        let mut hits = [box_hit(a, ro, rd), box_hit(b, ro, rd)];
+        // La más cercana primero; si su texel es recorte (cutout) el rayo sigue a la otra
+        if let [Some(x), Some(y)] = hits {
+            if y.0 < x.0 { hits.swap(0, 1); }
+        }
+        for &(t, face) in hits.iter().flatten() {
+            if let Some(hit) = self.shade(ro, rd, t, face, flat) { return hit; }
+        }
+        Intersect::empty()
+    }
+
+    /// Material de la cara `face` en el punto a distancia `t` (None = texel recortado).
+    fn shade(&self, ro: &Vector3, rd: &Vector3, t: f32, face: Face, flat: bool) -> Option<Intersect> {
+        let p = *ro + *rd * t;
+        let normal = match face {
+            Face::PosX => Vector3::new( 1.0, 0.0, 0.0),
+            Face::NegX => Vector3::new(-1.0, 0.0, 0.0),
+            Face::PosY => Vector3::new( 0.0, 1.0, 0.0),
+            Face::NegY => Vector3::new( 0.0,-1.0, 0.0),
+            Face::PosZ => Vector3::new( 0.0, 0.0, 1.0),
+            Face::NegZ => Vector3::new( 0.0, 0.0,-1.0),
+        };
+
+        // UV por cara sobre la celda completa (igual que Cube)
+        let size = self.max - self.min;
+        let (u, v) = match face {
+            Face::PosX => ( (p.z - self.min.z) / size.z, (self.max.y - p.y) / size.y ),
+            Face::NegX => ( (self.max.z - p.z) / size.z, (self.max.y - p.y) / size.y ),
+            Face::PosY => ( (p.x - self.min.x) / size.x, (p.z - self.min.z) / size.z ),
+            Face::NegY => ( (p.x - self.min.x) / size.x, (self.max.z - p.z) / size.z ),
+            Face::PosZ => ( (p.x - self.min.x) / size.x, (self.max.y - p.y) / size.y ),
+            Face::NegZ => ( (self.max.x - p.x) / size.x, (self.max.y - p.y) / size.y ),
+        };
+        let tiny = 1e-6f32;
+        let (u, v) = (u.clamp(tiny, 1.0 - tiny), v.clamp(tiny, 1.0 - tiny));
+
+        let mut shading_normal = normal;
+        let (final_material, coverage) = if let Some(face_layer) = &self.face_textures[face.idx()] {
+            let (u, v) = face_layer.rotate_uv(u, v);
+            if flat {
+                let mut m = Material { diffuse: face_layer.avg_color, ..self.material };
+                face_layer.apply_overrides(&mut m);
+                if let Some(map) = &face_layer.specular { map.apply_value(&mut m, map.avg); }
+                (m, face_layer.avg_coverage)
+            } else {
+                let (tex_color, cov) = sample_with_style(face_layer.texture(), u, v, &face_layer.style, face_layer.sample)?;
+                let mut m = Material { diffuse: tex_color, ..self.material };
+                face_layer.apply_overrides(&mut m);
+                if let Some(map) = &face_layer.specular { map.apply(&mut m, u, v); }
+                shading_normal = face_layer.shading_normal(normal, face.uv_axes(), u, v);
+                (m, cov)
+            }
+        } else { (self.material, 1.0) };
+
+        let mut hit = Intersect::with_coverage(p, normal, t, final_material, coverage);
+        hit.normal = shading_normal;
+        hit.textured = self.face_textures[face.idx()].is_some();
+        Some(hit)
+    }
+}
+
+/// Test de slabs contra una caja: (t, cara). Misma elección de cara que `Cube`.
+fn box_hit((min, max): (Vector3, Vector3), ro: &Vector3, rd: &Vector3) -> Option<(f32, Face)> {
+    let inv = Vector3::new(1.0 / rd.x, 1.0 / rd.y, 1.0 / rd.z);
+    let (tx1, tx2) = ((min.x - ro.x) * inv.x, (max.x - ro.x) * inv.x);
+    let (ty1, ty2) = ((min.y - ro.y) * inv.y, (max.y - ro.y) * inv.y);
+    let (tz1, tz2) = ((min.z - ro.z) * inv.z, (max.z - ro.z) * inv.z);
+
+    let (tmin_x, tmax_x) = (tx1.min(tx2), tx1.max(tx2));
+    let (tmin_y, tmax_y) = (ty1.min(ty2), ty1.max(ty2));
+    let (tmin_z, tmax_z) = (tz1.min(tz2), tz1.max(tz2));
+
+    let t_enter = tmin_x.max(tmin_y).max(tmin_z);
+    let t_exit  = tmax_x.min(tmax_y).min(tmax_z);
+    if t_exit < 0.0 || t_enter > t_exit { return None; }
+
+    let t_hit = if t_enter > 0.0 { t_enter } else { t_exit };
+    if !t_hit.is_finite() { return None; }
+
+    let face = if t_enter == tmin_x || (tmin_x > tmin_y && tmin_x > tmin_z) {
+        if rd.x > 0.0 { Face::NegX } else { Face::PosX }
+    } else if t_enter == tmin_y || (tmin_y > tmin_z) {
+        if rd.y > 0.0 { Face::NegY } else { Face::PosY }
+    } else if rd.z > 0.0 { Face::NegZ } else { Face::PosZ };
+    Some((t_hit, face))
+}
+
+impl RayIntersect for Stairs {
+    fn ray_intersect(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_impl(ro, rd, false)
+    }
+
+    fn ray_intersect_flat(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_impl(ro, rd, true)
+    }
+
+    /// La celda completa: las dos cajas juntas la cubren de punta a punta.
+    fn aabb(&self) -> (Vector3, Vector3) {
+        (self.min, self.max)
+    }
+
+    fn block_id(&self) -> Option<char> { self.block }
+}
@@ -0,0 +1,378 @@
+// startup.rs
+//! Arranque compartido por el binario y `--headless`: paleta, escena inicial, cámara, luces y
+//! los ajustes que vienen por línea de comandos.
+
+use raylib::prelude::*;
+use std::collections::hash_map::{Entry, HashMap};
+
+use crate::accel::UniformGridAccel;
+use crate::build::{find_object_index_by_center, FLOOD_MAX_DEFAULT};
+use crate::camera::Camera;
+use crate::light;
+use crate::material::Material;
+use crate::mesh::{Mesh, NormalMode};
+use crate::palette::{Palette, CubeTemplate, SoundCategory, TexStyle};
+use crate::ray_intersect::RayIntersect;
+use crate::scene::{self, SceneIndex};
+use crate::settings::{self, RenderSettings};
+use crate::stairs::StairFacing;
+use crate::texture::{AnimatedTexture, Texture, TextureCache};
+use crate::tools::{EditEvent, SceneEdit};
+use crate::{script, transform, ui};
+
+/// Aplica ediciones de script a la escena (reemplaza lo que haya en la celda).
+/// Devuelve cuántos bloques se colocaron; caracteres fuera de la paleta se ignoran.
+pub fn apply_script_edits(objects: &mut Vec<Box<dyn RayIntersect>>, palette: &Palette, params: &scene::SceneParams, edits: &[(scene::Cell, char)]) -> usize {
+    let mut edit = SceneEdit::new(objects, palette, params.cube_size);
+    for &(cell, ch) in edits.iter() {
+        if palette.get(ch).is_none() { continue; }
+        let center = scene::cell_center(params, cell);
+        if let Some(idx) = find_object_index_by_center(edit.objects, center) { edit.remove(idx); }
+        edit.place(center, ch);
+    }
+    edit.events.iter().filter(|e| matches!(e, EditEvent::Placed { .. })).count()
+}
+
+/// Vidrios de color sin textura (`R` y `B`): tiñen lo que dejan pasar al entrar y absorben con el grosor.
+pub(crate) fn tinted_glass() -> [Material; 2] {
+    let red = Material::new(Vector3::new(1.0, 0.35, 0.35), 120.0, [0.10, 0.30, 0.05, 0.85], 1.5)
+        .with_absorption(Vector3::new(0.0, 0.35, 0.35));
+    let blue = Material::new(Vector3::new(0.35, 0.45, 1.0), 120.0, [0.10, 0.30, 0.05, 0.85], 1.5)
+        .with_absorption(Vector3::new(0.35, 0.25, 0.0));
+    [red, blue]
+}
+
+/// Paleta de bloques por carácter (materiales + texturas). Devuelve también el material por defecto.
+pub fn build_palette(textures: &mut TextureCache) -> (Palette, Material) {
+    let stone_mat = Material::new(Vector3::new(0.55, 0.55, 0.55), 20.0, [0.90, 0.10, 0.0, 0.0], 0.0);
+    let grass_mat = Material::new(Vector3::new(1.0, 1.0, 1.0), 10.0, [0.95, 0.05, 0.0, 0.0], 0.0);
+    let dirt_mat  = Material::new(Vector3::new(1.0, 1.0, 1.0), 8.0,  [0.98, 0.02, 0.0, 0.0], 0.0);
+    let log_mat   = Material::new(Vector3::new(1.0, 1.0, 1.0), 15.0, [0.92, 0.08, 0.0, 0.0], 0.0);
+    let planks_mat= Material::new(Vector3::new(1.0, 1.0, 1.0), 12.0, [0.90, 0.10, 0.0, 0.0], 0.0);
+    let glass_mat = Material::new(Vector3::new(1.0, 1.0, 1.0),120.0,[0.80, 0.15, 0.06, 0.0], 1.5);
+    let leaves_mat= Material::new(Vector3::new(1.0, 1.0, 1.0), 35.0, [0.92, 0.08, 0.0, 0.0], 0.0);
+    let ice_mat   = Material::new(Vector3::new(1.0, 1.0, 1.0), 10.0, [0.80, 0.10, 0.20, 0.05], 1.31);
+
+    let diamond_mat = Material::new(Vector3::new(1.0, 1.0, 1.0), 140.0, [0.88, 0.12, 0.10, 0.0], 0.0).with_fresnel();
+    let gold_mat    = Material::new(Vector3::new(1.0, 1.0, 1.0), 120.0, [0.85, 0.15, 0.12, 0.0], 0.0);
+    let iron_mat    = Material::new(Vector3::new(1.0, 1.0, 1.0),  60.0, [0.90, 0.10, 0.08, 0.0], 0.0);
+
+    let lava_mat    = Material::new(Vector3::new(1.0, 1.0, 1.0),   0.0, [1.00, 0.00, 0.00, 0.0], 0.0);
+
+    // Cada PNG se carga una sola vez (los slabs comparten textura con los tablones)
+    let grass_top    = textures.get("assets/snow_grass/posy.png");
+    let grass_side   = textures.get("assets/snow_grass/posx.png");
+    let grass_bottom = textures.get("assets/snow_grass/negy.png");
+    let snow_height  = textures.get("assets/snow_grass/height.png");
+    let dirt_tex     = textures.get("assets/dirt/dirt.png");
+
+    let log_top     = textures.get("assets/spruce_log/spruce_log_top.png");
+    let log_bottom  = textures.get("assets/spruce_log/spruce_log_top.png");
+    let log_side    = textures.get("assets/spruce_log/spruce_log.png");
+
+    let planks = textures.get("assets/spruce_planks/spruce_planks.png");
+    let uslab_planks = textures.get("assets/spruce_planks/spruce_planks.png");
+    let lslab_planks = textures.get("assets/spruce_planks/spruce_planks.png");
+    let stairs_planks = textures.get("assets/spruce_planks/spruce_planks.png");
+
+    let glass = textures.get("assets/glass/glass.png");
+    let glass_tpl = CubeTemplate::with_same_texture_image_alpha_window(glass_mat, glass.clone(), 0.05);
+
+    let leaves = textures.get("assets/spruce_leaves/spruce_leaves.png");
+    let leaves_tpl = CubeTemplate::with_same_texture_tinted_black_transparent(
+        leaves_mat, leaves.clone(), Vector3::new(0.2, 0.6, 0.25), 0.05,
+    );
+
+    let ice = textures.get("assets/ice/ice.png");
+
+    let diamond_tex = textures.get("assets/diamond_block/diamond_block.png");
+    let gold_tex    = textures.get("assets/gold_block/gold_block.png");
+    let iron_tex    = textures.get("assets/iron_block/iron_block.png");
+    let lava_tex    = textures.get("assets/lava/lava.png");
+
+    let diamond_ore_tex    = textures.get("assets/diamond_ore/diamond_ore.png");
+    let iron_ore_tex    = textures.get("assets/iron_ore/iron_ore.png");
+    let gold_ore_tex    = textures.get("assets/gold_ore/gold_ore.png");
+    
+    let stone    = textures.get("assets/stone/stone.png");
+
+    // Mapas de normales derivados de la luminancia de la propia textura (sin assets extra):
+    // las vetas de los tablones, la corteza y la piedra toman relieve con la luz puntual
+    let planks_normal = std::sync::Arc::new(planks.normal_map_from_luminance(3.0));
+    let bark_normal = std::sync::Arc::new(log_side.normal_map_from_luminance(4.0));
+    let stone_normal = std::sync::Arc::new(stone.normal_map_from_luminance(3.0));
+
+    let mut palette = Palette::new();
+    palette.set('X', CubeTemplate::with_top_bottom_sides(grass_mat, grass_top, grass_bottom, grass_side)
+        .with_top_displacement(snow_height, 0.08)
+        .with_sound(SoundCategory::Dirt));
+    palette.set('D', CubeTemplate::with_same_texture(dirt_mat,  dirt_tex).with_sound(SoundCategory::Dirt));
+    // Tronco acostado a lo largo de X: anillos en ±X y la corteza girada un cuarto de vuelta
+    palette.set('Y', CubeTemplate::with_faces_styled(log_mat, [
+        Some((log_top.clone(), TexStyle::Normal)), Some((log_bottom.clone(), TexStyle::Normal)),
+        Some((log_side.clone(), TexStyle::Normal)), Some((log_side.clone(), TexStyle::Normal)),
+        Some((log_side.clone(), TexStyle::Normal)), Some((log_side.clone(), TexStyle::Normal)),
+    ]).with_face_rotations([0, 0, 1, 1, 1, 1])
+        .with_normal_map_on(bark_normal.clone(), [false, false, true, true, true, true])
+        .with_sound(SoundCategory::Wood));
+    palette.set('L', CubeTemplate::with_top_bottom_sides(log_mat,  log_top, log_bottom, log_side)
+        .with_normal_map_on(bark_normal, [true, true, false, false, true, true]).with_sound(SoundCategory::Wood));
+    palette.set('P', CubeTemplate::with_same_texture(planks_mat,  planks).with_normal_map(planks_normal.clone()).with_sound(SoundCategory::Wood));
+    palette.set('G', glass_tpl.with_sound(SoundCategory::Glass));
+    palette.set('Q', leaves_tpl.clone().as_sphere().with_sound(SoundCategory::Foliage)); // copa redonda
+    palette.set('l', leaves_tpl.with_sound(SoundCategory::Foliage));
+    // Hielo: pulido con rayas y un parche escarchado (mapa especular; exponente 4..48)
+    let ice_spec = textures.get("assets/ice/specular.png");
+    palette.set('H', CubeTemplate::with_same_texture(ice_mat,  ice.clone()).with_specular_map(ice_spec, Some((4.0, 48.0))).with_sound(SoundCategory::Glass));
+    // Mesa de hielo pulido: tapa espejada (refleja el cielo y tiene glints) y lados mate
+    let ice_table_mat = Material::new(Vector3::new(1.0, 1.0, 1.0), 8.0, [0.95, 0.05, 0.0, 0.0], 0.0);
+    palette.set('T', CubeTemplate::with_same_texture(ice_table_mat, ice)
+        .with_face_albedo(2, [0.25, 0.10, 0.65, 0.0])
+        .with_face_specular(2, 250.0)
+        .with_sound(SoundCategory::Glass));
+    palette.set('-', CubeTemplate::with_same_texture(planks_mat,  uslab_planks).with_normal_map(planks_normal.clone()).with_sound(SoundCategory::Wood));
+    palette.set('_', CubeTemplate::with_same_texture(planks_mat,  lslab_planks).with_normal_map(planks_normal.clone()).with_sound(SoundCategory::Wood));
+    // Escaleras de tablones: el carácter apunta hacia donde sube (en el mapa ASCII, ^ = fila anterior = -Z)
+    let stairs_tpl = CubeTemplate::with_same_texture(planks_mat, stairs_planks).with_normal_map(planks_normal.clone()).with_sound(SoundCategory::Wood);
+    palette.set('>', stairs_tpl.clone().as_stairs(StairFacing::PosX));
+    palette.set('<', stairs_tpl.clone().as_stairs(StairFacing::NegX));
+    palette.set('v', stairs_tpl.clone().as_stairs(StairFacing::PosZ));
+    palette.set('^', stairs_tpl.as_stairs(StairFacing::NegZ));
+
+    palette.set('M', CubeTemplate::with_same_texture(diamond_mat, diamond_tex).with_sound(SoundCategory::Metal));
+    palette.set('O', CubeTemplate::with_same_texture(gold_mat,    gold_tex).with_sound(SoundCategory::Metal));   
+    palette.set('I', CubeTemplate::with_same_texture(iron_mat,    iron_tex).with_sound(SoundCategory::Metal));
+    // Cromo: GGX de rugosidad baja (brillos estirados en rasante) + Fresnel en la reflexión
+    let chrome_block_mat = Material::new(Vector3::new(0.85, 0.86, 0.90), 0.0, [0.25, 0.9, 0.55, 0.0], 0.0)
+        .with_ggx(0.22)
+        .with_fresnel();
+    palette.set('C', CubeTemplate::material_only(chrome_block_mat).with_sound(SoundCategory::Metal));   
+    // Lava que fluye: 16 frames de 256 px desplazándose hacia abajo
+    let lava_anim = std::sync::Arc::new(AnimatedTexture::scrolling(&lava_tex, 16, 256, 0.15));
+    palette.set('V', CubeTemplate::with_animated_texture(lava_mat, lava_anim));
+
+    palette.set('m', CubeTemplate::with_same_texture(stone_mat, diamond_ore_tex));
+    palette.set('o', CubeTemplate::with_same_texture(stone_mat, gold_ore_tex));
+    palette.set('i', CubeTemplate::with_same_texture(stone_mat, iron_ore_tex));
+    
+    palette.set('S', CubeTemplate::with_same_texture(stone_mat, stone.clone()).with_normal_map(stone_normal));
+
+    let [red_glass_mat, blue_glass_mat] = tinted_glass();
+    palette.set('R', CubeTemplate::material_only(red_glass_mat).with_sound(SoundCategory::Glass));
+    palette.set('B', CubeTemplate::material_only(blue_glass_mat).with_sound(SoundCategory::Glass));
+
+    (palette, stone_mat)
+}
+
+/// Grilla de la escena de arranque: celdas de `cube_size` sin huecos, alineadas al origen.
+pub fn startup_params(cube_size: Vector3) -> scene::SceneParams {
+    let mut params = scene::default_params(cube_size);
+    params.gap = Vector3::new(0.0, 0.0, 0.0);
+    params.origin = Vector3::new(0.0, 0.0, 0.0);
+    params.y0 = -0.5;
+    params.y_step = 1.0;
+    params
+}
+
+/// `--scene <ruta>`: carpeta de capas ASCII o archivo JSON.
+pub fn scene_arg(args: &[String]) -> Option<&String> {
+    args.iter().position(|a| a == "--scene").and_then(|i| args.get(i + 1))
+}
+
+/// Tamaño de celda inicial de la grilla: `--cell-size <n>`, `--cell-size auto`
+/// (`UniformGridAccel::suggest_cell_size`) o, sin el flag, el tamaño de un bloque.
+pub fn grid_cell_from_args(args: &[String], objects: &[Box<dyn RayIntersect>], cube_size: Vector3) -> f32 {
+    match args.iter().position(|a| a == "--cell-size").and_then(|i| args.get(i + 1)).map(|s| s.as_str()) {
+        Some("auto") => UniformGridAccel::suggest_cell_size(objects),
+        Some(s) => s.parse::<f32>().map_or(cube_size.x, |v| v.clamp(0.01, 1000.0)),
+        None => cube_size.x,
+    }.max(0.01)
+}
+
+/// Archivo de escena JSON: el de `--scene`, o dónde guarda `Ctrl+S` si no se pasó ninguno
+/// (o si `--scene` es una carpeta de capas).
+pub fn scene_json_path(args: &[String]) -> String {
+    scene_arg(args).filter(|p| !std::path::Path::new(p).is_dir()).cloned()
+        .unwrap_or_else(|| "scene.json".to_string())
+}
+
+/// Escena de arranque: capas de `assets/scene` (u otra carpeta, o un JSON que puede cambiar
+/// `params`, con `--scene`), scripts `--run` y mallas `--obj*`.
+/// La comparten la ventana y el render sin ventana (`--headless` / `--render`).
+pub fn load_startup_scene(
+    args: &[String], palette: &mut Palette, default_mat: Material, params: &mut scene::SceneParams,
+    textures: &mut TextureCache, notes: &mut ui::Notifications,
+) -> Vec<Box<dyn RayIntersect>> {
+    let scene_dir = scene_arg(args).filter(|p| std::path::Path::new(p).is_dir()).map_or("assets/scene", |p| p.as_str());
+    let json_scene = scene_arg(args).filter(|p| !std::path::Path::new(p).is_dir())
+        .and_then(|path| match scene::load_json(path, palette, params.clone()) {
+            Ok((p, cells)) => {
+                notes.info(format!("Escena {}: {} bloques", path, cells.len()));
+                *params = p;
+                Some(scene::objects_from_cells(&cells, params, palette, default_mat))
+            }
+            Err(e) => {
+                notes.error(format!("No se pudo cargar la escena ({}); se usa {}", e, scene_dir));
+                None
+            }
+        });
+    let mut objects: Vec<Box<dyn RayIntersect>> = match json_scene {
+        Some(objects) => objects,
+        None => scene::load_ascii_layers_with_palette(scene_dir, params, palette, default_mat)
+            .unwrap_or_else(|e| panic!("Error leyendo {}: {}", scene_dir, e)),
+    };
+    let params = &*params;
+
+    // Scripts de construcción: `--run <archivo>` (repetible), aplicados antes de armar accel/índices
+    for pair in args.windows(2).filter(|p| p[0] == "--run") {
+        match script::run_file(&pair[1], script::ScriptConfig::default()) {
+            Ok(edits) => {
+                let placed = apply_script_edits(&mut objects, palette, params, &edits);
+                notes.info(format!("Script {}: {} bloques colocados", pair[1], placed));
+            }
+            Err(e) => notes.error(format!("Error en script {}", e)),
+        }
+    }
+
+    // Modelos MagicaVoxel: `--vox <archivo>` (repetible) reemplaza las celdas que ocupa;
+    // `--vox-map 1=S,12=P` usa bloques de la paleta para esos índices de color
+    let vox_map: HashMap<u8, char> = args.iter().position(|a| a == "--vox-map")
+        .and_then(|i| args.get(i + 1))
+        .map(|m| m.split(',').filter_map(|pair| {
+            let (idx, ch) = pair.split_once('=')?;
+            Some((idx.trim().parse().ok()?, ch.trim().chars().next()?))
+        }).collect())
+        .unwrap_or_default();
+    let vox_mat = Material::new(Vector3::one(), 10.0, [0.90, 0.10, 0.0, 0.0], 0.0);
+    // Índice celda → objeto armado una sola vez; el voxel pisa al bloque en su lugar, así los
+    // índices no se corren. Mallas y objetos grandes no tienen celda y quedan.
+    let mut by_cell: HashMap<scene::Cell, usize> = HashMap::new();
+    if args.iter().any(|a| a == "--vox") {
+        by_cell.extend(objects.iter().enumerate().filter_map(|(i, o)| Some((scene::block_cell(o.as_ref(), params)?, i))));
+    }
+    for pair in args.windows(2).filter(|p| p[0] == "--vox") {
+        match scene::load_vox(&pair[1], params, palette, &vox_map, vox_mat) {
+            Ok(voxels) => {
+                notes.info(format!("Modelo {}: {} voxels", pair[1], voxels.len()));
+                for v in voxels {
+                    let Some(cell) = scene::block_cell(v.as_ref(), params) else { objects.push(v); continue; };
+                    match by_cell.entry(cell) {
+                        Entry::Occupied(e) => objects[*e.get()] = v,
+                        Entry::Vacant(e) => { e.insert(objects.len()); objects.push(v); }
+                    }
+                }
+            }
+            Err(e) => notes.error(format!("No se pudo cargar {}", e)),
+        }
+    }
+
+    // `--tilt <col> <capa> <fila> <grados>` (repetible): gira el bloque de esa celda alrededor
+    // del eje vertical que pasa por su centro (decoraciones en diagonal)
+    for w in args.windows(5).filter(|w| w[0] == "--tilt") {
+        let nums: Vec<f32> = w[1..].iter().filter_map(|s| s.parse().ok()).collect();
+        let [x, y, z, deg] = nums[..] else {
+            notes.error(format!("--tilt {} {} {} {}: se esperan col capa fila grados", w[1], w[2], w[3], w[4]));
+            continue;
+        };
+        let center = scene::cell_center(params, (x as i32, y as i32, z as i32));
+        let Some(idx) = find_object_index_by_center(&objects, center) else {
+            notes.error(format!("--tilt: no hay bloque en ({}, {}, {})", x, y, z));
+            continue;
+        };
+        let inner = objects.swap_remove(idx);
+        let (mn, mx) = inner.aabb();
+        let pivot = (mn + mx) * 0.5;
+        objects.push(Box::new(transform::Transformed::rotate_about(inner, pivot, Vector3::new(0.0, 1.0, 0.0), deg.to_radians())));
+    }
+
+    // Mallas OBJ: `--obj <archivo>` (normales suaves) / `--obj-flat <archivo>` (facetada),
+    // apoyadas sobre la escena una al lado de la otra desde la columna central.
+    // `--obj-texture <png>` texturiza (por UV del OBJ) las mallas que vienen después.
+    let chrome_mat = Material::new(Vector3::new(0.9, 0.9, 0.95), 80.0, [0.35, 0.30, 0.60, 0.0], 0.0);
+    let ground = SceneIndex::build(&objects, params.cube_size, params.origin);
+    let mut mesh_x = 0.0;
+    let mut mesh_tex: Option<std::sync::Arc<Texture>> = None;
+    for pair in args.windows(2) {
+        let mode = match pair[0].as_str() {
+            "--obj" => NormalMode::Smooth,
+            "--obj-flat" => NormalMode::Geometric,
+            "--obj-texture" => {
+                mesh_tex = Some(textures.get(&pair[1]));
+                continue;
+            }
+            _ => continue,
+        };
+        match Mesh::from_obj(&pair[1], chrome_mat, mode) {
+            Ok(mesh) => {
+                let mut mesh = match &mesh_tex {
+                    Some(tex) => mesh.with_texture(tex.clone()),
+                    None => mesh,
+                };
+                let (mn, mx) = mesh.aabb();
+                let top = ground.column_top(mesh_x, 0.0).unwrap_or(0.0);
+                mesh.translate(Vector3::new(mesh_x - (mn.x + mx.x) * 0.5, top - mn.y, -(mn.z + mx.z) * 0.5));
+                mesh_x += (mx.x - mn.x) + 1.0;
+                objects.push(Box::new(mesh));
+            }
+            Err(e) => notes.error(format!("No se pudo cargar {}: {}", pair[1], e)),
+        }
+    }
+
+    objects
+}
+
+/// Cámara orbital inicial (mirando al centro de la escena).
+pub fn startup_camera() -> Camera {
+    Camera::new(
+        Vector3::new(20.0, 12.0, 25.0),
+        Vector3::new(0.0, 5.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+    )
+}
+
+/// Luces iniciales: sol direccional + puntual naranja.
+pub fn startup_lights() -> [light::Light; 2] {
+    [
+        light::Light::directional(Vector3::new(-1.0, -1.0, 0.3), Color::new(255,255,255,255), 1.2),
+        light::Light::new(Vector3::new(-8.0, 2.0, 5.0), Color::new(245, 149, 39,255), 1.2),
+    ]
+}
+
+/// Preset de `--sky-preset`, si se pasó uno válido.
+pub fn sky_preset_arg(args: &[String]) -> Option<settings::ProceduralSky> {
+    args.iter().position(|a| a == "--sky-preset").and_then(|i| args.get(i + 1))
+        .and_then(|n| settings::ProceduralSky::by_name(n))
+}
+
+/// `--flood-max <n>`: tope de bloques que repinta la herramienta de inundación.
+pub fn flood_max_arg(args: &[String]) -> usize {
+    args.iter().position(|a| a == "--flood-max").and_then(|i| args.get(i + 1))
+        .and_then(|v| v.parse::<usize>().ok()).unwrap_or(FLOOD_MAX_DEFAULT).max(1)
+}
+
+/// Ajustes de render por defecto más los flags de línea de comandos que los tocan.
+pub fn settings_from_args(args: &[String]) -> RenderSettings {
+    let mut render_settings = RenderSettings::default();
+    render_settings.packets = args.iter().any(|a| a == "--packets");
+    // `--fog <densidad>` enciende la niebla exponencial; `--fog-sky` la tiñe con el cielo
+    if let Some(density) = args.iter().position(|a| a == "--fog")
+        .and_then(|i| args.get(i + 1)).and_then(|v| v.parse::<f32>().ok())
+    {
+        render_settings.fog.enabled = true;
+        render_settings.fog.mode = settings::FogMode::Exponential { density: density.max(0.0) };
+    }
+    render_settings.fog.from_sky = args.iter().any(|a| a == "--fog-sky");
+    render_settings.progressive = args.iter().any(|a| a == "--progressive");
+    render_settings.preview.tint_hovered = args.iter().any(|a| a == "--hover-tint");
+    // `--sky-preset <dia|atardecer|noche>`: colores del cielo procedural (y se usa en vez del skybox)
+    if let Some(sky) = sky_preset_arg(args) { render_settings.procedural_sky = sky; }
+    // `--sun-disk <grados>`: radio angular del disco del sol (0 lo apaga); `--sun-halo <brillo>` (0 sin halo)
+    let num_arg = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).and_then(|v| v.parse::<f32>().ok());
+    if let Some(deg) = num_arg("--sun-disk") { render_settings.sun.set_disk_angle(deg.to_radians()); }
+    if let Some(k) = num_arg("--sun-halo") { render_settings.sun.halo_intensity = k.max(0.0); }
+    // `--max-depth <n>`: rebotes de arranque (los vidrios apilados piden más que los 3 de siempre)
+    if let Some(depth) = args.iter().position(|a| a == "--max-depth").and_then(|i| args.get(i + 1)).and_then(|v| v.parse::<u32>().ok()) {
+        render_settings.max_depth = depth.min(settings::MAX_DEPTH_LIMIT);
+    }
+    render_settings
+}
@@ -0,0 +1,251 @@
+// stats.rs
+use std::cell::Cell;
+use std::io::Write;
+use std::ops::AddAssign;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Lado (pixeles) de los tiles en que se agregan los contadores.
+pub const STATS_TILE: usize = 16;
+
+/// Solo se cuenta mientras `render` arma un volcado; el resto del tiempo `count` es una
+/// lectura relajada y nada más.
+static RECORDING: AtomicBool = AtomicBool::new(false);
+
+/// Contadores en vivo (HUD): mientras esté activo cada `count` también suma a los atómicos del
+/// hilo, que `take_live` junta y vacía una vez por frame. Sin tiempos ni tiles.
+static LIVE: AtomicBool = AtomicBool::new(false);
+
+/// Campos de `RayCounters` sin `nanos`, en el orden de `RayCounters::fields`.
+const LIVE_FIELDS: usize = 7;
+type LiveSlots = [AtomicU64; LIVE_FIELDS];
+
+static LIVE_THREADS: Mutex<Vec<Arc<LiveSlots>>> = Mutex::new(Vec::new());
+
+/// Contadores de recorrido de un pixel/tile.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RayCounters {
+    pub primary: u64,
+    pub shadow: u64,
+    /// Celdas de la grilla visitadas (todas las consultas: primarias, sombra, rebotes).
+    pub cells: u64,
+    /// Pruebas rayo-objeto completas.
+    pub tests: u64,
+    /// Consultas `trace` que encontraron algo.
+    pub hits: u64,
+    /// Objetos descartados por el prefiltro de esfera sin probar la geometría.
+    pub fast_rejects: u64,
+    /// Pruebas evitadas por el buzón (objeto ya probado por el mismo rayo en otra celda).
+    pub repeats: u64,
+    pub nanos: u64,
+}
+
+impl AddAssign for RayCounters {
+    fn add_assign(&mut self, o: Self) {
+        self.primary += o.primary;
+        self.shadow += o.shadow;
+        self.cells += o.cells;
+        self.tests += o.tests;
+        self.hits += o.hits;
+        self.fast_rejects += o.fast_rejects;
+        self.repeats += o.repeats;
+        self.nanos += o.nanos;
+    }
+}
+
+impl RayCounters {
+    fn fields(&self) -> [u64; LIVE_FIELDS] {
+        [self.primary, self.shadow, self.cells, self.tests, self.hits, self.fast_rejects, self.repeats]
+    }
+
+    fn from_fields(f: [u64; LIVE_FIELDS]) -> Self {
+        let [primary, shadow, cells, tests, hits, fast_rejects, repeats] = f;
+        RayCounters { primary, shadow, cells, tests, hits, fast_rejects, repeats, nanos: 0 }
+    }
+
+    /// Línea corta para HUD/log: primarios, sombra, celdas, pruebas y pruebas por rayo.
+    pub fn summary(&self) -> String {
+        let rays = (self.primary + self.shadow).max(1);
+        format!(
+            "{} prim, {} sombra, {} celdas, {} pruebas ({:.1}/rayo, {} rápidas, {} repetidas evitadas), {} hits",
+            self.primary, self.shadow, self.cells, self.tests, self.tests as f64 / rays as f64,
+            self.fast_rejects, self.repeats, self.hits,
+        )
+    }
+}
+
+thread_local! {
+    /// Scratch por hilo: se suma acá y `take` lo vacía al cerrar cada pixel.
+    static SCRATCH: Cell<RayCounters> = Cell::new(RayCounters::default());
+    /// Atómicos en vivo del hilo; se registran la primera vez que el hilo cuenta.
+    static LIVE_LOCAL: Arc<LiveSlots> = {
+        let slots: Arc<LiveSlots> = Arc::new(std::array::from_fn(|_| AtomicU64::new(0)));
+        LIVE_THREADS.lock().unwrap().push(slots.clone());
+        slots
+    };
+}
+
+#[inline]
+pub fn recording() -> bool { RECORDING.load(Ordering::Relaxed) }
+
+pub fn set_recording(on: bool) { RECORDING.store(on, Ordering::Relaxed); }
+
+#[inline]
+pub fn live() -> bool { LIVE.load(Ordering::Relaxed) }
+
+/// Activa los contadores en vivo; al apagarlos se descarta lo que quedó sin leer.
+pub fn set_live(on: bool) {
+    LIVE.store(on, Ordering::Relaxed);
+    if !on { take_live(); }
+}
+
+/// Suma al scratch del hilo actual y/o a sus contadores en vivo (no hace nada si ninguno está activo).
+/// Apagados cuesta dos lecturas relajadas; con la feature `no-ray-stats` no genera código.
+#[inline]
+pub fn count(f: impl FnOnce(&mut RayCounters)) {
+    if cfg!(feature = "no-ray-stats") { return; }
+    let (rec, live) = (recording(), live());
+    if !rec && !live { return; }
+    let mut d = RayCounters::default();
+    f(&mut d);
+    if rec {
+        SCRATCH.with(|s| {
+            let mut c = s.get();
+            c += d;
+            s.set(c);
+        });
+    }
+    if live {
+        LIVE_LOCAL.with(|slots| {
+            for (slot, v) in slots.iter().zip(d.fields()) {
+                if v != 0 { slot.fetch_add(v, Ordering::Relaxed); }
+            }
+        });
+    }
+}
+
+/// Suma y vacía los contadores en vivo de todos los hilos (lo contado desde la llamada anterior).
+pub fn take_live() -> RayCounters {
+    let mut sum = [0u64; LIVE_FIELDS];
+    for slots in LIVE_THREADS.lock().unwrap().iter() {
+        for (o, s) in sum.iter_mut().zip(slots.iter()) { *o += s.swap(0, Ordering::Relaxed); }
+    }
+    RayCounters::from_fields(sum)
+}
+
+/// Devuelve y vacía el scratch del hilo actual.
+#[inline]
+pub fn take() -> RayCounters {
+    SCRATCH.with(|s| s.replace(RayCounters::default()))
+}
+
+/// Lo que devuelve `render`: tiempo de pared del frame y, con los contadores en vivo activos,
+/// lo contado durante él (si no, ceros).
+#[derive(Clone, Copy, Default, Debug)]
+pub struct RenderStats {
+    pub counters: RayCounters,
+    pub elapsed: Duration,
+}
+
+impl RenderStats {
+    /// Rayos trazados contra la grilla: primarios y de sombra (los rebotes no se cuentan aparte).
+    pub fn rays(&self) -> u64 { self.counters.primary + self.counters.shadow }
+
+    pub fn cells_per_ray(&self) -> f64 { self.counters.cells as f64 / self.rays().max(1) as f64 }
+
+    /// "1.1M rayos, 4.2 celdas/rayo, 48.0 ms": muchas celdas por rayo apuntan a la grilla,
+    /// pocas con el frame lento, al sombreado.
+    pub fn summary(&self) -> String {
+        let ms = self.elapsed.as_secs_f64() * 1000.0;
+        if !live() { return format!("{:.1} ms (contadores apagados)", ms); }
+        format!("{:.1}M rayos, {:.1} celdas/rayo, {:.1} ms", self.rays() as f64 / 1e6, self.cells_per_ray(), ms)
+    }
+}
+
+/// Agregados por tile de un frame.
+pub struct TileStats {
+    pub width: usize,
+    pub height: usize,
+    tiles_x: usize,
+    tiles: Vec<RayCounters>,
+}
+
+impl TileStats {
+    pub fn new(width: usize, height: usize) -> Self {
+        let tiles_x = width.div_ceil(STATS_TILE);
+        let tiles_y = height.div_ceil(STATS_TILE);
+        TileStats { width, height, tiles_x, tiles: vec![RayCounters::default(); tiles_x * tiles_y] }
+    }
+
+    #[inline]
+    pub fn add(&mut self, x: usize, y: usize, c: RayCounters) {
+        let t = (y / STATS_TILE) * self.tiles_x + x / STATS_TILE;
+        if let Some(tile) = self.tiles.get_mut(t) { *tile += c; }
+    }
+
+    /// Cierra un pixel: vacía el scratch del hilo en su tile junto con el tiempo desde `started`.
+    #[inline]
+    pub fn flush(&mut self, x: usize, y: usize, started: Instant) {
+        let mut c = take();
+        c.nanos = started.elapsed().as_nanos() as u64;
+        self.add(x, y, c);
+    }
+
+    /// Suma otro parcial del mismo tamaño (una banda de filas de otro hilo).
+    pub fn merge(&mut self, other: &TileStats) {
+        for (a, b) in self.tiles.iter_mut().zip(other.tiles.iter()) { *a += *b; }
+    }
+
+    /// Rectángulo de pantalla del tile `t`: (x0, y0, x1, y1) exclusivo.
+    fn rect(&self, t: usize) -> (usize, usize, usize, usize) {
+        let (tx, ty) = (t % self.tiles_x, t / self.tiles_x);
+        let (x0, y0) = (tx * STATS_TILE, ty * STATS_TILE);
+        (x0, y0, (x0 + STATS_TILE).min(self.width), (y0 + STATS_TILE).min(self.height))
+    }
+
+    pub fn total(&self) -> RayCounters {
+        let mut sum = RayCounters::default();
+        for t in self.tiles.iter() { sum += *t; }
+        sum
+    }
+
+    /// CSV: líneas `# clave=valor` con los parámetros y después una fila por tile.
+    pub fn write_csv(&self, path: &str, header: &[(&str, String)]) -> std::io::Result<()> {
+        if let Some(dir) = std::path::Path::new(path).parent() { std::fs::create_dir_all(dir)?; }
+        let mut f = std::io::BufWriter::new(std::fs::File::create(path)?);
+        for (k, v) in header.iter() { writeln!(f, "# {}={}", k, v)?; }
+        writeln!(f, "# tile={}", STATS_TILE)?;
+        writeln!(f, "tile_x,tile_y,x0,y0,x1,y1,primary,shadow,cells,tests,hits,fast_rejects,repeats,micros")?;
+        for (t, c) in self.tiles.iter().enumerate() {
+            let (x0, y0, x1, y1) = self.rect(t);
+            writeln!(
+                f, "{},{},{},{},{},{},{},{},{},{},{},{},{},{:.1}",
+                t % self.tiles_x, t / self.tiles_x, x0, y0, x1, y1,
+                c.primary, c.shadow, c.cells, c.tests, c.hits, c.fast_rejects, c.repeats, c.nanos as f64 / 1000.0,
+            )?;
+        }
+        f.flush()
+    }
+
+    /// Resumen por stdout: totales y los `n` tiles más caros (tiempo) con su rectángulo de pantalla.
+    pub fn print_summary(&self, n: usize) {
+        let total = self.total();
+        println!(
+            "Tiles {}×{}: {} primarios, {} sombra, {} celdas, {} pruebas ({} descartes rápidos, {} repetidas evitadas), {} hits, {:.1} ms de CPU",
+            self.tiles_x, self.tiles.len() / self.tiles_x.max(1),
+            total.primary, total.shadow, total.cells, total.tests, total.fast_rejects, total.repeats, total.hits,
+            total.nanos as f64 / 1e6,
+        );
+        let mut order: Vec<usize> = (0..self.tiles.len()).collect();
+        order.sort_by(|&a, &b| self.tiles[b].nanos.cmp(&self.tiles[a].nanos));
+        for &t in order.iter().take(n) {
+            let c = &self.tiles[t];
+            let (x0, y0, x1, y1) = self.rect(t);
+            println!(
+                "  ({:>4},{:>4})-({:>4},{:>4})  {:>8.2} ms  {:>7} celdas  {:>7} pruebas  {:>5} sombra",
+                x0, y0, x1, y1, c.nanos as f64 / 1e6, c.cells, c.tests, c.shadow,
+            );
+        }
+    }
+}
@@ -1,24 +1,150 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
 use raylib::prelude::*;
+use crate::gamma;
+
+/// Cómo se reparten los texeles sobre UV 0..1 (muestreo sin repetición).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleMode {
+    /// Cada texel ocupa exactamente 1/N del rango y u=1 cae en el último: los bordes de dos
+    /// bloques vecinos se ven continuos. Para texturas de bloque que se repiten.
+    Edge,
+    /// Recorte de medio texel en los bordes (el texel 0 ocupa 1.5/N y el último 0.5/N).
+    /// Para íconos y el skybox.
+    Inset,
+}
+
+/// Error al cargar una textura desde disco.
+#[derive(Debug, Clone)]
+pub enum TextureError {
+    NotFound { path: String },
+    Decode { path: String, reason: String },
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::NotFound { path } => write!(f, "{}: no existe", path),
+            TextureError::Decode { path, reason } => write!(f, "{}: no se pudo decodificar ({})", path, reason),
+        }
+    }
+}
 
 /// Textura CPU-side con muestreo por UV.
 /// Guarda el buffer de colores para muestrear sin pedir &mut.
+/// El RGB muestreado sale siempre en lineal (el render lo pasa a su espacio con `Gamma`); alpha no se toca.
 pub struct Texture {
     width: i32,
     height: i32,
-    pixels: ImageColors, // Box<[Color]> administrado por raylib-rs (incluye alpha)
+    pixels: Box<[Color]>, // row-major, origen top-left (incluye alpha)
 }
 
 impl Texture {
+    /// Como `try_from_file`, pero entra en pánico si falla (para assets obligatorios).
     pub fn from_file(path: &str) -> Self {
-        let img = Image::load_image(path).expect("No se pudo cargar la textura");
+        Self::try_from_file(path).unwrap_or_else(|e| panic!("No se pudo cargar la textura {}", e))
+    }
+
+    pub fn try_from_file(path: &str) -> Result<Self, TextureError> {
+        if !Path::new(path).is_file() {
+            return Err(TextureError::NotFound { path: path.to_string() });
+        }
+        let img = Image::load_image(path)
+            .map_err(|e| TextureError::Decode { path: path.to_string(), reason: e.to_string() })?;
+        Ok(Self::from_image(&img))
+    }
+
+    fn from_image(img: &Image) -> Self {
         let w = img.width();
         let h = img.height();
-        let pixels = img.get_image_data(); // row-major, origen top-left (RGBA)
+        let pixels = img.get_image_data().to_vec().into_boxed_slice(); // row-major, origen top-left (RGBA)
         Texture { width: w, height: h, pixels }
     }
 
+    /// Copia reducida (o ampliada) a `w`×`h` promediando los texeles que cubre cada destino.
+    pub fn resized(&self, w: i32, h: i32) -> Self { self.box_filtered(w, h, false) }
+
+    /// Como `resized`, pero promedia el RGB en lineal (los texeles son sRGB): al reducir mucho
+    /// un cielo, las zonas con contraste no se oscurecen.
+    pub fn resized_srgb(&self, w: i32, h: i32) -> Self { self.box_filtered(w, h, true) }
+
+    fn box_filtered(&self, w: i32, h: i32, srgb: bool) -> Self {
+        let (w, h) = (w.max(1), h.max(1));
+        let to_sum = |c: u8| if srgb { gamma::srgb_to_linear(c as f32 / 255.0) } else { c as f32 };
+        let from_avg = |v: f32| if srgb { (gamma::linear_to_srgb(v) * 255.0 + 0.5).min(255.0) as u8 } else { v as u8 };
+        let mut pixels = Vec::with_capacity((w * h) as usize);
+        for y in 0..h {
+            let (y0, y1) = (y * self.height / h, ((y + 1) * self.height / h).max(y * self.height / h + 1));
+            for x in 0..w {
+                let (x0, x1) = (x * self.width / w, ((x + 1) * self.width / w).max(x * self.width / w + 1));
+                let mut sum = [0f32; 4];
+                for sy in y0..y1.min(self.height) {
+                    for sx in x0..x1.min(self.width) {
+                        let c = self.pixels[(sy * self.width + sx) as usize];
+                        sum[0] += to_sum(c.r); sum[1] += to_sum(c.g); sum[2] += to_sum(c.b); sum[3] += c.a as f32;
+                    }
+                }
+                let n = ((y1.min(self.height) - y0) * (x1.min(self.width) - x0)).max(1) as f32;
+                pixels.push(Color::new(from_avg(sum[0] / n), from_avg(sum[1] / n), from_avg(sum[2] / n), (sum[3] / n) as u8));
+            }
+        }
+        Texture { width: w, height: h, pixels: pixels.into_boxed_slice() }
+    }
+
+    /// Copia desplazada `rows` filas hacia abajo, con repetición (lo que sale por abajo entra arriba).
+    pub fn scrolled(&self, rows: i32) -> Self {
+        let mut pixels = self.pixels.clone();
+        let shift = rows.rem_euclid(self.height.max(1)) as usize * self.width as usize;
+        pixels.rotate_right(shift);
+        Texture { width: self.width, height: self.height, pixels }
+    }
+
+    /// Textura armada en memoria: `pixels` row-major desde arriba a la izquierda (`width*height`).
+    pub fn from_pixels(width: i32, height: i32, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), (width.max(0) * height.max(0)) as usize, "from_pixels: tamaño incorrecto");
+        Texture { width, height, pixels: pixels.into_boxed_slice() }
+    }
+
+    /// Tablero de `size`×`size` texeles con cuadros de 2×2 (reemplazo visible de texturas faltantes).
+    pub fn checkerboard(size: i32, color_a: Color, color_b: Color) -> Self {
+        let size = size.max(2);
+        let pixels = (0..size * size)
+            .map(|i| if ((i % size / 2) + (i / size / 2)) % 2 == 1 { color_b } else { color_a })
+            .collect();
+        Self::from_pixels(size, size, pixels)
+    }
+
+    /// Tablero magenta/negro de 16×16: la textura que se usa cuando un PNG falta.
+    pub fn missing() -> Self {
+        Self::checkerboard(16, Color::new(255, 0, 255, 255), Color::new(20, 20, 20, 255))
+    }
+
+    #[inline]
+    pub fn width(&self) -> i32 { self.width }
+
+    #[inline]
+    pub fn height(&self) -> i32 { self.height }
+
+    /// Memoria del buffer de texeles (RGBA8).
+    #[inline]
+    pub fn bytes(&self) -> usize { self.pixels.len() * std::mem::size_of::<Color>() }
+
+    /// Alimenta el hasher con el contenido (tamaño + RGBA), p. ej. para claves de caché.
+    pub fn content_hash<H: Hasher>(&self, state: &mut H) {
+        self.width.hash(state);
+        self.height.hash(state);
+        for c in self.pixels.iter() {
+            [c.r, c.g, c.b, c.a].hash(state);
+        }
+    }
+
     #[inline]
     pub fn sample_repeat(&self, mut u: f32, mut v: f32) -> Vector3 {
+        profile_scope!(Texture);
         u = u.fract(); if u < 0.0 { u += 1.0; }
         v = v.fract(); if v < 0.0 { v += 1.0; }
         let sx = u * self.width as f32  - 0.5;
@@ -27,11 +153,12 @@ impl Texture {
         let yi = sy.floor().clamp(0.0, self.height as f32 - 1.0)  as usize;
         let idx = yi * self.width as usize + xi;
         let c = self.pixels[idx];
-        Vector3::new(c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0)
+        Vector3::new(gamma::srgb_u8_to_linear(c.r), gamma::srgb_u8_to_linear(c.g), gamma::srgb_u8_to_linear(c.b))
     }
 
     #[inline]
     pub fn sample_repeat_rgba(&self, mut u: f32, mut v: f32) -> (Vector3, f32) {
+        profile_scope!(Texture);
         u = u.fract(); if u < 0.0 { u += 1.0; }
         v = v.fract(); if v < 0.0 { v += 1.0; }
         let sx = u * self.width as f32  - 0.5;
@@ -40,11 +167,12 @@ impl Texture {
         let yi = sy.floor().clamp(0.0, self.height as f32 - 1.0)  as usize;
         let idx = yi * self.width as usize + xi;
         let c = self.pixels[idx];
-        (Vector3::new(c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0), c.a as f32 / 255.0)
+        (Vector3::new(gamma::srgb_u8_to_linear(c.r), gamma::srgb_u8_to_linear(c.g), gamma::srgb_u8_to_linear(c.b)), c.a as f32 / 255.0)
     }
 
     #[inline]
     pub fn sample_clamp(&self, mut u: f32, mut v: f32) -> Vector3 {
+        profile_scope!(Texture);
         let eps_u = 0.5 / self.width as f32;
         let eps_v = 0.5 / self.height as f32;
         u = u.clamp(eps_u, 1.0 - eps_u);
@@ -55,11 +183,12 @@ impl Texture {
         let yi = sy.floor().clamp(0.0, self.height as f32 - 1.0)  as usize;
         let idx = yi * self.width as usize + xi;
         let c = self.pixels[idx];
-        Vector3::new(c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0)
+        Vector3::new(gamma::srgb_u8_to_linear(c.r), gamma::srgb_u8_to_linear(c.g), gamma::srgb_u8_to_linear(c.b))
     }
 
     #[inline]
     pub fn sample_clamp_rgba(&self, mut u: f32, mut v: f32) -> (Vector3, f32) {
+        profile_scope!(Texture);
         let eps_u = 0.5 / self.width as f32;
         let eps_v = 0.5 / self.height as f32;
         u = u.clamp(eps_u, 1.0 - eps_u);
@@ -70,7 +199,100 @@ impl Texture {
         let yi = sy.floor().clamp(0.0, self.height as f32 - 1.0)  as usize;
         let idx = yi * self.width as usize + xi;
         let c = self.pixels[idx];
-        (Vector3::new(c.r as f32 / 255.0, c.g as f32 / 255.0, c.b as f32 / 255.0), c.a as f32 / 255.0)
+        (Vector3::new(gamma::srgb_u8_to_linear(c.r), gamma::srgb_u8_to_linear(c.g), gamma::srgb_u8_to_linear(c.b)), c.a as f32 / 255.0)
+    }
+
+    /// Índice del texel con reparto exacto (ver `SampleMode::Edge`).
+    #[inline]
+    fn edge_index(&self, u: f32, v: f32) -> usize {
+        let xi = (u * self.width as f32).floor().clamp(0.0, self.width  as f32 - 1.0) as usize;
+        let yi = (v * self.height as f32).floor().clamp(0.0, self.height as f32 - 1.0) as usize;
+        yi * self.width as usize + xi
+    }
+
+    #[inline]
+    pub fn sample_edge(&self, u: f32, v: f32) -> Vector3 {
+        profile_scope!(Texture);
+        let c = self.pixels[self.edge_index(u, v)];
+        Vector3::new(gamma::srgb_u8_to_linear(c.r), gamma::srgb_u8_to_linear(c.g), gamma::srgb_u8_to_linear(c.b))
+    }
+
+    #[inline]
+    pub fn sample_edge_rgba(&self, u: f32, v: f32) -> (Vector3, f32) {
+        profile_scope!(Texture);
+        let c = self.pixels[self.edge_index(u, v)];
+        (Vector3::new(gamma::srgb_u8_to_linear(c.r), gamma::srgb_u8_to_linear(c.g), gamma::srgb_u8_to_linear(c.b)), c.a as f32 / 255.0)
+    }
+
+    #[inline]
+    pub fn sample_mode(&self, u: f32, v: f32, mode: SampleMode) -> Vector3 {
+        match mode {
+            SampleMode::Edge => self.sample_edge(u, v),
+            SampleMode::Inset => self.sample_clamp(u, v),
+        }
+    }
+
+    #[inline]
+    pub fn sample_mode_rgba(&self, u: f32, v: f32, mode: SampleMode) -> (Vector3, f32) {
+        match mode {
+            SampleMode::Edge => self.sample_edge_rgba(u, v),
+            SampleMode::Inset => self.sample_clamp_rgba(u, v),
+        }
+    }
+
+    /// Valor 0..1 del canal rojo sin decodificar (mapas de datos), con el reparto de `Edge`.
+    #[inline]
+    pub fn sample_data(&self, u: f32, v: f32) -> f32 {
+        self.pixels[self.edge_index(u, v)].r as f32 / 255.0
+    }
+
+    /// Normal en espacio tangente (x = +u, y = arriba en la imagen, z = fuera de la cara) de un
+    /// mapa de normales RGB sin decodificar, con el reparto de `Edge`.
+    #[inline]
+    pub fn sample_normal(&self, u: f32, v: f32) -> Vector3 {
+        let c = self.pixels[self.edge_index(u, v)];
+        let d = |x: u8| x as f32 / 255.0 * 2.0 - 1.0;
+        Vector3::new(d(c.r), d(c.g), d(c.b)).normalized()
+    }
+
+    /// Mapa de normales derivado de esta textura: la luminancia hace de altura (claro = alto) y
+    /// el gradiente, con repetición en los bordes, inclina la normal. `strength` escala el relieve.
+    pub fn normal_map_from_luminance(&self, strength: f32) -> Self {
+        let (w, h) = (self.width.max(1), self.height.max(1));
+        let lum = |x: i32, y: i32| -> f32 {
+            let c = self.pixels[(y.rem_euclid(h) * w + x.rem_euclid(w)) as usize];
+            (0.2126 * c.r as f32 + 0.7152 * c.g as f32 + 0.0722 * c.b as f32) / 255.0
+        };
+        let enc = |x: f32| ((x * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+        let mut pixels = Vec::with_capacity((w * h) as usize);
+        for y in 0..h {
+            for x in 0..w {
+                let dx = (lum(x + 1, y) - lum(x - 1, y)) * 0.5 * strength;
+                // La imagen crece hacia abajo; el verde del mapa apunta hacia arriba
+                let dy = (lum(x, y + 1) - lum(x, y - 1)) * 0.5 * strength;
+                let n = Vector3::new(-dx, dy, 1.0).normalized();
+                pixels.push(Color::new(enc(n.x), enc(n.y), enc(n.z), 255));
+            }
+        }
+        Texture { width: w, height: h, pixels: pixels.into_boxed_slice() }
+    }
+
+    /// Altura 0..1 (canal rojo, sin decodificar: es un dato, no un color) con filtrado bilineal
+    /// y repetición, para que el relieve sea suave y continuo entre bloques vecinos.
+    pub fn sample_height(&self, u: f32, v: f32) -> f32 {
+        let (w, h) = (self.width, self.height);
+        let sx = u * w as f32 - 0.5;
+        let sy = v * h as f32 - 0.5;
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (tx, ty) = (sx - x0, sy - y0);
+        let at = |x: i32, y: i32| -> f32 {
+            let (x, y) = (x.rem_euclid(w) as usize, y.rem_euclid(h) as usize);
+            self.pixels[y * w as usize + x].r as f32 / 255.0
+        };
+        let (x0, y0) = (x0 as i32, y0 as i32);
+        let a = at(x0, y0) * (1.0 - tx) + at(x0 + 1, y0) * tx;
+        let b = at(x0, y0 + 1) * (1.0 - tx) + at(x0 + 1, y0 + 1) * tx;
+        a * (1.0 - ty) + b * ty
     }
 
     #[inline]
@@ -79,3 +301,94 @@ impl Texture {
     #[inline]
     pub fn sample_rgba(&self, u: f32, v: f32) -> (Vector3, f32) { self.sample_repeat_rgba(u, v) }
 }
+
+/// Reloj de las texturas animadas (segundos desde el arranque, bits de f32). Global porque el
+/// muestreo ocurre dentro de `ray_intersect` y así no hay que pasar el tiempo por todas las firmas. `main` lo avanza una vez por frame, antes de renderizar.
+static ANIM_TIME: AtomicU32 = AtomicU32::new(0);
+
+#[inline]
+pub fn set_anim_time(secs: f32) { ANIM_TIME.store(secs.to_bits(), Ordering::Relaxed); }
+
+#[inline]
+pub fn anim_time() -> f32 { f32::from_bits(ANIM_TIME.load(Ordering::Relaxed)) }
+
+/// Textura de varios frames que se recorren en bucle cada `frame_secs` segundos (agua, lava,
+/// portales). Todos los frames deberían tener el mismo tamaño.
+pub struct AnimatedTexture {
+    frames: Vec<Arc<Texture>>,
+    frame_secs: f32,
+}
+
+impl AnimatedTexture {
+    pub fn new(frames: Vec<Arc<Texture>>, frame_secs: f32) -> Self {
+        assert!(!frames.is_empty(), "AnimatedTexture sin frames");
+        AnimatedTexture { frames, frame_secs: frame_secs.max(1e-3) }
+    }
+
+    /// Flujo: `base` reducida a `size`×`size` y desplazada hacia abajo un poco en cada uno de
+    /// los `count` frames, de modo que el último empalma con el primero.
+    pub fn scrolling(base: &Texture, count: usize, size: i32, frame_secs: f32) -> Self {
+        let small = base.resized(size, size);
+        let count = count.max(1);
+        let frames = (0..count)
+            .map(|k| Arc::new(small.scrolled((k as i32 * small.height()) / count as i32)))
+            .collect();
+        Self::new(frames, frame_secs)
+    }
+
+    #[inline]
+    pub fn frame_secs(&self) -> f32 { self.frame_secs }
+
+    #[inline]
+    pub fn first(&self) -> &Arc<Texture> { &self.frames[0] }
+
+    /// Frame que corresponde a `t` segundos.
+    #[inline]
+    pub fn frame_at(&self, t: f32) -> &Arc<Texture> {
+        let i = (t.max(0.0) / self.frame_secs) as usize % self.frames.len();
+        &self.frames[i]
+    }
+
+    /// Frame actual según el reloj global (`set_anim_time`).
+    #[inline]
+    pub fn current(&self) -> &Arc<Texture> { self.frame_at(anim_time()) }
+}
+
+/// Texturas ya cargadas por ruta: el mismo PNG pedido dos veces devuelve el mismo `Arc`.
+/// La dueña es quien arma la escena (paleta, skyboxes, mallas); no hay estado global.
+/// Un archivo que falta o no se puede leer se reemplaza por `Texture::missing()` (compartida)
+/// y el error queda en `errors` para avisarle al usuario.
+#[derive(Default)]
+pub struct TextureCache {
+    by_path: HashMap<String, Arc<Texture>>,
+    requests: usize,
+    fallback: Option<Arc<Texture>>,
+    errors: Vec<TextureError>,
+}
+
+impl TextureCache {
+    pub fn new() -> Self { Self::default() }
+
+    /// Carga `path` la primera vez; después devuelve la textura compartida.
+    pub fn get(&mut self, path: &str) -> Arc<Texture> {
+        self.requests += 1;
+        if let Some(tex) = self.by_path.get(path) {
+            return tex.clone();
+        }
+        let tex = match Texture::try_from_file(path) {
+            Ok(tex) => Arc::new(tex),
+            Err(e) => {
+                self.errors.push(e);
+                self.fallback.get_or_insert_with(|| Arc::new(Texture::missing())).clone()
+            }
+        };
+        self.by_path.insert(path.to_string(), tex.clone());
+        tex
+    }
+
+    /// (archivos distintos cargados, pedidos totales)
+    pub fn stats(&self) -> (usize, usize) { (self.by_path.len(), self.requests) }
+
+    /// Errores acumulados desde la última llamada (cada ruta falla una sola vez).
+    pub fn take_errors(&mut self) -> Vec<TextureError> { std::mem::take(&mut self.errors) }
+}
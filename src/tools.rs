@@ -0,0 +1,340 @@
+// tools.rs
+use raylib::prelude::*;
+
+use crate::accel::UniformGridAccel;
+use crate::build::{cell_index, cell_index_center, find_object_index_by_center, make_block_from_palette, slab_half, slab_twin, FillTool, ReplaceTool, FLOOD_MAX_DEFAULT};
+use crate::palette::{Palette, SoundCategory};
+use crate::ray_intersect::RayIntersect;
+use crate::ui::NotifyLevel;
+
+/// Identidad de un bloque: el carácter de la paleta.
+pub type BlockId = char;
+
+/// Herramientas que pueden ocupar un slot del hotbar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToolKind {
+    /// Quita el bloque apuntado con click izquierdo.
+    Eraser,
+    /// Relleno de región entre dos esquinas (`build::FillTool`).
+    Fill,
+    /// Bloques sobre la recta entre dos celdas (`FillTool` en forma de línea).
+    Line,
+    /// Cambia todos los bloques del tipo apuntado por el pincel (`build::ReplaceTool`).
+    Replace,
+    /// Como `Replace`, pero solo la región conexa por caras del bloque apuntado.
+    Flood,
+}
+
+impl ToolKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ToolKind::Eraser => "Borrador",
+            ToolKind::Fill => "Relleno",
+            ToolKind::Line => "Línea",
+            ToolKind::Replace => "Reemplazar todos",
+            ToolKind::Flood => "Inundación",
+        }
+    }
+
+    /// Pinta con el bloque del último slot de bloque activo (`BuildState::brush`).
+    pub fn uses_brush(self) -> bool {
+        !matches!(self, ToolKind::Eraser)
+    }
+
+    /// Ícono procedural 16x16 (sin assets).
+    pub fn icon_image(self) -> Image {
+        match self {
+            ToolKind::Eraser => {
+                let mut img = Image::gen_image_color(16, 16, Color::BLANK);
+                for y in 4..12 {
+                    for x in 2..14 {
+                        let c = if x < 6 { Color::new(90, 120, 220, 255) } else { Color::new(240, 140, 170, 255) };
+                        img.draw_pixel(x, y, c);
+                    }
+                }
+                img
+            }
+            ToolKind::Fill => {
+                // Caja amarilla (el color de la vista previa) con el interior relleno
+                let mut img = Image::gen_image_color(16, 16, Color::BLANK);
+                for y in 3..13 {
+                    for x in 3..13 {
+                        let edge = x == 3 || x == 12 || y == 3 || y == 12;
+                        img.draw_pixel(x, y, if edge { Color::new(255, 230, 120, 255) } else { Color::new(150, 110, 60, 255) });
+                    }
+                }
+                img
+            }
+            ToolKind::Line => {
+                // Escalera diagonal de celdas unidas por caras, como las que arma `line_cells`
+                let mut img = Image::gen_image_color(16, 16, Color::BLANK);
+                for step in 0..6 {
+                    let (x, y) = (2 + step * 2, 12 - step * 2);
+                    for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1), (2, 0), (2, 1)] {
+                        img.draw_pixel(x + dx, y + dy, Color::new(255, 230, 120, 255));
+                    }
+                }
+                img
+            }
+            ToolKind::Replace | ToolKind::Flood => {
+                // Dos bloques (de → a); la inundación marca además los vecinos del de origen
+                let mut img = Image::gen_image_color(16, 16, Color::BLANK);
+                let (from, to) = (Color::new(110, 170, 80, 255), Color::new(235, 240, 250, 255));
+                for y in 5..11 {
+                    for x in 1..7 { img.draw_pixel(x, y, from); }
+                    for x in 9..15 { img.draw_pixel(x, y, to); }
+                }
+                if self == ToolKind::Flood {
+                    for x in 1..7 {
+                        img.draw_pixel(x, 2, from);
+                        img.draw_pixel(x, 13, from);
+                    }
+                }
+                img.draw_pixel(8, 7, Color::WHITE);
+                img.draw_pixel(8, 8, Color::WHITE);
+                img
+            }
+        }
+    }
+}
+
+/// Contenido de un slot del hotbar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SlotContent {
+    Block(BlockId),
+    Tool(ToolKind),
+}
+
+impl SlotContent {
+    pub fn label(&self) -> String {
+        match self {
+            SlotContent::Block(ch) => format!("Bloque '{}'", ch),
+            SlotContent::Tool(kind) => kind.name().to_string(),
+        }
+    }
+}
+
+/// Lo que hay bajo el mouse (o la mira) en este frame.
+pub struct Pick {
+    pub point: Vector3,
+    pub normal: Vector3,
+    pub object_index: Option<usize>,
+    /// Centro de la celda vecina a la cara apuntada (donde se coloca).
+    pub place_center: Vector3,
+}
+
+/// Geometría de la grilla con la que se arman las herramientas (las de región trabajan en celdas).
+#[derive(Clone, Copy, Debug)]
+pub struct ToolConfig {
+    pub cube_size: Vector3,
+    pub grid_origin: Vector3,
+    /// Tope de bloques que repinta la inundación (`--flood-max`).
+    pub flood_max: usize,
+}
+
+impl ToolConfig {
+    pub fn new(cube_size: Vector3, grid_origin: Vector3) -> Self {
+        ToolConfig { cube_size, grid_origin, flood_max: FLOOD_MAX_DEFAULT }
+    }
+}
+
+/// Región a medias de una herramienta, para dibujarla sobre la escena.
+pub enum RegionPreview {
+    /// Caja entre dos celdas (inclusivas): un solo contorno.
+    Box { a: (i32, i32, i32), b: (i32, i32, i32), removing: bool },
+    /// Celdas sueltas, cada una con su contorno.
+    Cells { cells: Vec<(i32, i32, i32)>, removing: bool },
+}
+
+/// Límite de construcción: |x|, |y|, |z| del centro de celda.
+pub const BUILD_LIMIT: f32 = 128.0;
+
+/// Resultado de una edición, para el feedback (sonido + efecto en la celda).
+#[derive(Clone, Copy, Debug)]
+pub enum EditEvent {
+    Placed { center: Vector3, sound: SoundCategory },
+    Removed { center: Vector3, sound: SoundCategory },
+    /// Celda ocupada o fuera de límites.
+    Failed { center: Vector3 },
+}
+
+/// Acceso de las herramientas a la escena. `dirty` le avisa a main que reconstruya los índices;
+/// con una grilla enganchada (`with_accel`) cada alta/baja la actualiza en su lugar y
+/// `rebuild_grid` solo queda en true si eso no alcanzó.
+pub struct SceneEdit<'a> {
+    pub objects: &'a mut Vec<Box<dyn RayIntersect>>,
+    pub palette: &'a Palette,
+    pub cube_size: Vector3,
+    accel: Option<&'a mut UniformGridAccel>,
+    /// Bloque con el que pintan las herramientas de región (`BuildState::brush`).
+    pub brush: Option<BlockId>,
+    pub dirty: bool,
+    /// La grilla no refleja la edición: hay que llamar a `UniformGridAccel::build`.
+    pub rebuild_grid: bool,
+    pub events: Vec<EditEvent>,
+    /// Mensajes para los toasts (main los pasa a `Notifications`).
+    pub notes: Vec<(NotifyLevel, String)>,
+}
+
+impl<'a> SceneEdit<'a> {
+    pub fn new(objects: &'a mut Vec<Box<dyn RayIntersect>>, palette: &'a Palette, cube_size: Vector3) -> Self {
+        SceneEdit {
+            objects, palette, cube_size, accel: None, brush: None,
+            dirty: false, rebuild_grid: false, events: Vec::new(), notes: Vec::new(),
+        }
+    }
+
+    pub fn with_brush(mut self, brush: Option<BlockId>) -> Self {
+        self.brush = brush;
+        self
+    }
+
+    pub fn info(&mut self, text: impl Into<String>) { self.notes.push((NotifyLevel::Info, text.into())); }
+    pub fn warn(&mut self, text: impl Into<String>) { self.notes.push((NotifyLevel::Warn, text.into())); }
+
+    /// Actualiza `accel` con cada edición en vez de pedir una reconstrucción.
+    pub fn with_accel(mut self, accel: &'a mut UniformGridAccel) -> Self {
+        self.accel = Some(accel);
+        self
+    }
+
+    /// Agrega un objeto a la escena (y a la grilla, si hay una enganchada).
+    pub fn push(&mut self, obj: Box<dyn RayIntersect>) {
+        self.objects.push(obj);
+        self.dirty = true;
+        match self.accel.as_deref_mut() {
+            Some(accel) if !self.rebuild_grid => {
+                let idx = self.objects.len() - 1;
+                if !accel.insert(idx, self.objects[idx].as_ref()) { self.rebuild_grid = true; }
+            }
+            _ => self.rebuild_grid = true,
+        }
+    }
+
+    #[inline]
+    pub fn in_bounds(center: Vector3) -> bool {
+        center.x.abs() <= BUILD_LIMIT && center.y.abs() <= BUILD_LIMIT && center.z.abs() <= BUILD_LIMIT
+    }
+
+    pub fn place(&mut self, center: Vector3, block: BlockId) {
+        if !Self::in_bounds(center) || find_object_index_by_center(self.objects, center).is_some() {
+            self.events.push(EditEvent::Failed { center });
+            return;
+        }
+        if let Some(tpl) = self.palette.get(block) {
+            self.push(make_block_from_palette(center, self.cube_size, block, tpl));
+            self.events.push(EditEvent::Placed { center, sound: tpl.sound });
+        }
+    }
+
+    /// Cambia el objeto `idx` por un bloque `block` en la misma celda, sin mover índices. No toca la
+    /// grilla en el lugar (la forma puede cambiar: cubo, esfera, escalera): pide una reconstrucción,
+    /// que se hace una sola vez aunque se reemplacen miles. El centro sale de la celda, no del AABB
+    /// (el de una losa está corrido un cuarto de bloque).
+    pub fn replace(&mut self, idx: usize, block: BlockId, grid_origin: Vector3) {
+        let (Some(obj), Some(tpl)) = (self.objects.get(idx), self.palette.get(block)) else { return; };
+        let (mn, mx) = obj.aabb();
+        let center = cell_index_center(cell_index((mn + mx) * 0.5, self.cube_size, grid_origin), self.cube_size, grid_origin);
+        self.objects[idx] = make_block_from_palette(center, self.cube_size, block, tpl);
+        self.dirty = true;
+        self.rebuild_grid = true;
+        self.events.push(EditEvent::Placed { center, sound: tpl.sound });
+    }
+
+    pub fn remove(&mut self, idx: usize) {
+        if idx < self.objects.len() {
+            let (mn, mx) = self.objects[idx].aabb();
+            // Objetos sin carácter de paleta (mallas) suenan como piedra
+            let sound = self.objects[idx].block_id()
+                .and_then(|ch| self.palette.get(ch))
+                .map_or(SoundCategory::Stone, |tpl| tpl.sound);
+            self.objects.swap_remove(idx);
+            self.dirty = true;
+            match self.accel.as_deref_mut() {
+                Some(accel) if !self.rebuild_grid => accel.swap_remove(idx),
+                _ => self.rebuild_grid = true,
+            }
+            self.events.push(EditEvent::Removed { center: (mn + mx) * 0.5, sound });
+        }
+    }
+}
+
+/// Comportamiento de un slot: click izquierdo/derecho y qué objeto resaltar.
+pub trait Tool {
+    fn on_primary(&mut self, pick: &Pick, edit: &mut SceneEdit);
+
+    fn on_secondary(&mut self, pick: &Pick, edit: &mut SceneEdit) {
+        if let Some(idx) = pick.object_index { edit.remove(idx); }
+    }
+
+    fn preview(&self, pick: &Pick) -> Option<usize> { pick.object_index }
+
+    /// Celda (centro) donde el click primario pondría un bloque, para el cubo fantasma.
+    fn ghost(&self, _pick: &Pick) -> Option<Vector3> { None }
+
+    /// Esc: descarta lo que haya a medias. True si había algo (el Esc no sigue de largo).
+    fn cancel(&mut self) -> bool { false }
+
+    /// `\`: pasa a la siguiente variante (caja llena, hueca...).
+    fn cycle_mode(&mut self) {}
+
+    /// Línea del HUD con la variante y lo que haya a medias.
+    fn status(&self) -> Option<String> { None }
+
+    /// Región pendiente con el cursor en `pick` (None: sin nada apuntado).
+    fn region(&self, _pick: Option<&Pick>) -> Option<RegionPreview> { None }
+}
+
+/// Slot de bloque: coloca en la cara apuntada, click derecho quita.
+pub struct PlaceBlock {
+    pub block: BlockId,
+}
+
+impl PlaceBlock {
+    /// Las losas eligen la mitad como en Minecraft: arriba si el punto apuntado queda sobre la
+    /// mitad de la celda destino (clic en la mitad alta de un costado o en la cara de abajo de
+    /// un bloque), abajo si no.
+    fn block_for(&self, pick: &Pick) -> BlockId {
+        match (slab_half(self.block), slab_twin(self.block)) {
+            (Some(_), Some(twin)) => {
+                let top = pick.point.y > pick.place_center.y;
+                let is_top = self.block == '-';
+                if top == is_top { self.block } else { twin }
+            }
+            _ => self.block,
+        }
+    }
+}
+
+impl Tool for PlaceBlock {
+    fn on_primary(&mut self, pick: &Pick, edit: &mut SceneEdit) {
+        edit.place(pick.place_center, self.block_for(pick));
+    }
+
+    fn ghost(&self, pick: &Pick) -> Option<Vector3> {
+        SceneEdit::in_bounds(pick.place_center).then_some(pick.place_center)
+    }
+}
+
+pub struct Eraser;
+
+impl Tool for Eraser {
+    fn on_primary(&mut self, pick: &Pick, edit: &mut SceneEdit) {
+        if let Some(idx) = pick.object_index { edit.remove(idx); }
+    }
+
+    fn on_secondary(&mut self, _pick: &Pick, _edit: &mut SceneEdit) {}
+}
+
+/// Herramienta nueva para el slot. `BuildState` arma una por slot y la reusa entre frames;
+/// solo se rehace cuando cambia el contenido del slot (pick-block).
+pub fn tool_for(slot: SlotContent, cfg: ToolConfig) -> Box<dyn Tool> {
+    match slot {
+        SlotContent::Block(block) => Box::new(PlaceBlock { block }),
+        SlotContent::Tool(ToolKind::Eraser) => Box::new(Eraser),
+        SlotContent::Tool(ToolKind::Fill) => Box::new(FillTool::new(cfg)),
+        SlotContent::Tool(ToolKind::Line) => Box::new(FillTool::line(cfg)),
+        SlotContent::Tool(ToolKind::Replace) => Box::new(ReplaceTool { flood: false, cfg }),
+        SlotContent::Tool(ToolKind::Flood) => Box::new(ReplaceTool { flood: true, cfg }),
+    }
+}
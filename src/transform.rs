@@ -0,0 +1,106 @@
+// transform.rs
+use raylib::prelude::Vector3;
+
+use crate::ray_intersect::{Intersect, RayIntersect};
+
+/// Objeto envuelto con una rotación + traslación (matriz de modelo rígida: sin escala).
+/// El rayo pasa a espacio local, el objeto interno intersecta como siempre y el hit vuelve a
+/// mundo. Sin escala la dirección conserva su largo, así la distancia `t` es la misma en ambos
+/// espacios y la grilla puede comparar hits de objetos transformados y comunes.
+pub struct Transformed {
+    inner: Box<dyn RayIntersect>,
+    /// Filas de la rotación local → mundo (ortonormal: la inversa es la transpuesta).
+    rows: [Vector3; 3],
+    translation: Vector3,
+    /// AABB en mundo de las 8 esquinas rotadas (cacheado: la grilla lo pide al armar).
+    bounds: (Vector3, Vector3),
+    solid_bounds: (Vector3, Vector3),
+}
+
+impl Transformed {
+    /// Gira `inner` `angle` radianes alrededor de `axis` pasando por `pivot` (Rodrigues).
+    pub fn rotate_about(inner: Box<dyn RayIntersect>, pivot: Vector3, axis: Vector3, angle: f32) -> Self {
+        let k = axis.normalized();
+        let (s, c) = angle.sin_cos();
+        let t = 1.0 - c;
+        let rows = [
+            Vector3::new(c + k.x * k.x * t, k.x * k.y * t - k.z * s, k.x * k.z * t + k.y * s),
+            Vector3::new(k.y * k.x * t + k.z * s, c + k.y * k.y * t, k.y * k.z * t - k.x * s),
+            Vector3::new(k.z * k.x * t - k.y * s, k.z * k.y * t + k.x * s, c + k.z * k.z * t),
+        ];
+        // p_mundo = R (p - pivot) + pivot
+        let translation = pivot - mul(&rows, pivot);
+        Self::new(inner, rows, translation)
+    }
+
+    fn new(inner: Box<dyn RayIntersect>, rows: [Vector3; 3], translation: Vector3) -> Self {
+        let mut tr = Transformed { inner, rows, translation, bounds: (Vector3::zero(), Vector3::zero()), solid_bounds: (Vector3::zero(), Vector3::zero()) };
+        tr.bounds = tr.world_box(tr.inner.aabb());
+        tr.solid_bounds = tr.world_box(tr.inner.solid_aabb());
+        tr
+    }
+
+    #[inline]
+    fn to_world(&self, p: Vector3) -> Vector3 { mul(&self.rows, p) + self.translation }
+
+    /// Rᵀ v: dirección de mundo a local.
+    #[inline]
+    fn dir_to_local(&self, v: Vector3) -> Vector3 {
+        let [a, b, c] = self.rows;
+        a * v.x + b * v.y + c * v.z
+    }
+
+    fn world_box(&self, (mn, mx): (Vector3, Vector3)) -> (Vector3, Vector3) {
+        let (mut lo, mut hi) = (Vector3::one() * f32::INFINITY, Vector3::one() * f32::NEG_INFINITY);
+        for i in 0..8 {
+            let p = self.to_world(Vector3::new(
+                if i & 1 == 0 { mn.x } else { mx.x },
+                if i & 2 == 0 { mn.y } else { mx.y },
+                if i & 4 == 0 { mn.z } else { mx.z },
+            ));
+            lo = Vector3::new(lo.x.min(p.x), lo.y.min(p.y), lo.z.min(p.z));
+            hi = Vector3::new(hi.x.max(p.x), hi.y.max(p.y), hi.z.max(p.z));
+        }
+        (lo, hi)
+    }
+
+    /// Pasa el rayo a local, intersecta con `f` y devuelve el hit en mundo.
+    fn intersect_with(&self, ro: &Vector3, rd: &Vector3, f: impl Fn(&dyn RayIntersect, &Vector3, &Vector3) -> Intersect) -> Intersect {
+        let lo = self.dir_to_local(*ro - self.translation);
+        let ld = self.dir_to_local(*rd);
+        let mut hit = f(self.inner.as_ref(), &lo, &ld);
+        if !hit.is_intersecting { return hit; }
+        hit.point = self.to_world(hit.point);
+        // Rotación pura: las normales giran con la misma matriz (no hace falta la inversa transpuesta)
+        hit.normal = mul(&self.rows, hit.normal).normalized();
+        hit.geometric_normal = mul(&self.rows, hit.geometric_normal).normalized();
+        hit
+    }
+}
+
+#[inline]
+fn mul(rows: &[Vector3; 3], v: Vector3) -> Vector3 {
+    Vector3::new(rows[0].dot(v), rows[1].dot(v), rows[2].dot(v))
+}
+
+impl RayIntersect for Transformed {
+    fn ray_intersect(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_with(ro, rd, |o, a, b| o.ray_intersect(a, b))
+    }
+
+    fn ray_intersect_flat(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_with(ro, rd, |o, a, b| o.ray_intersect_flat(a, b))
+    }
+
+    fn ray_intersect_detailed(&self, ro: &Vector3, rd: &Vector3) -> Intersect {
+        self.intersect_with(ro, rd, |o, a, b| o.ray_intersect_detailed(a, b))
+    }
+
+    fn aabb(&self) -> (Vector3, Vector3) { self.bounds }
+
+    fn solid_aabb(&self) -> (Vector3, Vector3) { self.solid_bounds }
+
+    fn prefer_sphere_prefilter(&self) -> bool { self.inner.prefer_sphere_prefilter() }
+
+    fn block_id(&self) -> Option<char> { self.inner.block_id() }
+}
@@ -0,0 +1,109 @@
+// ui.rs
+use std::collections::VecDeque;
+use std::time::{SystemTime, UNIX_EPOCH};
+use raylib::prelude::*;
+
+/// Segundos que un toast queda en pantalla (el último medio segundo se desvanece).
+const TOAST_SECONDS: f32 = 4.0;
+const FADE_SECONDS: f32 = 0.5;
+
+/// Toasts visibles a la vez; el resto se resume en "+N más".
+const MAX_VISIBLE: usize = 4;
+
+/// Tamaño de letra y alto de cada toast (pixeles).
+const FONT: i32 = 14;
+const ROW_H: i32 = 24;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NotifyLevel { Info, Warn, Error }
+
+impl NotifyLevel {
+    fn tag(self) -> &'static str {
+        match self {
+            NotifyLevel::Info => "INFO",
+            NotifyLevel::Warn => "WARN",
+            NotifyLevel::Error => "ERROR",
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            NotifyLevel::Info => Color::new(90, 200, 255, 255),
+            NotifyLevel::Warn => Color::new(255, 200, 60, 255),
+            NotifyLevel::Error => Color::new(255, 80, 80, 255),
+        }
+    }
+}
+
+struct Toast {
+    level: NotifyLevel,
+    text: String,
+    /// Repeticiones seguidas del mismo mensaje (se muestran como "×N" en vez de apilarse).
+    count: u32,
+    age: f32,
+}
+
+/// Cola de notificaciones: toasts apilados en la esquina superior derecha + copia por stdout.
+#[derive(Default)]
+pub struct Notifications {
+    toasts: VecDeque<Toast>,
+}
+
+/// Hora UTC "HH:MM:SS" del reloj del sistema (para el log por stdout).
+fn timestamp() -> String {
+    let secs = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    format!("{:02}:{:02}:{:02}", (secs / 3600) % 24, (secs / 60) % 60, secs % 60)
+}
+
+impl Notifications {
+    pub fn new() -> Self { Self::default() }
+
+    /// Encola un mensaje. Si repite el más reciente solo suma la cuenta y reinicia su tiempo.
+    pub fn notify(&mut self, level: NotifyLevel, text: impl Into<String>) {
+        let text = text.into();
+        println!("[{} UTC] {:<5} {}", timestamp(), level.tag(), text);
+        if let Some(last) = self.toasts.back_mut() {
+            if last.level == level && last.text == text {
+                last.count += 1;
+                last.age = 0.0;
+                return;
+            }
+        }
+        self.toasts.push_back(Toast { level, text, count: 1, age: 0.0 });
+    }
+
+    #[inline]
+    pub fn info(&mut self, text: impl Into<String>) { self.notify(NotifyLevel::Info, text); }
+    #[inline]
+    pub fn warn(&mut self, text: impl Into<String>) { self.notify(NotifyLevel::Warn, text); }
+    #[inline]
+    pub fn error(&mut self, text: impl Into<String>) { self.notify(NotifyLevel::Error, text); }
+
+    /// Envejece los toasts con el frame time y descarta los vencidos.
+    pub fn update(&mut self, dt: f32) {
+        for t in self.toasts.iter_mut() { t.age += dt; }
+        self.toasts.retain(|t| t.age < TOAST_SECONDS);
+    }
+
+    /// Los más nuevos arriba; los que no entran se colapsan en una línea "+N más".
+    pub fn draw(&self, d: &mut RaylibDrawHandle, screen_w: i32) {
+        let margin = 12;
+        let mut y = margin;
+        for t in self.toasts.iter().rev().take(MAX_VISIBLE) {
+            let fade = ((TOAST_SECONDS - t.age) / FADE_SECONDS).clamp(0.0, 1.0);
+            let text = if t.count > 1 { format!("{} (×{})", t.text, t.count) } else { t.text.clone() };
+            let w = d.measure_text(&text, FONT) + 24;
+            let x = screen_w - margin - w;
+            d.draw_rectangle(x, y, w, ROW_H - 4, Color::new(20, 20, 24, 200).alpha(fade));
+            d.draw_rectangle(x, y, 4, ROW_H - 4, t.level.color().alpha(fade));
+            d.draw_text(&text, x + 12, y + 3, FONT, Color::WHITE.alpha(fade));
+            y += ROW_H;
+        }
+        let hidden = self.toasts.len().saturating_sub(MAX_VISIBLE);
+        if hidden > 0 {
+            let text = format!("+{} más", hidden);
+            let w = d.measure_text(&text, FONT);
+            d.draw_text(&text, screen_w - margin - w, y + 2, FONT, Color::LIGHTGRAY);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+// vox.rs
+//! Lector de modelos MagicaVoxel (`.vox`): chunks `SIZE`, `XYZI` y `RGBA` del primer modelo.
+//! Lo demás (escenas `nTRN`/`nGRP`, materiales `MATL`, varios modelos) se ignora.
+
+use std::{fs, io};
+
+/// Un modelo: tamaño en voxels (x, y, z con z hacia arriba, como en MagicaVoxel), voxels
+/// `(x, y, z, índice de color 1..=255)` y la paleta RGBA (`palette[i]` = color del índice i).
+pub struct VoxModel {
+    pub size: (u32, u32, u32),
+    pub voxels: Vec<(u8, u8, u8, u8)>,
+    pub palette: [[u8; 4]; 256],
+}
+
+pub fn load(path: &str) -> io::Result<VoxModel> {
+    let bytes = fs::read(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))?;
+    parse(&bytes).map_err(|msg| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path, msg)))
+}
+
+fn parse(bytes: &[u8]) -> Result<VoxModel, String> {
+    if bytes.len() < 8 || &bytes[0..4] != b"VOX " { return Err("no es un archivo VOX".to_string()); }
+    let u32_at = |i: usize| -> Result<u32, String> {
+        bytes.get(i..i + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])).ok_or_else(|| "archivo cortado".to_string())
+    };
+
+    let mut size = None;
+    let mut voxels = None;
+    let mut palette = None;
+    // Los chunks hijos de MAIN van seguidos: se recorren todos planos desde el primero
+    let mut at = 8;
+    while at + 12 <= bytes.len() {
+        let id = &bytes[at..at + 4];
+        let content = u32_at(at + 4)? as usize;
+        let children = u32_at(at + 8)? as usize;
+        let body = at + 12;
+        let end = body.checked_add(content).filter(|&e| e <= bytes.len()).ok_or("chunk más largo que el archivo")?;
+        match id {
+            // MAIN no tiene contenido propio: sus hijos siguen a continuación
+            b"MAIN" => { at = body; continue; }
+            b"SIZE" if size.is_none() => size = Some((u32_at(body)?, u32_at(body + 4)?, u32_at(body + 8)?)),
+            b"XYZI" if voxels.is_none() => {
+                let n = u32_at(body)? as usize;
+                let data = n.checked_mul(4).and_then(|len| bytes[body..end].get(4..4 + len))
+                    .ok_or("XYZI más corto que su cantidad de voxels")?;
+                voxels = Some(data.chunks_exact(4).map(|v| (v[0], v[1], v[2], v[3])).collect::<Vec<_>>());
+            }
+            b"RGBA" => {
+                // El color i del chunk es el del índice i + 1 (el índice 0 es "vacío")
+                let mut p = [[0u8; 4]; 256];
+                for (i, c) in bytes[body..end].chunks_exact(4).take(255).enumerate() {
+                    p[i + 1] = [c[0], c[1], c[2], c[3]];
+                }
+                palette = Some(p);
+            }
+            _ => {}
+        }
+        at = end + children;
+    }
+
+    Ok(VoxModel {
+        size: size.ok_or("falta el chunk SIZE")?,
+        voxels: voxels.ok_or("falta el chunk XYZI")?,
+        palette: palette.unwrap_or_else(default_palette),
+    })
+}
+
+/// Paleta por defecto de MagicaVoxel (archivos sin chunk `RGBA`): el cubo de 6×6×6 niveles
+/// (ff, cc, 99, 66, 33, 00; azul el más rápido) sin el negro, y rampas de 10 tonos de rojo,
+/// verde, azul y gris (ee, dd, bb, aa, 88, 77, 55, 44, 22, 11).
+fn default_palette() -> [[u8; 4]; 256] {
+    const CUBE: [u8; 6] = [0xff, 0xcc, 0x99, 0x66, 0x33, 0x00];
+    const RAMP: [u8; 10] = [0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11];
+    let mut p = [[0u8; 4]; 256];
+    let mut i = 1;
+    for r in CUBE {
+        for g in CUBE {
+            for b in CUBE {
+                if (r, g, b) == (0, 0, 0) { continue; }
+                p[i] = [r, g, b, 0xff];
+                i += 1;
+            }
+        }
+    }
+    for channel in 0..4 {
+        for v in RAMP {
+            p[i] = match channel {
+                0 => [v, 0, 0, 0xff],
+                1 => [0, v, 0, 0xff],
+                2 => [0, 0, v, 0xff],
+                _ => [v, v, v, 0xff],
+            };
+            i += 1;
+        }
+    }
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], content: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.extend((content.len() as u32).to_le_bytes());
+        out.extend(0u32.to_le_bytes());
+        out.extend(content);
+        out
+    }
+
+    /// Cabecera `VOX ` 150 y un MAIN vacío y los chunks dados como hijos.
+    fn file(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let children: Vec<u8> = chunks.concat();
+        let mut out = b"VOX ".to_vec();
+        out.extend(150u32.to_le_bytes());
+        out.extend(b"MAIN");
+        out.extend(0u32.to_le_bytes());
+        out.extend((children.len() as u32).to_le_bytes());
+        out.extend(children);
+        out
+    }
+
+    fn size(x: u32, y: u32, z: u32) -> Vec<u8> {
+        chunk(b"SIZE", &[x.to_le_bytes(), y.to_le_bytes(), z.to_le_bytes()].concat())
+    }
+
+    fn xyzi(n: u32, voxels: &[[u8; 4]]) -> Vec<u8> {
+        let mut content = n.to_le_bytes().to_vec();
+        content.extend(voxels.concat());
+        chunk(b"XYZI", &content)
+    }
+
+    #[test]
+    fn reads_size_voxels_and_palette() {
+        let mut rgba = vec![0u8; 256 * 4];
+        rgba[0..4].copy_from_slice(&[10, 20, 30, 255]);
+        rgba[4..8].copy_from_slice(&[40, 50, 60, 128]);
+        let bytes = file(&[size(2, 3, 4), xyzi(2, &[[0, 1, 2, 1], [1, 2, 3, 2]]), chunk(b"RGBA", &rgba)]);
+
+        let model = parse(&bytes).unwrap();
+        assert_eq!(model.size, (2, 3, 4));
+        assert_eq!(model.voxels, vec![(0, 1, 2, 1), (1, 2, 3, 2)]);
+        // El color i del chunk es el del índice i + 1
+        assert_eq!(model.palette[1], [10, 20, 30, 255]);
+        assert_eq!(model.palette[2], [40, 50, 60, 128]);
+        assert_eq!(model.palette[0], [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn missing_rgba_uses_the_default_palette() {
+        let model = parse(&file(&[size(1, 1, 1), xyzi(1, &[[0, 0, 0, 1]])])).unwrap();
+        assert_eq!(model.palette, default_palette());
+        assert_eq!(model.palette[1], [0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn truncated_xyzi_is_an_error() {
+        // Dice 3 voxels pero trae 1; el RGBA que sigue no se lee como voxels
+        let bytes = file(&[size(1, 1, 1), xyzi(3, &[[0, 0, 0, 1]]), chunk(b"RGBA", &[0u8; 256 * 4])]);
+        assert!(parse(&bytes).is_err());
+        // Y cortado a mitad del chunk
+        let whole = file(&[size(1, 1, 1), xyzi(1, &[[0, 0, 0, 1]])]);
+        assert!(parse(&whole[..whole.len() - 2]).is_err());
+    }
+}
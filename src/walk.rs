@@ -0,0 +1,146 @@
+// walk.rs
+use raylib::prelude::*;
+use crate::scene::SceneIndex;
+
+/// Parámetros del modo caminar (en unidades de celda).
+#[derive(Clone, Copy, Debug)]
+pub struct WalkConfig {
+    /// Altura del ojo sobre los pies.
+    pub eye_height: f32,
+    /// Altura del cuerpo (lo que se revisa contra celdas ocupadas).
+    pub body_height: f32,
+    /// Radio horizontal de la “cápsula”.
+    pub radius: f32,
+    /// Velocidad horizontal (celdas por segundo).
+    pub speed: f32,
+    pub jump_speed: f32,
+    pub gravity: f32,
+    /// Radianes por pixel de movimiento del mouse.
+    pub mouse_sensitivity: f32,
+}
+
+impl Default for WalkConfig {
+    fn default() -> Self {
+        Self {
+            eye_height: 1.6,
+            body_height: 1.8,
+            radius: 0.3,
+            speed: 4.3,
+            jump_speed: 6.0,
+            gravity: 18.0,
+            mouse_sensitivity: 0.003,
+        }
+    }
+}
+
+/// Entrada de un frame: ejes en [-1, 1], salto y delta del mouse.
+pub struct WalkInput {
+    pub forward: f32,
+    pub strafe: f32,
+    pub jump: bool,
+    pub look_delta: Vector2,
+}
+
+/// Estado del caminante: posición de los pies + orientación.
+pub struct WalkState {
+    pub feet: Vector3,
+    pub yaw: f32,
+    pub pitch: f32,
+    vel_y: f32,
+    on_ground: bool,
+    pub config: WalkConfig,
+}
+
+impl WalkState {
+    /// Arranca sobre la columna que mira la cámara orbital (o en `center` si está vacía).
+    pub fn spawn(center: Vector3, forward: Vector3, index: &SceneIndex) -> Self {
+        let f = forward.normalized();
+        let ground = index.column_top(center.x, center.z).unwrap_or(center.y);
+        Self {
+            feet: Vector3::new(center.x, ground, center.z),
+            yaw: f.z.atan2(f.x),
+            pitch: 0.0,
+            vel_y: 0.0,
+            on_ground: false,
+            config: WalkConfig::default(),
+        }
+    }
+
+    #[inline]
+    pub fn eye(&self) -> Vector3 {
+        self.feet + Vector3::new(0.0, self.config.eye_height, 0.0)
+    }
+
+    #[inline]
+    pub fn forward(&self) -> Vector3 {
+        let cp = self.pitch.cos();
+        Vector3::new(cp * self.yaw.cos(), self.pitch.sin(), cp * self.yaw.sin())
+    }
+
+    /// ¿El cuerpo con los pies en `feet` toca alguna celda ocupada?
+    fn blocked(&self, feet: Vector3, index: &SceneIndex) -> bool {
+        let c = &self.config;
+        let eps = 1e-3;
+        let min = Vector3::new(feet.x - c.radius, feet.y + eps, feet.z - c.radius);
+        let max = Vector3::new(feet.x + c.radius, feet.y + c.body_height - eps, feet.z + c.radius);
+        index.box_occupied(min, max)
+    }
+
+    /// Mueve en un eje; si choca estando en el suelo intenta subir un escalón de una celda.
+    fn move_axis(&mut self, delta: Vector3, index: &SceneIndex) {
+        if delta.length() <= 0.0 { return; }
+        let target = self.feet + delta;
+        if !self.blocked(target, index) {
+            self.feet = target;
+            return;
+        }
+        if self.on_ground {
+            let up = target + Vector3::new(0.0, index.cell_size().y, 0.0);
+            if !self.blocked(up, index) { self.feet = up; }
+        }
+    }
+
+    pub fn update(&mut self, input: &WalkInput, dt: f32, index: &SceneIndex) {
+        let dt = dt.clamp(0.0, 0.1);
+        let c = self.config;
+
+        // Mirar con el mouse
+        self.yaw += input.look_delta.x * c.mouse_sensitivity;
+        self.pitch = (self.pitch - input.look_delta.y * c.mouse_sensitivity).clamp(-1.5, 1.5);
+
+        // Movimiento horizontal, eje por eje
+        let fwd = Vector3::new(self.yaw.cos(), 0.0, self.yaw.sin());
+        let right = Vector3::new(-self.yaw.sin(), 0.0, self.yaw.cos());
+        let mut wish = fwd * input.forward + right * input.strafe;
+        if wish.length() > 1.0 { wish = wish.normalized(); }
+        let step = wish * (c.speed * dt);
+        self.move_axis(Vector3::new(step.x, 0.0, 0.0), index);
+        self.move_axis(Vector3::new(0.0, 0.0, step.z), index);
+
+        // Salto + gravedad
+        if input.jump && self.on_ground {
+            self.vel_y = c.jump_speed;
+            self.on_ground = false;
+        }
+        self.vel_y -= c.gravity * dt;
+        let next = self.feet + Vector3::new(0.0, self.vel_y * dt, 0.0);
+
+        if self.vel_y > 0.0 {
+            if self.blocked(next, index) { self.vel_y = 0.0; } else { self.feet = next; }
+            self.on_ground = false;
+            return;
+        }
+
+        match index.ground_below(self.feet, c.radius) {
+            Some(g) if next.y <= g => {
+                self.feet.y = g;
+                self.vel_y = 0.0;
+                self.on_ground = true;
+            }
+            _ => {
+                self.feet = next;
+                self.on_ground = false;
+            }
+        }
+    }
+}
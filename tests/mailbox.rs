@@ -0,0 +1,53 @@
+// tests/mailbox.rs
+//! Escena de losas anchas para el buzón. Va en su propio binario de test porque los contadores
+//! se encienden con un flag global que `render` apaga al terminar cada frame.
+#![cfg(not(feature = "no-ray-stats"))]
+
+use computer_graphics_v3::cube::Cube;
+use computer_graphics_v3::stats;
+use computer_graphics_v3::{Material, RayIntersect, UniformGridAccel};
+use raylib::prelude::Vector3;
+
+/// Cuatro losas de 16 celdas de largo, una al lado de la otra, y rayos casi rasantes que
+/// bajan a lo largo de cada una: pasan por varias celdas de la losa antes de tocarla. Un poste
+/// aparte levanta la grilla por encima de las losas (si no, los rayos entrarían justo en el hit).
+#[test]
+fn mailbox_skips_repeated_tests_on_wide_slabs() {
+    let mat = Material::new(Vector3::one(), 10.0, [1.0, 0.0, 0.0, 0.0], 0.0);
+    let mut objects: Vec<Box<dyn RayIntersect>> = (0..4)
+        .map(|z| {
+            let z = z as f32;
+            Box::new(Cube::new(Vector3::new(-8.0, 0.0, z), Vector3::new(8.0, 0.5, z + 0.9), mat)) as Box<dyn RayIntersect>
+        })
+        .collect();
+    objects.push(Box::new(Cube::new(Vector3::new(-8.0, 0.0, -2.0), Vector3::new(-7.0, 3.0, -1.0), mat)));
+    let grid = UniformGridAccel::build(&objects, 1.0);
+
+    stats::set_recording(true);
+    stats::take();
+    let mut refs = 0u64;
+    for z in 0..4 {
+        for k in 0..8 {
+            let ro = Vector3::new(-9.0, 0.9, z as f32 + 0.1 + k as f32 * 0.1);
+            let rd = Vector3::new(1.0, -0.05, 0.0).normalized();
+            let (hit, cost) = grid.trace_cost(&ro, &rd, &objects, f32::INFINITY, false);
+            refs += cost.refs as u64;
+
+            // Mismo hit que probando todo sin grilla
+            let brute = objects.iter()
+                .map(|o| o.ray_intersect(&ro, &rd))
+                .filter(|i| i.is_intersecting)
+                .map(|i| i.distance)
+                .fold(f32::INFINITY, f32::min);
+            assert!(hit.is_intersecting && (hit.distance - brute).abs() < 1e-4, "z {} k {}", z, k);
+        }
+    }
+    let counters = stats::take();
+    stats::set_recording(false);
+
+    // Sin buzón, el recorrido probaría cada referencia de cada celda visitada
+    assert!(counters.repeats > 0);
+    assert!(counters.tests < refs, "{} pruebas de {} referencias", counters.tests, refs);
+    assert_eq!(counters.tests + counters.fast_rejects + counters.repeats, refs);
+    eprintln!("{}: {} referencias, {} pruebas", counters.summary(), refs, counters.tests);
+}